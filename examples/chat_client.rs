@@ -0,0 +1,83 @@
+//! Chat client for the `echo_server` example: sends each line typed on stdin
+//! and prints what the server sends back, including its join and leave notices.
+//!
+//! ```text
+//! cargo run --example chat_client [server_addr]
+//! ```
+//!
+//! `server_addr` defaults to `127.0.0.1:7777`. Closing stdin (Ctrl-D) waits for
+//! the last replies, then disconnects.
+
+use std::io::BufRead;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use reudp::{ReUDP, ReUDPError};
+
+/// How long replies are still waited for once stdin is closed.
+const LINGER: Duration = Duration::from_secs(1);
+
+fn main() -> Result<(), ReUDPError> {
+    let server_addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:7777".to_string());
+    let mut client = ReUDP::client(server_addr.as_str())?;
+    match client.connect() {
+        Ok(()) => println!("Connected to {} from {}", server_addr, client.local_addr()?),
+        Err(ReUDPError::HandshakeTimeout) => {
+            eprintln!("{} didn't answer; is the echo server running?", server_addr);
+            return Ok(());
+        }
+        Err(error) => return Err(error),
+    }
+
+    // Stdin is read on its own thread so the client keeps receiving while
+    // waiting for the user.
+    let (lines_tx, lines_rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if lines_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut stdin_closed_at = None;
+    loop {
+        match lines_rx.try_recv() {
+            Ok(line) => client.send(line, true)?,
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                let closed_at = *stdin_closed_at.get_or_insert_with(Instant::now);
+                if client.pending_acks() == 0 && closed_at.elapsed() >= LINGER {
+                    break;
+                }
+            }
+        }
+
+        match client.recv_timeout(Duration::from_millis(50)) {
+            Ok(Some((_, data))) => println!("{}", String::from_utf8_lossy(&data)),
+            Ok(None) => {}
+            Err(ReUDPError::ConnectionLost) => {
+                eprintln!("The server ended the session");
+                return Ok(());
+            }
+            Err(error) => return Err(error),
+        }
+
+        while let Some(event) = client.poll_event() {
+            println!("Event: {:?}", event);
+        }
+        if !client.is_running() {
+            eprintln!("Lost the connection to {}", server_addr);
+            return Ok(());
+        }
+    }
+
+    let unacked = client.disconnect()?;
+    if unacked > 0 {
+        eprintln!("{} messages were never acknowledged", unacked);
+    }
+    println!("Disconnected");
+    Ok(())
+}
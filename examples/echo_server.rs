@@ -0,0 +1,109 @@
+//! Echo server: sends every message back to the client it came from and tells
+//! all clients when someone joins or leaves.
+//!
+//! ```text
+//! cargo run --example echo_server [bind_addr]
+//! ```
+//!
+//! `bind_addr` defaults to `127.0.0.1:7777`. Press Enter, or close stdin, to
+//! disconnect the clients and shut down.
+
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use reudp::{ReUDP, ReUDPError};
+
+/// How often the status of each client is printed.
+const STATUS_INTERVAL: Duration = Duration::from_secs(5);
+
+fn main() -> Result<(), ReUDPError> {
+    let bind_addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:7777".to_string());
+    let mut server = ReUDP::server(bind_addr.as_str())?;
+    println!("Echo server listening on {}", server.local_addr()?);
+
+    // Any line on stdin, or its end, asks for a shutdown.
+    let (quit_tx, quit_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = std::io::stdin().lock().lines().next();
+        let _ = quit_tx.send(());
+    });
+
+    let mut clients = HashSet::new();
+    let mut last_status = Instant::now();
+    while matches!(quit_rx.try_recv(), Err(TryRecvError::Empty)) {
+        match server.recv_timeout(Duration::from_millis(50)) {
+            Ok(Some((addr, data))) => {
+                println!("{} says {:?}", addr, String::from_utf8_lossy(&data));
+                if let Err(error) = echo(&mut server, addr, &data) {
+                    eprintln!("Couldn't echo to {}: {:?}", addr, error);
+                }
+            }
+            Ok(None) => {}
+            Err(ReUDPError::Closing) => break,
+            // One misbehaving client shouldn't bring the server down.
+            Err(error) => eprintln!("Receive failed: {:?}", error),
+        }
+
+        while let Some(event) = server.poll_event() {
+            println!("Event: {:?}", event);
+        }
+        track_clients(&mut server, &mut clients);
+
+        if last_status.elapsed() >= STATUS_INTERVAL {
+            print_status(&server, &clients);
+            last_status = Instant::now();
+        }
+    }
+
+    println!("Shutting down");
+    let unacked = server.disconnect()?;
+    if unacked > 0 {
+        println!("{} messages were never acknowledged", unacked);
+    }
+    Ok(())
+}
+
+/// Sends `data` back to the client at `addr` only.
+fn echo(server: &mut ReUDP, addr: SocketAddr, data: &[u8]) -> Result<(), ReUDPError> {
+    let mut results = server.send_to_group([addr], data, true)?;
+    results.remove(&addr).unwrap_or(Ok(()))
+}
+
+/// Announces the clients that connected or went away since the last call to
+/// every client still connected.
+fn track_clients(server: &mut ReUDP, clients: &mut HashSet<SocketAddr>) {
+    let current: HashSet<SocketAddr> = server.client_addrs().into_iter().collect();
+    let joined: Vec<SocketAddr> = current.difference(clients).copied().collect();
+    let left: Vec<SocketAddr> = clients.difference(&current).copied().collect();
+    *clients = current;
+
+    let notices = joined
+        .iter()
+        .map(|addr| format!("* {} joined", addr))
+        .chain(left.iter().map(|addr| format!("* {} left", addr)));
+    for notice in notices {
+        println!("{}", notice);
+        if let Err(error) = server.send(notice.as_bytes(), true) {
+            eprintln!("Couldn't announce {:?}: {:?}", notice, error);
+        }
+    }
+}
+
+/// Prints how long each client has been quiet and the measured round-trip time.
+fn print_status(server: &ReUDP, clients: &HashSet<SocketAddr>) {
+    let srtt = server.srtt().map(|srtt| format!("{:?}", srtt));
+    println!(
+        "{} clients, smoothed RTT {}",
+        clients.len(),
+        srtt.as_deref().unwrap_or("not measured yet")
+    );
+    for addr in clients {
+        if let Some(idle) = server.idle_time(*addr) {
+            println!("  {} last heard {:?} ago", addr, idle);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// Weight given to each new sample in the smoothed estimate.
+const SMOOTHING: f64 = 0.125;
+/// Shortest gap accepted between the two replies of a pair; anything below is
+/// timer noise and would yield an absurd bandwidth.
+const MIN_GAP: Duration = Duration::from_micros(1);
+
+/// Estimates the available bandwidth with packet-pair probing: two probes of
+/// `size` bytes are sent back-to-back, and the gap between the arrivals of their
+/// replies gives `bandwidth ≈ size / gap`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BandwidthEstimator {
+    /// Identifier of the probe pair in flight and the size of each probe, in bytes
+    probe: Option<(u64, usize)>,
+    /// Arrival time of the first reply of the current pair, per peer
+    first_replies: HashMap<SocketAddr, Instant>,
+    /// Smoothed estimate, in bytes per second
+    estimate: Option<f64>,
+}
+
+impl BandwidthEstimator {
+    /// Starts a new probe pair, forgetting replies to any earlier one.
+    pub(crate) fn start(&mut self, id: u64, size: usize) {
+        self.probe = Some((id, size));
+        self.first_replies.clear();
+    }
+
+    /// Records the reply from `addr` to probe `index` (0 or 1) of pair `id`,
+    /// received at `now`.
+    pub(crate) fn add_reply(&mut self, addr: SocketAddr, id: u64, index: u8, now: Instant) {
+        let Some((probe_id, size)) = self.probe else {
+            return;
+        };
+        if id != probe_id {
+            return;
+        }
+        match index {
+            0 => {
+                self.first_replies.insert(addr, now);
+            }
+            _ => {
+                let Some(first) = self.first_replies.remove(&addr) else {
+                    return;
+                };
+                let gap = now.duration_since(first).max(MIN_GAP);
+                let sample = size as f64 / gap.as_secs_f64();
+                self.estimate = Some(match self.estimate {
+                    Some(estimate) => estimate + SMOOTHING * (sample - estimate),
+                    None => sample,
+                });
+            }
+        }
+    }
+
+    /// Returns the current estimate in bytes per second, if a pair has completed.
+    pub(crate) fn estimate(&self) -> Option<u64> {
+        self.estimate.map(|estimate| estimate.round() as u64)
+    }
+}
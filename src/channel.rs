@@ -0,0 +1,71 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+
+use crate::message::Message;
+
+/// State of one channel, kept apart from every other channel so a gap in one
+/// never holds back the messages of another.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Channel {
+    /// Whether messages sent on this channel are ordered, fixed by the first send
+    ordered: Option<bool>,
+    /// Sequence number for the next message to send on this channel
+    send_sequence: u64,
+    /// Lowest sequence number not received yet on this channel
+    recv_sequence: u64,
+    /// Messages of an ordered channel that arrived ahead of their turn
+    recv_buffer: HashMap<u64, (SocketAddr, Message)>,
+    /// Sequence numbers past `recv_sequence` already delivered on an unordered channel
+    delivered: HashSet<u64>,
+}
+
+impl Channel {
+    /// Returns the sequence number for the next message sent on this channel,
+    /// or `None` if the channel was already used with the other ordering.
+    pub(crate) fn next_send_sequence(&self, ordered: bool) -> Option<u64> {
+        if self.ordered.is_some_and(|used| used != ordered) {
+            return None;
+        }
+        Some(self.send_sequence)
+    }
+
+    /// Records that the message numbered by `next_send_sequence` went out,
+    /// which fixes the ordering of the channel.
+    pub(crate) fn on_sent(&mut self, ordered: bool) {
+        self.ordered = Some(ordered);
+        self.send_sequence += 1;
+    }
+
+    /// Handles a message received on this channel, returning the messages that
+    /// can be delivered as a result, in order.
+    ///
+    /// An ordered channel holds a message back until every earlier one was
+    /// delivered; an unordered one delivers it right away. Either way each
+    /// message is delivered only once.
+    pub(crate) fn receive(&mut self, addr: SocketAddr, ordered: bool, message: Message) -> Vec<(SocketAddr, Message)> {
+        let sequence = message.sequence;
+        if sequence < self.recv_sequence {
+            return Vec::new();
+        }
+        if !ordered {
+            if !self.delivered.insert(sequence) {
+                return Vec::new();
+            }
+            while self.delivered.remove(&self.recv_sequence) {
+                self.recv_sequence += 1;
+            }
+            return vec![(addr, message)];
+        }
+        if sequence > self.recv_sequence {
+            self.recv_buffer.entry(sequence).or_insert((addr, message));
+            return Vec::new();
+        }
+        let mut ready = vec![(addr, message)];
+        self.recv_sequence += 1;
+        while let Some(message) = self.recv_buffer.remove(&self.recv_sequence) {
+            ready.push(message);
+            self.recv_sequence += 1;
+        }
+        ready
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use crate::time::{SystemTime, UNIX_EPOCH};
+
+/// Weight given to each new accepted sample in the smoothed estimate.
+const SMOOTHING: f64 = 0.125;
+/// Samples whose round-trip delay exceeds the best delay seen by this factor are
+/// considered to be taken during an RTT spike and are discarded.
+const SPIKE_FACTOR: u64 = 2;
+/// Slack added to the spike threshold so sub-millisecond LAN delays don't reject everything.
+const SPIKE_SLACK_MICROS: u64 = 1_000;
+/// After this many consecutive rejected samples the path is assumed to have changed
+/// and the minimum delay is re-seeded from the current sample.
+const MAX_REJECTED: u32 = 8;
+
+/// Returns the current wall-clock time as microseconds since the UNIX epoch.
+///
+/// Wall-clock time (rather than `Instant`) is used for the heartbeat timestamps
+/// because it is the only clock both peers can meaningfully compare.
+pub(crate) fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+/// Estimated offset between a remote peer's clock and the local clock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockOffset {
+    /// Remote clock minus local clock, in microseconds.
+    offset_micros: i64,
+    /// Estimated error bound, in microseconds.
+    error_micros: u64,
+}
+
+impl ClockOffset {
+    /// Returns the remote clock minus the local clock, in microseconds.
+    pub fn offset_micros(&self) -> i64 {
+        self.offset_micros
+    }
+
+    /// Returns the magnitude of the offset. Use `is_remote_ahead` for its sign.
+    pub fn offset(&self) -> Duration {
+        Duration::from_micros(self.offset_micros.unsigned_abs())
+    }
+
+    /// Returns `true` if the remote clock is ahead of the local clock.
+    pub fn is_remote_ahead(&self) -> bool {
+        self.offset_micros > 0
+    }
+
+    /// Returns the estimated error bound of the offset.
+    pub fn error_bound(&self) -> Duration {
+        Duration::from_micros(self.error_micros)
+    }
+
+    /// Translates a timestamp taken on the remote clock into local time.
+    pub fn to_local_time(&self, remote_time: SystemTime) -> SystemTime {
+        if self.is_remote_ahead() {
+            remote_time - self.offset()
+        } else {
+            remote_time + self.offset()
+        }
+    }
+}
+
+/// Maintains a smoothed clock offset estimate from NTP-style timestamp samples.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClockOffsetEstimator {
+    /// Smoothed offset and error bound, in microseconds.
+    estimate: Option<(f64, f64)>,
+    /// Lowest round-trip delay observed so far, in microseconds.
+    min_delay: Option<u64>,
+    /// Number of consecutive samples rejected as RTT spikes.
+    rejected: u32,
+}
+
+impl ClockOffsetEstimator {
+    /// Adds a sample made of the four heartbeat timestamps (all in microseconds):
+    /// local send time `t0`, remote receive time `t1`, remote transmit time `t2`
+    /// and local receive time `t3`.
+    pub(crate) fn add_sample(&mut self, t0: u64, t1: u64, t2: u64, t3: u64) {
+        let (t0, t1, t2, t3) = (t0 as i64, t1 as i64, t2 as i64, t3 as i64);
+        let delay = ((t3 - t0) - (t2 - t1)).max(0) as u64;
+        let offset = ((t1 - t0) + (t2 - t3)) as f64 / 2.0;
+
+        match self.min_delay {
+            Some(min_delay) if delay > min_delay * SPIKE_FACTOR + SPIKE_SLACK_MICROS => {
+                self.rejected += 1;
+                if self.rejected < MAX_REJECTED {
+                    return;
+                }
+                self.min_delay = Some(delay);
+            }
+            Some(min_delay) => self.min_delay = Some(min_delay.min(delay)),
+            None => self.min_delay = Some(delay),
+        }
+        self.rejected = 0;
+
+        let error = delay as f64 / 2.0;
+        self.estimate = Some(match self.estimate {
+            Some((smoothed_offset, smoothed_error)) => (
+                smoothed_offset + SMOOTHING * (offset - smoothed_offset),
+                smoothed_error + SMOOTHING * (error - smoothed_error),
+            ),
+            None => (offset, error),
+        });
+    }
+
+    /// Returns the current estimate, if any sample has been accepted.
+    pub(crate) fn offset(&self) -> Option<ClockOffset> {
+        self.estimate.map(|(offset, error)| ClockOffset {
+            offset_micros: offset.round() as i64,
+            error_micros: error.round() as u64,
+        })
+    }
+}
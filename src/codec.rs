@@ -0,0 +1,51 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Error returned by a codec that failed to encode or decode a value.
+pub type CodecError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Encoding of the values sent with `ReUDP::send_typed_with` and received with
+/// `ReUDP::recv_typed_with`.
+///
+/// A codec only produces the payload of a message; the ReUDP header is the
+/// same whatever the codec, so peers using different codecs still talk to each
+/// other and only fail when decoding, with `ReUDPError::DecodeError`.
+pub trait Codec {
+    /// Encodes `value` into a payload.
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError>;
+
+    /// Decodes a payload into a `T`.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// Compact binary encoding with [`postcard`](https://crates.io/crates/postcard),
+/// used by `ReUDP::send_typed` and `ReUDP::recv_typed`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostcardCodec;
+
+impl Codec for PostcardCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+}
+
+/// JSON encoding with [`serde_json`](https://crates.io/crates/serde_json),
+/// readable but larger than `PostcardCodec`.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+#[cfg(feature = "json")]
+impl Codec for JsonCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
@@ -0,0 +1,558 @@
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::factory::SocketFactory;
+use crate::message::HEADER_SIZE;
+use crate::socket::IpFamily;
+use crate::socks5::Socks5Config;
+
+/// Smallest receive buffer that holds any datagram a typical path carries
+/// without fragmentation. Smaller buffers work, but are logged as a warning.
+pub(crate) const RECOMMENDED_BUFFER_SIZE: usize = 1200;
+
+/// How the interval between heartbeats is chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeartbeatPolicy {
+    /// Heartbeats are sent at a fixed interval.
+    Fixed(Duration),
+    /// The interval follows the measured round-trip time:
+    /// `clamp(srtt * rtt_multiplier, min, max)`, starting at `min` until the
+    /// first RTT sample is taken.
+    Adaptive {
+        min: Duration,
+        max: Duration,
+        rtt_multiplier: f64,
+    },
+}
+
+impl HeartbeatPolicy {
+    /// Returns the interval to use before any RTT has been measured.
+    pub(crate) fn initial_interval(&self) -> Duration {
+        match *self {
+            HeartbeatPolicy::Fixed(interval) => interval,
+            HeartbeatPolicy::Adaptive { min, .. } => min,
+        }
+    }
+
+    /// Returns the interval to use given the smoothed RTT.
+    pub(crate) fn interval_for(&self, srtt: Duration) -> Duration {
+        match *self {
+            HeartbeatPolicy::Fixed(interval) => interval,
+            HeartbeatPolicy::Adaptive {
+                min,
+                max,
+                rtt_multiplier,
+            } => srtt.mul_f64(rtt_multiplier).clamp(min, max),
+        }
+    }
+}
+
+/// When packets travel over TCP instead of UDP, for networks that drop UDP.
+///
+/// Packets are framed with a length prefix on a TCP connection to the server's
+/// address. A server with any setting other than `Never` accepts such
+/// connections on the TCP port matching its UDP one, and its TCP clients go
+/// through the same handling as the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TcpFallback {
+    /// Only UDP is used.
+    #[default]
+    Never,
+    /// A client switches to TCP when the handshake of `ReUDP::connect` gets
+    /// no answer over UDP, and tries it once more over TCP.
+    OnHandshakeTimeout,
+    /// A client uses TCP from the start.
+    Always,
+}
+
+/// What happens to a received datagram that can't be handled: one that isn't
+/// a well-formed ReUDP message, or one cut off by the receive buffer.
+///
+/// Such datagrams are never delivered, and are counted by
+/// `ReUDP::malformed_packets`, except for those that aren't ReUDP messages
+/// at all while a handler is set with `ReUDP::set_raw_handler`: they go to
+/// the handler instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MalformedPolicy {
+    /// `recv` fails with `ReUDPError::MalformedPacket` or
+    /// `ReUDPError::Truncated`, e.g. to notice mismatched peers during
+    /// development.
+    Error,
+    /// The datagram is dropped silently.
+    Drop,
+    /// The datagram is dropped and a warning goes to the logger set with
+    /// `ReUDP::set_logger`.
+    DropAndLog,
+}
+
+/// A configuration that would produce a broken instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The receive buffer can't hold a message with any payload.
+    BufferTooSmall { size: usize },
+    /// Heartbeats would be sent in a busy loop.
+    ZeroHeartbeatInterval,
+    /// The minimum of an adaptive heartbeat interval is above its maximum.
+    InvalidHeartbeatRange { min: Duration, max: Duration },
+    /// Peers would be declared lost before a single retransmission.
+    ResendNotBelowLivenessTimeout { resend: Duration, liveness: Duration },
+    /// The maximum packet size leaves no room for a payload after the header.
+    PacketSizeTooSmall { size: usize },
+    /// Packets of the maximum size wouldn't fit in the receive buffer.
+    PacketSizeAboveBuffer { packet_size: usize, buffer_size: usize },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::BufferTooSmall { size } => write!(
+                f,
+                "buffer size {} must be larger than the {}-byte header",
+                size, HEADER_SIZE
+            ),
+            ConfigError::ZeroHeartbeatInterval => write!(f, "heartbeat interval must not be zero"),
+            ConfigError::InvalidHeartbeatRange { min, max } => {
+                write!(f, "minimum heartbeat interval {:?} is above the maximum {:?}", min, max)
+            }
+            ConfigError::ResendNotBelowLivenessTimeout { resend, liveness } => write!(
+                f,
+                "resend interval {:?} must be shorter than the liveness timeout {:?}",
+                resend, liveness
+            ),
+            ConfigError::PacketSizeTooSmall { size } => write!(
+                f,
+                "maximum packet size {} must be larger than the {}-byte header",
+                size, HEADER_SIZE
+            ),
+            ConfigError::PacketSizeAboveBuffer {
+                packet_size,
+                buffer_size,
+            } => write!(
+                f,
+                "maximum packet size {} doesn't fit in the {}-byte receive buffer",
+                packet_size, buffer_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Configuration for a ReUDP instance.
+///
+/// Built with `ReUDPConfig::default()` (or one of the profiles such as
+/// `ReUDPConfig::low_power()`), adjusted with the builder methods and checked
+/// with `build`.
+#[derive(Debug, Clone)]
+pub struct ReUDPConfig {
+    pub(crate) heartbeat_policy: HeartbeatPolicy,
+    pub(crate) liveness_timeout: Duration,
+    pub(crate) resend_interval: Duration,
+    pub(crate) ack_flush_interval: Option<Duration>,
+    pub(crate) coalesce_window: Duration,
+    pub(crate) buffer_size: usize,
+    pub(crate) max_recv_batch: usize,
+    pub(crate) max_queued_per_peer: usize,
+    pub(crate) max_unacked_packets: Option<usize>,
+    pub(crate) max_packet_size: usize,
+    pub(crate) ping_history_size: usize,
+    pub(crate) handshake_retries: u32,
+    pub(crate) handshake_retry_interval: Duration,
+    pub(crate) max_clients: Option<usize>,
+    pub(crate) ip_family: IpFamily,
+    pub(crate) drain_timeout: Duration,
+    pub(crate) migration_grace_period: Duration,
+    pub(crate) session_token_ttl: Duration,
+    pub(crate) connect_probe_size: Option<usize>,
+    pub(crate) respect_socket_blocking: bool,
+    pub(crate) connect_client_socket: bool,
+    pub(crate) recv_buffer_bytes: Option<usize>,
+    pub(crate) send_buffer_bytes: Option<usize>,
+    pub(crate) ttl: Option<u32>,
+    pub(crate) tos: Option<u32>,
+    pub(crate) socket_options_required: bool,
+    pub(crate) bind_device: Option<String>,
+    pub(crate) socket_factory: Option<Arc<dyn SocketFactory>>,
+    pub(crate) proxy: Option<Socks5Config>,
+    pub(crate) tcp_fallback: TcpFallback,
+    pub(crate) on_malformed: Option<MalformedPolicy>,
+}
+
+impl Default for ReUDPConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_policy: HeartbeatPolicy::Fixed(Duration::from_secs(1)),
+            liveness_timeout: Duration::from_secs(2),
+            resend_interval: Duration::from_secs(1),
+            ack_flush_interval: None,
+            coalesce_window: Duration::from_millis(10),
+            buffer_size: RECOMMENDED_BUFFER_SIZE,
+            max_recv_batch: 1024,
+            max_queued_per_peer: 1024,
+            max_unacked_packets: None,
+            max_packet_size: 1024,
+            ping_history_size: 100,
+            handshake_retries: 5,
+            handshake_retry_interval: Duration::from_millis(250),
+            max_clients: None,
+            ip_family: IpFamily::Auto,
+            drain_timeout: Duration::from_secs(2),
+            migration_grace_period: Duration::from_secs(5),
+            session_token_ttl: Duration::from_secs(300),
+            connect_probe_size: Some(1000),
+            respect_socket_blocking: false,
+            connect_client_socket: true,
+            recv_buffer_bytes: None,
+            send_buffer_bytes: None,
+            ttl: None,
+            tos: None,
+            socket_options_required: false,
+            bind_device: None,
+            socket_factory: None,
+            proxy: None,
+            tcp_fallback: TcpFallback::Never,
+            on_malformed: None,
+        }
+    }
+}
+
+impl ReUDPConfig {
+    /// Returns a profile for battery-powered clients that keeps the radio quiet:
+    /// heartbeats every few minutes, a long liveness timeout, batched
+    /// retransmissions and lazily flushed acknowledgments.
+    pub fn low_power() -> Self {
+        Self {
+            heartbeat_policy: HeartbeatPolicy::Fixed(Duration::from_secs(180)),
+            liveness_timeout: Duration::from_secs(600),
+            resend_interval: Duration::from_secs(10),
+            ack_flush_interval: Some(Duration::from_millis(500)),
+            ..Self::default()
+        }
+    }
+
+    /// Sets a fixed interval between heartbeats.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_policy = HeartbeatPolicy::Fixed(interval);
+        self
+    }
+
+    /// Sets how the interval between heartbeats is chosen.
+    pub fn heartbeat_policy(mut self, policy: HeartbeatPolicy) -> Self {
+        self.heartbeat_policy = policy;
+        self
+    }
+
+    /// Sets how long a peer may stay silent before it is considered lost.
+    pub fn liveness_timeout(mut self, timeout: Duration) -> Self {
+        self.liveness_timeout = timeout;
+        self
+    }
+
+    /// Sets the interval between retransmissions of unacknowledged packets.
+    pub fn resend_interval(mut self, interval: Duration) -> Self {
+        self.resend_interval = interval;
+        self
+    }
+
+    /// Sets how long acknowledgments may be held back so they can be sent together.
+    /// `None` acknowledges every message immediately.
+    pub fn ack_flush_interval(mut self, interval: Option<Duration>) -> Self {
+        self.ack_flush_interval = interval;
+        self
+    }
+
+    /// Sets how long messages sent after `ReUDP::begin_batch` may wait to be
+    /// coalesced, counted from the first one, before they are sent without an
+    /// explicit `flush`.
+    pub fn coalesce_window(mut self, window: Duration) -> Self {
+        self.coalesce_window = window;
+        self
+    }
+
+    /// Sets the size of the buffer for received messages. It must be larger
+    /// than the header and hold packets of the maximum packet size; below
+    /// 1200 bytes, a warning is logged when an instance is created.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Sets how many datagrams `ReUDP::recv_all` processes at most in one call,
+    /// so a flood of packets can't starve the rest of the application.
+    pub fn max_recv_batch(mut self, max: usize) -> Self {
+        self.max_recv_batch = max;
+        self
+    }
+
+    /// Sets how many received messages from one peer may wait for the
+    /// application. Beyond that, the oldest ones are dropped, so a peer nobody
+    /// reads from with `ReUDP::recv_from` can't use up memory.
+    pub fn max_queued_per_peer(mut self, max: usize) -> Self {
+        self.max_queued_per_peer = max;
+        self
+    }
+
+    /// Sets how many reliable messages sent with `ReUDP::send` and its variants
+    /// may wait for an acknowledgment. Beyond that, sending a reliable message
+    /// fails with `ReUDPError::QueueFull` until some are acknowledged.
+    /// Unlimited by default.
+    pub fn max_unacked_packets(mut self, max: usize) -> Self {
+        self.max_unacked_packets = Some(max);
+        self
+    }
+
+    /// Sets the largest datagram `send` may produce, header included. Larger
+    /// messages are refused.
+    pub fn max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size;
+        self
+    }
+
+    /// Sets how many of the most recent RTT samples `ReUDP::jitter` and the
+    /// other ping statistics are computed over. 0 keeps no history.
+    pub fn ping_history_size(mut self, size: usize) -> Self {
+        self.ping_history_size = size;
+        self
+    }
+
+    /// Sets how many times `connect` resends its request before giving up.
+    pub fn handshake_retries(mut self, retries: u32) -> Self {
+        self.handshake_retries = retries;
+        self
+    }
+
+    /// Sets how long `connect` waits for an answer before resending its request.
+    pub fn handshake_retry_interval(mut self, interval: Duration) -> Self {
+        self.handshake_retry_interval = interval;
+        self
+    }
+
+    /// Sets the maximum number of clients a server accepts. Further clients are
+    /// refused during the handshake.
+    pub fn max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = Some(max_clients);
+        self
+    }
+
+    /// Restricts the socket to IPv4. The local address must be an IPv4 address.
+    pub fn ipv4_only(mut self) -> Self {
+        self.ip_family = IpFamily::Ipv4Only;
+        self
+    }
+
+    /// Restricts the socket to IPv6 by setting `IPV6_V6ONLY`, whatever the OS
+    /// default is. The local address must be an IPv6 address.
+    pub fn ipv6_only(mut self) -> Self {
+        self.ip_family = IpFamily::Ipv6Only;
+        self
+    }
+
+    /// Binds an IPv6 socket with `IPV6_V6ONLY` cleared so it receives both IPv4
+    /// and IPv6 traffic. The local address must be an IPv6 address or `0.0.0.0`.
+    /// IPv4 peers are reported under their plain IPv4 address, never the
+    /// IPv4-mapped IPv6 form, and can be addressed the same way.
+    pub fn dual_stack(mut self) -> Self {
+        self.ip_family = IpFamily::DualStack;
+        self
+    }
+
+    /// Sets how long `disconnect` keeps retransmitting unacknowledged messages
+    /// before giving up on them.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a client keeps accepting packets from its previous server
+    /// address after `migrate_to` switched to a new one.
+    pub fn migration_grace_period(mut self, period: Duration) -> Self {
+        self.migration_grace_period = period;
+        self
+    }
+
+    /// Sets the size of the bandwidth probes sent once `connect` succeeds.
+    /// `None` disables probing at session start.
+    pub fn connect_probe_size(mut self, size: Option<usize>) -> Self {
+        self.connect_probe_size = size;
+        self
+    }
+
+    /// Sets how long a session token issued by a server stays valid for
+    /// resuming the session with `ReUDP::resume`.
+    pub fn session_token_ttl(mut self, ttl: Duration) -> Self {
+        self.session_token_ttl = ttl;
+        self
+    }
+
+    /// Sets whether `ReUDP::from_socket` leaves the socket in the blocking mode
+    /// it was given instead of switching it to non-blocking. On a blocking socket
+    /// `recv` waits for a datagram (or the socket's read timeout) before returning.
+    pub fn respect_socket_blocking(mut self, respect: bool) -> Self {
+        self.respect_socket_blocking = respect;
+        self
+    }
+
+    /// Sets whether a client's UDP socket is connected to its server, on Linux
+    /// and macOS. A connected socket has the OS drop datagrams from other
+    /// addresses, and makes an error such as the server's port being
+    /// unreachable come out of the next `ReUDP::recv` as an `IoError`, where
+    /// an unconnected socket would go on waiting for the liveness timeout.
+    /// Enabled by default.
+    pub fn connect_client_socket(mut self, connect: bool) -> Self {
+        self.connect_client_socket = connect;
+        self
+    }
+
+    /// Sets the size of the OS receive buffer (`SO_RCVBUF`). The OS may grant a
+    /// different size; `ReUDP::recv_buffer_bytes` reports the actual one.
+    pub fn recv_buffer_bytes(mut self, size: usize) -> Self {
+        self.recv_buffer_bytes = Some(size);
+        self
+    }
+
+    /// Sets the size of the OS send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_bytes(mut self, size: usize) -> Self {
+        self.send_buffer_bytes = Some(size);
+        self
+    }
+
+    /// Sets the time-to-live (hop limit on IPv6) of outgoing packets.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the type-of-service byte (traffic class on IPv6) of outgoing packets,
+    /// e.g. `0xb8` for DSCP expedited forwarding.
+    pub fn tos(mut self, tos: u32) -> Self {
+        self.tos = Some(tos);
+        self
+    }
+
+    /// Sets whether failing to apply a socket option fails construction. By
+    /// default the failure is reported as `Event::SocketOptionFailed` instead.
+    pub fn socket_options_required(mut self, required: bool) -> Self {
+        self.socket_options_required = required;
+        self
+    }
+
+    /// Pins the socket to a network interface by name (e.g. `"eth0"`), so its
+    /// traffic never leaves through another one whatever the routing table says.
+    /// Uses `SO_BINDTODEVICE` on Linux and `IP_BOUND_IF` on macOS and iOS; binding
+    /// fails with `Unsupported` elsewhere. Only applies when ReUDP binds the socket.
+    pub fn bind_device(mut self, interface: &str) -> Self {
+        self.bind_device = Some(interface.to_string());
+        self
+    }
+
+    /// Sets how `ReUDP::with_config` creates its socket, instead of binding
+    /// one itself according to the IP family and `bind_device` options.
+    pub fn socket_factory(mut self, factory: Box<dyn SocketFactory>) -> Self {
+        self.socket_factory = Some(Arc::from(factory));
+        self
+    }
+
+    /// Makes a client reach its server through a SOCKS5 proxy supporting UDP
+    /// ASSOCIATE, for networks that only let traffic out through one. The
+    /// association is set up when the instance is created; the client socket
+    /// isn't connected to the server then, as every datagram goes to the
+    /// proxy's relay. Servers can't use a proxy.
+    pub fn proxy(mut self, proxy: Socks5Config) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Sets when packets go over TCP instead of UDP. Messages are still
+    /// acknowledged and retransmitted over TCP, which is redundant but keeps
+    /// both transports behaving the same. Can't be combined with a proxy.
+    pub fn tcp_fallback(mut self, fallback: TcpFallback) -> Self {
+        self.tcp_fallback = fallback;
+        self
+    }
+
+    /// Sets what happens to received datagrams that can't be handled. By
+    /// default, a server drops them, so that anyone sending it garbage can't
+    /// make its `recv` fail, and a client, which only hears from its server,
+    /// reports them as errors.
+    pub fn on_malformed(mut self, policy: MalformedPolicy) -> Self {
+        self.on_malformed = Some(policy);
+        self
+    }
+
+    /// Checks the configuration for combinations that would produce a broken
+    /// instance. `ReUDP::with_config` runs the same checks.
+    pub fn build(self) -> Result<Self, ConfigError> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Returns the first problem found in the configuration, if any.
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        if self.buffer_size <= HEADER_SIZE {
+            return Err(ConfigError::BufferTooSmall { size: self.buffer_size });
+        }
+        match self.heartbeat_policy {
+            HeartbeatPolicy::Fixed(interval) if interval.is_zero() => {
+                return Err(ConfigError::ZeroHeartbeatInterval);
+            }
+            HeartbeatPolicy::Adaptive { min, .. } if min.is_zero() => {
+                return Err(ConfigError::ZeroHeartbeatInterval);
+            }
+            HeartbeatPolicy::Adaptive { min, max, .. } if min > max => {
+                return Err(ConfigError::InvalidHeartbeatRange { min, max });
+            }
+            _ => {}
+        }
+        if self.resend_interval >= self.liveness_timeout {
+            return Err(ConfigError::ResendNotBelowLivenessTimeout {
+                resend: self.resend_interval,
+                liveness: self.liveness_timeout,
+            });
+        }
+        if self.max_packet_size <= HEADER_SIZE {
+            return Err(ConfigError::PacketSizeTooSmall {
+                size: self.max_packet_size,
+            });
+        }
+        if self.max_packet_size > self.buffer_size {
+            return Err(ConfigError::PacketSizeAboveBuffer {
+                packet_size: self.max_packet_size,
+                buffer_size: self.buffer_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Configuration shared between a ReUDP instance and its heartbeat thread.
+///
+/// Readers take a snapshot with `load` and never hold the lock while using it;
+/// `update` swaps in a whole new configuration, so a change is seen all at once
+/// or not at all.
+#[derive(Debug)]
+pub(crate) struct SharedConfig(RwLock<Arc<ReUDPConfig>>);
+
+impl SharedConfig {
+    pub(crate) fn new(config: ReUDPConfig) -> Self {
+        Self(RwLock::new(Arc::new(config)))
+    }
+
+    /// Returns the current configuration.
+    pub(crate) fn load(&self) -> Arc<ReUDPConfig> {
+        Arc::clone(&self.0.read().unwrap())
+    }
+
+    /// Replaces the configuration with `f` applied to a copy of it, unless the
+    /// result is invalid. Concurrent updates are applied one after the other.
+    pub(crate) fn update<F: FnOnce(ReUDPConfig) -> ReUDPConfig>(
+        &self,
+        f: F,
+    ) -> Result<Arc<ReUDPConfig>, ConfigError> {
+        let mut current = self.0.write().unwrap();
+        let config = f((**current).clone());
+        config.validate()?;
+        *current = Arc::new(config);
+        Ok(Arc::clone(&current))
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::{Arc, Mutex};
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use crate::error::ReUDPError;
+use crate::message::MessageType;
+
+/// Size of the random nonce prepended to each ciphertext.
+pub(crate) const NONCE_SIZE: usize = 12;
+/// Size of the authentication tag appended to each ciphertext.
+pub(crate) const TAG_SIZE: usize = 16;
+
+/// Cipher set with `set_encryption_key`, shared with the heartbeat thread.
+pub(crate) type SharedCipher = Arc<Mutex<Option<Aes256Gcm>>>;
+
+/// Creates the cipher for a 256-bit key.
+pub(crate) fn cipher(key: &[u8; 32]) -> Aes256Gcm {
+    Aes256Gcm::new(key.into())
+}
+
+/// Returns the data authenticated along with the payload of a message of
+/// `message_type` numbered `sequence`.
+///
+/// The sequence number and the type are authenticated, so a ciphertext can't
+/// be replayed under another sequence number, or as another kind of message
+/// with sequence numbers of its own.
+fn aad(sequence: u64, message_type: &MessageType) -> [u8; 9] {
+    let mut aad = [0; 9];
+    aad[..8].copy_from_slice(&sequence.to_be_bytes());
+    aad[8] = message_type.code();
+    aad
+}
+
+/// Encrypts `plaintext` under a fresh random nonce, returning the nonce
+/// followed by the ciphertext and its tag.
+pub(crate) fn encrypt(cipher: &Aes256Gcm, sequence: u64, message_type: &MessageType, plaintext: &[u8]) -> Vec<u8> {
+    let nonce = rand::random::<[u8; NONCE_SIZE]>();
+    let aad = aad(sequence, message_type);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: &aad })
+        .expect("AES-GCM encryption of a packet-sized payload cannot fail");
+    let mut sealed = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    sealed
+}
+
+/// Encrypts `plaintext` for a message of `message_type` numbered `sequence`
+/// if a cipher is set.
+pub(crate) fn seal(
+    cipher: &SharedCipher,
+    sequence: u64,
+    message_type: &MessageType,
+    plaintext: &[u8],
+) -> Option<Vec<u8>> {
+    cipher
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|cipher| encrypt(cipher, sequence, message_type, plaintext))
+}
+
+/// Decrypts a payload produced by `encrypt` for the same sequence number and type.
+pub(crate) fn decrypt(
+    cipher: &Aes256Gcm,
+    sequence: u64,
+    message_type: &MessageType,
+    sealed: &[u8],
+) -> Result<Vec<u8>, ReUDPError> {
+    if sealed.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(ReUDPError::DecryptionFailed);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+    let aad = aad(sequence, message_type);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+        .map_err(|_| ReUDPError::DecryptionFailed)
+}
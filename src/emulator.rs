@@ -0,0 +1,243 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// How long the forwarding thread waits between two polls of its sockets.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// How long a packet picked for reordering waits for a successor before it is
+/// forwarded anyway.
+const MAX_REORDER_HOLD: Duration = Duration::from_millis(50);
+/// Largest datagram the emulator forwards.
+const MAX_DATAGRAM: usize = 65536;
+
+/// Impairments applied to the packets travelling in one direction through a
+/// `NetworkEmulator`.
+///
+/// Built with `LinkPolicy::default()` (a perfect link) and adjusted with the
+/// builder methods.
+#[derive(Debug, Clone, Default)]
+pub struct LinkPolicy {
+    drop_rate: f64,
+    delay: Duration,
+    delay_jitter: Duration,
+    reorder_rate: f64,
+    duplicate_rate: f64,
+}
+
+impl LinkPolicy {
+    /// Sets the probability (0.0 to 1.0) that a packet is dropped.
+    pub fn drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Sets a fixed delay added to every packet.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the upper bound of a random delay added on top of the fixed delay.
+    pub fn delay_jitter(mut self, jitter: Duration) -> Self {
+        self.delay_jitter = jitter;
+        self
+    }
+
+    /// Sets the probability (0.0 to 1.0) that a packet is swapped with the next one.
+    pub fn reorder_rate(mut self, rate: f64) -> Self {
+        self.reorder_rate = rate;
+        self
+    }
+
+    /// Sets the probability (0.0 to 1.0) that a packet is forwarded twice.
+    pub fn duplicate_rate(mut self, rate: f64) -> Self {
+        self.duplicate_rate = rate;
+        self
+    }
+
+    /// Returns the delay to apply to one packet.
+    fn sample_delay(&self) -> Duration {
+        self.delay + self.delay_jitter.mul_f64(rand::random::<f64>())
+    }
+}
+
+/// A UDP proxy that sits between a client and a server and impairs the traffic
+/// between them, for testing behavior over bad networks on the loopback interface.
+///
+/// The client is pointed at `addr()` instead of the server; the emulator
+/// forwards its packets to the server from a socket of its own and sends the
+/// replies back. Each direction has its own `LinkPolicy`. The emulator serves
+/// a single client: the first address to send to it. It stops when dropped.
+pub struct NetworkEmulator {
+    /// Socket the client talks to
+    addr: SocketAddr,
+    /// Flag telling the forwarding thread to keep running
+    running: Arc<AtomicBool>,
+    /// Handle of the forwarding thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl NetworkEmulator {
+    /// Starts an emulator in front of `server_addr`.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_addr` - Address of the server to forward to.
+    /// * `to_server` - Impairments for packets from the client to the server.
+    /// * `to_client` - Impairments for packets from the server to the client.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Self>` - The running emulator, or an error if its sockets couldn't be bound.
+    pub fn new(server_addr: SocketAddr, to_server: LinkPolicy, to_client: LinkPolicy) -> io::Result<Self> {
+        let local_addr = SocketAddr::new(server_addr.ip(), 0);
+        let client_side = UdpSocket::bind(local_addr)?;
+        let server_side = UdpSocket::bind(local_addr)?;
+        client_side.set_nonblocking(true)?;
+        server_side.set_nonblocking(true)?;
+        let addr = client_side.local_addr()?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let running = Arc::clone(&running);
+            thread::spawn(move || {
+                let mut upstream = Link::new(to_server);
+                let mut downstream = Link::new(to_client);
+                let mut client_addr = None;
+                let mut buf = vec![0; MAX_DATAGRAM];
+                while running.load(Ordering::SeqCst) {
+                    let now = Instant::now();
+                    while let Ok((len, from)) = client_side.recv_from(&mut buf) {
+                        client_addr = Some(from);
+                        upstream.push(buf[..len].to_vec(), now);
+                    }
+                    while let Ok(len) = server_side.recv(&mut buf) {
+                        downstream.push(buf[..len].to_vec(), now);
+                    }
+                    for packet in upstream.due(now) {
+                        let _ = server_side.send_to(&packet, server_addr);
+                    }
+                    if let Some(client_addr) = client_addr {
+                        for packet in downstream.due(now) {
+                            let _ = client_side.send_to(&packet, client_addr);
+                        }
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns the address the client should send to instead of the server's.
+    ///
+    /// # Returns
+    ///
+    /// * `SocketAddr` - Address of the client-facing socket.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for NetworkEmulator {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Packets in flight in one direction.
+pub(crate) struct Link {
+    policy: LinkPolicy,
+    /// Packets waiting for their delivery time, ordered by it
+    queue: BinaryHeap<Reverse<(Instant, u64, Vec<u8>)>>,
+    /// Tie-breaker keeping packets with the same delivery time in arrival order
+    counter: u64,
+    /// Delivery time of the last packet queued
+    last_at: Option<Instant>,
+    /// Packet held back to be delivered after the next one, with the time it arrived
+    held: Option<(Vec<u8>, Instant)>,
+}
+
+impl Link {
+    pub(crate) fn new(policy: LinkPolicy) -> Self {
+        Self {
+            policy,
+            queue: BinaryHeap::new(),
+            counter: 0,
+            last_at: None,
+            held: None,
+        }
+    }
+
+    /// Replaces the policy applied to the packets arriving from now on.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn set_policy(&mut self, policy: LinkPolicy) {
+        self.policy = policy;
+    }
+
+    /// Applies the policy to a packet that just arrived.
+    pub(crate) fn push(&mut self, packet: Vec<u8>, now: Instant) {
+        if rand::random::<f64>() < self.policy.drop_rate {
+            return;
+        }
+        if rand::random::<f64>() < self.policy.duplicate_rate {
+            self.schedule(packet.clone(), now);
+        }
+        match self.held.take() {
+            Some((held, _)) => {
+                self.schedule(packet, now);
+                self.schedule(held, now);
+            }
+            None if rand::random::<f64>() < self.policy.reorder_rate => {
+                self.held = Some((packet, now));
+            }
+            None => self.schedule(packet, now),
+        }
+    }
+
+    /// Queues a packet for delivery after the policy's delay. Delivery times
+    /// never go backwards, so reordering only happens where the policy asks for it.
+    fn schedule(&mut self, packet: Vec<u8>, now: Instant) {
+        let mut at = now + self.policy.sample_delay();
+        if let Some(last_at) = self.last_at {
+            at = at.max(last_at);
+        }
+        self.last_at = Some(at);
+        self.counter += 1;
+        self.queue.push(Reverse((at, self.counter, packet)));
+    }
+
+    /// Removes and returns the packets whose delivery time has come.
+    pub(crate) fn due(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        if let Some((_, held_at)) = &self.held {
+            if now.duration_since(*held_at) >= MAX_REORDER_HOLD {
+                let (held, _) = self.held.take().unwrap();
+                self.schedule(held, now);
+            }
+        }
+        let mut due = Vec::new();
+        while let Some(Reverse((at, _, _))) = self.queue.peek() {
+            if *at > now {
+                break;
+            }
+            let Reverse((_, _, packet)) = self.queue.pop().unwrap();
+            due.push(packet);
+        }
+        due
+    }
+}
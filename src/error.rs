@@ -1,12 +1,224 @@
+use std::fmt;
+use std::net::SocketAddr;
+
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ReUDPError {
     IoError(std::io::Error),
     ConnectionLost,
     NoResponseFromServer,
+    /// The server didn't answer any of the handshake attempts.
+    HandshakeTimeout,
+    /// The server actively refused the connection.
+    ConnectionRefused { reason: Vec<u8> },
+    /// The instance is disconnecting and doesn't accept new messages.
+    Closing,
+    /// No matching reply arrived in time.
+    Timeout,
+    /// A received message couldn't be decoded into the requested type. `data`
+    /// holds its payload, for the application to handle some other way.
+    DecodeError { data: Vec<u8>, reason: String },
+    /// An encrypted message couldn't be decrypted: the key differs from the
+    /// sender's, none was set, or the message was tampered with.
+    DecryptionFailed,
+    /// The buffer passed to `recv_into` can't hold the message, which needs
+    /// `needed` bytes. The message is kept for the next receive.
+    BufferTooSmall { needed: usize },
+    /// The socket is non-blocking and its send buffer is full. Nothing was
+    /// sent; the message can be sent again later.
+    WouldBlock,
+    /// A server has no client to send to.
+    NotConnected,
+    /// A message of `len` bytes, header included, is larger than the `max`
+    /// bytes a packet may take. Nothing was sent.
+    MessageTooLarge { len: usize, max: usize },
+    /// `len` bytes don't parse as a ReUDP message, for the given reason.
+    MalformedPacket { len: usize, reason: String },
+    /// As many reliable messages as the configured `capacity` are waiting for
+    /// an acknowledgment. Nothing was sent; the message can be sent again once
+    /// some are acknowledged.
+    QueueFull { capacity: usize },
+    /// No client is connected from `addr` (server mode).
+    ClientNotFound { addr: SocketAddr },
+    /// The socket failed while sending to the peer at `addr`.
+    PeerIo {
+        addr: SocketAddr,
+        source: std::io::Error,
+        during: IoContext,
+    },
+    /// A datagram from `addr` was larger than the `buffer` bytes of the
+    /// receive buffer and got cut off. It was dropped, not delivered.
+    Truncated { addr: SocketAddr, buffer: usize },
+    /// `operation` was called on an instance not in the `expected` mode, such
+    /// as `publish` on a client or `connect` on a server. Nothing was done.
+    WrongMode { operation: &'static str, expected: ModeKind },
+    /// The type code passed to `send_with_type` is reserved for ReUDP's own
+    /// messages, below `FIRST_CUSTOM_TYPE`, or above 127. Nothing was sent.
+    InvalidMessageType(u8),
+    /// A message was sent on `channel` as `ordered` while the channel is of
+    /// the other kind, which it takes from its first message. Nothing was sent.
+    ChannelMismatch { channel: u8, ordered: bool },
+    /// A message couldn't be encoded by the codec, for the given reason.
+    EncodeError { reason: String },
+}
+
+/// The mode a `ReUDPError::WrongMode` operation needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModeKind {
+    Server,
+    Client,
+}
+
+impl fmt::Display for ModeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ModeKind::Server => "server",
+            ModeKind::Client => "client",
+        })
+    }
+}
+
+/// What was being sent when a `ReUDPError::PeerIo` happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IoContext {
+    /// A message sent by the application
+    Send,
+    /// A retransmission of an unacknowledged message
+    Retransmit,
+    /// An acknowledgment of a received message
+    Ack,
+    /// A heartbeat or the answer to one
+    Heartbeat,
+    /// Another protocol message: handshake, disconnect, reset, probe or migration
+    Control,
+}
+
+impl fmt::Display for IoContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IoContext::Send => "send",
+            IoContext::Retransmit => "retransmit",
+            IoContext::Ack => "ack",
+            IoContext::Heartbeat => "heartbeat",
+            IoContext::Control => "control message",
+        })
+    }
+}
+
+impl ReUDPError {
+    /// Returns the I/O error behind this error, if any, whether or not it
+    /// happened with a specific peer.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&std::io::Error>` - The error of `IoError` or `PeerIo`.
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match self {
+            ReUDPError::IoError(error) | ReUDPError::PeerIo { source: error, .. } => Some(error),
+            _ => None,
+        }
+    }
 }
 
 impl From<std::io::Error> for ReUDPError {
     fn from(error: std::io::Error) -> Self {
         ReUDPError::IoError(error)
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for ReUDPError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // The I/O error itself is the source.
+            ReUDPError::IoError(_) => f.write_str("I/O error"),
+            ReUDPError::ConnectionLost => f.write_str("connection lost"),
+            ReUDPError::NoResponseFromServer => f.write_str("no response from server"),
+            ReUDPError::HandshakeTimeout => f.write_str("handshake timed out"),
+            ReUDPError::ConnectionRefused { reason } if reason.is_empty() => f.write_str("connection refused"),
+            ReUDPError::ConnectionRefused { reason } => {
+                write!(f, "connection refused: {}", String::from_utf8_lossy(reason))
+            }
+            ReUDPError::Closing => f.write_str("instance is disconnecting"),
+            ReUDPError::Timeout => f.write_str("timed out"),
+            ReUDPError::DecodeError { reason, .. } => write!(f, "decode error: {}", reason),
+            ReUDPError::DecryptionFailed => f.write_str("decryption failed"),
+            ReUDPError::BufferTooSmall { needed } => write!(f, "buffer too small: {} bytes needed", needed),
+            ReUDPError::WouldBlock => f.write_str("send buffer full"),
+            ReUDPError::NotConnected => f.write_str("no client connected"),
+            ReUDPError::MessageTooLarge { len, max } => write!(
+                f,
+                "message of {} bytes exceeds the maximum packet size of {} bytes",
+                len, max
+            ),
+            ReUDPError::MalformedPacket { len, reason } => write!(f, "malformed packet of {} bytes: {}", len, reason),
+            ReUDPError::QueueFull { capacity } => {
+                write!(f, "{} messages already wait for an acknowledgment", capacity)
+            }
+            ReUDPError::ClientNotFound { addr } => write!(f, "no client connected from {}", addr),
+            ReUDPError::PeerIo { addr, during, .. } => write!(f, "I/O error during {} to {}", during, addr),
+            ReUDPError::Truncated { addr, buffer } => write!(
+                f,
+                "datagram from {} is larger than the {}-byte receive buffer",
+                addr, buffer
+            ),
+            ReUDPError::WrongMode { operation, expected } => {
+                write!(f, "{} is only available in {} mode", operation, expected)
+            }
+            ReUDPError::InvalidMessageType(code) => write!(
+                f,
+                "message type {} is not in the custom range {}..=127",
+                code,
+                crate::message::FIRST_CUSTOM_TYPE
+            ),
+            ReUDPError::ChannelMismatch { channel, ordered } => write!(
+                f,
+                "channel {} is {}",
+                channel,
+                if *ordered { "unordered" } else { "ordered" }
+            ),
+            ReUDPError::EncodeError { reason } => write!(f, "encode error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReUDPError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReUDPError::IoError(error) | ReUDPError::PeerIo { source: error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Lets ReUDP be used where an `io::Result` is expected. I/O errors are passed
+/// through; the others get the closest `ErrorKind`, or the kind of the error
+/// they wrap, and are kept as the inner error.
+impl From<ReUDPError> for std::io::Error {
+    fn from(error: ReUDPError) -> Self {
+        use std::io::{Error, ErrorKind};
+
+        let kind = match error {
+            ReUDPError::IoError(error) => return error,
+            ReUDPError::ConnectionLost => ErrorKind::ConnectionReset,
+            ReUDPError::NoResponseFromServer | ReUDPError::HandshakeTimeout | ReUDPError::Timeout => {
+                ErrorKind::TimedOut
+            }
+            ReUDPError::ConnectionRefused { .. } => ErrorKind::ConnectionRefused,
+            ReUDPError::Closing => ErrorKind::BrokenPipe,
+            ReUDPError::DecodeError { .. } | ReUDPError::DecryptionFailed => ErrorKind::InvalidData,
+            ReUDPError::BufferTooSmall { .. } => ErrorKind::InvalidInput,
+            ReUDPError::WouldBlock | ReUDPError::QueueFull { .. } => ErrorKind::WouldBlock,
+            ReUDPError::NotConnected => ErrorKind::NotConnected,
+            ReUDPError::MessageTooLarge { .. } => ErrorKind::InvalidInput,
+            ReUDPError::MalformedPacket { .. } | ReUDPError::Truncated { .. } => ErrorKind::InvalidData,
+            ReUDPError::ClientNotFound { .. } => ErrorKind::NotFound,
+            ReUDPError::WrongMode { .. }
+            | ReUDPError::InvalidMessageType(_)
+            | ReUDPError::ChannelMismatch { .. }
+            | ReUDPError::EncodeError { .. } => ErrorKind::InvalidInput,
+            ReUDPError::PeerIo { ref source, .. } => source.kind(),
+        };
+        Error::new(kind, error)
+    }
+}
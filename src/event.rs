@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+
+use crate::quality::ConnectionQuality;
+use crate::socket::SocketOption;
+
+/// Notable changes in the state of a ReUDP instance, retrieved with `ReUDP::poll_event`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// The client switched to a new server address after `migrate_to` validated it.
+    Migrated { old: SocketAddr, new: SocketAddr },
+    /// The address passed to `migrate_to` never answered the path validation;
+    /// the client keeps using its current server.
+    MigrationFailed { addr: SocketAddr },
+    /// A socket option of the configuration couldn't be applied; the instance
+    /// runs with the OS default instead.
+    SocketOptionFailed { option: SocketOption, error: String },
+    /// The connection quality moved to a different level.
+    QualityChanged(ConnectionQuality),
+    /// A client resumed an earlier session with a valid session token (server
+    /// mode). `previous` is the address it had in that session.
+    SessionResumed { addr: SocketAddr, previous: SocketAddr },
+    /// The handshake got no answer over UDP, so the client switched to TCP as
+    /// its configuration asked (see `TcpFallback`).
+    FellBackToTcp,
+}
+
+/// Why a client stopped, passed to the callback set with `ReUDP::on_disconnect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisconnectReason {
+    /// The server went silent for longer than the liveness timeout.
+    ConnectionLost,
+    /// The server never answered within the liveness timeout.
+    NoResponseFromServer,
+    /// The SOCKS5 proxy closed the control connection, ending the UDP association.
+    ProxyClosed,
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+use std::io;
+use std::net::UdpSocket;
+
+/// Creates the UDP socket of a ReUDP instance, in place of binding one to the
+/// local address given to `ReUDP::with_config`.
+///
+/// Set with `ReUDPConfig::socket_factory`. The socket returned is used as is;
+/// the IP family and `bind_device` options of the configuration only apply
+/// when ReUDP binds the socket itself.
+pub trait SocketFactory: fmt::Debug + Send + Sync {
+    /// Creates a socket for the local address `addr`.
+    fn create(&self, addr: &str) -> io::Result<UdpSocket>;
+}
+
+/// Binds a new socket to the local address with `UdpSocket::bind`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultSocketFactory;
+
+impl SocketFactory for DefaultSocketFactory {
+    fn create(&self, addr: &str) -> io::Result<UdpSocket> {
+        UdpSocket::bind(addr)
+    }
+}
+
+/// Hands out a socket that is already bound, e.g. one inherited through
+/// systemd socket activation or launchd. The local address is ignored; each
+/// instance created gets a handle to the same socket.
+#[derive(Debug)]
+pub struct PreBoundSocketFactory(pub UdpSocket);
+
+impl SocketFactory for PreBoundSocketFactory {
+    fn create(&self, _addr: &str) -> io::Result<UdpSocket> {
+        self.0.try_clone()
+    }
+}
+
+/// Always fails with `AddrInUse`, for testing how socket creation errors are handled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FailingSocketFactory;
+
+impl SocketFactory for FailingSocketFactory {
+    fn create(&self, _addr: &str) -> io::Result<UdpSocket> {
+        Err(io::Error::new(io::ErrorKind::AddrInUse, "test"))
+    }
+}
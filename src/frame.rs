@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// Callback producing the data of each frame, or `None` to skip the frame.
+pub(crate) type FrameFn = Arc<dyn Fn() -> Option<Vec<u8>> + Send + Sync>;
+
+/// Fixed-rate sending set up with `set_frame_rate` and `set_frame_fn`. Shared
+/// between `ReUDP` and its heartbeat thread, which sends the frames.
+#[derive(Default)]
+pub(crate) struct FrameSync {
+    /// Time between two frames; `None` while frames are stopped
+    interval: Option<Duration>,
+    /// When the next frame is due
+    next_frame: Option<Instant>,
+    /// Callback set with `set_frame_fn`
+    pub(crate) frame_fn: Option<FrameFn>,
+    /// Whether frames require an acknowledgment
+    pub(crate) require_ack: bool,
+}
+
+impl FrameSync {
+    /// Starts sending a frame every `interval`, the first one an interval from
+    /// now, or stops if `interval` is `None`.
+    pub(crate) fn set_interval(&mut self, interval: Option<Duration>) {
+        self.interval = interval;
+        self.next_frame = interval.map(|interval| Instant::now() + interval);
+    }
+
+    /// Returns the callback and whether the frame requires an acknowledgment
+    /// if a frame is due at `now`, and schedules the next one.
+    ///
+    /// Frames keep a fixed rate rather than drifting with late ticks, but
+    /// frames missed by more than an interval are skipped, not sent in a burst.
+    pub(crate) fn take_due(&mut self, now: Instant) -> Option<(FrameFn, bool)> {
+        let (interval, next_frame) = (self.interval?, self.next_frame?);
+        if now < next_frame {
+            return None;
+        }
+        let following = next_frame + interval;
+        self.next_frame = Some(if following > now { following } else { now + interval });
+        self.frame_fn.clone().map(|frame_fn| (frame_fn, self.require_ack))
+    }
+
+    /// Returns how long until the next frame is due, if frames are running.
+    pub(crate) fn time_until_next(&self, now: Instant) -> Option<Duration> {
+        self.next_frame.map(|next_frame| next_frame.saturating_duration_since(now))
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+use crate::socket;
+
+/// A named set of clients messages can be sent to at once (server mode), e.g.
+/// the players of a lobby or the members of a chat room.
+///
+/// Groups are created and filled by the application through `ReUDP::group`; a
+/// client can be in any number of them. Clients leave every group when they
+/// disconnect or are evicted, and addresses that aren't connected clients are
+/// dropped from a group when a message is sent to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientGroup {
+    members: HashSet<SocketAddr>,
+}
+
+impl ClientGroup {
+    /// Adds a client to the group.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the client.
+    pub fn add(&mut self, addr: SocketAddr) {
+        self.members.insert(socket::canonical(addr));
+    }
+
+    /// Removes a client from the group.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the client.
+    pub fn remove(&mut self, addr: SocketAddr) {
+        self.members.remove(&socket::canonical(addr));
+    }
+
+    /// Returns whether a client is in the group.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the client.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the client is a member.
+    pub fn contains(&self, addr: SocketAddr) -> bool {
+        self.members.contains(&socket::canonical(addr))
+    }
+
+    /// Returns the number of clients in the group.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of members.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns whether the group has no members.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the group is empty.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Keeps only the members `f` returns `true` for.
+    pub(crate) fn retain<F: FnMut(&SocketAddr) -> bool>(&mut self, f: F) {
+        self.members.retain(f);
+    }
+
+    /// Returns the members' addresses.
+    pub(crate) fn members(&self) -> Vec<SocketAddr> {
+        self.members.iter().copied().collect()
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::ReUDPError;
+use crate::event::Event;
+use crate::reudp::ReUDP;
+use crate::stats::Statistics;
+use crate::time::Instant;
+
+/// How long `ReUDPHandle::recv_timeout` sleeps, without holding the lock,
+/// between two looks at the socket.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Cheaply clonable handle to a ReUDP instance, created by `ReUDP::into_handle`,
+/// so several threads can use the instance at once.
+///
+/// # Locking
+///
+/// The instance sits behind one lock, taken by every method of the handle for
+/// the duration of that call only. No method waits on the socket while holding
+/// it: receiving processes the datagrams already pending and returns, and
+/// `recv_timeout` releases the lock between its looks at the socket. So a
+/// thread sending is held up at most by one send or one batch of pending
+/// datagrams on another thread. `disconnect` is the exception, holding the
+/// lock while it drains.
+///
+/// The heartbeat thread never takes this lock. Retransmissions, heartbeats
+/// and liveness checks run under their own, narrower locks (unacknowledged
+/// packets, peers, clients, configuration), which each call also only holds
+/// briefly, so they go on whatever the application threads do.
+#[derive(Clone)]
+pub struct ReUDPHandle {
+    inner: Arc<Mutex<ReUDP>>,
+}
+
+impl ReUDPHandle {
+    pub(crate) fn new(reudp: ReUDP) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(reudp)),
+        }
+    }
+
+    /// Runs `f` with exclusive access to the instance, for anything the handle
+    /// has no method for. The lock is held until `f` returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function given the instance.
+    ///
+    /// # Returns
+    ///
+    /// * `R` - What `f` returned.
+    pub fn with<R, F: FnOnce(&mut ReUDP) -> R>(&self, f: F) -> R {
+        f(&mut self.inner.lock().unwrap())
+    }
+
+    /// Returns the instance if this is its last handle.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ReUDP, ReUDPHandle>` - The instance, or the handle back if
+    ///   other handles are still around.
+    pub fn try_into_inner(self) -> Result<ReUDP, ReUDPHandle> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => Ok(inner.into_inner().unwrap()),
+            Err(inner) => Err(Self { inner }),
+        }
+    }
+
+    /// Returns whether both handles refer to the same instance.
+    pub(crate) fn same_instance(&self, other: &ReUDPHandle) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Sends a message, like `ReUDP::send`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`, or an error.
+    pub fn send<D: AsRef<[u8]>>(&self, data: D, require_ack: bool) -> Result<(), ReUDPError> {
+        self.inner.lock().unwrap().send(data, require_ack)
+    }
+
+    /// Sends a message stamped with the current time, like `ReUDP::send_timestamped`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`, or an error.
+    pub fn send_timestamped<D: AsRef<[u8]>>(&self, data: D, require_ack: bool) -> Result<(), ReUDPError> {
+        self.inner.lock().unwrap().send_timestamped(data, require_ack)
+    }
+
+    /// Sends a message on an ordered channel, like `ReUDP::send_ordered_channel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - The channel to send on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn send_ordered_channel<D: AsRef<[u8]>>(
+        &self,
+        channel_id: u8,
+        data: D,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        self.inner.lock().unwrap().send_ordered_channel(channel_id, data, require_ack)
+    }
+
+    /// Sends a message on an unordered channel, like `ReUDP::send_unordered_channel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - The channel to send on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn send_unordered_channel<D: AsRef<[u8]>>(
+        &self,
+        channel_id: u8,
+        data: D,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        self.inner.lock().unwrap().send_unordered_channel(channel_id, data, require_ack)
+    }
+
+    /// Sends a message to a subset of the clients, like `ReUDP::send_to_group`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addrs` - Addresses of the clients to send to.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<HashMap<SocketAddr, Result<(), ReUDPError>>, ReUDPError>` - The
+    ///   outcome of the send for each address, or `Closing` after `disconnect`.
+    pub fn send_to_group<I: IntoIterator<Item = SocketAddr>, D: AsRef<[u8]>>(
+        &self,
+        addrs: I,
+        data: D,
+        require_ack: bool,
+    ) -> Result<HashMap<SocketAddr, Result<(), ReUDPError>>, ReUDPError> {
+        self.inner.lock().unwrap().send_to_group(addrs, data, require_ack)
+    }
+
+    /// Sends a message to the subscribers of a topic, like `ReUDP::publish`.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic to publish on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of subscribers the message was sent to, or an error.
+    pub fn publish<D: AsRef<[u8]>>(&self, topic: &str, data: D, require_ack: bool) -> Result<usize, ReUDPError> {
+        self.inner.lock().unwrap().publish(topic, data, require_ack)
+    }
+
+    /// Sends the messages of an open batch, like `ReUDP::flush`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of datagrams sent, or an error.
+    pub fn flush(&self) -> Result<usize, ReUDPError> {
+        self.inner.lock().unwrap().flush()
+    }
+
+    /// Receives the next message without waiting, like `ReUDP::recv` in
+    /// non-blocking mode, whatever the mode of the instance.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError>` - The address and data received, or an error.
+    pub fn recv(&self) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        self.inner.lock().unwrap().recv_pending()
+    }
+
+    /// Receives a message, waiting for up to `timeout` for one to arrive.
+    ///
+    /// The socket is looked at every millisecond; the lock is released in
+    /// between, so other threads can go on meanwhile.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for a message.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError>` - The received message
+    ///   and its sender, `None` if none arrived in time, or an error.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(received) = self.recv()? {
+                return Ok(Some(received));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Receives every message pending on the socket, like `ReUDP::recv_all`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<(SocketAddr, Vec<u8>)>, ReUDPError>` - The received messages, or an error.
+    pub fn recv_all(&self) -> Result<Vec<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        self.inner.lock().unwrap().recv_all()
+    }
+
+    /// Returns the next pending event, like `ReUDP::poll_event`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Event>` - The oldest event not polled yet, if any.
+    pub fn poll_event(&self) -> Option<Event> {
+        self.inner.lock().unwrap().poll_event()
+    }
+
+    /// Returns the traffic counters, like `ReUDP::stats`.
+    ///
+    /// # Returns
+    ///
+    /// * `Statistics` - A snapshot of the counters.
+    pub fn stats(&self) -> Statistics {
+        self.inner.lock().unwrap().stats()
+    }
+
+    /// Returns the local address of the socket, like `ReUDP::local_addr`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SocketAddr, ReUDPError>` - The local address, or an error.
+    pub fn local_addr(&self) -> Result<SocketAddr, ReUDPError> {
+        self.inner.lock().unwrap().local_addr()
+    }
+
+    /// Gracefully shuts the instance down, like `ReUDP::disconnect`. The lock
+    /// is held until draining ends.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of messages still unacknowledged
+    ///   when the drain timeout expired, or an error.
+    pub fn disconnect(&self) -> Result<usize, ReUDPError> {
+        self.inner.lock().unwrap().disconnect()
+    }
+}
@@ -0,0 +1,62 @@
+use std::net::SocketAddr;
+
+/// A step of the pipeline application payloads go through, added with
+/// `ReUDP::add_hook`, e.g. to compress, validate or route messages.
+///
+/// Outgoing payloads go through the hooks in the order they were added, and
+/// incoming ones in the reverse order, so each hook undoes its own processing
+/// on the other side. Both methods pass the payload on unchanged by default.
+pub trait Hook: Send + Sync {
+    /// Processes the payload of a received message, after decryption and
+    /// before it is acknowledged and delivered.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the sender.
+    /// * `payload` - The application data of the message.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<u8>>` - The payload to deliver, or `None` to drop the message.
+    fn on_recv(&self, addr: SocketAddr, payload: Vec<u8>) -> Option<Vec<u8>> {
+        let _ = addr;
+        Some(payload)
+    }
+
+    /// Processes the payload of a message about to be sent, before encryption.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The application data of the message.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<u8>>` - The payload to send, or `None` to drop the message.
+    fn on_send(&self, payload: Vec<u8>) -> Option<Vec<u8>> {
+        Some(payload)
+    }
+}
+
+/// A receive-only hook set with `ReUDP::set_recv_hook`.
+pub(crate) struct RecvHook<F>(pub(crate) F);
+
+impl<F> Hook for RecvHook<F>
+where
+    F: Fn(SocketAddr, Vec<u8>) -> Option<Vec<u8>> + Send + Sync,
+{
+    fn on_recv(&self, addr: SocketAddr, payload: Vec<u8>) -> Option<Vec<u8>> {
+        (self.0)(addr, payload)
+    }
+}
+
+/// A send-only hook set with `ReUDP::set_send_hook`.
+pub(crate) struct SendHook<F>(pub(crate) F);
+
+impl<F> Hook for SendHook<F>
+where
+    F: Fn(Vec<u8>) -> Option<Vec<u8>> + Send + Sync,
+{
+    fn on_send(&self, payload: Vec<u8>) -> Option<Vec<u8>> {
+        (self.0)(payload)
+    }
+}
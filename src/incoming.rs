@@ -0,0 +1,85 @@
+use std::io::ErrorKind;
+use std::iter::FusedIterator;
+use std::net::SocketAddr;
+
+use crate::error::ReUDPError;
+use crate::reudp::ReUDP;
+
+/// Iterator over the messages currently pending on a ReUDP instance, created
+/// by `ReUDP::incoming`.
+///
+/// Ends once the socket has nothing more to deliver, or after yielding an error.
+pub struct Incoming<'a> {
+    reudp: &'a mut ReUDP,
+    done: bool,
+}
+
+impl<'a> Incoming<'a> {
+    pub(crate) fn new(reudp: &'a mut ReUDP) -> Self {
+        Self { reudp, done: false }
+    }
+}
+
+impl Iterator for Incoming<'_> {
+    type Item = Result<(SocketAddr, Vec<u8>), ReUDPError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.reudp.recv_pending().transpose();
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+impl FusedIterator for Incoming<'_> {}
+
+/// Iterator over the messages currently pending on a ReUDP instance, created
+/// by `ReUDP::messages`.
+///
+/// Unlike `Incoming`, it reads on past errors about a single datagram. It ends
+/// once the socket has nothing more to deliver, or after yielding an error
+/// that the next read would only repeat, such as `ConnectionLost`.
+pub struct MessageIterator<'a> {
+    reudp: &'a mut ReUDP,
+    done: bool,
+}
+
+impl<'a> MessageIterator<'a> {
+    pub(crate) fn new(reudp: &'a mut ReUDP) -> Self {
+        Self { reudp, done: false }
+    }
+}
+
+impl Iterator for MessageIterator<'_> {
+    type Item = Result<(SocketAddr, Vec<u8>), ReUDPError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.reudp.recv_pending().transpose();
+        self.done = match &next {
+            None => true,
+            Some(Err(error)) => is_terminal(error),
+            Some(Ok(_)) => false,
+        };
+        next
+    }
+}
+
+impl FusedIterator for MessageIterator<'_> {}
+
+/// Whether `error` ends the connection or the socket, rather than concerning
+/// one datagram, so that reading on would only fail again.
+pub(crate) fn is_terminal(error: &ReUDPError) -> bool {
+    match error {
+        ReUDPError::ConnectionLost | ReUDPError::NoResponseFromServer => true,
+        ReUDPError::IoError(error) => !matches!(
+            error.kind(),
+            ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+        ),
+        _ => false,
+    }
+}
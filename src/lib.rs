@@ -1,9 +1,82 @@
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+compile_error!("building for wasm32 requires the `wasm` feature");
+
+#[macro_use]
+mod log;
+
+mod bandwidth;
+mod channel;
+mod clock;
+#[cfg(feature = "serde")]
+mod codec;
+mod config;
+#[cfg(feature = "crypto")]
+mod crypto;
+mod emulator;
+mod event;
+mod factory;
+mod frame;
+mod group;
+mod handle;
+mod hook;
+mod incoming;
+#[cfg(feature = "test-util")]
+mod memory;
 mod message;
 mod mode;
+mod peer;
+mod ping_history;
+mod probe;
+mod quality;
 mod reudp;
+mod session;
+mod snapshot;
+mod socket;
+mod socks5;
+mod split;
+mod state;
+mod stats;
+mod tcp;
+mod time;
+mod timeout_future;
+#[cfg(feature = "opentelemetry")]
+mod trace_context;
+mod transport;
+#[cfg(unix)]
+mod unix;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod wasm;
 mod error;
 
-pub use message::{Message, MessageType};
+pub use clock::ClockOffset;
+#[cfg(feature = "json")]
+pub use codec::JsonCodec;
+#[cfg(feature = "serde")]
+pub use codec::{Codec, CodecError, PostcardCodec};
+pub use config::{ConfigError, HeartbeatPolicy, MalformedPolicy, ReUDPConfig, TcpFallback};
+pub use emulator::{LinkPolicy, NetworkEmulator};
+pub use event::{DisconnectReason, Event};
+pub use factory::{DefaultSocketFactory, FailingSocketFactory, PreBoundSocketFactory, SocketFactory};
+pub use group::ClientGroup;
+pub use handle::ReUDPHandle;
+pub use hook::Hook;
+pub use incoming::{Incoming, MessageIterator};
+pub use log::LogLevel;
+#[cfg(feature = "test-util")]
+pub use memory::{MemoryNetwork, MemoryTransport};
+pub use message::{Message, MessageType, FIRST_CUSTOM_TYPE, TRACEPARENT_EXTENSION, TRACESTATE_EXTENSION};
 pub use mode::Mode;
-pub use error::ReUDPError;
+pub use probe::ProbeResult;
+pub use quality::ConnectionQuality;
+pub use error::{IoContext, ModeKind, ReUDPError};
 pub use reudp::ReUDP;
+pub use session::SessionToken;
+pub use snapshot::SequenceSnapshot;
+pub use socket::{RecvSource, SocketOption};
+pub use socks5::Socks5Config;
+pub use split::{RecvHalf, SendHalf};
+pub use stats::Statistics;
+pub use timeout_future::TimeoutFuture;
+pub use transport::{Transport, UdpTransport};
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use wasm::WasmTransport;
@@ -0,0 +1,90 @@
+//! Internal logging macros, and the logger applications can set per instance.
+//!
+//! With the `tracing` feature enabled the macros forward to the corresponding
+//! `tracing` macros; without it they expand to nothing, so call sites don't need
+//! `cfg` guards.
+
+use std::sync::{Arc, Mutex};
+
+/// Severity of a message passed to the logger set with `ReUDP::set_logger`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Something worth knowing while debugging, such as an unknown message type.
+    Debug,
+    /// Something went wrong, such as the connection being lost.
+    Warn,
+}
+
+/// Logger set with `ReUDP::set_logger`, shared with the heartbeat thread.
+pub(crate) type SharedLogger = Arc<Mutex<Option<Arc<dyn Fn(LogLevel, &str) + Send + Sync>>>>;
+
+/// Passes `message` to the logger, if one is set.
+pub(crate) fn emit(logger: &SharedLogger, level: LogLevel, message: &str) {
+    // Not called with the lock held, so the logger may take its time.
+    let logger = logger.lock().unwrap().clone();
+    if let Some(logger) = logger {
+        logger(level, message);
+    }
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! log_trace {
+    ($($arg:tt)+) => { tracing::trace!($($arg)+) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log_trace {
+    ($($arg:tt)+) => {};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! log_debug {
+    ($($arg:tt)+) => { tracing::debug!($($arg)+) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log_debug {
+    ($($arg:tt)+) => {};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! log_warn {
+    ($($arg:tt)+) => { tracing::warn!($($arg)+) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log_warn {
+    ($($arg:tt)+) => {};
+}
+
+/// Enters a trace-level span until the end of the enclosing block.
+///
+/// Called as `log_span!(name = ...)`, it only creates the span and binds it to
+/// `name`, for `log_enter!` to enter once it is set up.
+#[cfg(feature = "tracing")]
+macro_rules! log_span {
+    ($name:ident = $($arg:tt)+) => {
+        let $name = tracing::trace_span!($($arg)+);
+    };
+    ($($arg:tt)+) => {
+        let _span = tracing::trace_span!($($arg)+).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log_span {
+    ($($arg:tt)+) => {};
+}
+
+/// Enters the span bound by `log_span!(name = ...)` until the end of the enclosing block.
+#[cfg(feature = "tracing")]
+macro_rules! log_enter {
+    ($name:ident) => {
+        let _entered = $name.enter();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log_enter {
+    ($name:ident) => {};
+}
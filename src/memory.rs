@@ -0,0 +1,256 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::emulator::{Link, LinkPolicy};
+use crate::time::Instant;
+use crate::transport::Transport;
+
+/// Most datagrams waiting for an endpoint to receive them; more are dropped,
+/// as by a full socket buffer.
+const MAX_QUEUED: usize = 1024;
+/// First port handed out to endpoints bound to port 0.
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// An in-process network connecting `MemoryTransport` endpoints through
+/// queues, for tests that shouldn't depend on real sockets or the OS.
+///
+/// Every direction between two endpoints is a link impaired according to a
+/// `LinkPolicy`, perfect unless configured otherwise. Delays are measured in
+/// real time, or in time that only moves with `advance` for a network created
+/// with `with_manual_clock`. Cloning gives another handle to the same network.
+#[derive(Clone, Default)]
+pub struct MemoryNetwork {
+    hub: Arc<Mutex<Hub>>,
+}
+
+impl MemoryNetwork {
+    /// Creates an empty network whose links delay packets in real time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty network whose links delay packets until `advance`
+    /// has moved its clock far enough, so that tests control when they arrive.
+    pub fn with_manual_clock() -> Self {
+        let network = Self::default();
+        network.hub.lock().unwrap().manual_clock = Some(Instant::now());
+        network
+    }
+
+    /// Adds an endpoint to the network.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the endpoint. Port 0 picks an unused port.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<MemoryTransport>` - The endpoint, or an `AddrInUse` error if
+    ///   the address is taken.
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<MemoryTransport> {
+        let mut hub = self.hub.lock().unwrap();
+        let addr = match addr.port() {
+            0 => hub.free_addr(addr)?,
+            _ => addr,
+        };
+        if hub.inboxes.contains_key(&addr) {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is already bound", addr)));
+        }
+        hub.inboxes.insert(addr, VecDeque::new());
+        Ok(MemoryTransport {
+            network: self.clone(),
+            addr,
+        })
+    }
+
+    /// Sets the impairments of the packets sent from `from` to `to`.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Address of the sending endpoint.
+    /// * `to` - Address of the receiving endpoint.
+    /// * `policy` - Impairments for the packets sent from now on.
+    pub fn set_link(&self, from: SocketAddr, to: SocketAddr, policy: LinkPolicy) {
+        let mut hub = self.hub.lock().unwrap();
+        hub.link(from, to).set_policy(policy.clone());
+        hub.policies.insert((from, to), policy);
+    }
+
+    /// Sets the impairments of every link not configured with `set_link`.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Impairments for the packets sent from now on.
+    pub fn set_default_link(&self, policy: LinkPolicy) {
+        let mut hub = self.hub.lock().unwrap();
+        let hub = &mut *hub;
+        for (key, link) in hub.links.iter_mut() {
+            if !hub.policies.contains_key(key) {
+                link.set_policy(policy.clone());
+            }
+        }
+        hub.default_policy = policy;
+    }
+
+    /// Moves the clock of a network created with `with_manual_clock` forward,
+    /// delivering the packets whose delay has passed.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - How far to move the clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the network follows real time.
+    pub fn advance(&self, duration: Duration) {
+        let mut hub = self.hub.lock().unwrap();
+        let clock = hub
+            .manual_clock
+            .as_mut()
+            .expect("MemoryNetwork::advance needs a network created with with_manual_clock");
+        *clock += duration;
+        let now = *clock;
+        hub.deliver(now);
+    }
+}
+
+impl fmt::Debug for MemoryNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hub = self.hub.lock().unwrap();
+        f.debug_struct("MemoryNetwork")
+            .field("endpoints", &hub.inboxes.keys().collect::<Vec<_>>())
+            .field("manual_clock", &hub.manual_clock.is_some())
+            .finish()
+    }
+}
+
+/// State of a `MemoryNetwork`, shared by its handles and endpoints.
+#[derive(Default)]
+struct Hub {
+    /// Datagrams waiting to be received by each bound endpoint, with their sender
+    inboxes: HashMap<SocketAddr, VecDeque<(Vec<u8>, SocketAddr)>>,
+    /// Packets in flight, by sender and receiver
+    links: HashMap<(SocketAddr, SocketAddr), Link>,
+    /// Policies set with `set_link`, by sender and receiver
+    policies: HashMap<(SocketAddr, SocketAddr), LinkPolicy>,
+    /// Policy of the other links
+    default_policy: LinkPolicy,
+    /// Current time of a network with a manual clock
+    manual_clock: Option<Instant>,
+    /// Next port to try for an endpoint bound to port 0
+    next_port: u16,
+}
+
+impl Hub {
+    fn now(&self) -> Instant {
+        self.manual_clock.unwrap_or_else(Instant::now)
+    }
+
+    fn link(&mut self, from: SocketAddr, to: SocketAddr) -> &mut Link {
+        let policy = self.policies.get(&(from, to)).unwrap_or(&self.default_policy).clone();
+        self.links.entry((from, to)).or_insert_with(|| Link::new(policy))
+    }
+
+    /// Picks an unused port on the IP of `addr`.
+    fn free_addr(&mut self, addr: SocketAddr) -> io::Result<SocketAddr> {
+        for _ in FIRST_EPHEMERAL_PORT..=u16::MAX {
+            if self.next_port < FIRST_EPHEMERAL_PORT {
+                self.next_port = FIRST_EPHEMERAL_PORT;
+            }
+            let candidate = SocketAddr::new(addr.ip(), self.next_port);
+            self.next_port = self.next_port.wrapping_add(1);
+            if !self.inboxes.contains_key(&candidate) {
+                return Ok(candidate);
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::AddrInUse, "no free port left"))
+    }
+
+    /// Moves the packets whose delay has passed to the queue of their receiver,
+    /// dropping those for endpoints that aren't bound.
+    fn deliver(&mut self, now: Instant) {
+        for ((from, to), link) in self.links.iter_mut() {
+            for packet in link.due(now) {
+                if let Some(inbox) = self.inboxes.get_mut(to) {
+                    if inbox.len() < MAX_QUEUED {
+                        inbox.push_back((packet, *from));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An endpoint of a `MemoryNetwork`, to run ReUDP with `ReUDP::with_transport`
+/// without touching the OS. Dropping it unbinds its address.
+pub struct MemoryTransport {
+    network: MemoryNetwork,
+    addr: SocketAddr,
+}
+
+impl MemoryTransport {
+    /// Creates two endpoints on a new network with a real-time clock.
+    ///
+    /// # Returns
+    ///
+    /// * `(MemoryTransport, MemoryTransport)` - The endpoints; `network` gives
+    ///   access to the links between them.
+    pub fn pair() -> (MemoryTransport, MemoryTransport) {
+        let network = MemoryNetwork::new();
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+        (network.bind(addr).unwrap(), network.bind(addr).unwrap())
+    }
+
+    /// Returns the network the endpoint is part of.
+    ///
+    /// # Returns
+    ///
+    /// * `MemoryNetwork` - A handle to the network.
+    pub fn network(&self) -> MemoryNetwork {
+        self.network.clone()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        let mut hub = self.network.hub.lock().unwrap();
+        let now = hub.now();
+        hub.link(self.addr, addr).push(buf.to_vec(), now);
+        hub.deliver(now);
+        Ok(buf.len())
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let mut hub = self.network.hub.lock().unwrap();
+        let now = hub.now();
+        hub.deliver(now);
+        match hub.inboxes.get_mut(&self.addr).and_then(VecDeque::pop_front) {
+            Some((packet, from)) => {
+                let len = packet.len().min(buf.len());
+                buf[..len].copy_from_slice(&packet[..len]);
+                Ok((len, from))
+            }
+            None => Err(io::Error::from(io::ErrorKind::WouldBlock)),
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+}
+
+impl fmt::Debug for MemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryTransport").field("addr", &self.addr).finish()
+    }
+}
+
+impl Drop for MemoryTransport {
+    fn drop(&mut self) {
+        self.network.hub.lock().unwrap().inboxes.remove(&self.addr);
+    }
+}
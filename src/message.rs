@@ -1,18 +1,147 @@
-const HEADER_SIZE: usize = 9; // 8 bytes for sequence number, 1 byte for message type
+use std::fmt;
 
-#[derive(Debug, PartialEq, Clone)]
+use crate::error::ReUDPError;
+
+pub(crate) const HEADER_SIZE: usize = 11; // 8 bytes for sequence number, 1 byte for message type, 2 bytes for payload length
+/// High bit of the message type byte, set when extensions follow the header.
+const EXTENSIONS_FLAG: u8 = 0x80;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum MessageType {
     Data,
     Ack,
     Heartbeat,
+    HeartbeatAck,
+    Sleep,
+    Connect,
+    Accept,
+    ConnectDeny,
+    Disconnect,
+    SessionUnknown,
+    ResetAck,
+    Reset,
+    TimestampedData,
+    PathChallenge,
+    PathResponse,
+    Probe,
+    ProbeReply,
+    ChannelData,
+    ChannelAck,
+    Batch,
+    TypedData,
+    EncryptedData,
+    Rollback,
+    Nack,
+    GroupData,
+    GroupAck,
+    /// Application-defined type, sent with `ReUDP::send_with_type`. The code is
+    /// between `FIRST_CUSTOM_TYPE` and 127.
+    Custom(u8),
     Unknown(u8),
 }
 
-#[derive(Debug, Clone)]
+/// Lowest type code available to applications; the codes below are reserved
+/// for the protocol.
+pub const FIRST_CUSTOM_TYPE: u8 = 64;
+
+/// Extension holding the W3C `traceparent` of the span a message was sent in,
+/// added with the `opentelemetry` feature.
+pub const TRACEPARENT_EXTENSION: u8 = 1;
+
+/// Extension holding the W3C `tracestate` going with a `TRACEPARENT_EXTENSION`.
+pub const TRACESTATE_EXTENSION: u8 = 2;
+
+impl MessageType {
+    /// Returns the code of the type on the wire, without the extensions flag.
+    pub(crate) fn code(&self) -> u8 {
+        match self {
+            MessageType::Data => 0,
+            MessageType::Ack => 1,
+            MessageType::Heartbeat => 2,
+            MessageType::HeartbeatAck => 3,
+            MessageType::Sleep => 4,
+            MessageType::Connect => 5,
+            MessageType::Accept => 6,
+            MessageType::ConnectDeny => 7,
+            MessageType::Disconnect => 8,
+            MessageType::SessionUnknown => 9,
+            MessageType::PathChallenge => 10,
+            MessageType::PathResponse => 11,
+            MessageType::ResetAck => 16,
+            MessageType::Reset => 17,
+            MessageType::TimestampedData => 18,
+            MessageType::Probe => 19,
+            MessageType::ProbeReply => 20,
+            MessageType::ChannelData => 21,
+            MessageType::ChannelAck => 22,
+            MessageType::Batch => 23,
+            MessageType::TypedData => 24,
+            MessageType::EncryptedData => 25,
+            MessageType::Rollback => 26,
+            MessageType::Nack => 27,
+            MessageType::GroupData => 28,
+            MessageType::GroupAck => 29,
+            MessageType::Custom(t) => t & !EXTENSIONS_FLAG,
+            MessageType::Unknown(t) => t & !EXTENSIONS_FLAG,
+        }
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MessageType::Data => "Data",
+            MessageType::Ack => "Ack",
+            MessageType::Heartbeat => "Heartbeat",
+            MessageType::HeartbeatAck => "HeartbeatAck",
+            MessageType::Sleep => "Sleep",
+            MessageType::Connect => "Connect",
+            MessageType::Accept => "Accept",
+            MessageType::ConnectDeny => "ConnectDeny",
+            MessageType::Disconnect => "Disconnect",
+            MessageType::SessionUnknown => "SessionUnknown",
+            MessageType::ResetAck => "ResetAck",
+            MessageType::Reset => "Reset",
+            MessageType::TimestampedData => "TimestampedData",
+            MessageType::PathChallenge => "PathChallenge",
+            MessageType::PathResponse => "PathResponse",
+            MessageType::Probe => "Probe",
+            MessageType::ProbeReply => "ProbeReply",
+            MessageType::ChannelData => "ChannelData",
+            MessageType::ChannelAck => "ChannelAck",
+            MessageType::Batch => "Batch",
+            MessageType::TypedData => "TypedData",
+            MessageType::EncryptedData => "EncryptedData",
+            MessageType::Rollback => "Rollback",
+            MessageType::Nack => "Nack",
+            MessageType::GroupData => "GroupData",
+            MessageType::GroupAck => "GroupAck",
+            MessageType::Custom(t) => return write!(f, "Custom({})", t),
+            MessageType::Unknown(t) => return write!(f, "Unknown({})", t),
+        };
+        f.write_str(name)
+    }
+}
+
+/// A message as sent on the wire.
+///
+/// The fixed header holds the sequence number, the message type and the
+/// payload length. Optional header fields travel as extensions: when there are
+/// any, the high bit of the type byte is set and the header is followed by
+/// their count (one byte) and each extension as type, length (one byte each)
+/// and value, before the payload.
+///
+/// Messages are ordered by sequence number first, so sorting them or keeping
+/// them in a `BTreeSet` puts them in sequence order; messages with the same
+/// sequence number are told apart by their type, payload and extensions, so
+/// the order stays consistent with equality.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Message {
     pub sequence: u64,
     pub message_type: MessageType,
     pub payload: Vec<u8>,
+    /// Optional header fields as `(type, value)`, in order
+    pub extensions: Vec<(u8, Vec<u8>)>,
 }
 
 impl Message {
@@ -21,38 +150,212 @@ impl Message {
             sequence,
             message_type,
             payload,
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Adds an extension to the message.
+    pub fn with_extension(mut self, extension_type: u8, value: Vec<u8>) -> Self {
+        self.extensions.push((extension_type, value));
+        self
+    }
+
+    /// Returns the value of the first extension of `extension_type`, if any.
+    pub fn extension(&self, extension_type: u8) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .find(|(t, _)| *t == extension_type)
+            .map(|(_, value)| &value[..])
+    }
+
+    /// Returns the number of bytes a message of `message_type` takes on top of
+    /// the application data: the header, plus the timestamp of `TimestampedData`,
+    /// the channel header of `ChannelData`, or the nonce and authentication tag
+    /// of `EncryptedData`.
+    pub fn overhead_for(message_type: &MessageType) -> usize {
+        HEADER_SIZE + Self::prefix_len(message_type)
+    }
+
+    /// Returns the number of payload bytes a message of `message_type` uses
+    /// before the application data.
+    pub(crate) fn prefix_len(message_type: &MessageType) -> usize {
+        match message_type {
+            MessageType::TimestampedData => 8,
+            MessageType::ChannelData => 2,
+            // 12-byte nonce and 16-byte tag
+            MessageType::EncryptedData => 28,
+            _ => 0,
         }
     }
 
+    /// Returns the number of bytes `to_bytes` produces.
+    pub fn encoded_len(&self) -> usize {
+        HEADER_SIZE + extensions_len(&self.extensions) + self.payload.len()
+    }
+
+    /// Serializes the message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload is longer than `u16::MAX` bytes, which can't be
+    /// encoded in the header (and wouldn't fit in a UDP datagram anyway), or if
+    /// there are more than 255 extensions or one is longer than 255 bytes.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.payload.len());
-        bytes.extend_from_slice(&self.sequence.to_be_bytes());
-        bytes.push(match self.message_type {
-            MessageType::Data => 0,
-            MessageType::Ack => 1,
-            MessageType::Heartbeat => 2,
-            MessageType::Unknown(t) => t,
-        });
-        bytes.extend_from_slice(&self.payload);
-        bytes
+        encode_with_extensions(self.sequence, self.message_type.clone(), &self.extensions, &[&self.payload])
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Self {
+    /// Parses a message from the start of `bytes`. Bytes past the length encoded
+    /// in the header are ignored; `encoded_len` tells where the next message starts.
+    ///
+    /// Fails with `MalformedPacket` if `bytes` is shorter than the header, than the
+    /// extensions or than the payload length the header announces.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReUDPError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(malformed(bytes, format!(
+                "{} bytes is shorter than the {}-byte header",
+                bytes.len(),
+                HEADER_SIZE
+            )));
+        }
+        let payload_len = u16::from_be_bytes(bytes[9..11].try_into().unwrap()) as usize;
+        let mut offset = HEADER_SIZE;
+        let mut extensions = Vec::new();
+        if bytes[8] & EXTENSIONS_FLAG != 0 {
+            let count = *bytes
+                .get(offset)
+                .ok_or_else(|| malformed(bytes, "extension count missing".to_string()))?;
+            offset += 1;
+            for _ in 0..count {
+                let (Some(&extension_type), Some(&len)) = (bytes.get(offset), bytes.get(offset + 1)) else {
+                    return Err(malformed(bytes, "extension header truncated".to_string()));
+                };
+                let value = bytes
+                    .get(offset + 2..offset + 2 + len as usize)
+                    .ok_or_else(|| malformed(bytes, format!("extension {} truncated", extension_type)))?;
+                extensions.push((extension_type, value.to_vec()));
+                offset += 2 + len as usize;
+            }
+        }
+        if bytes.len() < offset + payload_len {
+            return Err(malformed(bytes, format!(
+                "header announces a {}-byte payload but only {} bytes follow",
+                payload_len,
+                bytes.len() - offset
+            )));
+        }
         let sequence = u64::from_be_bytes(bytes[..8].try_into().unwrap());
-        let message_type = match bytes[8] {
+        let message_type = match bytes[8] & !EXTENSIONS_FLAG {
             0 => MessageType::Data,
             1 => MessageType::Ack,
             2 => MessageType::Heartbeat,
-            t => {
-                eprintln!("Unknown message type: {}", t);
-                MessageType::Unknown(t)
-            }
+            3 => MessageType::HeartbeatAck,
+            4 => MessageType::Sleep,
+            5 => MessageType::Connect,
+            6 => MessageType::Accept,
+            7 => MessageType::ConnectDeny,
+            8 => MessageType::Disconnect,
+            9 => MessageType::SessionUnknown,
+            10 => MessageType::PathChallenge,
+            11 => MessageType::PathResponse,
+            16 => MessageType::ResetAck,
+            17 => MessageType::Reset,
+            18 => MessageType::TimestampedData,
+            19 => MessageType::Probe,
+            20 => MessageType::ProbeReply,
+            21 => MessageType::ChannelData,
+            22 => MessageType::ChannelAck,
+            23 => MessageType::Batch,
+            24 => MessageType::TypedData,
+            25 => MessageType::EncryptedData,
+            26 => MessageType::Rollback,
+            27 => MessageType::Nack,
+            28 => MessageType::GroupData,
+            29 => MessageType::GroupAck,
+            t if t >= FIRST_CUSTOM_TYPE => MessageType::Custom(t),
+            t => MessageType::Unknown(t),
         };
-        let payload = bytes[9..].to_vec();
-        Self {
+        let payload = bytes[offset..offset + payload_len].to_vec();
+        Ok(Self {
             sequence,
             message_type,
             payload,
+            extensions,
+        })
+    }
+}
+
+/// Serializes a message without extensions whose payload is `parts` put end
+/// to end, sparing the copy of the payload into a `Message` first.
+///
+/// # Panics
+///
+/// Panics if the payload is longer than `u16::MAX` bytes, as `to_bytes` does.
+pub(crate) fn encode(sequence: u64, message_type: MessageType, parts: &[&[u8]]) -> Vec<u8> {
+    encode_with_extensions(sequence, message_type, &[], parts)
+}
+
+/// Serializes a message like `encode`, with `extensions` after the header.
+///
+/// # Panics
+///
+/// Panics in the cases `to_bytes` does.
+pub(crate) fn encode_with_extensions(
+    sequence: u64,
+    message_type: MessageType,
+    extensions: &[(u8, Vec<u8>)],
+    parts: &[&[u8]],
+) -> Vec<u8> {
+    let len: usize = parts.iter().map(|part| part.len()).sum();
+    let payload_len = u16::try_from(len).expect("payload too long to encode");
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + extensions_len(extensions) + len);
+    bytes.extend_from_slice(&sequence.to_be_bytes());
+    if extensions.is_empty() {
+        bytes.push(message_type.code());
+        bytes.extend_from_slice(&payload_len.to_be_bytes());
+    } else {
+        bytes.push(message_type.code() | EXTENSIONS_FLAG);
+        bytes.extend_from_slice(&payload_len.to_be_bytes());
+        bytes.push(u8::try_from(extensions.len()).expect("too many extensions to encode"));
+        for (extension_type, value) in extensions {
+            bytes.push(*extension_type);
+            bytes.push(u8::try_from(value.len()).expect("extension too long to encode"));
+            bytes.extend_from_slice(value);
         }
     }
+    for part in parts {
+        bytes.extend_from_slice(part);
+    }
+    bytes
+}
+
+/// Serializes a message without extensions into an array of `LEN` bytes,
+/// sparing the allocation for the messages sent the most, such as
+/// heartbeats and acknowledgments, whose size is known in advance.
+///
+/// # Panics
+///
+/// Panics if `LEN` isn't `HEADER_SIZE` plus the length of `payload`.
+pub(crate) fn encode_array<const LEN: usize>(sequence: u64, message_type: MessageType, payload: &[u8]) -> [u8; LEN] {
+    assert_eq!(LEN, HEADER_SIZE + payload.len(), "wrong array length for the payload");
+    let mut bytes = [0; LEN];
+    bytes[..8].copy_from_slice(&sequence.to_be_bytes());
+    bytes[8] = message_type.code();
+    bytes[9..HEADER_SIZE].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+    bytes[HEADER_SIZE..].copy_from_slice(payload);
+    bytes
+}
+
+/// Returns the number of bytes `extensions` take, count included.
+pub(crate) fn extensions_len(extensions: &[(u8, Vec<u8>)]) -> usize {
+    if extensions.is_empty() {
+        return 0;
+    }
+    1 + extensions.iter().map(|(_, value)| 2 + value.len()).sum::<usize>()
+}
+
+fn malformed(bytes: &[u8], reason: String) -> ReUDPError {
+    ReUDPError::MalformedPacket {
+        len: bytes.len(),
+        reason,
+    }
 }
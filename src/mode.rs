@@ -1,7 +1,17 @@
+use std::fmt;
 use std::net::SocketAddr;
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Mode {
     Server,
     Client(SocketAddr),
 }
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Server => f.write_str("Server"),
+            Mode::Client(addr) => write!(f, "Client of {}", addr),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::clock::ClockOffsetEstimator;
+use crate::mode::Mode;
+use crate::quality::LossEstimator;
+use crate::time::Instant;
+
+/// State kept for each remote peer: the server in client mode, or each client in
+/// server mode. Shared between `ReUDP` and its heartbeat thread.
+#[derive(Debug, Clone)]
+pub(crate) struct Peer {
+    /// Timestamp the peer started being tracked
+    pub(crate) created: Instant,
+    /// Timestamp of the last packet received from the peer
+    pub(crate) last_heard: Option<Instant>,
+    /// Time the peer announced it will wake up, if it is asleep
+    pub(crate) sleeping_until: Option<Instant>,
+    /// Clock offset estimate, fed by heartbeat round-trips
+    pub(crate) clock: ClockOffsetEstimator,
+    /// Identifier of the last sequence reset requested by the peer
+    pub(crate) last_reset_id: Option<u64>,
+    /// Loss rate estimate, fed by the peer's heartbeats
+    pub(crate) loss: LossEstimator,
+    /// Sequence number of the last of our heartbeats the peer answered
+    pub(crate) last_heartbeat_answered: Option<u64>,
+}
+
+impl Peer {
+    pub(crate) fn new() -> Self {
+        Self {
+            created: Instant::now(),
+            last_heard: None,
+            sleeping_until: None,
+            clock: ClockOffsetEstimator::default(),
+            last_reset_id: None,
+            loss: LossEstimator::default(),
+            last_heartbeat_answered: None,
+        }
+    }
+
+    /// Returns whether the peer is asleep at `now`.
+    pub(crate) fn is_sleeping(&self, now: Instant) -> bool {
+        self.sleeping_until.is_some_and(|wake_time| wake_time > now)
+    }
+
+    /// Returns whether the peer has been silent for longer than `timeout` at `now`.
+    ///
+    /// A sleeping peer is never considered lost; once its announced sleep is over
+    /// the timeout counts from its wake-up time.
+    pub(crate) fn is_lost(&self, now: Instant, timeout: Duration) -> bool {
+        if self.is_sleeping(now) {
+            return false;
+        }
+        let last_heard = self.last_heard.unwrap_or(self.created);
+        let quiet_since = self
+            .sleeping_until
+            .map_or(last_heard, |wake_time| wake_time.max(last_heard));
+        now.duration_since(quiet_since) > timeout
+    }
+}
+
+/// Returns the peers that should currently receive traffic: the server in client
+/// mode, or the connected clients in server mode, minus any peer that is asleep.
+pub(crate) fn awake_peers(
+    mode: &Mode,
+    clients: &Mutex<HashSet<SocketAddr>>,
+    peers: &Mutex<HashMap<SocketAddr, Peer>>,
+) -> Vec<SocketAddr> {
+    let targets = match mode {
+        Mode::Client(remote_addr) => vec![*remote_addr],
+        Mode::Server => clients.lock().unwrap().iter().copied().collect(),
+    };
+    let peers = peers.lock().unwrap();
+    let now = Instant::now();
+    targets
+        .into_iter()
+        .filter(|target| !peers.get(target).is_some_and(|peer| peer.is_sleeping(now)))
+        .collect()
+}
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The most recent heartbeat RTT samples, oldest first.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PingHistory {
+    samples: VecDeque<Duration>,
+}
+
+impl PingHistory {
+    /// Adds a sample, forgetting the oldest ones beyond `capacity`.
+    pub(crate) fn push(&mut self, rtt: Duration, capacity: usize) {
+        self.samples.push_back(rtt);
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the mean absolute deviation of the samples from their mean.
+    pub(crate) fn jitter(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let count = self.samples.len() as f64;
+        let mean = self.samples.iter().map(Duration::as_secs_f64).sum::<f64>() / count;
+        let deviation = self
+            .samples
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean).abs())
+            .sum::<f64>()
+            / count;
+        Some(Duration::from_secs_f64(deviation))
+    }
+
+    pub(crate) fn min(&self) -> Option<Duration> {
+        self.samples.iter().min().copied()
+    }
+
+    pub(crate) fn max(&self) -> Option<Duration> {
+        self.samples.iter().max().copied()
+    }
+
+    /// Returns the smallest sample that at least `p` percent of the samples
+    /// don't exceed (nearest rank), or `None` if `p` isn't between 0 and 100.
+    pub(crate) fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.samples.is_empty() || !(0.0..=100.0).contains(&p) {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+}
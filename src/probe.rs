@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// Weight given to each new RTT variation in the smoothed jitter, as in RFC 3550.
+const JITTER_SMOOTHING: f64 = 1.0 / 16.0;
+
+/// An answered path quality probe.
+///
+/// Timestamps are wall-clock microseconds since the UNIX epoch. The one-way
+/// delays mix both clocks and are only meaningful if the clocks are
+/// synchronized; `ReUDP::clock_offset` tells how far apart they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeResult {
+    addr: SocketAddr,
+    sequence: u64,
+    rtt: Duration,
+    jitter: Duration,
+    sent_at: u64,
+    remote_received_at: u64,
+    received_at: u64,
+}
+
+impl ProbeResult {
+    /// Returns the address of the peer that answered.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the number of the probe within its run, starting at 0.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the round-trip time of the probe.
+    pub fn rtt(&self) -> Duration {
+        self.rtt
+    }
+
+    /// Returns the smoothed variation of the round-trip time to the peer,
+    /// including this probe.
+    pub fn jitter(&self) -> Duration {
+        self.jitter
+    }
+
+    /// Returns when the probe was sent, on the local clock.
+    pub fn sent_at_micros(&self) -> u64 {
+        self.sent_at
+    }
+
+    /// Returns when the peer received the probe, on the peer's clock.
+    pub fn remote_received_at_micros(&self) -> u64 {
+        self.remote_received_at
+    }
+
+    /// Returns when the reply was received, on the local clock.
+    pub fn received_at_micros(&self) -> u64 {
+        self.received_at
+    }
+
+    /// Returns the delay from here to the peer, in microseconds.
+    pub fn forward_delay_micros(&self) -> i64 {
+        self.remote_received_at as i64 - self.sent_at as i64
+    }
+
+    /// Returns the delay from the peer back here, in microseconds.
+    pub fn return_delay_micros(&self) -> i64 {
+        self.received_at as i64 - self.remote_received_at as i64
+    }
+
+    /// Returns how much longer the forward path is than the return path, in microseconds.
+    pub fn asymmetry_micros(&self) -> i64 {
+        self.forward_delay_micros() - self.return_delay_micros()
+    }
+}
+
+/// Sends a run of path quality probes at a fixed interval and collects the replies.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PathProber {
+    /// Identifier of the current run, so replies to an earlier one are ignored
+    id: Option<u64>,
+    interval: Duration,
+    /// Probes left to send in the current run
+    remaining: u32,
+    next_sequence: u64,
+    next_at: Option<Instant>,
+    /// Last RTT and smoothed jitter (in seconds) per peer
+    jitter: HashMap<SocketAddr, (Duration, f64)>,
+    results: Vec<ProbeResult>,
+}
+
+impl PathProber {
+    /// Starts a run of `count` probes, forgetting the results of any earlier one.
+    pub(crate) fn start(&mut self, interval: Duration, count: u32) {
+        *self = Self {
+            id: Some(rand::random()),
+            interval,
+            remaining: count,
+            next_at: Some(Instant::now()),
+            ..Self::default()
+        };
+    }
+
+    /// Returns the run identifier and the sequence number of the next probe if
+    /// it is due, counting it as sent.
+    pub(crate) fn due(&mut self, now: Instant) -> Option<(u64, u64)> {
+        let id = self.id?;
+        if self.remaining == 0 || self.next_at.is_some_and(|next_at| now < next_at) {
+            return None;
+        }
+        self.remaining -= 1;
+        self.next_at = Some(now + self.interval);
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        Some((id, sequence))
+    }
+
+    /// Records the reply from `addr` to probe `sequence` of run `id`, received
+    /// at `received_at`.
+    pub(crate) fn add_reply(
+        &mut self,
+        addr: SocketAddr,
+        id: u64,
+        sequence: u64,
+        sent_at: u64,
+        remote_received_at: u64,
+        received_at: u64,
+    ) {
+        if self.id != Some(id) {
+            return;
+        }
+        if self
+            .results
+            .iter()
+            .any(|result| result.addr == addr && result.sequence == sequence)
+        {
+            return;
+        }
+        let rtt = Duration::from_micros(received_at.saturating_sub(sent_at));
+        let (last_rtt, jitter) = self.jitter.entry(addr).or_insert((rtt, 0.0));
+        let variation = rtt.abs_diff(*last_rtt).as_secs_f64();
+        *jitter += JITTER_SMOOTHING * (variation - *jitter);
+        *last_rtt = rtt;
+        self.results.push(ProbeResult {
+            addr,
+            sequence,
+            rtt,
+            jitter: Duration::from_secs_f64(*jitter),
+            sent_at,
+            remote_received_at,
+            received_at,
+        });
+    }
+
+    pub(crate) fn results(&self) -> &[ProbeResult] {
+        &self.results
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Weight given to each heartbeat, received or lost, in the smoothed loss rate.
+const LOSS_SMOOTHING: f64 = 0.1;
+/// Gaps longer than this count as this many losses; the smoothed rate is
+/// saturated well before.
+const MAX_COUNTED_GAP: u64 = 100;
+
+/// Upper bounds (exclusive) on RTT and loss rate for each level, best first.
+const THRESHOLDS: [(ConnectionQuality, Duration, f64); 4] = [
+    (ConnectionQuality::Excellent, Duration::from_millis(30), 0.01),
+    (ConnectionQuality::Good, Duration::from_millis(80), 0.05),
+    (ConnectionQuality::Fair, Duration::from_millis(150), 0.10),
+    (ConnectionQuality::Poor, Duration::from_millis(300), 0.20),
+];
+
+/// Human-meaningful classification of a connection, from its smoothed RTT and
+/// packet loss rate. Levels compare from worst (`Critical`) to best (`Excellent`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConnectionQuality {
+    /// Anything worse than `Poor`.
+    Critical,
+    /// RTT under 300 ms and loss under 20%.
+    Poor,
+    /// RTT under 150 ms and loss under 10%.
+    Fair,
+    /// RTT under 80 ms and loss under 5%.
+    Good,
+    /// RTT under 30 ms and loss under 1%.
+    Excellent,
+}
+
+impl ConnectionQuality {
+    /// Classifies a connection. An unknown RTT is judged on the loss rate alone.
+    pub(crate) fn classify(srtt: Option<Duration>, loss_rate: f64) -> Self {
+        THRESHOLDS
+            .iter()
+            .find(|(_, max_rtt, max_loss)| srtt.is_none_or(|srtt| srtt < *max_rtt) && loss_rate < *max_loss)
+            .map_or(ConnectionQuality::Critical, |(quality, _, _)| *quality)
+    }
+
+    /// Returns a numeric score from 0 (`Critical`) to 100 (`Excellent`).
+    pub fn score(&self) -> u8 {
+        match self {
+            ConnectionQuality::Critical => 0,
+            ConnectionQuality::Poor => 25,
+            ConnectionQuality::Fair => 50,
+            ConnectionQuality::Good => 75,
+            ConnectionQuality::Excellent => 100,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConnectionQuality::Critical => "Critical",
+            ConnectionQuality::Poor => "Poor",
+            ConnectionQuality::Fair => "Fair",
+            ConnectionQuality::Good => "Good",
+            ConnectionQuality::Excellent => "Excellent",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Estimates the loss rate from a peer from gaps in the sequence numbers of its
+/// heartbeats.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LossEstimator {
+    /// Sequence number of the last heartbeat received
+    last_sequence: Option<u64>,
+    /// Smoothed fraction of heartbeats lost
+    rate: f64,
+}
+
+impl LossEstimator {
+    /// Records the arrival of the heartbeat numbered `sequence`.
+    pub(crate) fn on_heartbeat(&mut self, sequence: u64) {
+        if let Some(last_sequence) = self.last_sequence {
+            if sequence <= last_sequence {
+                // Duplicated or reordered: already accounted for as lost.
+                return;
+            }
+            for _ in 0..(sequence - last_sequence - 1).min(MAX_COUNTED_GAP) {
+                self.rate += LOSS_SMOOTHING * (1.0 - self.rate);
+            }
+            self.rate -= LOSS_SMOOTHING * self.rate;
+        }
+        self.last_sequence = Some(sequence);
+    }
+
+    /// Returns the smoothed loss rate, between 0.0 and 1.0.
+    pub(crate) fn rate(&self) -> f64 {
+        self.rate
+    }
+}
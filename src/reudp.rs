@@ -1,49 +1,256 @@
-use std::collections::{HashMap, HashSet};
-use std::net::{UdpSocket, SocketAddr};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::{Duration, Instant};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::error::ReUDPError;
-use crate::message::{Message, MessageType};
+use crate::bandwidth::BandwidthEstimator;
+use crate::channel::Channel;
+use crate::clock::{self, ClockOffset};
+#[cfg(feature = "serde")]
+use crate::codec::{Codec, PostcardCodec};
+#[cfg(feature = "crypto")]
+use crate::crypto::{self, SharedCipher};
+use crate::config::{ConfigError, MalformedPolicy, ReUDPConfig, SharedConfig, TcpFallback, RECOMMENDED_BUFFER_SIZE};
+use crate::error::{IoContext, ModeKind, ReUDPError};
+use crate::event::{DisconnectReason, Event};
+use crate::frame::FrameSync;
+use crate::group::ClientGroup;
+use crate::handle::ReUDPHandle;
+use crate::hook::{Hook, RecvHook, SendHook};
+use crate::incoming::{self, Incoming, MessageIterator};
+use crate::log::{self, LogLevel, SharedLogger};
+use crate::message::{self, Message, MessageType, FIRST_CUSTOM_TYPE, HEADER_SIZE};
+#[cfg(feature = "opentelemetry")]
+use crate::message::{TRACEPARENT_EXTENSION, TRACESTATE_EXTENSION};
 use crate::mode::Mode;
+use crate::peer::{awake_peers, Peer};
+use crate::ping_history::PingHistory;
+use crate::probe::{PathProber, ProbeResult};
+use crate::quality::ConnectionQuality;
+use crate::session::{SessionToken, TokenCache};
+use crate::snapshot::SequenceSnapshot;
+use crate::socket::{self, MappedSocket, RecvSource, SocketOption};
+use crate::socks5::Socks5Relay;
+#[cfg(unix)]
+use crate::unix::UnixSocket;
+use crate::split::{self, RecvHalf, SendHalf};
+use crate::state::PersistedState;
+use crate::stats::Statistics;
+use crate::tcp::TcpTransport;
+use crate::time::{Instant, SystemTime, UNIX_EPOCH};
+use crate::timeout_future::TimeoutFuture;
+#[cfg(feature = "opentelemetry")]
+use crate::trace_context;
+use crate::transport::{CustomSocket, Transport};
+
+/// Weight of a new RTT sample in the smoothed RTT (as in RFC 6298).
+const RTT_SMOOTHING: f64 = 0.125;
+/// Bounds on how long the heartbeat thread sleeps between two iterations.
+const MIN_TICK: Duration = Duration::from_millis(10);
+const MAX_TICK: Duration = Duration::from_secs(1);
+/// Data from an address we have no session with is only plausible for the first
+/// few sequence numbers; anything beyond this means the sender's session is stale.
+const SESSION_WINDOW: u64 = 1024;
+/// Out-of-order messages buffered before the sender is told which ones are
+/// missing with a `Nack`.
+const NACK_THRESHOLD: usize = 8;
+/// Sequence numbers a `Nack` covers from its base, one bit each.
+const NACK_WINDOW: u64 = 128;
+
+/// Packets sent to a single client, keyed by client address and group sequence number.
+type GroupPackets = HashMap<(SocketAddr, u64), Vec<u8>>;
+/// Packets sent on a channel, keyed by channel and sequence number within it.
+type ChannelPackets = HashMap<(u8, u64), Vec<u8>>;
+/// Sends queued with `schedule_send`, as `(send_at, schedule_id, data, require_ack)`,
+/// earliest first.
+type ScheduledSends = BinaryHeap<Reverse<(Instant, u64, Vec<u8>, bool)>>;
+/// Callback told about gaps in the received sequence numbers, as `(expected, received)`.
+type SequenceGapCallback = Arc<dyn Fn(u64, u64) + Send + Sync>;
+/// Handler given the datagrams that aren't ReUDP messages, with their sender.
+type RawHandler = Arc<dyn Fn(SocketAddr, &[u8]) + Send + Sync>;
+/// Callback set with `ReUDP::on_disconnect`, shared with the heartbeat thread.
+type DisconnectCallback = Arc<Mutex<Option<Arc<dyn Fn(DisconnectReason) + Send + Sync>>>>;
+/// Callback set with `ReUDP::on_client_connect`.
+type ClientConnectCallback = Arc<dyn Fn(SocketAddr) + Send + Sync>;
 
 /// ReUDP provides a reliable layer over UDP, ensuring reliable message delivery
 /// and supporting client-server communication patterns.
+///
+/// `ReUDP` is `Send` and `Sync`. Every method that touches connection state
+/// takes `&mut self`, so the borrow checker rules out data races; to use one
+/// instance from several threads, wrap it in a `Mutex`.
 pub struct ReUDP {
     /// Buffer for received messages that are out of sequence
-    pub recv_buffer: HashMap<u64, Vec<u8>>,
-    /// Sequence number for the next message to send
-    pub send_sequence: u64,
+    recv_buffer: HashMap<u64, (SocketAddr, Message)>,
+    /// Sequence number for the next message to send, shared with the heartbeat
+    /// thread, which sends frames; held while a message is sent so the sequence
+    /// numbers go out in order
+    send_sequence: Arc<Mutex<u64>>,
     /// Sequence number for the next message to receive
-    pub recv_sequence: u64,
-    /// Unacknowledged packets waiting for acknowledgment
-    pub unacked_packets: HashMap<u64, Vec<u8>>,
+    recv_sequence: u64,
+    /// One past the highest sequence number received, so each gap is reported once
+    recv_frontier: u64,
+    /// Callback set with `on_sequence_gap`
+    sequence_gap_callback: Option<SequenceGapCallback>,
+    /// Handler set with `set_raw_handler`
+    raw_handler: Option<RawHandler>,
+    /// Pipeline application payloads go through, in the order hooks were added
+    hooks: Vec<Box<dyn Hook>>,
+    /// Callback set with `on_disconnect`, shared with the heartbeat thread
+    disconnect_callback: DisconnectCallback,
+    /// Callback set with `on_client_connect`
+    client_connect_callback: Option<ClientConnectCallback>,
+    /// Unacknowledged packets waiting for acknowledgment, shared with the heartbeat thread
+    unacked_packets: Arc<Mutex<HashMap<u64, Vec<u8>>>>,
+    /// Unacknowledged packets sent to a single client by `send_to_group`, shared with the heartbeat thread
+    unacked_group_packets: Arc<Mutex<GroupPackets>>,
+    /// Sequence number for the next group message to each client
+    group_send_sequences: HashMap<SocketAddr, u64>,
+    /// Receive buffers of the group messages from each peer
+    group_channels: HashMap<SocketAddr, Channel>,
+    /// Sequence numbers and receive buffers of each channel
+    channels: HashMap<u8, Channel>,
+    /// Unacknowledged packets sent on a channel, shared with the heartbeat thread
+    unacked_channel_packets: Arc<Mutex<ChannelPackets>>,
+    /// Messages received in order, waiting to be handed to the application
+    delivery_queue: VecDeque<Delivery>,
+    /// Number of messages in `delivery_queue` from each peer
+    queued_per_peer: HashMap<SocketAddr, usize>,
     /// Operating mode (Client or Server)
-    pub mode: Mode,
-    /// List of clients (for server mode)
-    pub clients: HashSet<SocketAddr>,
-    /// Timestamp of the last heartbeat sent
-    pub last_heartbeat_time: Instant,
-    /// Interval between heartbeats
-    pub heartbeat_interval: Duration,
+    mode: Mode,
+    /// Copy of `mode` read by the heartbeat thread, updated when a client migrates
+    heartbeat_mode: Arc<Mutex<Mode>>,
+    /// List of clients (for server mode), shared with the heartbeat thread
+    clients: Arc<Mutex<HashSet<SocketAddr>>>,
+    /// Subscribers of each topic (server mode)
+    topics: HashMap<String, HashSet<SocketAddr>>,
+    /// Client groups by name, filled by the application (server mode)
+    groups: HashMap<String, ClientGroup>,
+    /// Current interval between heartbeats, shared with the heartbeat thread
+    heartbeat_interval: Arc<Mutex<Duration>>,
     /// Timestamp of the last heartbeat response received
-    pub last_heartbeat_response_time: Option<Instant>,
-    /// Timestamp of the last ping sent
-    pub last_ping_time: Option<Instant>,
+    last_heartbeat_response_time: Option<Instant>,
     /// Current ping duration
-    pub current_ping: Option<Duration>,
+    current_ping: Option<Duration>,
+    /// Send-to-delivery latency of the last delivered message, if it was timestamped
+    last_message_latency: Option<Duration>,
+    /// Channel of the last delivered message, if it was sent on one
+    last_message_channel: Option<u8>,
+    /// Source address of the last packet read from the socket and let through
+    last_recv_addr: Option<SocketAddr>,
+    /// Smoothed round-trip time over all heartbeat samples
+    srtt: Option<Duration>,
+    /// Most recent RTT samples, for jitter and percentiles
+    ping_history: PingHistory,
+    /// Connection quality last reported through `Event::QualityChanged`
+    quality: ConnectionQuality,
+    /// Available bandwidth estimate, fed by packet-pair probes
+    bandwidth: BandwidthEstimator,
+    /// Path quality probes in flight and their results
+    path_prober: PathProber,
+    /// Per-peer liveness, sleep and clock state, shared with the heartbeat thread
+    peers: Arc<Mutex<HashMap<SocketAddr, Peer>>>,
+    /// Acknowledgments held back until the next flush (lazy ack flushing)
+    pending_acks: HashMap<SocketAddr, Vec<u64>>,
+    /// Timestamp of the last acknowledgment flush
+    last_ack_flush: Instant,
+    /// When the last `Nack` went out, to send at most one per round trip
+    last_nack: Option<Instant>,
+    /// Whether the handshake with the server has completed (client mode)
+    connected: bool,
+    /// Nonce of the handshake in progress, if any
+    handshake_nonce: Option<u64>,
+    /// Nonce of the handshake that established the current session
+    session_nonce: Option<u64>,
+    /// Token for resuming the session, issued by the server (client mode)
+    session_token: Option<SessionToken>,
+    /// Session tokens issued to clients (server mode)
+    issued_tokens: TokenCache,
+    /// Reason given by the server for refusing the handshake in progress
+    connection_refusal: Option<Vec<u8>>,
+    /// Whether `disconnect` was called; no new messages are accepted once set
+    closing: bool,
+    /// Whether `begin_batch` was called and sent messages are queued until `flush`
+    batching: bool,
+    /// Serialized messages waiting to be sent together
+    pending_batch: Vec<Vec<u8>>,
+    /// When the first message of `pending_batch` was queued
+    batch_started: Option<Instant>,
+    /// Sends waiting for their scheduled time
+    scheduled: ScheduledSends,
+    /// Identifier given to the next scheduled send
+    next_schedule_id: u64,
+    /// Peers that haven't confirmed a sequence reset yet, with the time it was last sent
+    pending_resets: HashMap<SocketAddr, Instant>,
+    /// Identifier of the last sequence reset we initiated, so peers can ignore resends
+    reset_id: u64,
+    /// Sequences the last reset we initiated rolls peers back to, if it came
+    /// from `restore_sequence_snapshot`, as (our send sequence, our receive sequence)
+    rollback: Option<(u64, u64)>,
+    /// Senders whose packets are accepted; `None` accepts packets from anyone
+    allowed_senders: Option<HashSet<SocketAddr>>,
+    /// Server address being validated by `migrate_to`, if any
+    pending_migration: Option<PendingMigration>,
+    /// Server address used before the last migration, accepted until the grace period ends
+    previous_server: Option<(SocketAddr, Instant)>,
+    /// Events waiting to be retrieved with `poll_event`
+    events: VecDeque<Event>,
+    /// When application data was last sent
+    last_send_time: Instant,
+    /// When application data was last received
+    last_recv_time: Instant,
+    /// Traffic counters
+    stats: Statistics,
+    /// Traffic counters of the session before the last `reconnect`
+    previous_session_stats: Option<Statistics>,
+    /// Number of calls to `reconnect`
+    session_number: u32,
+    /// Current configuration, shared with the heartbeat thread
+    config: Arc<SharedConfig>,
+    /// Random identifier used to correlate log output of this instance
+    session_id: u64,
+    /// Span of the whole connection, parent of the spans of its sends, receives and heartbeats
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    /// Cipher set with `set_encryption_key`, shared with the heartbeat thread
+    #[cfg(feature = "crypto")]
+    cipher: SharedCipher,
     /// UDP socket for communication
-    socket: Arc<UdpSocket>,
-    /// Buffer size for received messages
-    buffer_size: usize,
+    socket: Arc<MappedSocket>,
+    /// Buffer datagrams are read into, reused by every read; it is one byte
+    /// longer than the configured buffer size, so that a datagram that doesn't
+    /// fit can be told apart from one that fills it exactly
+    recv_buf: Vec<u8>,
+    /// Whether the socket is in non-blocking mode
+    nonblocking: bool,
+    /// Whether `recv` waits for a message (`set_blocking`)
+    blocking: bool,
+    /// Error hit by `recv_all` after it had already received messages, returned
+    /// by the next receive call
+    pending_error: Option<ReUDPError>,
     /// Flag indicating whether the ReUDP instance is running
     running: Arc<Mutex<bool>>,
+    /// Logger set with `set_logger`, shared with the heartbeat thread
+    logger: SharedLogger,
+    /// Fixed-rate sending, shared with the heartbeat thread, which sends the frames
+    frames: Arc<Mutex<FrameSync>>,
+    /// Handle of the heartbeat thread, joined by `stop`
+    heartbeat_thread: Option<JoinHandle<()>>,
 }
 
 impl ReUDP {
     /// Creates a new ReUDP instance.
     ///
+    /// Shorthand for `with_config` with a default configuration; retransmissions
+    /// happen at least once per heartbeat interval and peers are considered lost
+    /// after two intervals of silence.
+    ///
     /// # Arguments
     ///
     /// * `local_addr` - Local address to bind the UDP socket.
@@ -60,104 +267,578 @@ impl ReUDP {
         heartbeat_interval: Duration,
         buffer_size: usize,
     ) -> Result<Self, std::io::Error> {
-        let socket = UdpSocket::bind(local_addr)?;
+        let config = ReUDPConfig::default();
+        let resend_interval = config.resend_interval.min(heartbeat_interval);
+        let config = config
+            .heartbeat_interval(heartbeat_interval)
+            .liveness_timeout(heartbeat_interval * 2)
+            .resend_interval(resend_interval)
+            .buffer_size(buffer_size)
+            .max_packet_size(buffer_size);
+        Self::with_config(local_addr, mode, config)
+    }
+
+    /// Creates a client of the server at `server_addr`, with the default
+    /// configuration.
+    ///
+    /// The socket is bound to a port picked by the OS on every local address of
+    /// the server address's IP family.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_addr` - Address of the server, e.g. `"127.0.0.1:4000"` or a `SocketAddr`;
+    ///   the first address it resolves to is used.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, std::io::Error>` - The created ReUDP instance or an error.
+    pub fn client<A: ToSocketAddrs>(server_addr: A) -> Result<Self, std::io::Error> {
+        let server_addr = socket::resolve(server_addr)?;
+        let local_addr = if server_addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        Self::with_config(local_addr, Mode::Client(server_addr), ReUDPConfig::default())
+    }
+
+    /// Creates a server bound to `bind_addr`, with the default configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `bind_addr` - Local address to bind the UDP socket, e.g. `"0.0.0.0:4000"`;
+    ///   the first address it resolves to is used.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, std::io::Error>` - The created ReUDP instance or an error.
+    pub fn server<A: ToSocketAddrs>(bind_addr: A) -> Result<Self, std::io::Error> {
+        let bind_addr = socket::resolve(bind_addr)?;
+        Self::with_config(&bind_addr.to_string(), Mode::Server, ReUDPConfig::default())
+    }
+
+    /// Creates a new ReUDP instance from a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `local_addr` - Local address to bind the UDP socket, or to pass to the
+    ///   configured socket factory.
+    /// * `mode` - Operating mode (Client or Server).
+    /// * `config` - Configuration of the instance.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, std::io::Error>` - The created ReUDP instance, an `InvalidInput`
+    ///   error wrapping a `ConfigError` if the configuration is invalid, or another error.
+    pub fn with_config(
+        local_addr: &str,
+        mode: Mode,
+        config: ReUDPConfig,
+    ) -> Result<Self, std::io::Error> {
+        let socket = match &config.socket_factory {
+            Some(factory) => factory.create(local_addr)?,
+            None => socket::bind(local_addr, &config)?,
+        };
+        Self::from_socket(socket, mode, config)
+    }
+
+    /// Creates a new ReUDP instance around an already bound socket, e.g. one with
+    /// platform-specific options set.
+    ///
+    /// The socket is switched to non-blocking mode unless the configuration says
+    /// to respect its blocking mode, and the socket options set in the
+    /// configuration are applied; nothing else about it is changed. The IP
+    /// family options of the configuration only apply when ReUDP binds the socket
+    /// itself and are ignored here.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - Bound UDP socket the instance takes ownership of.
+    /// * `mode` - Operating mode (Client or Server).
+    /// * `config` - Configuration of the instance.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, std::io::Error>` - The created ReUDP instance, an `InvalidInput`
+    ///   error wrapping a `ConfigError` if the configuration is invalid, or another error.
+    pub fn from_socket(
+        socket: UdpSocket,
+        mode: Mode,
+        config: ReUDPConfig,
+    ) -> Result<Self, std::io::Error> {
+        config
+            .validate()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let nonblocking = if config.respect_socket_blocking {
+            socket::is_nonblocking(&socket)?
+        } else {
+            socket.set_nonblocking(true)?;
+            true
+        };
+        socket::disable_connection_reset(&socket)?;
+        let events = socket::apply_options(&socket, &config)?
+            .into_iter()
+            .map(|(option, error)| Event::SocketOptionFailed {
+                option,
+                error: error.to_string(),
+            })
+            .collect();
+        if config.proxy.is_some() && config.tcp_fallback != TcpFallback::Never {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "TCP fallback can't be combined with a SOCKS5 proxy",
+            ));
+        }
+        let socket = match &config.proxy {
+            Some(proxy) => {
+                if !matches!(mode, Mode::Client(_)) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "a SOCKS5 proxy can only be used in client mode",
+                    ));
+                }
+                let relay = Socks5Relay::associate(proxy, socket.local_addr()?)?;
+                MappedSocket::with_proxy(socket, Some(relay))?
+            }
+            None => MappedSocket::new(socket)?,
+        };
+        match (&mode, config.tcp_fallback) {
+            (_, TcpFallback::Never) => {}
+            (Mode::Server, _) => socket.set_tcp(TcpTransport::listen(socket.local_addr()?)?)?,
+            (Mode::Client(remote_addr), TcpFallback::Always) => {
+                let transport = TcpTransport::connect(*remote_addr, socket.local_addr()?, handshake_time(&config))?;
+                socket.set_tcp(transport)?;
+            }
+            (Mode::Client(_), TcpFallback::OnHandshakeTimeout) => {}
+        }
+        if let (Mode::Client(remote_addr), true) = (&mode, config.connect_client_socket) {
+            socket.connect(socket::canonical(*remote_addr))?;
+        }
+        Self::from_mapped_socket(socket, mode, config, nonblocking, events)
+    }
+
+    /// Creates a new ReUDP instance communicating over a Unix datagram socket,
+    /// for processes on the same host.
+    ///
+    /// The wire format is the same as over UDP. Peers are tracked under
+    /// placeholder addresses in `100::/64`, which is what `recv`, `client_addrs`
+    /// and the other methods taking or returning a `SocketAddr` use; `source_of`
+    /// and `recv_source` tell the path behind one. Peers must be bound to a path
+    /// to be answered. The socket options, the socket factory and the TCP
+    /// fallback of the configuration don't apply, and neither does `socket`,
+    /// which panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket_path` - Path to bind the socket to. It must not exist yet, and
+    ///   is removed when the instance is dropped.
+    /// * `remote_path` - Path of the server's socket in client mode, `None` in server mode.
+    /// * `config` - Configuration of the instance.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, ReUDPError>` - The created ReUDP instance, an `InvalidInput`
+    ///   error wrapping a `ConfigError` if the configuration is invalid, or another error.
+    #[cfg(unix)]
+    pub fn new_unix(socket_path: &Path, remote_path: Option<&Path>, config: ReUDPConfig) -> Result<Self, ReUDPError> {
+        config
+            .validate()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let socket = UnixSocket::bind(socket_path)?;
         socket.set_nonblocking(true)?;
-        let reudp = Self {
+        let mode = match remote_path {
+            Some(path) => Mode::Client(socket.addr_of(path)),
+            None => Mode::Server,
+        };
+        Ok(Self::from_mapped_socket(
+            MappedSocket::Unix(socket),
+            mode,
+            config,
+            true,
+            VecDeque::new(),
+        )?)
+    }
+
+    /// Creates a new ReUDP instance communicating over `transport` instead of
+    /// a UDP socket of its own.
+    ///
+    /// The wire format, reliability and heartbeats are the same as over UDP;
+    /// the heartbeat thread sends through the transport as well. Peers are
+    /// tracked under the addresses the transport reports. Waiting for a
+    /// message, as `recv_timeout` and blocking mode do, polls the transport.
+    /// The socket options, the socket factory, the proxy and the TCP fallback
+    /// of the configuration don't apply, and neither does `socket`, which
+    /// panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - The transport to send and receive datagrams with.
+    /// * `mode` - Operating mode (Client or Server).
+    /// * `config` - Configuration of the instance.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, std::io::Error>` - The created ReUDP instance, an `InvalidInput`
+    ///   error wrapping a `ConfigError` if the configuration is invalid, or another error.
+    pub fn with_transport<T: Transport + 'static>(
+        transport: T,
+        mode: Mode,
+        config: ReUDPConfig,
+    ) -> Result<Self, std::io::Error> {
+        config
+            .validate()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let socket = MappedSocket::Custom(CustomSocket::new(Box::new(transport)));
+        Self::from_mapped_socket(socket, mode, config, true, VecDeque::new())
+    }
+
+    /// Creates a new ReUDP instance around a socket ready for use.
+    fn from_mapped_socket(
+        socket: MappedSocket,
+        mode: Mode,
+        config: ReUDPConfig,
+        nonblocking: bool,
+        events: VecDeque<Event>,
+    ) -> Result<Self, std::io::Error> {
+        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+        let local_addr = socket.local_addr()?;
+        // Peers are tracked under their canonical address, whatever family the
+        // socket receives them through.
+        let mode = match mode {
+            Mode::Client(remote_addr) => Mode::Client(socket::canonical(remote_addr)),
+            Mode::Server => Mode::Server,
+        };
+        // A client only expects traffic from its server.
+        let allowed_senders = match mode {
+            Mode::Client(remote_addr) => Some(HashSet::from([remote_addr])),
+            Mode::Server => None,
+        };
+        let session_id = rand::random::<u64>();
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "reudp.connection",
+            mode = match mode {
+                Mode::Server => "server",
+                Mode::Client(_) => "client",
+            },
+            local_addr = %local_addr,
+            session_id
+        );
+        let created = Instant::now();
+        let mut reudp = Self {
             recv_buffer: HashMap::new(),
-            send_sequence: 0,
+            send_sequence: Arc::new(Mutex::new(0)),
             recv_sequence: 0,
-            unacked_packets: HashMap::new(),
+            recv_frontier: 0,
+            sequence_gap_callback: None,
+            raw_handler: None,
+            hooks: Vec::new(),
+            disconnect_callback: Arc::new(Mutex::new(None)),
+            client_connect_callback: None,
+            unacked_packets: Arc::new(Mutex::new(HashMap::new())),
+            unacked_group_packets: Arc::new(Mutex::new(HashMap::new())),
+            group_send_sequences: HashMap::new(),
+            group_channels: HashMap::new(),
+            channels: HashMap::new(),
+            unacked_channel_packets: Arc::new(Mutex::new(HashMap::new())),
+            delivery_queue: VecDeque::new(),
+            queued_per_peer: HashMap::new(),
+            heartbeat_mode: Arc::new(Mutex::new(mode.clone())),
             mode,
-            clients: HashSet::new(),
-            last_heartbeat_time: Instant::now(),
-            heartbeat_interval,
+            clients: Arc::new(Mutex::new(HashSet::new())),
+            topics: HashMap::new(),
+            groups: HashMap::new(),
+            heartbeat_interval: Arc::new(Mutex::new(config.heartbeat_policy.initial_interval())),
             last_heartbeat_response_time: None,
-            last_ping_time: None,
             current_ping: None,
+            last_message_latency: None,
+            last_message_channel: None,
+            last_recv_addr: None,
+            srtt: None,
+            ping_history: PingHistory::default(),
+            quality: ConnectionQuality::Excellent,
+            bandwidth: BandwidthEstimator::default(),
+            path_prober: PathProber::default(),
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            pending_acks: HashMap::new(),
+            last_ack_flush: Instant::now(),
+            last_nack: None,
+            connected: false,
+            handshake_nonce: None,
+            session_nonce: None,
+            session_token: None,
+            issued_tokens: TokenCache::default(),
+            connection_refusal: None,
+            closing: false,
+            batching: false,
+            pending_batch: Vec::new(),
+            batch_started: None,
+            scheduled: BinaryHeap::new(),
+            next_schedule_id: 0,
+            pending_resets: HashMap::new(),
+            reset_id: 0,
+            rollback: None,
+            allowed_senders,
+            pending_migration: None,
+            previous_server: None,
+            events,
+            last_send_time: created,
+            last_recv_time: created,
+            stats: Statistics::default(),
+            previous_session_stats: None,
+            session_number: 0,
+            session_id,
+            #[cfg(feature = "tracing")]
+            span,
+            #[cfg(feature = "crypto")]
+            cipher: Arc::new(Mutex::new(None)),
             socket: Arc::new(socket),
-            buffer_size,
+            recv_buf: vec![0; config.buffer_size + 1],
+            nonblocking,
+            blocking: false,
+            pending_error: None,
+            config: Arc::new(SharedConfig::new(config)),
             running: Arc::new(Mutex::new(true)),
+            logger: Arc::new(Mutex::new(None)),
+            frames: Arc::new(Mutex::new(FrameSync::default())),
+            heartbeat_thread: None,
         };
 
+        log_debug!(session_id = reudp.session_id, local_addr = %local_addr, "ReUDP instance created");
+        if reudp.buffer_size() < RECOMMENDED_BUFFER_SIZE {
+            log_warn!(
+                session_id = reudp.session_id,
+                buffer_size = reudp.buffer_size(),
+                "Receive buffer is smaller than {} bytes; larger datagrams will be dropped",
+                RECOMMENDED_BUFFER_SIZE
+            );
+        }
         reudp.start_heartbeat();
         Ok(reudp)
     }
 
-    /// Starts the heartbeat mechanism in a separate thread.
-    fn start_heartbeat(&self) {
+    /// Starts the heartbeat mechanism in a separate thread, or on wasm32 in a
+    /// task of the browser's event loop.
+    ///
+    /// The thread resends unacknowledged packets, sends heartbeats and checks the
+    /// liveness of each peer on its own: a client only watches its server and
+    /// stops when it is lost, while a server evicts clients that went silent
+    /// without ever stopping itself.
+    fn start_heartbeat(&mut self) {
         let socket = Arc::clone(&self.socket);
-        let heartbeat_interval = self.heartbeat_interval;
-        let mode = self.mode.clone();
-        let clients = self.clients.clone();
-        let unacked_packets = Arc::new(Mutex::new(self.unacked_packets.clone()));
-        let last_heartbeat_time = Arc::new(Mutex::new(self.last_heartbeat_time));
-        let last_heartbeat_response_time = Arc::new(Mutex::new(self.last_heartbeat_response_time));
-        let last_ping_time = Arc::new(Mutex::new(self.last_ping_time));
-        let current_ping = Arc::new(Mutex::new(self.current_ping));
+        let heartbeat_interval = Arc::clone(&self.heartbeat_interval);
+        let config = Arc::clone(&self.config);
+        let mode = Arc::clone(&self.heartbeat_mode);
+        let clients = Arc::clone(&self.clients);
+        let unacked_packets = Arc::clone(&self.unacked_packets);
+        let unacked_group_packets = Arc::clone(&self.unacked_group_packets);
+        let unacked_channel_packets = Arc::clone(&self.unacked_channel_packets);
+        let peers = Arc::clone(&self.peers);
         let running = Arc::clone(&self.running);
+        let session_id = self.session_id;
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        let logger = Arc::clone(&self.logger);
+        let send_sequence = Arc::clone(&self.send_sequence);
+        #[cfg(feature = "crypto")]
+        let cipher = Arc::clone(&self.cipher);
+        let frames = Arc::clone(&self.frames);
+        let disconnect_callback = Arc::clone(&self.disconnect_callback);
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(parent: &span, "reudp.heartbeat");
+        let started = Instant::now();
+        let mut last_resend_time = Instant::now();
+        let mut last_heartbeat_time = Instant::now();
+        let mut heartbeat_sequences = HashMap::new();
+        // One round of the work, returning how long to wait before the next,
+        // or `None` once the instance stopped running.
+        let tick = move || {
+            if !*running.lock().unwrap() {
+                return None;
+            }
+            #[cfg(feature = "tracing")]
+            let _entered = span.enter();
+            // Re-read the configuration each tick so updates apply without a restart.
+            let config = config.load();
+            let liveness_timeout = config.liveness_timeout;
+            let resend_interval = config.resend_interval;
+            // Likewise for the interval: an adaptive policy updates it
+            // whenever a new RTT sample comes in.
+            let heartbeat_interval = *heartbeat_interval.lock().unwrap();
+            // And for the mode: a client's server changes when it migrates.
+            let mode = mode.lock().unwrap().clone();
 
-        thread::spawn(move || {
-            while *running.lock().unwrap() {
+            // Peers that announced a sleep get no traffic until they wake up
+            let targets = awake_peers(&mode, &clients, &peers);
+
+            // Resend unacknowledged packets
+            if last_resend_time.elapsed() >= resend_interval {
                 let packets = unacked_packets.lock().unwrap();
-                let mut last_heartbeat = last_heartbeat_time.lock().unwrap();
-                let last_response_time = last_heartbeat_response_time.lock().unwrap();
-                let mut ping_time = last_ping_time.lock().unwrap();
-                let mut ping = current_ping.lock().unwrap();
-
-                // Resend unacknowledged packets
-                for packet in packets.values() {
-                    match mode {
-                        Mode::Client(ref remote_addr) => {
-                            socket.send_to(packet, remote_addr).unwrap();
-                        }
-                        Mode::Server => {
-                            for client in &clients {
-                                socket.send_to(packet, client).unwrap();
-                            }
-                        }
+                let channel_packets = unacked_channel_packets.lock().unwrap();
+                for packet in packets.values().chain(channel_packets.values()) {
+                    for target in &targets {
+                        let result = socket.send_to(packet, *target);
+                        report_send_error(&logger, session_id, *target, IoContext::Retransmit, result);
+                    }
+                }
+                let group_packets = unacked_group_packets.lock().unwrap();
+                for ((addr, _), packet) in group_packets.iter() {
+                    if targets.contains(addr) {
+                        let result = socket.send_to(packet, *addr);
+                        report_send_error(&logger, session_id, *addr, IoContext::Retransmit, result);
+                    }
+                }
+                last_resend_time = Instant::now();
+            }
+
+            // Send heartbeat
+            if last_heartbeat_time.elapsed() > heartbeat_interval {
+                #[cfg(feature = "tracing")]
+                let peers = peers.lock().unwrap();
+                for target in &targets {
+                    // Numbered per peer so the peer can measure loss from the gaps.
+                    let sequence = heartbeat_sequences.entry(*target).or_insert(0);
+                    let serialized_heartbeat: [u8; HEADER_SIZE + 8] = message::encode_array(
+                        *sequence,
+                        MessageType::Heartbeat,
+                        &clock::now_micros().to_be_bytes(),
+                    );
+                    *sequence += 1;
+                    log_trace!(
+                        session_id,
+                        to = %target,
+                        since_last_response = ?peers.get(target).and_then(|p| p.last_heard).map(|t| t.elapsed()),
+                        "Sending heartbeat"
+                    );
+                    let result = socket.send_to(&serialized_heartbeat, *target);
+                    report_send_error(&logger, session_id, *target, IoContext::Heartbeat, result);
+                }
+                last_heartbeat_time = Instant::now();
+            }
+
+            // Send the frame that is due; the data is produced outside of any lock
+            let due = frames.lock().unwrap().take_due(Instant::now());
+            let frame = due.and_then(|(frame_fn, require_ack)| frame_fn().map(|data| (data, require_ack)));
+            if let Some((data, require_ack)) = frame {
+                let mut sequence = send_sequence.lock().unwrap();
+                #[cfg(feature = "crypto")]
+                let sealed = crypto::seal(&cipher, *sequence, &MessageType::EncryptedData, &data);
+                #[cfg(not(feature = "crypto"))]
+                let sealed: Option<Vec<u8>> = None;
+                let (message_type, payload) = match &sealed {
+                    Some(sealed) => (MessageType::EncryptedData, sealed),
+                    None => (MessageType::Data, &data),
+                };
+                let len = HEADER_SIZE + payload.len();
+                if len > config.max_packet_size || payload.len() > u16::MAX as usize {
+                    log_warn!(session_id, len, "Dropped frame above the maximum packet size");
+                    log::emit(
+                        &logger,
+                        LogLevel::Warn,
+                        &format!("Dropped frame of {} bytes, above the maximum packet size", len),
+                    );
+                } else {
+                    let serialized = message::encode(*sequence, message_type, &[payload]);
+                    log_trace!(session_id, sequence = *sequence, reliable = require_ack, "Sent frame");
+                    for target in &targets {
+                        let result = socket.send_to(&serialized, *target);
+                        report_send_error(&logger, session_id, *target, IoContext::Send, result);
+                    }
+                    if require_ack {
+                        unacked_packets.lock().unwrap().insert(*sequence, serialized);
                     }
+                    *sequence += 1;
                 }
+            }
 
-                // Send heartbeat
-                if Instant::now().duration_since(*last_heartbeat) > heartbeat_interval {
-                    let heartbeat_message = Message::new(0, MessageType::Heartbeat, vec![]);
-                    let serialized_heartbeat = heartbeat_message.to_bytes();
-                    match mode {
-                        Mode::Client(ref remote_addr) => {
-                            socket.send_to(&serialized_heartbeat, remote_addr).unwrap();
+            // Check liveness of each peer independently
+            let now = Instant::now();
+            match mode {
+                Mode::Client(_) if socket.proxy_lost() => {
+                    log_warn!(session_id, "SOCKS5 proxy closed the control connection");
+                    log::emit(&logger, LogLevel::Warn, "SOCKS5 proxy closed the control connection");
+                    stop_running(&running, &disconnect_callback, DisconnectReason::ProxyClosed);
+                }
+                Mode::Client(remote_addr) => {
+                    let peers = peers.lock().unwrap();
+                    match peers.get(&remote_addr) {
+                        Some(server) if server.last_heard.is_some() => {
+                            if server.is_lost(now, liveness_timeout) {
+                                log_warn!(session_id, "Connection lost");
+                                log::emit(&logger, LogLevel::Warn, "Connection lost");
+                                stop_running(&running, &disconnect_callback, DisconnectReason::ConnectionLost);
+                            }
                         }
-                        Mode::Server => {
-                            for client in &clients {
-                                socket.send_to(&serialized_heartbeat, client).unwrap();
+                        server => {
+                            let asleep = server.is_some_and(|s| s.is_sleeping(now));
+                            if !asleep && now.duration_since(started) > liveness_timeout {
+                                log_warn!(session_id, "No response from server");
+                                log::emit(&logger, LogLevel::Warn, "No response from server");
+                                stop_running(
+                                    &running,
+                                    &disconnect_callback,
+                                    DisconnectReason::NoResponseFromServer,
+                                );
                             }
                         }
                     }
-                    *ping_time = Some(Instant::now());
-                    *last_heartbeat = Instant::now();
-                }
-
-                // Check heartbeat response
-                if let Some(response_time) = *last_response_time {
-                    if Instant::now().duration_since(response_time) > heartbeat_interval * 2 {
-                        println!("Connection lost");
-                        // Connection lost
-                        *running.lock().unwrap() = false;
-                    } else if let Some(sent_time) = *ping_time {
-                        *ping = Some(Instant::now().duration_since(sent_time));
-                    }
-                } else if Instant::now().duration_since(*last_heartbeat) > heartbeat_interval * 2 {
-                    println!("No response from server");
-                    // No response from server
-                    *running.lock().unwrap() = false;
                 }
-
-                thread::sleep(Duration::from_secs(1));
+                Mode::Server => {
+                    let mut clients = clients.lock().unwrap();
+                    let mut peers = peers.lock().unwrap();
+                    clients.retain(|client| {
+                        let lost = peers
+                            .get(client)
+                            .is_none_or(|peer| peer.is_lost(now, liveness_timeout));
+                        if lost {
+                            log_debug!(session_id, client = %client, "Evicting silent client");
+                            peers.remove(client);
+                            heartbeat_sequences.remove(client);
+                        }
+                        !lost
+                    });
+                }
             }
-        });
+
+            // The thread parks rather than sleeps for that long, so `stop` and
+            // `set_frame_rate` can wake it up. Frames are due at their own pace,
+            // which may be faster than the shortest tick.
+            let tick = heartbeat_interval
+                .min(resend_interval)
+                .clamp(MIN_TICK, MAX_TICK);
+            let until_frame = frames.lock().unwrap().time_until_next(Instant::now());
+            Some(until_frame.map_or(tick, |until_frame| until_frame.min(tick)))
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.heartbeat_thread = Some(thread::spawn(move || {
+                let mut tick = tick;
+                while let Some(wait) = tick() {
+                    thread::park_timeout(wait);
+                }
+            }));
+        }
+        // Threads can't be spawned in the browser. The task can't be woken up
+        // early either, so it ends at its next tick once stopped.
+        #[cfg(target_arch = "wasm32")]
+        crate::wasm::spawn_heartbeat(tick);
+    }
+
+    /// Stops the heartbeat thread and waits for it to exit. On wasm32 the task
+    /// ends at its next tick instead.
+    fn stop_heartbeat(&mut self) {
+        *self.running.lock().unwrap() = false;
+        if let Some(heartbeat_thread) = self.heartbeat_thread.take() {
+            heartbeat_thread.thread().unpark();
+            let _ = heartbeat_thread.join();
+        }
     }
 
     /// Sends a message with optional acknowledgment requirement.
     ///
+    /// In client mode, sending a message also ends a sleep announced with `announce_sleep`.
+    ///
+    /// The data can be anything that can be viewed as bytes, such as a `Vec<u8>`,
+    /// a `&[u8]` or a `String`; it is copied once, into the serialized packet.
+    ///
     /// # Arguments
     ///
     /// * `data` - The data to be sent.
@@ -165,98 +846,3972 @@ impl ReUDP {
     ///
     /// # Returns
     ///
-    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
-    pub fn send(&mut self, data: Vec<u8>, require_ack: bool) -> Result<(), ReUDPError> {
-        let message = Message::new(self.send_sequence, MessageType::Data, data.clone());
-        let serialized = message.to_bytes();
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`,
+    ///   `NotConnected` for a server without clients, `MessageTooLarge` if the
+    ///   data doesn't fit in a packet, `QueueFull` if too many reliable messages
+    ///   wait for an acknowledgment, `WouldBlock` in non-blocking mode if the
+    ///   socket's send buffer is full, or an error.
+    pub fn send<D: AsRef<[u8]>>(&mut self, data: D, require_ack: bool) -> Result<(), ReUDPError> {
+        self.send_data(data.as_ref(), require_ack, true).map(|_| ())
+    }
 
-        match self.mode {
-            Mode::Client(ref remote_addr) => {
-                self.socket.send_to(&serialized, remote_addr)?;
-            }
-            Mode::Server => {
-                for client in &self.clients {
-                    self.socket.send_to(&serialized, client)?;
-                }
-            }
+    /// Sends a message like `send`, but never waits, even in blocking mode, and
+    /// never queues it in a batch started with `begin_batch`.
+    ///
+    /// If the socket's send buffer is full, nothing is sent and the message
+    /// takes no sequence number, so it can simply be tried again later. A
+    /// server whose buffer fills up partway through its clients still counts
+    /// the message as sent: the clients it didn't reach miss it as if it was
+    /// lost, and get it again by retransmission if it requires an acknowledgment.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, ReUDPError>` - `true` if the message was sent, `false` if the
+    ///   send buffer is full, `Closing` after `disconnect`, or an error.
+    pub fn try_send<D: AsRef<[u8]>>(&mut self, data: D, require_ack: bool) -> Result<bool, ReUDPError> {
+        if !self.nonblocking {
+            self.socket.set_nonblocking(true)?;
         }
+        let result = self.send_data(data.as_ref(), require_ack, false);
+        if !self.nonblocking {
+            self.socket.set_nonblocking(false)?;
+        }
+        result
+    }
 
-        if require_ack {
-            self.unacked_packets.insert(self.send_sequence, serialized);
+    /// Does the sending for `send` and `try_send`: `data` goes out as a `Data`
+    /// message, or as an `EncryptedData` one once an encryption key is set.
+    fn send_data(&mut self, data: &[u8], require_ack: bool, batchable: bool) -> Result<bool, ReUDPError> {
+        let Some(data) = self.run_send_hooks(data) else {
+            return Ok(true);
+        };
+        let data = &data[..];
+        // Held from the encryption on, so a frame can't take the sequence
+        // number the ciphertext is bound to.
+        let send_sequence = Arc::clone(&self.send_sequence);
+        let mut sequence = send_sequence.lock().unwrap();
+        #[cfg(feature = "crypto")]
+        if let Some(sealed) = crypto::seal(&self.cipher, *sequence, &MessageType::EncryptedData, data) {
+            return self.send_sequenced(&mut sequence, MessageType::EncryptedData, &[&sealed], require_ack, batchable);
         }
-        self.send_sequence += 1;
-        Ok(())
+        self.send_sequenced(&mut sequence, MessageType::Data, &[data], require_ack, batchable)
     }
 
-    /// Receives a message, handling acknowledgment and heartbeats.
+    /// Sends a message with an application-defined type, e.g. to prototype a
+    /// protocol extension. It is sequenced and acknowledged like a message
+    /// sent with `send`, and the receiver gets it from `recv_message` as
+    /// `MessageType::Custom`, or from `recv` like any other message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg_type` - The type code, between `FIRST_CUSTOM_TYPE` and 127; the
+    ///   codes below are reserved for the protocol.
+    /// * `payload` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
     ///
     /// # Returns
     ///
-    /// * `Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError>` - The address and data of the received message, or an error.
-    pub fn recv(&mut self) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
-        let mut buf = vec![0; self.buffer_size];
-        match self.socket.recv_from(&mut buf) {
-            Ok((len, addr)) => {
-                let message = Message::from_bytes(&buf[..len]);
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`,
+    ///   `InvalidMessageType` if the type code is reserved or out of range, or an error.
+    pub fn send_with_type<D: AsRef<[u8]>>(
+        &mut self,
+        msg_type: u8,
+        payload: D,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        if !(FIRST_CUSTOM_TYPE..=127).contains(&msg_type) {
+            return Err(ReUDPError::InvalidMessageType(msg_type));
+        }
+        let Some(payload) = self.run_send_hooks(payload.as_ref()) else {
+            return Ok(());
+        };
+        self.send_message(MessageType::Custom(msg_type), &[&payload], require_ack)
+    }
 
-                if let Mode::Server = self.mode {
-                    self.clients.insert(addr);
-                }
+    /// Queues a message to be sent with `send` once `send_at` is reached.
+    ///
+    /// Scheduled sends are made by `recv`, like the other timed work, at its
+    /// first call from `send_at` on; while `recv` blocks on the read timeout,
+    /// they can be late by up to that timeout. Sends due at the same time go
+    /// out in the order they were scheduled.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    /// * `send_at` - When to send the message.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The schedule ID, for `cancel_scheduled`.
+    pub fn schedule_send(&mut self, data: Vec<u8>, require_ack: bool, send_at: Instant) -> u64 {
+        let schedule_id = self.next_schedule_id;
+        self.next_schedule_id += 1;
+        self.scheduled.push(Reverse((send_at, schedule_id, data, require_ack)));
+        schedule_id
+    }
 
-                match message.message_type {
-                    MessageType::Data => {
-                        let ack = Message::new(message.sequence, MessageType::Ack, vec![]);
-                        let serialized_ack = ack.to_bytes();
-                        self.socket.send_to(&serialized_ack, &addr)?;
+    /// Cancels a send queued with `schedule_send`.
+    ///
+    /// # Arguments
+    ///
+    /// * `schedule_id` - The ID returned by `schedule_send`.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the send was still pending and is now cancelled.
+    pub fn cancel_scheduled(&mut self, schedule_id: u64) -> bool {
+        let pending = self.scheduled.len();
+        self.scheduled.retain(|Reverse((_, id, _, _))| *id != schedule_id);
+        self.scheduled.len() < pending
+    }
 
-                        if message.sequence == self.recv_sequence {
-                            self.recv_sequence += 1;
-                            Ok(Some((addr, message.payload)))
-                        } else {
-                            self.recv_buffer.insert(message.sequence, message.payload);
-                            Ok(None)
-                        }
-                    }
-                    MessageType::Ack => {
-                        self.unacked_packets.remove(&message.sequence);
-                        Ok(None)
-                    }
-                    MessageType::Heartbeat => {
-                        let response = Message::new(0, MessageType::Heartbeat, vec![]);
-                        let serialized_response = response.to_bytes();
-                        self.socket.send_to(&serialized_response, &addr)?;
+    /// Returns the number of sends queued with `schedule_send` and not sent yet.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of pending scheduled sends.
+    pub fn pending_scheduled(&self) -> usize {
+        self.scheduled.len()
+    }
 
-                        self.last_heartbeat_response_time = Some(Instant::now());
-                        self.current_ping = self.last_heartbeat_response_time.map(|resp_time| resp_time.elapsed());
+    /// Makes the scheduled sends that are due.
+    fn send_scheduled(&mut self) -> Result<(), ReUDPError> {
+        let now = Instant::now();
+        while self
+            .scheduled
+            .peek()
+            .is_some_and(|Reverse((send_at, _, _, _))| *send_at <= now)
+        {
+            let Reverse((_, _, data, require_ack)) = self.scheduled.pop().unwrap();
+            self.send(data, require_ack)?;
+        }
+        Ok(())
+    }
 
-                        Ok(None)
-                    }
-                    MessageType::Unknown(t) => {
-                        eprintln!("Received unknown message type: {}", t);
-                        Ok(None)
-                    }
-                }
-            }
-            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
-            Err(e) => Err(ReUDPError::IoError(e)),
+    /// Sends a frame `hz` times per second, e.g. to synchronize game state at a
+    /// fixed tick rate however often the application calls `send`.
+    ///
+    /// The heartbeat thread calls the function set with `set_frame_fn` at each
+    /// tick and sends the data it returns like `send` would, numbered with the
+    /// current send sequence; a tick where it returns `None` sends nothing.
+    /// Frames don't go to peers that announced a sleep, and stop with `disconnect`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hz` - The number of frames per second; zero, or any rate too low to
+    ///   give a frame interval, stops the frames.
+    pub fn set_frame_rate(&mut self, hz: f64) {
+        let interval = (hz > 0.0)
+            .then(|| Duration::try_from_secs_f64(1.0 / hz).ok())
+            .flatten();
+        self.frames.lock().unwrap().set_interval(interval);
+        if let Some(heartbeat_thread) = &self.heartbeat_thread {
+            heartbeat_thread.thread().unpark();
         }
     }
 
-    /// Returns the current ping duration.
+    /// Sets the function producing the data of each frame sent at the rate set
+    /// with `set_frame_rate`.
+    ///
+    /// It is called from the heartbeat thread and should return quickly, as
+    /// heartbeats and retransmissions wait for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function, returning the frame's data or `None` to skip the frame.
+    pub fn set_frame_fn<F>(&mut self, f: F)
+    where
+        F: Fn() -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.frames.lock().unwrap().frame_fn = Some(Arc::new(f));
+    }
+
+    /// Sets whether frames sent at the rate set with `set_frame_rate` require an
+    /// acknowledgment. They don't by default, since each frame usually
+    /// supersedes the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `require_ack` - Whether the next frames require an acknowledgment.
+    pub fn set_frame_require_ack(&mut self, require_ack: bool) {
+        self.frames.lock().unwrap().require_ack = require_ack;
+    }
+
+    /// Sets the key the data of sent messages is encrypted with, using
+    /// AES-256-GCM, and received data is decrypted with.
+    ///
+    /// Messages sent with `send` and frames go out as `EncryptedData`; the
+    /// other ways of sending, such as channels, groups, timestamped, typed and
+    /// custom-type messages, keep their type with an encrypted payload. Each
+    /// message gets a random 12-byte nonce, carried in front of the
+    /// ciphertext, and a 16-byte authentication tag after it, so an encrypted
+    /// message takes 28 bytes more of `max_packet_size`. Both peers must set
+    /// the same key; a message that doesn't decrypt, or carries data without
+    /// being encrypted, is dropped without being acknowledged and counted in
+    /// `messages_dropped_undecryptable`; the `MalformedPolicy` decides whether
+    /// `recv` also reports it as `DecryptionFailed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The 256-bit key shared with the peers.
+    #[cfg(feature = "crypto")]
+    pub fn set_encryption_key(&mut self, key: [u8; 32]) {
+        *self.cipher.lock().unwrap() = Some(crypto::cipher(&key));
+    }
+
+    /// Sends a message stamped with the current time, so the receiver can measure
+    /// its application-to-application latency with `last_message_latency`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
     ///
     /// # Returns
     ///
-    /// * `Option<Duration>` - The current ping duration, if available.
-    pub fn get_current_ping(&self) -> Option<Duration> {
-        self.current_ping
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`, or an error.
+    pub fn send_timestamped<D: AsRef<[u8]>>(&mut self, data: D, require_ack: bool) -> Result<(), ReUDPError> {
+        let Some(data) = self.run_send_hooks(data.as_ref()) else {
+            return Ok(());
+        };
+        let sent_at = clock::now_micros().to_be_bytes();
+        self.send_message(MessageType::TimestampedData, &[&sent_at, &data], require_ack)
     }
 
-    /// Returns a reference to the underlying UDP socket.
+    /// Sends a value encoded with `PostcardCodec`, a compact binary encoding, to
+    /// be decoded by the receiver with `recv_typed`.
+    ///
+    /// Typed messages are told apart from the ones sent with `send` by their
+    /// message type, so both can be mixed on the same connection: `recv` returns
+    /// the encoded bytes of a typed message, and `recv_message` tells its type.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The value to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`,
+    ///   `EncodeError` if the value can't be encoded, or an error.
+    #[cfg(feature = "serde")]
+    pub fn send_typed<T: serde::Serialize>(&mut self, message: &T, require_ack: bool) -> Result<(), ReUDPError> {
+        self.send_typed_with(&PostcardCodec, message, require_ack)
+    }
+
+    /// Sends a value encoded with `codec`, to be decoded by the receiver with
+    /// `recv_typed_with` and the same codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The encoding to use.
+    /// * `message` - The value to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`,
+    ///   `EncodeError` if the value can't be encoded, or an error.
+    #[cfg(feature = "serde")]
+    pub fn send_typed_with<C: Codec, T: serde::Serialize>(
+        &mut self,
+        codec: &C,
+        message: &T,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        let encoded = codec
+            .encode(message)
+            .map_err(|e| ReUDPError::EncodeError { reason: e.to_string() })?;
+        let Some(encoded) = self.run_send_hooks(&encoded) else {
+            return Ok(());
+        };
+        self.send_message(MessageType::TypedData, &[&encoded], require_ack)
+    }
+
+    /// Receives a message like `recv` and decodes it as a `T` sent with `send_typed`.
+    ///
+    /// A message that wasn't sent with `send_typed`, or doesn't decode as a `T`,
+    /// is returned as `DecodeError` with its payload, so it isn't lost.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, T)>, ReUDPError>` - The sender and the decoded value, or an error.
+    #[cfg(feature = "serde")]
+    pub fn recv_typed<T: serde::de::DeserializeOwned>(&mut self) -> Result<Option<(SocketAddr, T)>, ReUDPError> {
+        self.recv_typed_with(&PostcardCodec)
+    }
+
+    /// Receives a message like `recv` and decodes it as a `T` with `codec`.
+    ///
+    /// A message that wasn't sent with `send_typed_with`, or doesn't decode as
+    /// a `T` with this codec, is returned as `DecodeError` with its payload.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The encoding the sender used.
     ///
     /// # Returns
     ///
-    /// * `&UdpSocket` - Reference to the UDP socket.
-    pub fn socket(&self) -> &UdpSocket {
-        &self.socket
+    /// * `Result<Option<(SocketAddr, T)>, ReUDPError>` - The sender and the decoded value, or an error.
+    #[cfg(feature = "serde")]
+    pub fn recv_typed_with<C: Codec, T: serde::de::DeserializeOwned>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Option<(SocketAddr, T)>, ReUDPError> {
+        let Some((addr, message)) = self.recv_message()? else {
+            return Ok(None);
+        };
+        if message.message_type != MessageType::TypedData {
+            return Err(ReUDPError::DecodeError {
+                data: message.payload,
+                reason: "not a typed message".to_string(),
+            });
+        }
+        match codec.decode(&message.payload) {
+            Ok(value) => Ok(Some((addr, value))),
+            Err(e) => Err(ReUDPError::DecodeError {
+                data: message.payload,
+                reason: e.to_string(),
+            }),
+        }
     }
+
+    /// Sends a sequenced message of `message_type`, whose payload is `parts`
+    /// put end to end, to every awake peer. Once an encryption key is set, the
+    /// payload is encrypted and the message keeps its type.
+    fn send_message(&mut self, message_type: MessageType, parts: &[&[u8]], require_ack: bool) -> Result<(), ReUDPError> {
+        let send_sequence = Arc::clone(&self.send_sequence);
+        let mut sequence = send_sequence.lock().unwrap();
+        #[cfg(feature = "crypto")]
+        if let Some(sealed) = crypto::seal(&self.cipher, *sequence, &message_type, &parts.concat()) {
+            return self
+                .send_sequenced(&mut sequence, message_type, &[&sealed], require_ack, true)
+                .map(|_| ());
+        }
+        self.send_sequenced(&mut sequence, message_type, parts, require_ack, true)
+            .map(|_| ())
+    }
+
+    /// Does the sending for `send_message` under `sequence`, the locked send
+    /// sequence, queueing the message if a batch is open and `batchable` holds.
+    /// Returns `false`, without taking a sequence number, if the socket's send
+    /// buffer is full.
+    fn send_sequenced(
+        &mut self,
+        sequence: &mut u64,
+        message_type: MessageType,
+        parts: &[&[u8]],
+        require_ack: bool,
+        batchable: bool,
+    ) -> Result<bool, ReUDPError> {
+        let payload_len: usize = parts.iter().map(|part| part.len()).sum();
+        log_span!(
+            send_span = parent: &self.span,
+            "reudp.send",
+            sequence = *sequence,
+            reliable = require_ack,
+            payload_len
+        );
+        log_enter!(send_span);
+        #[cfg(feature = "opentelemetry")]
+        let extensions = trace_context::extensions(&send_span);
+        #[cfg(not(feature = "opentelemetry"))]
+        let extensions = Vec::new();
+        self.check_sendable(message::extensions_len(&extensions) + payload_len, require_ack)?;
+        let serialized = message::encode_with_extensions(*sequence, message_type, &extensions, parts);
+        if batchable {
+            self.send_to_peers(&serialized)?;
+        } else if !self.try_transmit(&serialized)? {
+            return Ok(false);
+        }
+
+        log_trace!(
+            session_id = self.session_id,
+            sequence = *sequence,
+            reliable = require_ack,
+            "Sent message"
+        );
+
+        if require_ack {
+            self.unacked_packets
+                .lock()
+                .unwrap()
+                .insert(*sequence, serialized);
+        }
+        *sequence += 1;
+        self.last_send_time = Instant::now();
+        Ok(true)
+    }
+
+    /// Sends a message on a channel where it is only ordered relative to the
+    /// other messages of the same channel.
+    ///
+    /// Each channel has its own sequence numbers, independent of every other
+    /// channel and of the messages sent with `send`: the receiver holds a
+    /// message back until the earlier ones of its channel were delivered, but
+    /// a message missing on one channel never delays another. The receiver
+    /// tells which channel a message came from with `last_message_channel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - The channel to send on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`,
+    ///   `ChannelMismatch` if the channel was already used by `send_unordered_channel`,
+    ///   or an error.
+    pub fn send_ordered_channel<D: AsRef<[u8]>>(
+        &mut self,
+        channel_id: u8,
+        data: D,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        self.send_channel_message(channel_id, true, data.as_ref(), require_ack)
+    }
+
+    /// Sends a message on a channel whose messages are delivered as soon as
+    /// they arrive, in any order, but still only once.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - The channel to send on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`,
+    ///   `ChannelMismatch` if the channel was already used by `send_ordered_channel`,
+    ///   or an error.
+    pub fn send_unordered_channel<D: AsRef<[u8]>>(
+        &mut self,
+        channel_id: u8,
+        data: D,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        self.send_channel_message(channel_id, false, data.as_ref(), require_ack)
+    }
+
+    /// Sends a message numbered within `channel_id` to every awake peer. The
+    /// payload starts with the channel and whether it is ordered.
+    fn send_channel_message(
+        &mut self,
+        channel_id: u8,
+        ordered: bool,
+        data: &[u8],
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        if self.closing {
+            return Err(ReUDPError::Closing);
+        }
+        let Some(data) = self.run_send_hooks(data) else {
+            return Ok(());
+        };
+        let data = &data[..];
+        let channel = self.channels.entry(channel_id).or_default();
+        let Some(sequence) = channel.next_send_sequence(ordered) else {
+            return Err(ReUDPError::ChannelMismatch {
+                channel: channel_id,
+                ordered,
+            });
+        };
+        log_span!(
+            send_span = parent: &self.span,
+            "reudp.send",
+            channel = channel_id,
+            sequence,
+            reliable = require_ack,
+            payload_len = data.len()
+        );
+        log_enter!(send_span);
+        #[cfg(feature = "opentelemetry")]
+        let extensions = trace_context::extensions(&send_span);
+        #[cfg(not(feature = "opentelemetry"))]
+        let extensions = Vec::new();
+        let len = message::extensions_len(&extensions) + 2 + data.len() + self.seal_overhead();
+        self.check_sendable(len, require_ack)?;
+        let header = [channel_id, ordered as u8];
+        #[cfg(feature = "crypto")]
+        let sealed = crypto::seal(&self.cipher, sequence, &MessageType::ChannelData, &[&header[..], data].concat());
+        #[cfg(not(feature = "crypto"))]
+        let sealed: Option<Vec<u8>> = None;
+        let serialized = match &sealed {
+            Some(sealed) => message::encode_with_extensions(sequence, MessageType::ChannelData, &extensions, &[sealed]),
+            None => message::encode_with_extensions(sequence, MessageType::ChannelData, &extensions, &[&header, data]),
+        };
+        self.send_to_peers(&serialized)?;
+        // Only taken once the message went out, so a failed send leaves no gap.
+        self.channels.entry(channel_id).or_default().on_sent(ordered);
+
+        log_trace!(
+            session_id = self.session_id,
+            channel = channel_id,
+            sequence,
+            reliable = require_ack,
+            "Sent channel message"
+        );
+
+        if require_ack {
+            self.unacked_channel_packets
+                .lock()
+                .unwrap()
+                .insert((channel_id, sequence), serialized);
+        }
+        self.last_send_time = Instant::now();
+        Ok(())
+    }
+
+    /// Sends a serialized message to every awake peer, or queues it while a
+    /// batch is open.
+    fn send_to_peers(&mut self, serialized: &[u8]) -> Result<(), ReUDPError> {
+        if !self.batching {
+            return self.transmit(serialized);
+        }
+        self.pending_batch.push(serialized.to_vec());
+        let started = *self.batch_started.get_or_insert_with(Instant::now);
+        if started.elapsed() >= self.config.load().coalesce_window {
+            self.send_pending_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Sends a serialized datagram to every awake peer, failing with
+    /// `WouldBlock` if the socket is non-blocking and its send buffer is full.
+    fn transmit(&mut self, serialized: &[u8]) -> Result<(), ReUDPError> {
+        if self.try_transmit(serialized)? {
+            Ok(())
+        } else {
+            Err(ReUDPError::WouldBlock)
+        }
+    }
+
+    /// Sends a serialized datagram to every awake peer. Returns `false` if the
+    /// socket's send buffer is full before anything went out; peers it fills up
+    /// for later on miss the datagram, as if it was lost.
+    ///
+    /// In client mode, this also ends a sleep announced with `announce_sleep`.
+    fn try_transmit(&mut self, serialized: &[u8]) -> Result<bool, ReUDPError> {
+        if let Mode::Client(ref remote_addr) = self.mode {
+            // Waking up: resume heartbeats and retransmissions to the server.
+            if let Some(server) = self.peers.lock().unwrap().get_mut(remote_addr) {
+                server.sleeping_until = None;
+            }
+        }
+        let mut sent = false;
+        for target in awake_peers(&self.mode, &self.clients, &self.peers) {
+            match self.socket.send_to(serialized, target) {
+                Ok(_) => sent = true,
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    if !sent {
+                        return Ok(false);
+                    }
+                }
+                Err(source) => {
+                    return Err(ReUDPError::PeerIo {
+                        addr: target,
+                        source,
+                        during: IoContext::Send,
+                    })
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Starts a batch: messages sent from now on are queued and sent together,
+    /// coalesced into as few datagrams as the maximum packet size allows, when
+    /// `flush` is called (e.g. at the end of a frame).
+    ///
+    /// Queued messages take their sequence number and are tracked for
+    /// retransmission as soon as they are sent. They also go out without a
+    /// `flush` once the configured `coalesce_window` has elapsed since the first
+    /// of them, which `send` and `recv` check.
+    pub fn begin_batch(&mut self) {
+        self.batching = true;
+    }
+
+    /// Sends every message queued since `begin_batch` and ends the batch.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of messages sent, or an error.
+    pub fn flush(&mut self) -> Result<usize, ReUDPError> {
+        self.batching = false;
+        self.send_pending_batch()
+    }
+
+    /// Sends the queued messages if they add up to more than `threshold` bytes.
+    /// The batch stays open.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Number of queued bytes above which the messages are sent.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of messages sent (0 if below
+    ///   the threshold), or an error.
+    pub fn flush_if_full(&mut self, threshold: usize) -> Result<usize, ReUDPError> {
+        let queued: usize = self.pending_batch.iter().map(Vec::len).sum();
+        if queued > threshold {
+            self.send_pending_batch()
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Sends the queued messages, packing as many as fit in the maximum packet
+    /// size into each `Batch` datagram, and returns how many were sent.
+    fn send_pending_batch(&mut self) -> Result<usize, ReUDPError> {
+        self.batch_started = None;
+        let pending = std::mem::take(&mut self.pending_batch);
+        let max_len = self.config.load().max_packet_size.min(HEADER_SIZE + u16::MAX as usize);
+        let mut start = 0;
+        while start < pending.len() {
+            let mut end = start + 1;
+            let mut len = HEADER_SIZE + pending[start].len();
+            while end < pending.len() && len + pending[end].len() <= max_len {
+                len += pending[end].len();
+                end += 1;
+            }
+            if end - start == 1 {
+                // Not worth wrapping on its own.
+                self.transmit(&pending[start])?;
+            } else {
+                let batch = Message::new(0, MessageType::Batch, pending[start..end].concat());
+                self.transmit(&batch.to_bytes())?;
+            }
+            start = end;
+        }
+        Ok(pending.len())
+    }
+
+    /// Sends `data` to `addr` as it is, bypassing the reliability layer, e.g. to
+    /// exchange a STUN binding request with a non-ReUDP peer on the same socket.
+    ///
+    /// The datagram has no ReUDP header, takes no sequence number, is never
+    /// retransmitted and doesn't make `addr` a client. Datagrams coming back
+    /// that aren't ReUDP messages go to the handler set with `set_raw_handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to send to.
+    /// * `data` - The bytes to send.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of bytes sent, or an error.
+    pub fn send_raw(&mut self, addr: SocketAddr, data: &[u8]) -> Result<usize, ReUDPError> {
+        self.send_to_peer(data, socket::canonical(addr), IoContext::Send)
+    }
+
+    /// Sends a message to a subset of the clients (server mode), e.g. the players
+    /// in one room.
+    ///
+    /// Where the platform supports it, the message is sent to all addresses
+    /// with a single syscall. When `require_ack` is set, each address has to
+    /// acknowledge the message separately and is the only one it is
+    /// retransmitted to.
+    ///
+    /// Group messages are numbered per client, apart from the messages sent
+    /// with `send`: each client receives the group messages sent to it in
+    /// order, and a message only some clients get leaves no gap in the
+    /// sequence of the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `addrs` - Addresses of the clients to send to.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<HashMap<SocketAddr, Result<(), ReUDPError>>, ReUDPError>` - The
+    ///   outcome of the send for each address, or `Closing` after `disconnect`.
+    pub fn send_to_group<I: IntoIterator<Item = SocketAddr>, D: AsRef<[u8]>>(
+        &mut self,
+        addrs: I,
+        data: D,
+        require_ack: bool,
+    ) -> Result<HashMap<SocketAddr, Result<(), ReUDPError>>, ReUDPError> {
+        if self.closing {
+            return Err(ReUDPError::Closing);
+        }
+        let Some(data) = self.run_send_hooks(data.as_ref()) else {
+            return Ok(HashMap::new());
+        };
+        let mut seen = HashSet::new();
+        let addrs: Vec<SocketAddr> = addrs
+            .into_iter()
+            .map(socket::canonical)
+            .filter(|addr| seen.insert(*addr))
+            .collect();
+        self.check_packet_size(data.len() + self.seal_overhead())?;
+        let datagrams: Vec<(SocketAddr, Vec<u8>)> = addrs
+            .iter()
+            .map(|addr| {
+                let sequence = self.group_send_sequences.get(addr).copied().unwrap_or(0);
+                #[cfg(feature = "crypto")]
+                if let Some(sealed) = crypto::seal(&self.cipher, sequence, &MessageType::GroupData, &data) {
+                    return (*addr, message::encode(sequence, MessageType::GroupData, &[&sealed]));
+                }
+                (*addr, message::encode(sequence, MessageType::GroupData, &[&data]))
+            })
+            .collect();
+
+        let results = self.socket.send_batch(&datagrams);
+
+        log_trace!(
+            session_id = self.session_id,
+            reliable = require_ack,
+            recipients = addrs.len(),
+            "Sent group message"
+        );
+
+        // A client's sequence only moves on once the message went out to it,
+        // so a failed send leaves no gap for it to wait on.
+        let mut unacked_group_packets = self.unacked_group_packets.lock().unwrap();
+        let outcomes = datagrams
+            .into_iter()
+            .zip(results)
+            .map(|((addr, serialized), result)| {
+                let result = match result {
+                    Ok(()) => {
+                        let sequence = self.group_send_sequences.entry(addr).or_insert(0);
+                        if require_ack {
+                            unacked_group_packets.insert((addr, *sequence), serialized);
+                        }
+                        *sequence += 1;
+                        Ok(())
+                    }
+                    Err(source) => Err(ReUDPError::PeerIo {
+                        addr,
+                        source,
+                        during: IoContext::Send,
+                    }),
+                };
+                (addr, result)
+            })
+            .collect();
+        drop(unacked_group_packets);
+        self.last_send_time = Instant::now();
+        Ok(outcomes)
+    }
+
+    /// Subscribes a client to `topic` (server mode), so it receives what is
+    /// published on it. Topics only exist on the server; clients don't know
+    /// about them.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the client.
+    /// * `topic` - The topic to subscribe to, created if needed.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if the client is subscribed, `ClientNotFound`
+    ///   if no client is connected from `addr`, or `WrongMode` in client mode.
+    pub fn subscribe_client(&mut self, addr: SocketAddr, topic: &str) -> Result<(), ReUDPError> {
+        if !matches!(self.mode, Mode::Server) {
+            return Err(ReUDPError::WrongMode {
+                operation: "subscribe_client",
+                expected: ModeKind::Server,
+            });
+        }
+        let addr = socket::canonical(addr);
+        if !self.clients.lock().unwrap().contains(&addr) {
+            return Err(ReUDPError::ClientNotFound { addr });
+        }
+        self.topics.entry(topic.to_string()).or_default().insert(addr);
+        Ok(())
+    }
+
+    /// Unsubscribes a client from `topic` (server mode). Clients are also
+    /// unsubscribed from every topic when they disconnect or are evicted.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the client.
+    /// * `topic` - The topic to unsubscribe from.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok whether or not the client was subscribed,
+    ///   or `WrongMode` in client mode.
+    pub fn unsubscribe_client(&mut self, addr: SocketAddr, topic: &str) -> Result<(), ReUDPError> {
+        if !matches!(self.mode, Mode::Server) {
+            return Err(ReUDPError::WrongMode {
+                operation: "unsubscribe_client",
+                expected: ModeKind::Server,
+            });
+        }
+        if let Some(subscribers) = self.topics.get_mut(topic) {
+            subscribers.remove(&socket::canonical(addr));
+            if subscribers.is_empty() {
+                self.topics.remove(topic);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes `addr` from every topic and group.
+    fn unsubscribe_all(&mut self, addr: SocketAddr) {
+        self.topics.retain(|_, subscribers| {
+            subscribers.remove(&addr);
+            !subscribers.is_empty()
+        });
+        for group in self.groups.values_mut() {
+            group.remove(addr);
+        }
+    }
+
+    /// Sends a message to the clients subscribed to `topic` (server mode), as
+    /// `send_to_group` does.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic to publish on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of subscribers the message was
+    ///   sent to, `WrongMode` in client mode, or an error after `disconnect`.
+    pub fn publish<D: AsRef<[u8]>>(&mut self, topic: &str, data: D, require_ack: bool) -> Result<usize, ReUDPError> {
+        if !matches!(self.mode, Mode::Server) {
+            return Err(ReUDPError::WrongMode {
+                operation: "publish",
+                expected: ModeKind::Server,
+            });
+        }
+        // Evicted clients are only noticed here, as the heartbeat thread evicts them.
+        let clients = self.clients.lock().unwrap().clone();
+        self.topics.retain(|_, subscribers| {
+            subscribers.retain(|addr| clients.contains(addr));
+            !subscribers.is_empty()
+        });
+        let Some(subscribers) = self.topics.get(topic) else {
+            return Ok(0);
+        };
+        let subscribers: Vec<SocketAddr> = subscribers.iter().copied().collect();
+        let results = self.send_to_group(subscribers, data, require_ack)?;
+        Ok(results.values().filter(|result| result.is_ok()).count())
+    }
+
+    /// Returns the client group called `name` (server mode), creating it empty
+    /// if it doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the group.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut ClientGroup` - The group, to add or remove clients.
+    pub fn group(&mut self, name: &str) -> &mut ClientGroup {
+        self.groups.entry(name.to_string()).or_default()
+    }
+
+    /// Sends a message to the members of the client group called `name`
+    /// (server mode), as `send_to_group` does.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the group.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of members the message was sent
+    ///   to, none if the group doesn't exist, `WrongMode` in client mode, or an
+    ///   error after `disconnect`.
+    pub fn send_to_group_name<D: AsRef<[u8]>>(
+        &mut self,
+        name: &str,
+        data: D,
+        require_ack: bool,
+    ) -> Result<usize, ReUDPError> {
+        if !matches!(self.mode, Mode::Server) {
+            return Err(ReUDPError::WrongMode {
+                operation: "send_to_group_name",
+                expected: ModeKind::Server,
+            });
+        }
+        let Some(group) = self.groups.get_mut(name) else {
+            return Ok(0);
+        };
+        // Evicted clients are only noticed here, as the heartbeat thread evicts them.
+        let clients = self.clients.lock().unwrap();
+        group.retain(|addr| clients.contains(addr));
+        drop(clients);
+        let members = group.members();
+        if members.is_empty() {
+            return Ok(0);
+        }
+        let results = self.send_to_group(members, data, require_ack)?;
+        Ok(results.values().filter(|result| result.is_ok()).count())
+    }
+
+    /// Sends a datagram to `addr`, naming the peer and what was being sent in
+    /// the error if the socket refuses it.
+    fn send_to_peer(&self, bytes: &[u8], addr: SocketAddr, during: IoContext) -> Result<usize, ReUDPError> {
+        self.socket
+            .send_to(bytes, addr)
+            .map_err(|source| ReUDPError::PeerIo { addr, source, during })
+    }
+
+    /// Refuses to send a message with a payload of `payload_len` bytes after
+    /// `disconnect`, while a server has no clients, if it is too large, or if
+    /// it is reliable and too many reliable messages wait for an acknowledgment.
+    /// Checked before a sequence number is taken, so a refused message leaves no gap.
+    fn check_sendable(&self, payload_len: usize, require_ack: bool) -> Result<(), ReUDPError> {
+        if self.closing {
+            return Err(ReUDPError::Closing);
+        }
+        if let (Mode::Server, true) = (&self.mode, self.clients.lock().unwrap().is_empty()) {
+            return Err(ReUDPError::NotConnected);
+        }
+        self.check_packet_size(payload_len)?;
+        if require_ack {
+            if let Some(capacity) = self.config.load().max_unacked_packets {
+                let unacked = self.unacked_packets.lock().unwrap().len();
+                if unacked + self.unacked_channel_packets.lock().unwrap().len() >= capacity {
+                    return Err(ReUDPError::QueueFull { capacity });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Refuses messages with a payload of `payload_len` bytes if they are larger
+    /// than the configured maximum packet size, or too large for the header to
+    /// encode their length.
+    fn check_packet_size(&self, payload_len: usize) -> Result<(), ReUDPError> {
+        let len = HEADER_SIZE + payload_len;
+        let max = self.config.load().max_packet_size.min(HEADER_SIZE + u16::MAX as usize);
+        if len > max {
+            return Err(ReUDPError::MessageTooLarge { len, max });
+        }
+        Ok(())
+    }
+
+    /// Returns the largest amount of data `send` accepts in one message with
+    /// the current configuration, taking the header and, once an encryption
+    /// key is set, the encryption overhead into account.
+    ///
+    /// Other ways of sending have their own overhead on top of the data; see
+    /// `Message::overhead_for`.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The maximum data length in bytes.
+    pub fn max_payload_len(&self) -> usize {
+        self.max_payload_len_for(&self.send_message_type())
+    }
+
+    /// Returns the largest amount of data a message of `message_type` can carry.
+    fn max_payload_len_for(&self, message_type: &MessageType) -> usize {
+        let prefix_len = Message::prefix_len(message_type);
+        let max_packet_size = self.config.load().max_packet_size;
+        max_packet_size
+            .saturating_sub(Message::overhead_for(message_type))
+            .min(u16::MAX as usize - prefix_len)
+    }
+
+    /// Returns the number of bytes encryption adds to a payload: none until
+    /// an encryption key is set.
+    fn seal_overhead(&self) -> usize {
+        Message::prefix_len(&self.send_message_type())
+    }
+
+    /// Returns the type of the messages sent by `send`.
+    fn send_message_type(&self) -> MessageType {
+        #[cfg(feature = "crypto")]
+        if self.cipher.lock().unwrap().is_some() {
+            return MessageType::EncryptedData;
+        }
+        MessageType::Data
+    }
+
+    /// Splits `data` into pieces of at most `max_payload_len` bytes, each small
+    /// enough to be sent with `send`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to split.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Iterator<Item = &[u8]>` - The pieces, in order; none for empty data.
+    pub fn split_payload<'a>(&self, data: &'a [u8]) -> impl Iterator<Item = &'a [u8]> {
+        data.chunks(self.max_payload_len().max(1))
+    }
+
+    /// Returns the number of reliable messages still waiting for an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of unacknowledged messages.
+    pub fn pending_acks(&self) -> usize {
+        self.unacked_packets.lock().unwrap().len()
+            + self.unacked_group_packets.lock().unwrap().len()
+            + self.unacked_channel_packets.lock().unwrap().len()
+    }
+
+    /// Performs the connection handshake with the server (client mode).
+    ///
+    /// The request is resent `handshake_retries` times, `handshake_retry_interval`
+    /// apart, until the server accepts or refuses it. An acceptance that arrives
+    /// after `connect` has given up is ignored and answered with a disconnect, so
+    /// it doesn't leave a half-open session on the server.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok once the server accepted the connection,
+    ///   `HandshakeTimeout` if it never answered, `ConnectionRefused` if it refused,
+    ///   `WrongMode` in server mode, or another error.
+    pub fn connect(&mut self) -> Result<(), ReUDPError> {
+        let Mode::Client(remote_addr) = self.mode else {
+            return Err(ReUDPError::WrongMode {
+                operation: "connect",
+                expected: ModeKind::Client,
+            });
+        };
+
+        let nonce = rand::random::<u64>();
+        self.handshake_nonce = Some(nonce);
+        self.connected = false;
+        self.connection_refusal = None;
+        let request = self.connect_request(nonce);
+        let mut result = self.await_handshake(remote_addr, &request);
+        if matches!(result, Err(ReUDPError::HandshakeTimeout)) && self.fall_back_to_tcp(remote_addr) {
+            result = self.await_handshake(remote_addr, &request);
+        }
+        self.handshake_nonce = None;
+        result?;
+
+        if let Some(probe_size) = self.config.load().connect_probe_size {
+            self.probe_bandwidth(probe_size)?;
+        }
+        Ok(())
+    }
+
+    /// Starts a new session with the server (client mode), performing the
+    /// handshake again like `connect`.
+    ///
+    /// The traffic counters of the session so far are kept for
+    /// `previous_session_stats` and the current ones start from zero, along
+    /// with the RTT samples, so measurements don't mix both sessions.
+    /// `session_number` is incremented. Sequence numbers carry on; call
+    /// `reset_sequence` to restart them as well.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok once the server accepted the connection,
+    ///   `HandshakeTimeout` if it never answered, `ConnectionRefused` if it refused,
+    ///   `WrongMode` in server mode, or another error.
+    pub fn reconnect(&mut self) -> Result<(), ReUDPError> {
+        if let Mode::Server = self.mode {
+            return Err(ReUDPError::WrongMode {
+                operation: "reconnect",
+                expected: ModeKind::Client,
+            });
+        }
+        self.previous_session_stats = Some(std::mem::take(&mut self.stats));
+        self.ping_history = PingHistory::default();
+        self.srtt = None;
+        self.current_ping = None;
+        self.session_number += 1;
+        self.connect()
+    }
+
+    /// Returns the traffic counters of the session before the last `reconnect`.
+    /// Add them to `stats` for counts across sessions.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Statistics>` - The counters of the previous session, if `reconnect` was called.
+    pub fn previous_session_stats(&self) -> Option<Statistics> {
+        self.previous_session_stats.clone()
+    }
+
+    /// Returns how many times `reconnect` was called.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of the current session, starting at 0.
+    pub fn session_number(&self) -> u32 {
+        self.session_number
+    }
+
+    /// Resumes an earlier session with the server without waiting for a
+    /// handshake (client mode), so data can be sent right away.
+    ///
+    /// The session token of the earlier session (kept from the last `connect`,
+    /// or restored with `set_session_token`) is sent with a handshake request and
+    /// the client considers itself connected immediately. The server answers as
+    /// it would a `connect`, with a fresh token; an expired or unknown token just
+    /// means a regular handshake on its side. If the server refuses the
+    /// connection, `recv` returns `ConnectionRefused`. Without a token this is
+    /// the same as `connect`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok once the request is sent, `WrongMode` in
+    ///   server mode, or an error.
+    pub fn resume(&mut self) -> Result<(), ReUDPError> {
+        let Mode::Client(remote_addr) = self.mode else {
+            return Err(ReUDPError::WrongMode {
+                operation: "resume",
+                expected: ModeKind::Client,
+            });
+        };
+        if self.session_token.is_none() {
+            return self.connect();
+        }
+
+        let nonce = rand::random::<u64>();
+        self.handshake_nonce = Some(nonce);
+        self.connection_refusal = None;
+        let request = self.connect_request(nonce);
+        self.send_to_peer(&request, remote_addr, IoContext::Control)?;
+        self.connected = true;
+        Ok(())
+    }
+
+    /// Returns the token for resuming the current session with `resume`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SessionToken>` - The token issued by the server, if any.
+    pub fn session_token(&self) -> Option<SessionToken> {
+        self.session_token
+    }
+
+    /// Sets the token `resume` presents to the server, e.g. one saved by an
+    /// earlier run of the application.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Token returned by `session_token`.
+    pub fn set_session_token(&mut self, token: SessionToken) {
+        self.session_token = Some(token);
+    }
+
+    /// Serializes what is needed to pick up the sessions of this instance
+    /// again after a restart, for `import_state`: its addresses, its sequence
+    /// numbers, the connected clients and the session tokens.
+    ///
+    /// Messages waiting for an acknowledgment aren't part of it; peers
+    /// retransmit what they sent, but what this instance sent and wasn't
+    /// acknowledged yet is lost. A server typically exports its state every
+    /// few seconds and imports the last one when it starts.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, ReUDPError>` - The serialized state, or an `Unsupported`
+    ///   error for an instance that doesn't run over a UDP socket.
+    pub fn export_state(&self) -> Result<Vec<u8>, ReUDPError> {
+        let snapshot = self.take_sequence_snapshot();
+        let state = PersistedState {
+            local_addr: self.socket.udp()?.local_addr()?,
+            mode: self.mode.clone(),
+            send_sequence: snapshot.send_sequence,
+            recv_sequence: snapshot.recv_sequence,
+            recv_frontier: self.recv_frontier,
+            session_token: self.session_token,
+            clients: self.client_addrs(),
+            issued_tokens: self.issued_tokens.entries(Instant::now()),
+        };
+        Ok(state.to_bytes())
+    }
+
+    /// Creates an instance from the state serialized by `export_state`, with
+    /// the default configuration. See `import_state_with_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The state returned by `export_state`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ReUDP, ReUDPError>` - The restored instance, an `InvalidData`
+    ///   error if `bytes` isn't a valid state, or another error.
+    pub fn import_state(bytes: &[u8]) -> Result<ReUDP, ReUDPError> {
+        Self::import_state_with_config(bytes, ReUDPConfig::default())
+    }
+
+    /// Creates an instance from the state serialized by `export_state`.
+    ///
+    /// The socket is bound to the address the exported instance had, so its
+    /// peers keep reaching it. A server takes its clients back as if it had
+    /// just heard from them, and accepts their data and the session tokens it
+    /// issued as before the restart. A client keeps its session token but
+    /// isn't connected; `resume` picks the session up with the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The state returned by `export_state`.
+    /// * `config` - Configuration of the instance, which isn't part of the state.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ReUDP, ReUDPError>` - The restored instance, an `InvalidData`
+    ///   error if `bytes` isn't a valid state, or another error.
+    pub fn import_state_with_config(bytes: &[u8], config: ReUDPConfig) -> Result<ReUDP, ReUDPError> {
+        let state = PersistedState::from_bytes(bytes)?;
+        let mut reudp = Self::with_config(&state.local_addr.to_string(), state.mode, config)?;
+        *reudp.send_sequence.lock().unwrap() = state.send_sequence;
+        reudp.recv_sequence = state.recv_sequence;
+        reudp.recv_frontier = state.recv_frontier;
+        reudp.session_token = state.session_token;
+
+        let now = Instant::now();
+        let mut clients = reudp.clients.lock().unwrap();
+        let mut peers = reudp.peers.lock().unwrap();
+        for addr in state.clients {
+            peers.entry(addr).or_insert_with(Peer::new).last_heard = Some(now);
+            if clients.insert(addr) {
+                reudp.stats.on_client_added(clients.len());
+            }
+        }
+        drop((clients, peers));
+        for (token, addr, age) in state.issued_tokens {
+            reudp.issued_tokens.restore(token, addr, age, now);
+        }
+        Ok(reudp)
+    }
+
+    /// Builds a handshake request carrying `nonce` and the session token, if any.
+    fn connect_request(&self, nonce: u64) -> Vec<u8> {
+        let mut payload = nonce.to_be_bytes().to_vec();
+        if let Some(token) = &self.session_token {
+            payload.extend_from_slice(token);
+        }
+        Message::new(0, MessageType::Connect, payload).to_bytes()
+    }
+
+    /// Switches to TCP after the handshake went unanswered over UDP, if the
+    /// configuration asks for it. Returns whether the handshake is worth
+    /// trying again.
+    fn fall_back_to_tcp(&mut self, remote_addr: SocketAddr) -> bool {
+        let config = self.config.load();
+        if config.tcp_fallback != TcpFallback::OnHandshakeTimeout || self.socket.uses_tcp(remote_addr) {
+            return false;
+        }
+        let transport = self
+            .socket
+            .local_addr()
+            .and_then(|local_addr| TcpTransport::connect(remote_addr, local_addr, handshake_time(&config)))
+            .and_then(|transport| self.socket.set_tcp(transport));
+        match transport {
+            Ok(()) => {
+                log_warn!(session_id = self.session_id, server = %remote_addr, "Handshake timed out over UDP, falling back to TCP");
+                log::emit(
+                    &self.logger,
+                    LogLevel::Warn,
+                    "Handshake timed out over UDP, falling back to TCP",
+                );
+                self.events.push_back(Event::FellBackToTcp);
+                true
+            }
+            Err(error) => {
+                log_debug!(session_id = self.session_id, server = %remote_addr, error = %error, "TCP fallback failed");
+                log::emit(&self.logger, LogLevel::Debug, &format!("TCP fallback failed: {}", error));
+                false
+            }
+        }
+    }
+
+    /// Returns whether packets to `addr` go over TCP rather than UDP, as set
+    /// up by the `tcp_fallback` option of the configuration: the server's
+    /// address for a client that fell back, or the address of a client that
+    /// reached the server over TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the peer.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the peer is reached over TCP.
+    pub fn uses_tcp(&self, addr: SocketAddr) -> bool {
+        self.socket.uses_tcp(socket::canonical(addr))
+    }
+
+    /// Sends the handshake request and waits for the server's answer.
+    fn await_handshake(&mut self, remote_addr: SocketAddr, request: &[u8]) -> Result<(), ReUDPError> {
+        let mut received = Vec::new();
+        let result = self.exchange_handshake(remote_addr, request, &mut received);
+        // Messages the server sent right after accepting were already
+        // acknowledged, so they are kept for the application.
+        for (addr, message) in received.into_iter().rev() {
+            self.requeue_delivery(addr, message);
+        }
+        result
+    }
+
+    /// Does the waiting for `await_handshake`, collecting the messages
+    /// delivered in the meantime into `received`.
+    fn exchange_handshake(
+        &mut self,
+        remote_addr: SocketAddr,
+        request: &[u8],
+        received: &mut Vec<(SocketAddr, Message)>,
+    ) -> Result<(), ReUDPError> {
+        let config = self.config.load();
+        for _ in 0..=config.handshake_retries {
+            self.send_to_peer(request, remote_addr, IoContext::Control)?;
+            let deadline = Instant::now() + config.handshake_retry_interval;
+            while Instant::now() < deadline {
+                match self.recv_blocking(Some(Instant::now() + Duration::from_millis(1))) {
+                    // An unreachable port is reported through ICMP; keep retrying
+                    // so it surfaces as a timeout like any other unanswered request.
+                    Err(ReUDPError::IoError(ref e))
+                        if e.kind() == std::io::ErrorKind::ConnectionRefused => {}
+                    Err(e) => return Err(e),
+                    Ok(Some(delivery)) => received.push(delivery),
+                    Ok(None) => {}
+                }
+                if self.connected {
+                    return Ok(());
+                }
+                if let Some(reason) = self.connection_refusal.take() {
+                    return Err(ReUDPError::ConnectionRefused { reason });
+                }
+            }
+        }
+        Err(ReUDPError::HandshakeTimeout)
+    }
+
+    /// Sends a pair of bandwidth probes to the server (or to every client).
+    ///
+    /// The two probes are sent back-to-back and answered immediately by the peer;
+    /// `recv` measures the gap between the two replies and updates the estimate
+    /// returned by `bandwidth_estimate`. In server mode the estimate follows
+    /// whichever client answered last.
+    ///
+    /// # Arguments
+    ///
+    /// * `probe_size` - Size of each probe datagram, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn probe_bandwidth(&mut self, probe_size: usize) -> Result<(), ReUDPError> {
+        let id = rand::random::<u64>();
+        let probes: Vec<Vec<u8>> = (0..2u8)
+            .map(|index| {
+                let mut payload = id.to_be_bytes().to_vec();
+                payload.push(index);
+                payload.extend_from_slice(&clock::now_micros().to_be_bytes());
+                payload.resize(payload.len().max(probe_size.saturating_sub(HEADER_SIZE)), 0);
+                Message::new(0, MessageType::Probe, payload).to_bytes()
+            })
+            .collect();
+        self.bandwidth.start(id, probes[0].len());
+        for target in awake_peers(&self.mode, &self.clients, &self.peers) {
+            for probe in &probes {
+                self.send_to_peer(probe, target, IoContext::Control)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts measuring the path to the server (or to every client) with a run
+    /// of timestamped probes.
+    ///
+    /// `recv` sends one probe every `interval` until `count` have been sent and
+    /// records each reply in `probe_results`, which starting a new run clears.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Time between two probes.
+    /// * `count` - Number of probes to send.
+    pub fn start_probing(&mut self, interval: Duration, count: u32) {
+        self.path_prober.start(interval, count);
+    }
+
+    /// Returns the replies to the path probes of the current run, in arrival order.
+    ///
+    /// # Returns
+    ///
+    /// * `&[ProbeResult]` - RTT, jitter and timestamps of each answered probe.
+    pub fn probe_results(&self) -> &[ProbeResult] {
+        self.path_prober.results()
+    }
+
+    /// Sends the next path probe if it is due.
+    fn send_due_probe(&mut self) -> Result<(), ReUDPError> {
+        let Some((id, sequence)) = self.path_prober.due(Instant::now()) else {
+            return Ok(());
+        };
+        let mut payload = id.to_be_bytes().to_vec();
+        payload.push(0);
+        payload.extend_from_slice(&clock::now_micros().to_be_bytes());
+        let probe = Message::new(sequence, MessageType::Probe, payload).to_bytes();
+        for target in awake_peers(&self.mode, &self.clients, &self.peers) {
+            self.send_to_peer(&probe, target, IoContext::Control)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the available bandwidth measured by the last probes.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u64>` - The smoothed estimate in bytes per second, if a probe pair has been answered.
+    pub fn bandwidth_estimate(&self) -> Option<u64> {
+        self.bandwidth.estimate()
+    }
+
+    /// Returns whether the handshake with the server has completed.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if `connect` succeeded and the server hasn't disconnected us since.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Gracefully shuts the instance down.
+    ///
+    /// Messages queued by `begin_batch` are flushed, and new `send` calls are
+    /// refused with `Closing` and frames stop from now on. Unacknowledged messages keep being retransmitted for up to the configured drain timeout,
+    /// then a disconnect is sent to the server (or to every client) and the
+    /// heartbeat thread stops. Messages received while draining stay queued
+    /// for the receive calls, as does an error about one of the datagrams.
+    ///
+    /// An error that ends the connection or the socket stops the drain early;
+    /// the disconnect is still sent and the heartbeat stopped before it is
+    /// returned.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of messages still unacknowledged
+    ///   when the drain timeout expired, or an error.
+    pub fn disconnect(&mut self) -> Result<usize, ReUDPError> {
+        self.flush()?;
+        self.closing = true;
+        self.frames.lock().unwrap().set_interval(None);
+
+        let mut result = Ok(());
+        let deadline = Instant::now() + self.config.load().drain_timeout;
+        while self.pending_acks() > 0 && Instant::now() < deadline {
+            // Deliveries are left in the queue rather than handed out here.
+            let drained = self.run_timers().and_then(|()| self.fill_delivery_queue(|_| false));
+            match drained {
+                Err(error) if incoming::is_terminal(&error) => {
+                    result = Err(error);
+                    break;
+                }
+                Err(error) => {
+                    self.pending_error.get_or_insert(error);
+                }
+                Ok(()) => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        let unacked = self.pending_acks();
+
+        let disconnect = Message::new(0, MessageType::Disconnect, vec![]).to_bytes();
+        for target in awake_peers(&self.mode, &self.clients, &self.peers) {
+            if let Err(error) = self.send_to_peer(&disconnect, target, IoContext::Control) {
+                result = result.and(Err(error));
+            }
+        }
+        self.connected = false;
+        self.stop_heartbeat();
+        result?;
+
+        log_debug!(session_id = self.session_id, unacked, "Disconnected");
+        Ok(unacked)
+    }
+
+    /// Shuts the instance down immediately.
+    ///
+    /// Unlike `disconnect`, unacknowledged messages are not waited for. A
+    /// disconnect is sent to the server if connected (or to every client), then
+    /// the heartbeat thread is stopped and joined. Called on drop if the instance
+    /// is still running.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or the error of sending the disconnect.
+    pub fn stop(&mut self) -> Result<(), ReUDPError> {
+        let mut result = Ok(());
+        if self.is_running() {
+            let notify = match self.mode {
+                Mode::Client(_) => self.connected,
+                Mode::Server => true,
+            };
+            if notify {
+                let disconnect = Message::new(0, MessageType::Disconnect, vec![]).to_bytes();
+                for target in awake_peers(&self.mode, &self.clients, &self.peers) {
+                    if let Err(e) = self.send_to_peer(&disconnect, target, IoContext::Control) {
+                        result = Err(e);
+                    }
+                }
+            }
+            self.connected = false;
+        }
+        // Join even if the thread already stopped on its own, so it is gone on return.
+        self.stop_heartbeat();
+
+        log_debug!(session_id = self.session_id, "Stopped");
+        result
+    }
+
+    /// Resets sequence numbers on both sides, e.g. after a peer restarted.
+    ///
+    /// Local `send_sequence` and `recv_sequence` go back to 0 and the receive
+    /// buffer and unacknowledged packets are cleared. A `Reset` is sent to the
+    /// server (or to every client), which does the same and confirms with a
+    /// `ResetAck`; unconfirmed resets are resent by `recv` every resend interval.
+    /// Each reset carries an identifier so a resent copy arriving after traffic
+    /// resumed doesn't reset the peer a second time.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn reset_sequence(&mut self) -> Result<(), ReUDPError> {
+        self.clear_sequence_state();
+        self.rollback = None;
+        self.announce_reset()
+    }
+
+    /// Captures the sequence numbers of the session, to roll it back to later
+    /// with `restore_sequence_snapshot`, e.g. when an application-level
+    /// transaction fails.
+    ///
+    /// # Returns
+    ///
+    /// * `SequenceSnapshot` - The next sequence numbers to send and to deliver.
+    pub fn take_sequence_snapshot(&self) -> SequenceSnapshot {
+        SequenceSnapshot {
+            send_sequence: self.send_sequence(),
+            recv_sequence: self.recv_sequence,
+        }
+    }
+
+    /// Rolls the session back to `snapshot` on both sides.
+    ///
+    /// Messages sent since the snapshot was taken are forgotten: their sequence
+    /// numbers are used again by the next sends, and they are no longer
+    /// retransmitted. Messages received but not delivered when the snapshot was
+    /// taken are rolled back too. A `Rollback` tells the server (or every
+    /// client) to rewind its own sequences to match, and is resent like a
+    /// `Reset` until confirmed; until then, `is_reset_pending` returns `true`.
+    /// A server shares its sequences between its clients, so rolling back one
+    /// client of a server rolls back all of them.
+    ///
+    /// Ordered channels keep their own sequences and aren't affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - A snapshot taken with `take_sequence_snapshot` in the current session.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn restore_sequence_snapshot(&mut self, snapshot: SequenceSnapshot) -> Result<(), ReUDPError> {
+        self.rewind_sequences(snapshot.send_sequence, snapshot.recv_sequence);
+        self.rollback = Some((snapshot.send_sequence, snapshot.recv_sequence));
+        self.announce_reset()
+    }
+
+    /// Sends the reset or rollback just applied to every peer, to be resent until confirmed.
+    fn announce_reset(&mut self) -> Result<(), ReUDPError> {
+        self.reset_id = rand::random::<u64>();
+        let reset = self.reset_message();
+        for target in awake_peers(&self.mode, &self.clients, &self.peers) {
+            self.send_to_peer(&reset, target, IoContext::Control)?;
+            self.pending_resets.insert(target, Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Moves the sequences back to `send_sequence` and `recv_sequence`, dropping
+    /// what was sent or received from there on.
+    fn rewind_sequences(&mut self, send_sequence: u64, recv_sequence: u64) {
+        let mut sequence = self.send_sequence.lock().unwrap();
+        let send_sequence = send_sequence.min(*sequence);
+        *sequence = send_sequence;
+        self.unacked_packets.lock().unwrap().retain(|&seq, _| seq < send_sequence);
+        drop(sequence);
+        self.pending_batch.clear();
+        self.batch_started = None;
+        self.recv_sequence = self.recv_sequence.min(recv_sequence);
+        self.recv_frontier = self.recv_frontier.min(recv_sequence);
+        self.recv_buffer.retain(|&seq, _| seq < recv_sequence);
+    }
+
+    /// Returns whether a sequence reset is still waiting for confirmation.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if some peer hasn't answered `reset_sequence` with a `ResetAck` yet.
+    pub fn is_reset_pending(&self) -> bool {
+        !self.pending_resets.is_empty()
+    }
+
+    /// Resets sequence numbers and drops all buffered and unacknowledged messages.
+    fn clear_sequence_state(&mut self) {
+        *self.send_sequence.lock().unwrap() = 0;
+        self.recv_sequence = 0;
+        self.recv_frontier = 0;
+        self.recv_buffer.clear();
+        self.unacked_packets.lock().unwrap().clear();
+        self.unacked_group_packets.lock().unwrap().clear();
+        self.group_send_sequences.clear();
+        self.group_channels.clear();
+        self.channels.clear();
+        self.unacked_channel_packets.lock().unwrap().clear();
+        self.pending_batch.clear();
+        self.batch_started = None;
+    }
+
+    /// Serializes the `Reset` or `Rollback` for the reset we initiated last.
+    fn reset_message(&self) -> Vec<u8> {
+        let mut payload = self.reset_id.to_be_bytes().to_vec();
+        match self.rollback {
+            Some((send_sequence, recv_sequence)) => {
+                payload.extend_from_slice(&send_sequence.to_be_bytes());
+                payload.extend_from_slice(&recv_sequence.to_be_bytes());
+                Message::new(0, MessageType::Rollback, payload).to_bytes()
+            }
+            None => Message::new(0, MessageType::Reset, payload).to_bytes(),
+        }
+    }
+
+    /// Resends sequence resets that haven't been confirmed within the resend interval.
+    fn resend_resets(&mut self) -> Result<(), ReUDPError> {
+        let reset = self.reset_message();
+        for (addr, sent_at) in self.pending_resets.iter_mut() {
+            if sent_at.elapsed() >= self.config.load().resend_interval {
+                self.socket.send_to(&reset, *addr).map_err(|source| ReUDPError::PeerIo {
+                    addr: *addr,
+                    source,
+                    during: IoContext::Retransmit,
+                })?;
+                *sent_at = Instant::now();
+            }
+        }
+        Ok(())
+    }
+
+    /// Tells the server that this client is going to sleep.
+    ///
+    /// Until `duration` has elapsed or the client sends again, the server keeps the
+    /// client registered and suppresses all traffic to it, and the client stops
+    /// sending heartbeats and retransmissions. Waking up doesn't require a reconnect:
+    /// the next `send` resumes normal operation on both sides. Has no effect in
+    /// server mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - How long the client intends to sleep.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn announce_sleep(&mut self, duration: Duration) -> Result<(), ReUDPError> {
+        if let Mode::Client(remote_addr) = self.mode {
+            self.flush_acks()?;
+            let millis = duration.as_millis().min(u64::MAX as u128) as u64;
+            let message = Message::new(0, MessageType::Sleep, millis.to_be_bytes().to_vec());
+            self.send_to_peer(&message.to_bytes(), remote_addr, IoContext::Control)?;
+            self.peers
+                .lock()
+                .unwrap()
+                .entry(remote_addr)
+                .or_insert_with(Peer::new)
+                .sleeping_until = Some(Instant::now() + duration);
+        }
+        Ok(())
+    }
+
+    /// Returns whether a peer is currently considered asleep.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the peer.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the peer announced a sleep that hasn't ended yet.
+    pub fn is_sleeping(&self, addr: SocketAddr) -> bool {
+        self.peers
+            .lock()
+            .unwrap()
+            .get(&socket::canonical(addr))
+            .is_some_and(|peer| peer.is_sleeping(Instant::now()))
+    }
+
+    /// Sends all acknowledgments held back by lazy ack flushing.
+    ///
+    /// Acknowledgments for the same peer are combined into a single datagram.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn flush_acks(&mut self) -> Result<(), ReUDPError> {
+        self.last_ack_flush = Instant::now();
+        for (addr, sequences) in std::mem::take(&mut self.pending_acks) {
+            let Some((first, rest)) = sequences.split_first() else {
+                continue;
+            };
+            let payload = rest.iter().flat_map(|s| s.to_be_bytes()).collect();
+            let ack = Message::new(*first, MessageType::Ack, payload);
+            self.send_to_peer(&ack.to_bytes(), addr, IoContext::Ack)?;
+        }
+        Ok(())
+    }
+
+    /// Receives a message, handling acknowledgment and heartbeats.
+    ///
+    /// By default this processes at most one datagram and returns `None` if
+    /// there was nothing to deliver. After `set_blocking(true)` it waits until a
+    /// message is delivered instead, like `recv_timeout` without a deadline.
+    ///
+    /// In client mode, fails with `ConnectionLost` when the server reports it has
+    /// no session for us (e.g. it restarted). Sequence numbers and unacknowledged
+    /// messages are dropped at that point, so sending can resume right away or
+    /// after calling `connect` again.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError>` - The address and data of the received message, or an error.
+    pub fn recv(&mut self) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        Ok(self.recv_message()?.map(|(addr, message)| (addr, message.payload)))
+    }
+
+    /// Receives a message like `recv`, but returns it whole, with its sequence
+    /// number, type and extensions, e.g. for debugging or custom message types.
+    ///
+    /// Only messages carrying application data are returned; control traffic
+    /// is handled internally as by `recv`. The payload is the application data:
+    /// the timestamp of a `TimestampedData` message and the channel header of a
+    /// `ChannelData` message are removed, and the sequence number of the latter
+    /// is the one within its channel, which `last_message_channel` tells.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Message)>, ReUDPError>` - The address and the received message, or an error.
+    pub fn recv_message(&mut self) -> Result<Option<(SocketAddr, Message)>, ReUDPError> {
+        if self.blocking {
+            self.recv_blocking(None)
+        } else {
+            self.recv_once()
+        }
+    }
+
+    /// Receives a message like `recv`, returning its data as `Bytes`.
+    ///
+    /// The data is copied once, out of the receive buffer, as with `recv`; the
+    /// `Bytes` then takes over that allocation without copying it again. To
+    /// send `Bytes`, pass them to `send`, which copies them only into the packet.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Bytes)>, ReUDPError>` - The address and data received, or an error.
+    #[cfg(feature = "bytes")]
+    pub fn recv_bytes(&mut self) -> Result<Option<(SocketAddr, bytes::Bytes)>, ReUDPError> {
+        Ok(self
+            .recv_message()?
+            .map(|(addr, message)| (addr, bytes::Bytes::from(message.payload))))
+    }
+
+    /// Receives a message like `recv`, copying its data into `buf` instead of
+    /// returning a new `Vec`.
+    ///
+    /// A message that doesn't fit is kept for the next call and reported as
+    /// `BufferTooSmall` with the size it needs.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to copy the data into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, usize)>, ReUDPError>` - The sender and the length
+    ///   of the data copied to the start of `buf`, or an error.
+    pub fn recv_into(&mut self, buf: &mut [u8]) -> Result<Option<(SocketAddr, usize)>, ReUDPError> {
+        let Some((addr, message)) = self.recv_message()? else {
+            return Ok(None);
+        };
+        let len = message.payload.len();
+        if len > buf.len() {
+            self.requeue_delivery(addr, message);
+            return Err(ReUDPError::BufferTooSmall { needed: len });
+        }
+        buf[..len].copy_from_slice(&message.payload);
+        Ok(Some((addr, len)))
+    }
+
+    /// Receives a message like `recv`, telling where it came from as a
+    /// `RecvSource`: the path of the sender's socket for an instance created
+    /// with `new_unix`, or its network address.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(RecvSource, Vec<u8>)>, ReUDPError>` - The source and data of the received message, or an error.
+    pub fn recv_source(&mut self) -> Result<Option<(RecvSource, Vec<u8>)>, ReUDPError> {
+        Ok(self.recv()?.map(|(addr, data)| (self.source_of(addr), data)))
+    }
+
+    /// Processes at most one datagram, returning the message it delivers, if any.
+    fn recv_once(&mut self) -> Result<Option<(SocketAddr, Message)>, ReUDPError> {
+        if let Some(error) = self.pending_error.take() {
+            return Err(error);
+        }
+        self.check_proxy()?;
+        self.run_timers()?;
+
+        if let Some(message) = self.next_delivery() {
+            return Ok(Some(message));
+        }
+
+        let mut buf = std::mem::take(&mut self.recv_buf);
+        let result = match self.socket.recv_from(&mut buf) {
+            Ok((len, addr)) => self.process_datagram(addr, &buf[..len], buf.len() - 1),
+            // A read timeout expiring is reported as either, depending on the platform.
+            Err(ref e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => Ok(()),
+            Err(ref e) if e.kind() == std::io::ErrorKind::ConnectionReset => {
+                self.ignore_connection_reset();
+                Ok(())
+            }
+            Err(e) => Err(ReUDPError::IoError(e)),
+        };
+        self.recv_buf = buf;
+        result?;
+        Ok(self.next_delivery())
+    }
+
+    /// Counts a receive that failed with a connection reset. On Windows, an
+    /// earlier datagram that hit a closed port makes the next receive fail that
+    /// way; it doesn't say which peer it was for, and the socket itself is fine.
+    fn ignore_connection_reset(&mut self) {
+        self.stats.connection_resets_ignored += 1;
+        log_debug!(session_id = self.session_id, "Ignored a connection reset reported by the socket");
+    }
+
+    /// Does the periodic work driven by `recv`: scheduled sends, ack flushing
+    /// and the resends of resets, path challenges and probes.
+    fn run_timers(&mut self) -> Result<(), ReUDPError> {
+        if !self.scheduled.is_empty() {
+            self.send_scheduled()?;
+        }
+
+        if let Some(started) = self.batch_started {
+            if started.elapsed() >= self.config.load().coalesce_window {
+                self.send_pending_batch()?;
+            }
+        }
+
+        if let Some(interval) = self.config.load().ack_flush_interval {
+            if self.last_ack_flush.elapsed() >= interval {
+                self.flush_acks()?;
+            }
+        }
+
+        if !self.pending_resets.is_empty() {
+            self.resend_resets()?;
+        }
+
+        if self.pending_migration.is_some() {
+            self.resend_path_challenge()?;
+        }
+
+        self.send_due_probe()?;
+
+        if let Some((old_addr, grace_end)) = self.previous_server {
+            if Instant::now() >= grace_end {
+                self.remove_allowed_sender(old_addr);
+                self.previous_server = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails with `ConnectionLost` once the SOCKS5 proxy the client goes
+    /// through closed the control connection, which ends the UDP association.
+    fn check_proxy(&mut self) -> Result<(), ReUDPError> {
+        if self.socket.proxy_lost() {
+            self.connected = false;
+            stop_running(&self.running, &self.disconnect_callback, DisconnectReason::ProxyClosed);
+            return Err(ReUDPError::ConnectionLost);
+        }
+        Ok(())
+    }
+
+    /// Handles one datagram received from `addr` into a buffer of `buffer`
+    /// bytes, queueing the messages it makes deliverable.
+    fn process_datagram(&mut self, addr: SocketAddr, bytes: &[u8], buffer: usize) -> Result<(), ReUDPError> {
+        if !self.is_allowed_sender(addr) {
+            self.stats.packets_dropped_unauthorized += 1;
+            log_debug!(session_id = self.session_id, from = %addr, "Dropped packet from unauthorized sender");
+            return Ok(());
+        }
+        self.last_recv_addr = Some(addr);
+
+        // Never deliver part of a datagram the buffer cut off.
+        if bytes.len() > buffer {
+            self.stats.packets_dropped_truncated += 1;
+            return self.reject_datagram(addr, ReUDPError::Truncated { addr, buffer });
+        }
+
+        let message = match Message::from_bytes(bytes) {
+            Ok(message) => message,
+            Err(error) => {
+                if let Some(handler) = &self.raw_handler {
+                    handler(addr, bytes);
+                    return Ok(());
+                }
+                self.stats.packets_dropped_malformed += 1;
+                return self.reject_datagram(addr, error);
+            }
+        };
+        self.process_message(addr, message)
+    }
+
+    /// Applies the configured `MalformedPolicy` to a datagram from `addr` that
+    /// was dropped because of `error`.
+    fn reject_datagram(&mut self, addr: SocketAddr, error: ReUDPError) -> Result<(), ReUDPError> {
+        log_debug!(session_id = self.session_id, from = %addr, error = %error, "Dropped datagram");
+        let policy = self.config.load().on_malformed.unwrap_or(match self.mode {
+            Mode::Client(_) => MalformedPolicy::Error,
+            Mode::Server => MalformedPolicy::Drop,
+        });
+        match policy {
+            MalformedPolicy::Error => return Err(error),
+            MalformedPolicy::Drop => {}
+            MalformedPolicy::DropAndLog => log::emit(
+                &self.logger,
+                LogLevel::Warn,
+                &format!("Dropped a datagram from {}: {}", addr, error),
+            ),
+        }
+        Ok(())
+    }
+
+    /// Returns how many received datagrams were dropped because they couldn't
+    /// be handled: those that don't parse as ReUDP messages (unless a raw
+    /// handler takes them), batches with a malformed message, datagrams
+    /// larger than the receive buffer and messages that failed to decrypt.
+    /// Counted whatever the `MalformedPolicy`, along with the other counters
+    /// of `stats`.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The number of dropped datagrams.
+    pub fn malformed_packets(&self) -> u64 {
+        self.stats.packets_dropped_malformed
+            + self.stats.packets_dropped_truncated
+            + self.stats.messages_dropped_undecryptable
+    }
+
+    /// Handles one message received from `addr`. Data messages go to the
+    /// delivery queue once every earlier one has.
+    fn process_message(&mut self, addr: SocketAddr, message: Message) -> Result<(), ReUDPError> {
+        log_span!(
+            recv_span = parent: &self.span,
+            "reudp.recv",
+            from = %addr,
+            sequence = message.sequence,
+            message_type = ?message.message_type
+        );
+        #[cfg(feature = "opentelemetry")]
+        if let Some(traceparent) = message.extension(TRACEPARENT_EXTENSION) {
+            trace_context::set_remote_parent(&recv_span, traceparent, message.extension(TRACESTATE_EXTENSION));
+        }
+        log_enter!(recv_span);
+        log_trace!(
+            session_id = self.session_id,
+            from = %addr,
+            sequence = message.sequence,
+            message_type = ?message.message_type,
+            "Received message"
+        );
+
+        // Forged or foreign ciphertexts are dropped before they can open a session.
+        let message = match self.decrypt_message(message) {
+            Ok(message) => message,
+            Err(error) => {
+                self.stats.messages_dropped_undecryptable += 1;
+                return self.reject_datagram(addr, error);
+            }
+        };
+        let Some(message) = self.run_recv_hooks(addr, message) else {
+            log_debug!(session_id = self.session_id, from = %addr, "Message dropped by a hook");
+            return Ok(());
+        };
+
+        let data = matches!(
+            message.message_type,
+            MessageType::Data
+                | MessageType::TimestampedData
+                | MessageType::TypedData
+                | MessageType::EncryptedData
+                | MessageType::Custom(_)
+        );
+        if let (Mode::Server, true) = (&self.mode, data) {
+            if message.sequence >= SESSION_WINDOW && !self.clients.lock().unwrap().contains(&addr) {
+                // The sender is mid-session with an instance that no longer exists
+                // (e.g. we restarted): tell it rather than black-holing its traffic.
+                log_debug!(session_id = self.session_id, from = %addr, sequence = message.sequence, "Data from unknown session");
+                let unknown = Message::new(message.sequence, MessageType::SessionUnknown, vec![]);
+                self.send_to_peer(&unknown.to_bytes(), addr, IoContext::Control)?;
+                return Ok(());
+            }
+        }
+
+        let handshake = matches!(
+            message.message_type,
+            MessageType::Connect | MessageType::Disconnect | MessageType::PathChallenge
+        );
+        let tracked = match self.mode {
+            Mode::Client(remote_addr) => remote_addr == addr,
+            Mode::Server => !handshake,
+        };
+        if tracked {
+            let mut peers = self.peers.lock().unwrap();
+            let peer = peers.entry(addr).or_insert_with(Peer::new);
+            peer.last_heard = Some(Instant::now());
+            if let (Mode::Server, false) = (&self.mode, message.message_type == MessageType::Sleep) {
+                // Any other traffic from a sleeping client means it woke up.
+                peer.sleeping_until = None;
+            }
+        }
+        if data || matches!(message.message_type, MessageType::ChannelData | MessageType::GroupData) {
+            self.last_recv_time = Instant::now();
+        }
+        // Registered after the peer, or the heartbeat thread could evict a
+        // client it hasn't heard from yet.
+        if let (Mode::Server, false) = (&self.mode, handshake) {
+            let mut clients = self.clients.lock().unwrap();
+            if clients.insert(addr) {
+                self.stats.on_client_added(clients.len());
+                drop(clients);
+                self.notify_client_connect(addr);
+            }
+        }
+
+        match message.message_type {
+            MessageType::Data
+            | MessageType::TimestampedData
+            | MessageType::TypedData
+            | MessageType::EncryptedData
+            | MessageType::Custom(_) => {
+                if self.config.load().ack_flush_interval.is_some() {
+                    self.pending_acks
+                        .entry(addr)
+                        .or_default()
+                        .push(message.sequence);
+                } else {
+                    let ack: [u8; HEADER_SIZE] = message::encode_array(message.sequence, MessageType::Ack, &[]);
+                    self.send_to_peer(&ack, addr, IoContext::Ack)?;
+                }
+
+                let expected = self.recv_frontier.max(self.recv_sequence);
+                if message.sequence > expected {
+                    if let Some(callback) = &self.sequence_gap_callback {
+                        callback(expected, message.sequence);
+                    }
+                }
+                self.recv_frontier = expected.max(message.sequence.saturating_add(1));
+
+                // Duplicates of delivered messages are only acknowledged again.
+                if message.sequence >= self.recv_sequence {
+                    self.recv_buffer.insert(message.sequence, (addr, message));
+                    self.release_in_order();
+                }
+                if self.recv_buffer.len() > NACK_THRESHOLD {
+                    self.send_nack(addr)?;
+                }
+                Ok(())
+            }
+            MessageType::ChannelData => {
+                let (Some(&channel_id), Some(&ordered)) = (message.payload.first(), message.payload.get(1)) else {
+                    return Ok(());
+                };
+                let ack: [u8; HEADER_SIZE + 1] =
+                    message::encode_array(message.sequence, MessageType::ChannelAck, &[channel_id]);
+                self.send_to_peer(&ack, addr, IoContext::Ack)?;
+
+                let mut message = message;
+                message.payload.drain(..2);
+                let ready = self.channels.entry(channel_id).or_default().receive(addr, ordered != 0, message);
+                for (addr, message) in ready {
+                    self.queue_delivery(Delivery {
+                        addr,
+                        message,
+                        latency: None,
+                        channel: Some(channel_id),
+                    });
+                }
+                Ok(())
+            }
+            MessageType::GroupData => {
+                let ack: [u8; HEADER_SIZE] = message::encode_array(message.sequence, MessageType::GroupAck, &[]);
+                self.send_to_peer(&ack, addr, IoContext::Ack)?;
+
+                let ready = self.group_channels.entry(addr).or_default().receive(addr, true, message);
+                for (addr, message) in ready {
+                    self.queue_delivery(Delivery {
+                        addr,
+                        message,
+                        latency: None,
+                        channel: None,
+                    });
+                }
+                Ok(())
+            }
+            MessageType::Batch => {
+                let mut rest = &message.payload[..];
+                // An error about one message doesn't keep the next ones from
+                // being handled; the first is returned once they were.
+                let mut result = Ok(());
+                while !rest.is_empty() {
+                    let inner = match Message::from_bytes(rest) {
+                        Ok(inner) => inner,
+                        Err(error) => {
+                            // The messages before the malformed one were handled.
+                            self.stats.packets_dropped_malformed += 1;
+                            return result.and(self.reject_datagram(addr, error));
+                        }
+                    };
+                    rest = &rest[inner.encoded_len()..];
+                    let handled = self.process_message(addr, inner);
+                    result = result.and(handled);
+                }
+                result
+            }
+            MessageType::ChannelAck => {
+                if let Some(&channel_id) = message.payload.first() {
+                    self.unacked_channel_packets
+                        .lock()
+                        .unwrap()
+                        .remove(&(channel_id, message.sequence));
+                }
+                Ok(())
+            }
+            MessageType::GroupAck => {
+                self.unacked_group_packets
+                    .lock()
+                    .unwrap()
+                    .remove(&(addr, message.sequence));
+                Ok(())
+            }
+            MessageType::Ack => {
+                // Batched acks carry further sequence numbers in the payload.
+                let mut unacked_packets = self.unacked_packets.lock().unwrap();
+                let sequences = message
+                    .payload
+                    .chunks_exact(8)
+                    .map(|sequence| u64::from_be_bytes(sequence.try_into().unwrap()));
+                for sequence in std::iter::once(message.sequence).chain(sequences) {
+                    unacked_packets.remove(&sequence);
+                }
+                Ok(())
+            }
+            MessageType::Heartbeat => {
+                // Echo the sender's transmit time along with our receive and
+                // transmit times so the sender can estimate RTT and clock offset,
+                // and its sequence number so it can tell which heartbeat this answers.
+                let received_at = clock::now_micros();
+                let sent_at = read_u64(&message.payload, 0).unwrap_or(0);
+                let mut payload = [0; 24];
+                payload[..8].copy_from_slice(&sent_at.to_be_bytes());
+                payload[8..16].copy_from_slice(&received_at.to_be_bytes());
+                payload[16..].copy_from_slice(&clock::now_micros().to_be_bytes());
+                let response: [u8; HEADER_SIZE + 24] =
+                    message::encode_array(message.sequence, MessageType::HeartbeatAck, &payload);
+                self.send_to_peer(&response, addr, IoContext::Heartbeat)?;
+
+                self.last_heartbeat_response_time = Some(Instant::now());
+                if let Some(peer) = self.peers.lock().unwrap().get_mut(&addr) {
+                    peer.loss.on_heartbeat(message.sequence);
+                }
+                self.update_quality();
+
+                Ok(())
+            }
+            MessageType::HeartbeatAck => {
+                let t3 = clock::now_micros();
+                self.last_heartbeat_response_time = Some(Instant::now());
+                if let Some(peer) = self.peers.lock().unwrap().get_mut(&addr) {
+                    // A duplicated answer would yield a second, too long RTT sample.
+                    if peer.last_heartbeat_answered == Some(message.sequence) {
+                        return Ok(());
+                    }
+                    peer.last_heartbeat_answered = Some(message.sequence);
+                }
+
+                if let (Some(t0), Some(t1), Some(t2)) = (
+                    read_u64(&message.payload, 0),
+                    read_u64(&message.payload, 8),
+                    read_u64(&message.payload, 16),
+                ) {
+                    let rtt = t3.saturating_sub(t0).saturating_sub(t2.saturating_sub(t1));
+                    self.update_rtt(Duration::from_micros(rtt));
+                    if let Some(peer) = self.peers.lock().unwrap().get_mut(&addr) {
+                        peer.clock.add_sample(t0, t1, t2, t3);
+                    }
+                    self.update_quality();
+                }
+
+                Ok(())
+            }
+            MessageType::Sleep => {
+                if let (Mode::Server, Some(millis)) = (&self.mode, read_u64(&message.payload, 0)) {
+                    let wake_time = Instant::now() + Duration::from_millis(millis);
+                    if let Some(peer) = self.peers.lock().unwrap().get_mut(&addr) {
+                        peer.sleeping_until = Some(wake_time);
+                    }
+                }
+                Ok(())
+            }
+            MessageType::Connect => {
+                if let Mode::Server = self.mode {
+                    self.accept_connection(addr, &message.payload)?;
+                }
+                Ok(())
+            }
+            MessageType::Accept => {
+                let nonce = read_u64(&message.payload, 0);
+                if nonce.is_some() && nonce == self.handshake_nonce {
+                    self.connected = true;
+                    self.handshake_nonce = None;
+                    self.session_nonce = nonce;
+                    self.last_heartbeat_response_time = Some(Instant::now());
+                    // The server numbers our group messages from 0 again.
+                    self.group_channels.remove(&addr);
+                    if let Some(token) = message.payload.get(8..40) {
+                        self.session_token = Some(token.try_into().unwrap());
+                    }
+                } else if nonce != self.session_nonce {
+                    // A late answer to a handshake we gave up on: tear down
+                    // the session the server just created for us.
+                    let disconnect = Message::new(0, MessageType::Disconnect, vec![]);
+                    self.send_to_peer(&disconnect.to_bytes(), addr, IoContext::Control)?;
+                }
+                Ok(())
+            }
+            MessageType::ConnectDeny => {
+                let nonce = read_u64(&message.payload, 0);
+                if nonce.is_some() && nonce == self.handshake_nonce {
+                    let reason = message.payload[8..].to_vec();
+                    if self.connected {
+                        // Refused after `resume` already assumed the session was back.
+                        self.connected = false;
+                        self.handshake_nonce = None;
+                        return Err(ReUDPError::ConnectionRefused { reason });
+                    }
+                    self.connection_refusal = Some(reason);
+                }
+                Ok(())
+            }
+            MessageType::Disconnect => {
+                match self.mode {
+                    Mode::Server => {
+                        self.clients.lock().unwrap().remove(&addr);
+                        self.peers.lock().unwrap().remove(&addr);
+                        self.unsubscribe_all(addr);
+                        self.forget_group_sequence(addr);
+                    }
+                    Mode::Client(remote_addr) if remote_addr == addr => {
+                        self.connected = false;
+                        self.session_nonce = None;
+                    }
+                    Mode::Client(_) => {}
+                }
+                Ok(())
+            }
+            MessageType::Reset | MessageType::Rollback => {
+                // The peer restarted or rolled back its sequences (or asked us
+                // to): follow suit without involving the application, unless
+                // this is a resent copy of a reset we already applied.
+                let reset_id = read_u64(&message.payload, 0);
+                let duplicate = reset_id.is_some()
+                    && self
+                        .peers
+                        .lock()
+                        .unwrap()
+                        .get_mut(&addr)
+                        .map(|peer| std::mem::replace(&mut peer.last_reset_id, reset_id))
+                        .is_some_and(|last_reset_id| last_reset_id == reset_id);
+                if !duplicate {
+                    if message.message_type == MessageType::Reset {
+                        self.clear_sequence_state();
+                    } else if let (Some(peer_send), Some(peer_recv)) =
+                        (read_u64(&message.payload, 8), read_u64(&message.payload, 16))
+                    {
+                        // What the peer delivers next is what we send next, and the other way round.
+                        self.rewind_sequences(peer_recv, peer_send);
+                    }
+                }
+                let ack = Message::new(0, MessageType::ResetAck, vec![]);
+                self.send_to_peer(&ack.to_bytes(), addr, IoContext::Ack)?;
+                Ok(())
+            }
+            MessageType::ResetAck => {
+                self.pending_resets.remove(&addr);
+                Ok(())
+            }
+            MessageType::Nack => {
+                let (Some(base), Some(bitmap)) = (read_u64(&message.payload, 0), message.payload.get(8..24)) else {
+                    return Ok(());
+                };
+                // Resend the marked messages right away rather than on the next tick.
+                let unacked_packets = self.unacked_packets.lock().unwrap();
+                for i in 0..NACK_WINDOW {
+                    if bitmap[i as usize / 8] & (1 << (i % 8)) == 0 {
+                        continue;
+                    }
+                    if let Some(packet) = unacked_packets.get(&base.saturating_add(i)) {
+                        self.send_to_peer(packet, addr, IoContext::Retransmit)?;
+                    }
+                }
+                Ok(())
+            }
+            MessageType::SessionUnknown => {
+                // Only act on it for a sequence sent in the current numbering, so
+                // replies to stale retransmissions don't tear down the new session.
+                if let Mode::Client(_) = self.mode {
+                    if message.sequence < self.send_sequence() {
+                        log_warn!(session_id = self.session_id, from = %addr, "Server doesn't know our session");
+                        self.clear_sequence_state();
+                        self.connected = false;
+                        return Err(ReUDPError::ConnectionLost);
+                    }
+                }
+                Ok(())
+            }
+            MessageType::Probe => {
+                // The identifier, index and send time are echoed without the
+                // padding, followed by the time the probe arrived.
+                let mut payload = message.payload.get(..17).unwrap_or(&message.payload).to_vec();
+                payload.extend_from_slice(&clock::now_micros().to_be_bytes());
+                let reply = Message::new(message.sequence, MessageType::ProbeReply, payload);
+                self.send_to_peer(&reply.to_bytes(), addr, IoContext::Control)?;
+                Ok(())
+            }
+            MessageType::ProbeReply => {
+                if let (Some(id), Some(&index)) = (read_u64(&message.payload, 0), message.payload.get(8)) {
+                    self.bandwidth.add_reply(addr, id, index, Instant::now());
+                }
+                if let (Some(id), Some(sent_at), Some(remote_received_at)) = (
+                    read_u64(&message.payload, 0),
+                    read_u64(&message.payload, 9),
+                    read_u64(&message.payload, 17),
+                ) {
+                    self.path_prober.add_reply(
+                        addr,
+                        id,
+                        message.sequence,
+                        sent_at,
+                        remote_received_at,
+                        clock::now_micros(),
+                    );
+                }
+                Ok(())
+            }
+            MessageType::PathChallenge => {
+                let response = Message::new(0, MessageType::PathResponse, message.payload);
+                self.send_to_peer(&response.to_bytes(), addr, IoContext::Control)?;
+                Ok(())
+            }
+            MessageType::PathResponse => {
+                let validated = self.pending_migration.as_ref().is_some_and(|migration| {
+                    migration.addr == addr && read_u64(&message.payload, 0) == Some(migration.nonce)
+                });
+                if validated {
+                    self.pending_migration = None;
+                    self.complete_migration(addr);
+                }
+                Ok(())
+            }
+            MessageType::Unknown(t) => {
+                log_warn!(session_id = self.session_id, from = %addr, message_type = t, "Received unknown message type");
+                log::emit(
+                    &self.logger,
+                    LogLevel::Debug,
+                    &format!("Received unknown message type {} from {}", t, addr),
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Receives every message currently pending, handling acknowledgments and
+    /// heartbeats along the way.
+    ///
+    /// Reads the socket until it has nothing more to offer, or until the
+    /// configured `max_recv_batch` datagrams have been processed, and returns the
+    /// messages in delivery order, including ones held back until a gap was
+    /// filled. If an error occurs after some messages were received, they are
+    /// returned and the error is reported by the next receive call.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<(SocketAddr, Vec<u8>)>, ReUDPError>` - The received messages
+    ///   and their senders (possibly none), or an error.
+    pub fn recv_all(&mut self) -> Result<Vec<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        let mut messages = Vec::new();
+        let result = self.recv_batch(&mut messages, usize::MAX);
+        self.defer_error(result, &messages)?;
+        Ok(messages)
+    }
+
+    /// Like `recv_all`, but appends the messages to `messages` so its allocation
+    /// can be reused. Messages received before an error are kept in `messages`.
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - Where to append the received messages and their senders.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of messages appended, or an error.
+    pub fn recv_all_into(&mut self, messages: &mut Vec<(SocketAddr, Vec<u8>)>) -> Result<usize, ReUDPError> {
+        self.recv_batch(messages, usize::MAX)
+    }
+
+    /// Returns an iterator over the messages currently pending, for receive loops
+    /// like `for message in reudp.incoming() { ... }`.
+    ///
+    /// The iterator reads the socket like `recv_all` and ends when nothing more
+    /// is pending, so it hands control back once the socket is drained. Messages
+    /// are taken from the socket one at a time, so none are lost when the loop
+    /// stops early. An error is yielded after every message received before it
+    /// and ends the iteration.
+    ///
+    /// # Returns
+    ///
+    /// * `Incoming<'_>` - An iterator over the received messages and their senders.
+    pub fn incoming(&mut self) -> Incoming<'_> {
+        Incoming::new(self)
+    }
+
+    /// Returns an iterator over the messages currently pending that reads on
+    /// past errors, for loops like `for message in reudp.messages() { ... }`.
+    ///
+    /// Like `incoming`, it drains the socket and ends once nothing more is
+    /// pending. An error about a single datagram is yielded in its place among
+    /// the messages and the iteration goes on, so a loop that only logs errors
+    /// still gets every message that arrived. An error that ends the connection
+    /// or the socket, such as `ConnectionLost` or a failed receive, is yielded
+    /// last.
+    ///
+    /// # Returns
+    ///
+    /// * `MessageIterator<'_>` - An iterator over the received messages and their senders.
+    pub fn messages(&mut self) -> MessageIterator<'_> {
+        MessageIterator::new(self)
+    }
+
+    /// Splits the instance into a half that sends and a half that receives,
+    /// to be used from different threads.
+    ///
+    /// Both halves share the instance behind a lock that each call holds only
+    /// for as long as it sends or processes the datagrams already pending, so
+    /// neither waits for the other to send or receive something. The heartbeat
+    /// thread keeps running as before. `SendHalf::reunite` gives the instance back.
+    ///
+    /// # Returns
+    ///
+    /// * `(SendHalf, RecvHalf)` - The two halves.
+    pub fn split(self) -> (SendHalf, RecvHalf) {
+        split::halves(self)
+    }
+
+    /// Turns the instance into a handle that can be cloned and used from
+    /// several threads at once. See `ReUDPHandle` for how access is locked.
+    ///
+    /// # Returns
+    ///
+    /// * `ReUDPHandle` - The first handle to the instance.
+    pub fn into_handle(self) -> ReUDPHandle {
+        ReUDPHandle::new(self)
+    }
+
+    /// Returns the next pending message without waiting, for the iterators and `ReUDPHandle`.
+    pub(crate) fn recv_pending(&mut self) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        let mut messages = Vec::with_capacity(1);
+        let result = self.recv_batch(&mut messages, 1);
+        self.defer_error(result, &messages)?;
+        Ok(messages.pop())
+    }
+
+    /// Holds back an error hit after some messages were received, so the
+    /// messages are returned first and the error by the next receive call.
+    fn defer_error(&mut self, result: Result<usize, ReUDPError>, messages: &[(SocketAddr, Vec<u8>)]) -> Result<(), ReUDPError> {
+        match result {
+            Err(e) if messages.is_empty() => Err(e),
+            Err(e) => {
+                self.pending_error = Some(e);
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+        }
+    }
+
+    /// Receives up to `max_messages` pending messages without waiting,
+    /// appending them to `messages`.
+    fn recv_batch(&mut self, messages: &mut Vec<(SocketAddr, Vec<u8>)>, max_messages: usize) -> Result<usize, ReUDPError> {
+        if let Some(error) = self.pending_error.take() {
+            return Err(error);
+        }
+        self.run_timers()?;
+
+        let result = self.fill_delivery_queue(|reudp| reudp.delivery_queue.len() >= max_messages);
+        let start = messages.len();
+        while messages.len() - start < max_messages {
+            let Some((addr, message)) = self.next_delivery() else {
+                break;
+            };
+            messages.push((addr, message.payload));
+        }
+        result.map(|_| messages.len() - start)
+    }
+
+    /// Receives the next message from `addr`, leaving the messages of other
+    /// peers queued for `recv` or their own `recv_from` calls (server mode).
+    ///
+    /// Datagrams pending on the socket are processed, without waiting, until a
+    /// message from `addr` is ready; messages from other peers met along the way
+    /// are queued. Each peer's queue holds at most the configured
+    /// `max_queued_per_peer` messages, so peers nobody reads from can't make it
+    /// grow forever: beyond that, their oldest messages are dropped and counted
+    /// in `Statistics::messages_dropped_queue_full`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the peer to receive from.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<u8>>, ReUDPError>` - The data of the next message from `addr`, if any, or an error.
+    pub fn recv_from(&mut self, addr: SocketAddr) -> Result<Option<Vec<u8>>, ReUDPError> {
+        if let Some(error) = self.pending_error.take() {
+            return Err(error);
+        }
+        self.run_timers()?;
+
+        let addr = socket::canonical(addr);
+        self.fill_delivery_queue(|reudp| reudp.queued_per_peer.contains_key(&addr))?;
+        let Some(index) = self.delivery_queue.iter().position(|delivery| delivery.addr == addr) else {
+            return Ok(None);
+        };
+        Ok(self.take_delivery(index).map(|(_, message)| message.payload))
+    }
+
+    /// Sends `data` reliably and returns a future resolving to the payload of
+    /// the first message received afterwards for which `match_fn` returns true.
+    ///
+    /// The future sends the message when first polled and fails with `Timeout`
+    /// if no matching message arrives within `timeout` of that. Messages that
+    /// don't match are left queued for `recv`. It works with any executor, as
+    /// it needs no runtime of its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `match_fn` - Tells whether a received payload is the awaited reply.
+    /// * `timeout` - How long to wait for the reply.
+    ///
+    /// # Returns
+    ///
+    /// * `TimeoutFuture<'_, D, F>` - A future resolving to the reply, `Timeout`, or an error.
+    pub fn send_and_wait<D, F>(&mut self, data: D, match_fn: F, timeout: Duration) -> TimeoutFuture<'_, D, F>
+    where
+        D: AsRef<[u8]>,
+        F: Fn(&[u8]) -> bool + Send + 'static,
+    {
+        TimeoutFuture::new(self, data, match_fn, timeout)
+    }
+
+    /// Receives the next message whose payload `match_fn` accepts, leaving the
+    /// others queued, without waiting.
+    pub(crate) fn recv_matching<F: Fn(&[u8]) -> bool>(&mut self, match_fn: F) -> Result<Option<Vec<u8>>, ReUDPError> {
+        if let Some(error) = self.pending_error.take() {
+            return Err(error);
+        }
+        self.run_timers()?;
+
+        self.fill_delivery_queue(|reudp| {
+            reudp
+                .delivery_queue
+                .iter()
+                .any(|delivery| match_fn(&delivery.message.payload))
+        })?;
+        let Some(index) = self
+            .delivery_queue
+            .iter()
+            .position(|delivery| match_fn(&delivery.message.payload))
+        else {
+            return Ok(None);
+        };
+        Ok(self.take_delivery(index).map(|(_, message)| message.payload))
+    }
+
+    /// Returns the next message `recv` would deliver, without consuming it.
+    ///
+    /// If no message is ready yet, datagrams pending on the socket are processed
+    /// (without waiting) until one is, like `recv_all` does. An error hit while
+    /// doing so is returned by the next receive call.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(&SocketAddr, &[u8])>` - The sender and data of the next message, if any.
+    pub fn peek(&mut self) -> Option<(&SocketAddr, &[u8])> {
+        if self.delivery_queue.is_empty() && self.pending_error.is_none() {
+            if let Err(e) = self.fill_delivery_queue(|reudp| !reudp.delivery_queue.is_empty()) {
+                self.pending_error = Some(e);
+            }
+        }
+        self.delivery_queue
+            .front()
+            .map(|delivery| (&delivery.addr, &delivery.message.payload[..]))
+    }
+
+    /// Processes datagrams pending on the socket, without waiting, until `filled`
+    /// holds, the socket is empty or the batch limit is reached.
+    fn fill_delivery_queue<F: Fn(&Self) -> bool>(&mut self, filled: F) -> Result<(), ReUDPError> {
+        // Stop at the first empty read even in blocking mode.
+        if !self.nonblocking {
+            self.socket.set_nonblocking(true)?;
+        }
+        let result = self.read_socket(filled);
+        if !self.nonblocking {
+            self.socket.set_nonblocking(false)?;
+        }
+        result
+    }
+
+    /// Does the reading for `fill_delivery_queue` once the socket is non-blocking.
+    fn read_socket<F: Fn(&Self) -> bool>(&mut self, filled: F) -> Result<(), ReUDPError> {
+        self.check_proxy()?;
+        let mut buf = std::mem::take(&mut self.recv_buf);
+        let mut result = Ok(());
+        for _ in 0..self.config.load().max_recv_batch {
+            if filled(self) {
+                break;
+            }
+            result = match self.socket.recv_from(&mut buf) {
+                Ok((len, addr)) => self.process_datagram(addr, &buf[..len], buf.len() - 1),
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == std::io::ErrorKind::ConnectionReset => {
+                    self.ignore_connection_reset();
+                    Ok(())
+                }
+                Err(e) => Err(ReUDPError::IoError(e)),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        self.recv_buf = buf;
+        result
+    }
+
+    /// Receives a message, waiting for up to `timeout` for one to arrive.
+    ///
+    /// Acknowledgments, heartbeats and other control traffic arriving in the
+    /// meantime are processed as by `recv`, and timed work such as ack flushing
+    /// still happens while waiting. The socket is switched to blocking mode with
+    /// a read timeout for the duration of the call and restored afterwards, so
+    /// `recv` keeps behaving as before.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for a message.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError>` - The received message
+    ///   and its sender, `None` if none arrived in time, or an error.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        let received = self.recv_blocking(Some(Instant::now() + timeout))?;
+        Ok(received.map(|(addr, message)| (addr, message.payload)))
+    }
+
+    /// Waits for a message until `deadline` (forever without one), with the
+    /// socket in blocking mode and its read timeout restored afterwards.
+    fn recv_blocking(&mut self, deadline: Option<Instant>) -> Result<Option<(SocketAddr, Message)>, ReUDPError> {
+        let read_timeout = self.socket.read_timeout()?;
+        if self.nonblocking {
+            self.socket.set_nonblocking(false)?;
+        }
+        let result = self.recv_until(deadline);
+        if self.nonblocking {
+            self.socket.set_nonblocking(true)?;
+        }
+        self.socket.set_read_timeout(read_timeout)?;
+        result
+    }
+
+    /// Processes datagrams on the blocking socket until a message is delivered
+    /// or `deadline` passes, waking up at least every tick for timed work.
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<Option<(SocketAddr, Message)>, ReUDPError> {
+        loop {
+            let wait = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(None);
+                    }
+                    deadline - now
+                }
+                None => MIN_TICK,
+            };
+            self.socket.set_read_timeout(Some(wait.clamp(Duration::from_millis(1), MIN_TICK)))?;
+            if let Some(received) = self.recv_once()? {
+                return Ok(Some(received));
+            }
+        }
+    }
+
+    /// Switches the instance between blocking and non-blocking mode.
+    ///
+    /// In blocking mode the socket's non-blocking flag is cleared and `recv`
+    /// waits until a message is delivered rather than returning `None`.
+    /// Acknowledgments, heartbeat answers and other control traffic are still
+    /// processed while it waits, and timed work such as ack flushing still
+    /// happens. The heartbeat thread only sends, so it keeps running either way;
+    /// its sends may block briefly while the socket's send buffer is full.
+    /// `connect`, `disconnect` and `recv_timeout` behave the same in both modes.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocking` - `true` to make `recv` wait for a message, `false` to make it return immediately.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if the socket mode was changed, or an error.
+    pub fn set_blocking(&mut self, blocking: bool) -> Result<(), ReUDPError> {
+        self.socket.set_nonblocking(!blocking)?;
+        self.nonblocking = !blocking;
+        self.blocking = blocking;
+        Ok(())
+    }
+
+    /// Returns whether `recv` waits for a message, as set by `set_blocking`.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` in blocking mode.
+    pub fn is_blocking(&self) -> bool {
+        self.blocking
+    }
+
+    /// Moves the connection to a new server address (client mode), e.g. after a
+    /// load balancer moved the session.
+    ///
+    /// A `PathChallenge` carrying a random nonce is sent to `new_addr`; once it
+    /// comes back in a `PathResponse`, `recv` switches the address used for
+    /// sends, retransmissions and heartbeats and emits `Event::Migrated`.
+    /// Sequence numbers and unacknowledged messages are kept. Until then the
+    /// current server stays in use; the challenge is resent like a handshake
+    /// request and, if `new_addr` never answers, `Event::MigrationFailed` is
+    /// emitted. Packets from the old address are still accepted for the
+    /// configured migration grace period. The socket is no longer connected to
+    /// the server from then on, so that both addresses can be heard from.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_addr` - Address of the server to migrate to.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok once the challenge is sent, `WrongMode` in
+    ///   server mode, or an error.
+    pub fn migrate_to(&mut self, new_addr: SocketAddr) -> Result<(), ReUDPError> {
+        let new_addr = socket::canonical(new_addr);
+        let Mode::Client(remote_addr) = self.mode else {
+            return Err(ReUDPError::WrongMode {
+                operation: "migrate_to",
+                expected: ModeKind::Client,
+            });
+        };
+        if let Some(migration) = self.pending_migration.take() {
+            self.abandon_migration(migration);
+        }
+        if new_addr == remote_addr {
+            return Ok(());
+        }
+        self.socket.disconnect()?;
+
+        let migration = PendingMigration {
+            addr: new_addr,
+            nonce: rand::random::<u64>(),
+            sent_at: Instant::now(),
+            attempts: 0,
+            newly_allowed: !self.is_allowed_sender(new_addr),
+        };
+        if migration.newly_allowed {
+            self.add_allowed_sender(new_addr);
+        }
+        self.send_to_peer(&migration.challenge(), new_addr, IoContext::Control)?;
+        self.pending_migration = Some(migration);
+        Ok(())
+    }
+
+    /// Returns whether a migration started with `migrate_to` is still being validated.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the new server address hasn't answered yet.
+    pub fn is_migration_pending(&self) -> bool {
+        self.pending_migration.is_some()
+    }
+
+    /// Resends the path challenge of the migration in progress, giving up after
+    /// the configured number of handshake retries.
+    fn resend_path_challenge(&mut self) -> Result<(), ReUDPError> {
+        let Some(migration) = self.pending_migration.as_mut() else {
+            return Ok(());
+        };
+        let config = self.config.load();
+        if migration.sent_at.elapsed() < config.handshake_retry_interval {
+            return Ok(());
+        }
+        if migration.attempts >= config.handshake_retries {
+            let migration = self.pending_migration.take().unwrap();
+            log_warn!(session_id = self.session_id, to = %migration.addr, "Path validation failed");
+            self.events.push_back(Event::MigrationFailed { addr: migration.addr });
+            self.abandon_migration(migration);
+            return Ok(());
+        }
+        migration.attempts += 1;
+        migration.sent_at = Instant::now();
+        let (challenge, addr) = (migration.challenge(), migration.addr);
+        self.send_to_peer(&challenge, addr, IoContext::Control)?;
+        Ok(())
+    }
+
+    /// Stops accepting packets from an address whose validation didn't complete.
+    fn abandon_migration(&mut self, migration: PendingMigration) {
+        if migration.newly_allowed {
+            self.remove_allowed_sender(migration.addr);
+        }
+    }
+
+    /// Switches the server address to `new_addr`, carrying over the peer state.
+    fn complete_migration(&mut self, new_addr: SocketAddr) {
+        let Mode::Client(old_addr) = self.mode else {
+            return;
+        };
+        self.mode = Mode::Client(new_addr);
+        *self.heartbeat_mode.lock().unwrap() = self.mode.clone();
+
+        {
+            let mut peers = self.peers.lock().unwrap();
+            let mut server = peers.remove(&old_addr).unwrap_or_else(Peer::new);
+            // The path was just validated, so the new address counts as heard from.
+            server.last_heard = Some(Instant::now());
+            peers.insert(new_addr, server);
+        }
+        if let Some(sent_at) = self.pending_resets.remove(&old_addr) {
+            self.pending_resets.insert(new_addr, sent_at);
+        }
+        if let Some((previous_addr, _)) = self.previous_server.take() {
+            self.remove_allowed_sender(previous_addr);
+        }
+        self.previous_server = Some((old_addr, Instant::now() + self.config.load().migration_grace_period));
+
+        log_debug!(session_id = self.session_id, old = %old_addr, new = %new_addr, "Migrated to new server address");
+        self.events.push_back(Event::Migrated {
+            old: old_addr,
+            new: new_addr,
+        });
+    }
+
+    /// Sets a logger told about noteworthy things happening to this instance,
+    /// such as the connection being lost, including on the heartbeat thread.
+    /// Without one, nothing is printed; with the `tracing` feature, the same
+    /// things are also emitted as `tracing` events.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The logger, taking the severity and a human-readable message.
+    pub fn set_logger<F>(&mut self, f: F)
+    where
+        F: Fn(LogLevel, &str) + Send + Sync + 'static,
+    {
+        *self.logger.lock().unwrap() = Some(Arc::new(f));
+    }
+
+    /// Sets a callback told about each gap in the sequence numbers of received
+    /// messages, e.g. to request a keyframe when data was lost.
+    ///
+    /// The callback is called from `recv` with the sequence number expected
+    /// next and the one that arrived instead, once per gap: messages arriving
+    /// after it without opening a new gap don't call it again, and neither do
+    /// the missing messages when they finally arrive. Only messages sent with
+    /// `send` are numbered this way; channels have their own sequences.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The callback, taking `(expected_seq, received_seq)`.
+    pub fn on_sequence_gap<F>(&mut self, f: F)
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.sequence_gap_callback = Some(Arc::new(f));
+    }
+
+    /// Sets a handler given the received datagrams that don't parse as ReUDP
+    /// messages, which are otherwise dropped, e.g. to read the replies to
+    /// `send_raw`.
+    ///
+    /// The handler is called from `recv` with the sender and the datagram as
+    /// received. Those datagrams don't touch sequence numbers or
+    /// acknowledgments and don't register clients. They still have to come from
+    /// an allowed sender: a client only hears from its server until
+    /// `add_allowed_sender` lets another address through.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The handler, taking the sender and the datagram.
+    pub fn set_raw_handler<F>(&mut self, f: F)
+    where
+        F: Fn(SocketAddr, &[u8]) + Send + Sync + 'static,
+    {
+        self.raw_handler = Some(Arc::new(f));
+    }
+
+    /// Adds a hook at the end of the pipeline application payloads go through.
+    ///
+    /// Hooks see the data of the messages sent with `send` and its variants,
+    /// including channels, groups, typed and custom-type messages, but not
+    /// frames, raw datagrams or protocol messages. A message a hook drops on
+    /// sending isn't sent and takes no sequence number; the send call still
+    /// succeeds. A message a hook drops on receipt isn't acknowledged, so a
+    /// reliable one is retransmitted, and messages numbered after it wait for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The hook to add.
+    pub fn add_hook<H: Hook + 'static>(&mut self, hook: H) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Adds a hook processing received payloads only. See `add_hook`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The hook, taking the sender and the payload, and returning the
+    ///   payload to deliver or `None` to drop the message.
+    pub fn set_recv_hook<F>(&mut self, f: F)
+    where
+        F: Fn(SocketAddr, Vec<u8>) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.add_hook(RecvHook(f));
+    }
+
+    /// Adds a hook processing payloads to send only. See `add_hook`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The hook, taking the payload, and returning the payload to send
+    ///   or `None` to drop the message.
+    pub fn set_send_hook<F>(&mut self, f: F)
+    where
+        F: Fn(Vec<u8>) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.add_hook(SendHook(f));
+    }
+
+    /// Sets a callback told when the heartbeat thread stops a client, so an
+    /// application that doesn't keep calling `recv` still learns about it.
+    ///
+    /// The callback is called once, from the heartbeat thread, when the server
+    /// is lost, never answered, or the SOCKS5 proxy in between went away. It
+    /// isn't called for `disconnect` or `stop`, nor in server mode, where the
+    /// heartbeat thread evicts silent clients without ever stopping.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The callback, taking the reason the client stopped.
+    pub fn on_disconnect<F>(&mut self, f: F)
+    where
+        F: Fn(DisconnectReason) + Send + Sync + 'static,
+    {
+        *self.disconnect_callback.lock().unwrap() = Some(Arc::new(f));
+    }
+
+    /// Sets a callback told about each new client (server mode).
+    ///
+    /// The callback is called from the receive call that registers the
+    /// client: the one accepting its handshake, or receiving its first message
+    /// if it didn't go through `connect`. A client evicted for going silent is
+    /// reported again if it comes back.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The callback, taking the address of the client.
+    pub fn on_client_connect<F>(&mut self, f: F)
+    where
+        F: Fn(SocketAddr) + Send + Sync + 'static,
+    {
+        self.client_connect_callback = Some(Arc::new(f));
+    }
+
+    /// Returns the next event that occurred on this instance.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Event>` - The oldest event not retrieved yet, if any.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// Runs `data` through the hooks, returning `None` if one of them dropped it.
+    fn run_send_hooks<'a>(&self, data: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        if self.hooks.is_empty() {
+            return Some(Cow::Borrowed(data));
+        }
+        let mut payload = data.to_vec();
+        for hook in &self.hooks {
+            payload = hook.on_send(payload)?;
+        }
+        Some(Cow::Owned(payload))
+    }
+
+    /// Runs the application data of a received data message through the hooks
+    /// in reverse order, returning `None` if one of them dropped the message.
+    /// Other messages are returned as they are.
+    fn run_recv_hooks(&self, addr: SocketAddr, mut message: Message) -> Option<Message> {
+        let prefix_len = match message.message_type {
+            MessageType::Data
+            | MessageType::TypedData
+            | MessageType::EncryptedData
+            | MessageType::GroupData
+            | MessageType::Custom(_) => 0,
+            MessageType::TimestampedData => 8,
+            MessageType::ChannelData => 2,
+            _ => return Some(message),
+        };
+        if self.hooks.is_empty() || message.payload.len() < prefix_len {
+            return Some(message);
+        }
+        let mut payload = message.payload.split_off(prefix_len);
+        for hook in self.hooks.iter().rev() {
+            payload = hook.on_recv(addr, payload)?;
+        }
+        message.payload.extend_from_slice(&payload);
+        Some(message)
+    }
+
+    /// Replaces the payload of a data message with its plaintext once an
+    /// encryption key is set, refusing `Data` messages, which are only sent
+    /// before. Without a key, `EncryptedData` messages are refused and the
+    /// others returned as they are, like messages that carry no data.
+    fn decrypt_message(&self, message: Message) -> Result<Message, ReUDPError> {
+        let sealed = matches!(
+            message.message_type,
+            MessageType::EncryptedData
+                | MessageType::TimestampedData
+                | MessageType::TypedData
+                | MessageType::ChannelData
+                | MessageType::GroupData
+                | MessageType::Custom(_)
+        );
+        if !sealed && message.message_type != MessageType::Data {
+            return Ok(message);
+        }
+        #[cfg(feature = "crypto")]
+        if let Some(cipher) = self.cipher.lock().unwrap().as_ref() {
+            let decrypted = sealed
+                .then(|| crypto::decrypt(cipher, message.sequence, &message.message_type, &message.payload).ok())
+                .flatten();
+            if let Some(payload) = decrypted {
+                return Ok(Message { payload, ..message });
+            }
+            log_debug!(session_id = self.session_id, sequence = message.sequence, "Dropped message that didn't decrypt");
+            return Err(ReUDPError::DecryptionFailed);
+        }
+        if message.message_type == MessageType::EncryptedData {
+            log_debug!(session_id = self.session_id, sequence = message.sequence, "Dropped message that didn't decrypt");
+            return Err(ReUDPError::DecryptionFailed);
+        }
+        Ok(message)
+    }
+
+    /// Tells `addr` which messages the receive buffer still waits for, so that
+    /// it resends them without waiting for its resend interval.
+    ///
+    /// The `Nack` carries the first missing sequence number followed by a
+    /// 128-bit bitmap, where bit `i % 8` of byte `i / 8` is set if the message
+    /// `i` after it is missing. One goes out per round trip at most, since the
+    /// messages it asks for take that long to arrive.
+    fn send_nack(&mut self, addr: SocketAddr) -> Result<(), ReUDPError> {
+        let interval = self.srtt.unwrap_or(self.config.load().resend_interval);
+        if self.last_nack.is_some_and(|sent| sent.elapsed() < interval) {
+            return Ok(());
+        }
+        let base = self.recv_sequence;
+        let mut payload = [0; 24];
+        payload[..8].copy_from_slice(&base.to_be_bytes());
+        for i in 0..NACK_WINDOW.min(self.recv_frontier.saturating_sub(base)) {
+            if !self.recv_buffer.contains_key(&(base + i)) {
+                payload[8 + i as usize / 8] |= 1 << (i % 8);
+            }
+        }
+        let nack: [u8; HEADER_SIZE + 24] = message::encode_array(0, MessageType::Nack, &payload);
+        self.send_to_peer(&nack, addr, IoContext::Ack)?;
+        self.last_nack = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Moves the data messages that no longer wait for a gap from the receive
+    /// buffer to the delivery queue.
+    fn release_in_order(&mut self) {
+        while let Some((addr, mut message)) = self.recv_buffer.remove(&self.recv_sequence) {
+            self.recv_sequence += 1;
+            let mut latency = None;
+            if message.message_type == MessageType::TimestampedData {
+                if let Some(sent_at) = read_u64(&message.payload, 0) {
+                    latency = Some(self.latency_since(addr, sent_at));
+                    message.payload.drain(..8);
+                }
+            }
+            self.queue_delivery(Delivery {
+                addr,
+                message,
+                latency,
+                channel: None,
+            });
+        }
+    }
+
+    /// Adds a message to the delivery queue, dropping the oldest message queued
+    /// from the same peer if it already has as many as the configured cap.
+    fn queue_delivery(&mut self, delivery: Delivery) {
+        let queued = self.queued_per_peer.entry(delivery.addr).or_insert(0);
+        if *queued >= self.config.load().max_queued_per_peer {
+            if let Some(oldest) = self.delivery_queue.iter().position(|queued| queued.addr == delivery.addr) {
+                self.delivery_queue.remove(oldest);
+                self.stats.messages_dropped_queue_full += 1;
+                *queued -= 1;
+            }
+        }
+        *queued += 1;
+        self.delivery_queue.push_back(delivery);
+    }
+
+    /// Hands the next message of the delivery queue to the application.
+    fn next_delivery(&mut self) -> Option<(SocketAddr, Message)> {
+        self.take_delivery(0)
+    }
+
+    /// Hands the message at `index` in the delivery queue to the application.
+    /// Puts the message just handed out by `next_delivery` back at the front of
+    /// the delivery queue.
+    fn requeue_delivery(&mut self, addr: SocketAddr, message: Message) {
+        *self.queued_per_peer.entry(addr).or_insert(0) += 1;
+        self.delivery_queue.push_front(Delivery {
+            addr,
+            message,
+            latency: self.last_message_latency,
+            channel: self.last_message_channel,
+        });
+    }
+
+    fn take_delivery(&mut self, index: usize) -> Option<(SocketAddr, Message)> {
+        let delivery = self.delivery_queue.remove(index)?;
+        if let Entry::Occupied(mut queued) = self.queued_per_peer.entry(delivery.addr) {
+            *queued.get_mut() -= 1;
+            if *queued.get() == 0 {
+                queued.remove();
+            }
+        }
+        self.last_message_latency = delivery.latency;
+        self.last_message_channel = delivery.channel;
+        Some((delivery.addr, delivery.message))
+    }
+
+    /// Adds an address to the senders whose packets are accepted.
+    ///
+    /// Once an allow-list exists, `recv` silently drops (without acknowledging)
+    /// packets from any address not on it. Clients start with an allow-list
+    /// containing only their server; servers accept packets from anyone until
+    /// the first sender is added. A client accepting packets from an address
+    /// other than its server stops having its socket connected to the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address to accept packets from.
+    pub fn add_allowed_sender(&mut self, addr: SocketAddr) {
+        let addr = socket::canonical(addr);
+        if matches!(self.mode, Mode::Client(remote_addr) if remote_addr != addr) {
+            self.disconnect_socket();
+        }
+        self.allowed_senders.get_or_insert_with(HashSet::new).insert(addr);
+    }
+
+    /// Removes an address from the senders whose packets are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address to stop accepting packets from.
+    pub fn remove_allowed_sender(&mut self, addr: SocketAddr) {
+        if let Some(allowed_senders) = self.allowed_senders.as_mut() {
+            allowed_senders.remove(&socket::canonical(addr));
+        }
+    }
+
+    /// Removes the allow-list so packets from any sender are accepted.
+    pub fn allow_all_senders(&mut self) {
+        if let Mode::Client(_) = self.mode {
+            self.disconnect_socket();
+        }
+        self.allowed_senders = None;
+    }
+
+    /// Stops having the socket connected to the server, so packets from other
+    /// addresses get through. A failure is returned by the next receive call.
+    fn disconnect_socket(&mut self) {
+        if let Err(e) = self.socket.disconnect() {
+            self.pending_error = Some(ReUDPError::IoError(e));
+        }
+    }
+
+    /// Returns whether packets from `addr` pass the allowed senders filter.
+    fn is_allowed_sender(&self, addr: SocketAddr) -> bool {
+        self.allowed_senders
+            .as_ref()
+            .is_none_or(|allowed_senders| allowed_senders.contains(&addr))
+    }
+
+    /// Answers a client's handshake request, accepting it unless the server is full.
+    fn accept_connection(&mut self, addr: SocketAddr, request: &[u8]) -> Result<(), ReUDPError> {
+        let nonce = request.get(..8).unwrap_or(request);
+        let now = Instant::now();
+        let resumed_from = request.get(8..40).and_then(|token| {
+            let ttl = self.config.load().session_token_ttl;
+            self.issued_tokens.redeem(token.try_into().unwrap(), now, ttl)
+        });
+        let mut clients = self.clients.lock().unwrap();
+        let full = self
+            .config
+            .load()
+            .max_clients
+            .is_some_and(|max_clients| clients.len() >= max_clients);
+        let mut added = false;
+        let response = if full && !clients.contains(&addr) {
+            let mut payload = nonce.to_vec();
+            payload.extend_from_slice(b"server full");
+            Message::new(0, MessageType::ConnectDeny, payload)
+        } else {
+            if clients.insert(addr) {
+                self.stats.on_client_added(clients.len());
+                added = true;
+            }
+            let mut peers = self.peers.lock().unwrap();
+            peers.entry(addr).or_insert_with(Peer::new).last_heard = Some(now);
+            if let Some(previous) = resumed_from {
+                // The client keeps its subscriptions across the new address.
+                for subscribers in self.topics.values_mut() {
+                    if subscribers.remove(&previous) {
+                        subscribers.insert(addr);
+                    }
+                }
+                self.events.push_back(Event::SessionResumed { addr, previous });
+            }
+            let mut payload = nonce.to_vec();
+            payload.extend_from_slice(&self.issued_tokens.issue(addr, now));
+            Message::new(0, MessageType::Accept, payload)
+        };
+        let accepted = response.message_type == MessageType::Accept;
+        drop(clients);
+        if accepted {
+            // The client starts receiving group messages from 0 again.
+            self.forget_group_sequence(addr);
+        }
+        self.send_to_peer(&response.to_bytes(), addr, IoContext::Control)?;
+        if added {
+            self.notify_client_connect(addr);
+        }
+        Ok(())
+    }
+
+    /// Numbers the next group message to `addr` from 0 again, dropping the
+    /// group messages it didn't acknowledge yet.
+    fn forget_group_sequence(&mut self, addr: SocketAddr) {
+        self.group_send_sequences.remove(&addr);
+        self.unacked_group_packets
+            .lock()
+            .unwrap()
+            .retain(|(client, _), _| *client != addr);
+    }
+
+    /// Tells the callback set with `on_client_connect` about a new client.
+    fn notify_client_connect(&self, addr: SocketAddr) {
+        if let Some(callback) = &self.client_connect_callback {
+            callback(addr);
+        }
+    }
+
+    /// Returns whether the instance is still running.
+    ///
+    /// A client stops running when its server is lost; a server keeps running and
+    /// evicts lost clients instead.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` while the heartbeat thread is active.
+    pub fn is_running(&self) -> bool {
+        *self.running.lock().unwrap()
+    }
+
+    /// Returns the traffic counters of this instance.
+    ///
+    /// # Returns
+    ///
+    /// * `Statistics` - A snapshot of the counters.
+    pub fn stats(&self) -> Statistics {
+        self.stats.clone()
+    }
+
+    /// Changes the configuration of the running instance.
+    ///
+    /// `f` gets a copy of the current configuration and returns the new one,
+    /// built with the usual `ReUDPConfig` builder methods. The change is rejected
+    /// if the result doesn't pass the same checks as `ReUDPConfig::build`. The
+    /// heartbeat thread picks it up on its next tick.
+    ///
+    /// Timing options (heartbeat policy, liveness timeout, resend interval, ack
+    /// flushing, handshake retries, drain timeout, migration grace period), the
+    /// maximum packet size and the maximum number of clients take effect right
+    /// away. Options that only apply when the socket is set up (IP family,
+    /// socket options, bound device) are kept but have no effect, and so is
+    /// the buffer size, which `set_buffer_size` changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function turning the current configuration into the new one.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ConfigError>` - Ok if the new configuration is in place, or
+    ///   why it was rejected.
+    pub fn update_config<F: FnOnce(ReUDPConfig) -> ReUDPConfig>(&self, f: F) -> Result<(), ConfigError> {
+        let config = self.config.update(f)?;
+        let policy = config.heartbeat_policy;
+        *self.heartbeat_interval.lock().unwrap() = match self.srtt {
+            Some(srtt) => policy.interval_for(srtt),
+            None => policy.initial_interval(),
+        };
+        // Wake the heartbeat thread up in case it's parked for an interval that just got shorter.
+        if let Some(heartbeat_thread) = &self.heartbeat_thread {
+            heartbeat_thread.thread().unpark();
+        }
+        Ok(())
+    }
+
+    /// Changes the size of the buffer for received messages, e.g. to receive
+    /// larger datagrams once a path is known to carry them. The next receive
+    /// call uses the new size.
+    ///
+    /// The size goes through the same checks as `ReUDPConfig::buffer_size`: it
+    /// must be larger than the header and hold packets of the maximum packet
+    /// size, so lower that first to shrink the buffer below it.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The new buffer size in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if the buffer was resized, or an `InvalidInput`
+    ///   error wrapping a `ConfigError` if the size was rejected.
+    pub fn set_buffer_size(&mut self, size: usize) -> Result<(), ReUDPError> {
+        self.config
+            .update(|config| config.buffer_size(size))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        self.recv_buf.resize(size + 1, 0);
+        self.recv_buf.shrink_to_fit();
+        Ok(())
+    }
+
+    /// Returns the size of the buffer for received messages.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The buffer size in bytes.
+    pub fn buffer_size(&self) -> usize {
+        self.recv_buf.len() - 1
+    }
+
+    /// Feeds an RTT sample into the smoothed RTT and the heartbeat policy.
+    fn update_rtt(&mut self, rtt: Duration) {
+        self.current_ping = Some(rtt);
+        self.ping_history.push(rtt, self.config.load().ping_history_size);
+        let srtt = match self.srtt {
+            Some(srtt) => srtt.mul_f64(1.0 - RTT_SMOOTHING) + rtt.mul_f64(RTT_SMOOTHING),
+            None => rtt,
+        };
+        self.srtt = Some(srtt);
+        *self.heartbeat_interval.lock().unwrap() = self.config.load().heartbeat_policy.interval_for(srtt);
+    }
+
+    /// Returns the packet loss rate, estimated from gaps in the heartbeats
+    /// received from the server (or averaged over all clients).
+    ///
+    /// # Returns
+    ///
+    /// * `f64` - The smoothed loss rate between 0.0 and 1.0; 0.0 until heartbeats were received.
+    pub fn packet_loss_rate(&self) -> f64 {
+        let peers = self.peers.lock().unwrap();
+        let rates: Vec<f64> = match self.mode {
+            Mode::Client(remote_addr) => peers.get(&remote_addr).map(|peer| peer.loss.rate()).into_iter().collect(),
+            Mode::Server => peers.values().map(|peer| peer.loss.rate()).collect(),
+        };
+        if rates.is_empty() {
+            return 0.0;
+        }
+        rates.iter().sum::<f64>() / rates.len() as f64
+    }
+
+    /// Returns the quality of the connection, classified from the smoothed RTT
+    /// and the packet loss rate. Changes are also reported through
+    /// `Event::QualityChanged`.
+    ///
+    /// # Returns
+    ///
+    /// * `ConnectionQuality` - The current quality level.
+    pub fn connection_quality(&self) -> ConnectionQuality {
+        ConnectionQuality::classify(self.srtt, self.packet_loss_rate())
+    }
+
+    /// Emits `Event::QualityChanged` if the quality level moved.
+    fn update_quality(&mut self) {
+        let quality = self.connection_quality();
+        if quality != self.quality {
+            self.quality = quality;
+            log_debug!(session_id = self.session_id, quality = %quality, "Connection quality changed");
+            self.events.push_back(Event::QualityChanged(quality));
+        }
+    }
+
+    /// Returns the smoothed round-trip time.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The smoothed RTT, if a heartbeat round-trip has completed.
+    pub fn srtt(&self) -> Option<Duration> {
+        self.srtt
+    }
+
+    /// Returns the jitter of the RTT: the mean absolute deviation of the recent
+    /// samples from their mean. How many samples are kept is set with
+    /// `ReUDPConfig::ping_history_size`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The jitter, if a heartbeat round-trip has completed.
+    pub fn jitter(&self) -> Option<Duration> {
+        self.ping_history.jitter()
+    }
+
+    /// Returns the lowest of the recent RTT samples.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The lowest RTT, if a heartbeat round-trip has completed.
+    pub fn min_ping(&self) -> Option<Duration> {
+        self.ping_history.min()
+    }
+
+    /// Returns the highest of the recent RTT samples.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The highest RTT, if a heartbeat round-trip has completed.
+    pub fn max_ping(&self) -> Option<Duration> {
+        self.ping_history.max()
+    }
+
+    /// Returns a percentile of the recent RTT samples, e.g. 95.0 for the P95.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The percentile, between 0.0 and 100.0.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The smallest sample at least `p` percent of the samples
+    ///   don't exceed, or `None` without samples or if `p` is out of range.
+    pub fn ping_percentile(&self, p: f64) -> Option<Duration> {
+        self.ping_history.percentile(p)
+    }
+
+    /// Returns the interval currently used between heartbeats.
+    ///
+    /// # Returns
+    ///
+    /// * `Duration` - The current heartbeat interval.
+    pub fn heartbeat_interval(&self) -> Duration {
+        *self.heartbeat_interval.lock().unwrap()
+    }
+
+    /// Returns the current ping duration.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The current ping duration, if available.
+    pub fn get_current_ping(&self) -> Option<Duration> {
+        self.current_ping
+    }
+
+    /// Returns the operating mode. A client's server address follows the
+    /// server when it migrates.
+    ///
+    /// # Returns
+    ///
+    /// * `&Mode` - The current mode.
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    /// Returns the sequence number the next reliable message will be sent with.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The next send sequence number.
+    pub fn send_sequence(&self) -> u64 {
+        *self.send_sequence.lock().unwrap()
+    }
+
+    /// Returns the sequence number of the next reliable message expected.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The next receive sequence number.
+    pub fn recv_sequence(&self) -> u64 {
+        self.recv_sequence
+    }
+
+    /// Returns which of the next `window` sequence numbers, starting at
+    /// `recv_sequence`, were already received out of order, e.g. to show
+    /// which ones are missing while debugging packet loss.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The number of sequence numbers to report.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<bool>` - `true` at index `i` if `recv_sequence + i` is waiting in
+    ///   the receive buffer; the first entry is always `false`.
+    pub fn recv_sequence_bitmap(&self, window: u64) -> Vec<bool> {
+        (0..window)
+            .map(|i| self.recv_buffer.contains_key(&(self.recv_sequence + i)))
+            .collect()
+    }
+
+    /// Returns which of the last `window` sequence numbers sent are still
+    /// waiting for an acknowledgment.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The number of sequence numbers to report.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<bool>` - `true` at index `i` if sequence number `send_sequence - window + i`
+    ///   is unacknowledged. Entries before sequence number 0 are `false`.
+    pub fn unacked_sequence_bitmap(&self, window: u64) -> Vec<bool> {
+        // Read before locking the packets, which the heartbeat thread locks
+        // while holding the send sequence.
+        let send_sequence = self.send_sequence();
+        let unacked_packets = self.unacked_packets.lock().unwrap();
+        (0..window)
+            .map(|i| {
+                let Some(sequence) = (send_sequence + i).checked_sub(window) else {
+                    return false;
+                };
+                unacked_packets.contains_key(&sequence)
+            })
+            .collect()
+    }
+
+    /// Returns the number of connected clients (server mode).
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of clients; 0 in client mode.
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Returns the largest number of clients connected at once (server mode).
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The peak number of clients since the instance was created.
+    pub fn max_client_count_ever(&self) -> usize {
+        self.stats.peak_client_count
+    }
+
+    /// Returns how many times a client connected (server mode), including
+    /// clients that are gone since. A client that comes back after being
+    /// evicted or disconnecting counts again.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The number of client connections since the instance was created.
+    pub fn total_client_connections_ever(&self) -> u64 {
+        self.stats.total_client_connections
+    }
+
+    /// Returns the addresses of the connected clients (server mode).
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<SocketAddr>` - The client addresses, in no particular order; empty in client mode.
+    pub fn client_addrs(&self) -> Vec<SocketAddr> {
+        self.clients.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Returns when the last packet from a peer was received.
+    ///
+    /// Every accepted packet counts, not only heartbeats. Available for the
+    /// server in client mode and for each connected client in server mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the peer.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Instant>` - The time of the last packet, if anything was received from the peer.
+    pub fn last_seen(&self, addr: SocketAddr) -> Option<Instant> {
+        self.peers
+            .lock()
+            .unwrap()
+            .get(&socket::canonical(addr))
+            .and_then(|peer| peer.last_heard)
+    }
+
+    /// Returns how long a peer has been silent.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the peer.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The time since the last packet, if anything was received from the peer.
+    pub fn idle_time(&self, addr: SocketAddr) -> Option<Duration> {
+        self.last_seen(addr).map(|last_seen| last_seen.elapsed())
+    }
+
+    /// Returns when application data was last sent: a message sent with `send`
+    /// or one of its variants, on a channel or to a group. Acknowledgments,
+    /// heartbeats and frames sent by the heartbeat thread don't count.
+    ///
+    /// # Returns
+    ///
+    /// * `Instant` - The time of the last send, or when the instance was created
+    ///   if nothing was sent yet.
+    pub fn last_send_time(&self) -> Instant {
+        self.last_send_time
+    }
+
+    /// Returns when application data was last received from any peer.
+    /// Acknowledgments and heartbeats don't count; a retransmitted message
+    /// that was already delivered does, as the peer is still sending.
+    ///
+    /// # Returns
+    ///
+    /// * `Instant` - The time of the last message received, or when the instance
+    ///   was created if nothing was received yet.
+    pub fn last_recv_time(&self) -> Instant {
+        self.last_recv_time
+    }
+
+    /// Returns how long no application data was sent or received, for
+    /// idle timeouts of the application's own. Unlike `idle_time`, traffic
+    /// kept up by heartbeats doesn't reset it.
+    ///
+    /// # Returns
+    ///
+    /// * `Duration` - The time since the last send or receive, whichever is later.
+    pub fn idle_since(&self) -> Duration {
+        self.last_send_time.elapsed().min(self.last_recv_time.elapsed())
+    }
+
+    /// Returns the estimated clock offset of a peer relative to the local clock.
+    ///
+    /// The estimate is derived from heartbeat round-trips (NTP-style four
+    /// timestamps) and smoothed over time; samples taken during RTT spikes are
+    /// discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the peer.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ClockOffset>` - The signed offset and its error bound, if a sample has been taken.
+    pub fn clock_offset(&self, addr: SocketAddr) -> Option<ClockOffset> {
+        self.peers
+            .lock()
+            .unwrap()
+            .get(&socket::canonical(addr))
+            .and_then(|peer| peer.clock.offset())
+    }
+
+    /// Translates a timestamp taken on a peer's clock into local time.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the peer.
+    /// * `remote_time` - Timestamp taken on the peer's clock.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SystemTime>` - The equivalent local time, if the offset to the peer is known.
+    pub fn to_local_time(&self, addr: SocketAddr, remote_time: SystemTime) -> Option<SystemTime> {
+        self.clock_offset(addr)
+            .map(|offset| offset.to_local_time(remote_time))
+    }
+
+    /// Returns the latency of the last message delivered by `recv`, measured from
+    /// the sender's `send_timestamped` call until the message could be delivered
+    /// in order.
+    ///
+    /// The sender's timestamp is translated to the local clock using the clock
+    /// offset estimate when one is available.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The latency, or `None` if the last message wasn't timestamped.
+    pub fn last_message_latency(&self) -> Option<Duration> {
+        self.last_message_latency
+    }
+
+    /// Returns the channel the last delivered message was sent on.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u8>` - The channel, or `None` if the last message was sent with `send`.
+    pub fn last_message_channel(&self) -> Option<u8> {
+        self.last_message_channel
+    }
+
+    /// Returns the source address of the last packet read from the socket,
+    /// whatever it carried, e.g. to notice that the server now answers from
+    /// another address than the configured one.
+    ///
+    /// Packets from senders that aren't allowed are dropped before being
+    /// recorded, so they can't change it.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SocketAddr>` - The address, or `None` if no packet was received yet.
+    pub fn last_recv_addr(&self) -> Option<SocketAddr> {
+        self.last_recv_addr
+    }
+
+    /// Returns how long ago the peer at `addr` took the timestamp `sent_at`
+    /// (microseconds since the epoch on its own clock).
+    fn latency_since(&self, addr: SocketAddr, sent_at: u64) -> Duration {
+        let sent_at = UNIX_EPOCH + Duration::from_micros(sent_at);
+        let sent_at = self.to_local_time(addr, sent_at).unwrap_or(sent_at);
+        SystemTime::now().duration_since(sent_at).unwrap_or_default()
+    }
+
+    /// Returns the random session identifier attached to this instance's log output.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The session identifier.
+    pub fn session_id(&self) -> u64 {
+        self.session_id
+    }
+
+    /// Returns the local address the socket is bound to, e.g. to find out which
+    /// port the OS picked when binding to port 0.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SocketAddr, ReUDPError>` - The local address, or an error.
+    pub fn local_addr(&self) -> Result<SocketAddr, ReUDPError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Returns the size of the OS receive buffer, as granted by the OS.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The buffer size in bytes, or an error.
+    pub fn recv_buffer_bytes(&self) -> Result<usize, ReUDPError> {
+        Ok(socket::get_option(self.socket.udp()?, SocketOption::RecvBufferSize)? as usize)
+    }
+
+    /// Returns the size of the OS send buffer, as granted by the OS.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The buffer size in bytes, or an error.
+    pub fn send_buffer_bytes(&self) -> Result<usize, ReUDPError> {
+        Ok(socket::get_option(self.socket.udp()?, SocketOption::SendBufferSize)? as usize)
+    }
+
+    /// Returns the time-to-live (hop limit on IPv6) of outgoing packets.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, ReUDPError>` - The TTL, or an error.
+    pub fn ttl(&self) -> Result<u32, ReUDPError> {
+        Ok(socket::get_option(self.socket.udp()?, SocketOption::Ttl)?)
+    }
+
+    /// Returns the type-of-service byte (traffic class on IPv6) of outgoing packets.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, ReUDPError>` - The TOS, or an error.
+    pub fn tos(&self) -> Result<u32, ReUDPError> {
+        Ok(socket::get_option(self.socket.udp()?, SocketOption::Tos)?)
+    }
+
+    /// Returns the network interface the socket is pinned to, as reported by the
+    /// OS, e.g. to confirm the `bind_device` configuration took effect.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<String>, ReUDPError>` - The interface name, `None` if the
+    ///   socket isn't pinned to one, or an error.
+    pub fn bound_device(&self) -> Result<Option<String>, ReUDPError> {
+        Ok(socket::device(self.socket.udp()?)?)
+    }
+
+    /// Returns a reference to the underlying UDP socket.
+    ///
+    /// A client's socket is connected to its server unless the configuration
+    /// says otherwise, and until the client accepts packets from other
+    /// addresses or migrates.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&UdpSocket>` - Reference to the UDP socket, none if the
+    ///   instance was created with `new_unix` or `with_transport`.
+    pub fn socket(&self) -> Option<&UdpSocket> {
+        self.socket.udp().ok()
+    }
+
+    /// Returns where the peer known under `addr` is: the path of its socket for
+    /// an instance created with `new_unix`, or `addr` itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the peer, as returned by `recv` or `client_addrs`.
+    ///
+    /// # Returns
+    ///
+    /// * `RecvSource` - The location of the peer.
+    pub fn source_of(&self, addr: SocketAddr) -> RecvSource {
+        self.socket.source(addr)
+    }
+}
+
+impl Drop for ReUDP {
+    fn drop(&mut self) {
+        if self.is_running() {
+            let _ = self.stop();
+        }
+    }
+}
+
+/// A received message ready to be handed to the application.
+struct Delivery {
+    addr: SocketAddr,
+    /// The message, its payload stripped down to the application data
+    message: Message,
+    /// Send-to-receive latency, if the message was timestamped
+    latency: Option<Duration>,
+    /// Channel the message was sent on, if any
+    channel: Option<u8>,
+}
+
+/// A server address being validated before a client migrates to it.
+struct PendingMigration {
+    /// Address to migrate to
+    addr: SocketAddr,
+    /// Nonce the address must echo back
+    nonce: u64,
+    /// Timestamp the challenge was last sent
+    sent_at: Instant,
+    /// Number of times the challenge has been resent
+    attempts: u32,
+    /// Whether the address was added to the allowed senders for the validation
+    newly_allowed: bool,
+}
+
+impl PendingMigration {
+    /// Serializes the `PathChallenge` for this migration.
+    fn challenge(&self) -> Vec<u8> {
+        Message::new(0, MessageType::PathChallenge, self.nonce.to_be_bytes().to_vec()).to_bytes()
+    }
+}
+
+/// Returns how long the handshake of `connect` waits in total, which is also
+/// how long connecting over TCP may take.
+fn handshake_time(config: &ReUDPConfig) -> Duration {
+    config.handshake_retry_interval * (config.handshake_retries + 1)
+}
+
+/// Reads a big-endian `u64` at `offset` in `bytes`, if there are enough bytes.
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|b| u64::from_be_bytes(b.try_into().unwrap()))
+}
+
+/// Marks the instance as stopped for `reason`, telling the callback set with
+/// `on_disconnect` unless it was already stopped.
+fn stop_running(running: &Mutex<bool>, callback: &DisconnectCallback, reason: DisconnectReason) {
+    let was_running = std::mem::replace(&mut *running.lock().unwrap(), false);
+    // Not called with the lock held, so the callback may take its time.
+    let callback = callback.lock().unwrap().clone();
+    if let (true, Some(callback)) = (was_running, callback) {
+        callback(reason);
+    }
+}
+
+/// Reports a send of the heartbeat thread that failed, as a `PeerIo` error
+/// naming `addr` like those returned to the application. A full send buffer
+/// isn't reported: the datagram is simply lost.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn report_send_error(
+    logger: &SharedLogger,
+    session_id: u64,
+    addr: SocketAddr,
+    during: IoContext,
+    result: std::io::Result<usize>,
+) {
+    let source = match result {
+        Err(source) if source.kind() != std::io::ErrorKind::WouldBlock => source,
+        _ => return,
+    };
+    let cause = source.to_string();
+    let message = format!("{}: {}", ReUDPError::PeerIo { addr, source, during }, cause);
+    log_debug!(session_id, peer = %addr, "{}", message);
+    log::emit(logger, LogLevel::Debug, &message);
 }
@@ -0,0 +1,75 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// Most resumption tokens a server keeps; the oldest are forgotten first.
+const MAX_TOKENS: usize = 4096;
+
+/// Token a server hands out with `Accept` so the client can resume the session
+/// later without waiting for a handshake.
+pub type SessionToken = [u8; 32];
+
+/// What a server remembers about the session a token was issued for.
+#[derive(Debug, Clone, Copy)]
+struct SessionResumptionData {
+    /// Address the client had when the token was issued
+    addr: SocketAddr,
+    issued_at: Instant,
+}
+
+/// Resumption tokens issued by a server, each usable once until it expires.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TokenCache {
+    tokens: HashMap<SessionToken, SessionResumptionData>,
+    /// Tokens in the order they were issued, for evicting the oldest
+    order: VecDeque<SessionToken>,
+}
+
+impl TokenCache {
+    /// Issues a new token for the client at `addr`.
+    pub(crate) fn issue(&mut self, addr: SocketAddr, now: Instant) -> SessionToken {
+        let token = rand::random::<SessionToken>();
+        while self.tokens.len() >= MAX_TOKENS {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.tokens.remove(&oldest);
+        }
+        self.tokens.insert(token, SessionResumptionData { addr, issued_at: now });
+        self.order.push_back(token);
+        token
+    }
+
+    /// Consumes `token`, returning the address of the session it was issued
+    /// for if it is known and younger than `ttl`.
+    pub(crate) fn redeem(&mut self, token: &SessionToken, now: Instant, ttl: Duration) -> Option<SocketAddr> {
+        let data = self.tokens.remove(token)?;
+        self.order.retain(|issued| issued != token);
+        (now.duration_since(data.issued_at) < ttl).then_some(data.addr)
+    }
+
+    /// Returns the tokens with the address and age of their session, oldest first.
+    pub(crate) fn entries(&self, now: Instant) -> Vec<(SessionToken, SocketAddr, Duration)> {
+        self.order
+            .iter()
+            .filter_map(|token| {
+                let data = self.tokens.get(token)?;
+                Some((*token, data.addr, now.duration_since(data.issued_at)))
+            })
+            .collect()
+    }
+
+    /// Remembers a token returned by `entries`, as if issued `age` before `now`.
+    pub(crate) fn restore(&mut self, token: SessionToken, addr: SocketAddr, age: Duration, now: Instant) {
+        // A token older than the monotonic clock can't be placed in time; it
+        // is forgotten rather than given a longer life.
+        let Some(issued_at) = now.checked_sub(age) else {
+            return;
+        };
+        if self.tokens.insert(token, SessionResumptionData { addr, issued_at }).is_none() {
+            self.order.push_back(token);
+        }
+    }
+}
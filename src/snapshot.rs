@@ -0,0 +1,20 @@
+/// Sequence numbers captured by `ReUDP::take_sequence_snapshot`, to roll a
+/// session back to with `ReUDP::restore_sequence_snapshot`, like a database
+/// savepoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceSnapshot {
+    pub(crate) send_sequence: u64,
+    pub(crate) recv_sequence: u64,
+}
+
+impl SequenceSnapshot {
+    /// Returns the sequence number the next message sent had when the snapshot was taken.
+    pub fn send_sequence(&self) -> u64 {
+        self.send_sequence
+    }
+
+    /// Returns the sequence number of the next message to deliver when the snapshot was taken.
+    pub fn recv_sequence(&self) -> u64 {
+        self.recv_sequence
+    }
+}
@@ -0,0 +1,788 @@
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::Ipv6Addr;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+
+use crate::config::ReUDPConfig;
+use crate::socks5::Socks5Relay;
+use crate::tcp::TcpTransport;
+use crate::transport::CustomSocket;
+#[cfg(unix)]
+use crate::unix::UnixSocket;
+
+/// Address families a socket may be restricted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IpFamily {
+    /// Inferred from the local address, using the OS defaults.
+    Auto,
+    /// IPv4 only.
+    Ipv4Only,
+    /// IPv6 only (`IPV6_V6ONLY` set).
+    Ipv6Only,
+    /// An IPv6 socket that also accepts IPv4 traffic (`IPV6_V6ONLY` cleared).
+    DualStack,
+}
+
+/// Where a received message came from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RecvSource {
+    /// A peer reached over UDP.
+    Network(SocketAddr),
+    /// A peer reached over a Unix datagram socket, by the path it is bound to.
+    Unix(PathBuf),
+}
+
+/// A socket that presents peers with a single address per host: IPv4 peers
+/// reaching a dual-stack socket show up as plain IPv4 addresses rather than
+/// IPv4-mapped IPv6 ones, and plain IPv4 addresses can be sent to. Peers of a
+/// Unix socket show up under placeholder addresses. Peers reached through a
+/// SOCKS5 relay show up under their own address, not the relay's, and peers
+/// reached over TCP under the address of their stream. Peers of a custom
+/// transport show up under whatever address it reports.
+#[derive(Debug)]
+pub(crate) enum MappedSocket {
+    Udp {
+        socket: UdpSocket,
+        /// Whether the socket is an IPv6 one, which needs IPv4 addresses mapped
+        ipv6: bool,
+        /// Address the socket is connected to, if any, and the one it was bound
+        /// to before
+        peer: Mutex<Option<(SocketAddr, SocketAddr)>>,
+        /// SOCKS5 association every datagram goes through, if any
+        proxy: Option<Socks5Relay>,
+        /// Streams to the peers reached over TCP, once set up
+        tcp: OnceLock<TcpTransport>,
+    },
+    #[cfg(unix)]
+    Unix(UnixSocket),
+    Custom(CustomSocket),
+}
+
+impl MappedSocket {
+    pub(crate) fn new(socket: UdpSocket) -> io::Result<Self> {
+        Self::with_proxy(socket, None)
+    }
+
+    /// Wraps a UDP socket whose datagrams all go through the relay of `proxy`.
+    pub(crate) fn with_proxy(socket: UdpSocket, proxy: Option<Socks5Relay>) -> io::Result<Self> {
+        let ipv6 = socket.local_addr()?.is_ipv6();
+        Ok(MappedSocket::Udp {
+            socket,
+            ipv6,
+            peer: Mutex::new(None),
+            proxy,
+            tcp: OnceLock::new(),
+        })
+    }
+
+    /// Carries the packets of the peers `tcp` reaches over TCP from now on.
+    /// The socket is disconnected, as the transport wakes up receives with
+    /// datagrams of its own.
+    pub(crate) fn set_tcp(&self, transport: TcpTransport) -> io::Result<()> {
+        match self {
+            MappedSocket::Udp { tcp, .. } => {
+                self.disconnect()?;
+                let _ = tcp.set(transport);
+                Ok(())
+            }
+            #[cfg(unix)]
+            MappedSocket::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "TCP isn't available on a Unix socket",
+            )),
+            MappedSocket::Custom(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "TCP isn't available on a custom transport",
+            )),
+        }
+    }
+
+    /// Returns whether packets to `addr` go over TCP.
+    pub(crate) fn uses_tcp(&self, addr: SocketAddr) -> bool {
+        match self {
+            MappedSocket::Udp { tcp, .. } => tcp.get().is_some_and(|tcp| tcp.reaches(addr)),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the SOCKS5 proxy the socket goes through, if any, closed
+    /// the control connection, which ends the association.
+    pub(crate) fn proxy_lost(&self) -> bool {
+        match self {
+            MappedSocket::Udp { proxy: Some(proxy), .. } => proxy.is_lost(),
+            _ => false,
+        }
+    }
+
+    /// Connects a UDP socket to `addr`, so the OS drops datagrams from any other
+    /// address and reports errors such as an unreachable port on the next call.
+    ///
+    /// Only done where the socket can be disconnected again; elsewhere, for
+    /// Unix sockets, custom transports, through a SOCKS5 relay and over TCP,
+    /// this does nothing.
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "macos", target_os = "ios")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn connect(&self, addr: SocketAddr) -> io::Result<()> {
+        match self {
+            #[cfg(any(target_os = "linux", target_os = "macos", target_os = "ios"))]
+            MappedSocket::Udp {
+                socket,
+                peer,
+                proxy: None,
+                tcp,
+                ..
+            } if tcp.get().is_none() => {
+                let bound = socket.local_addr()?;
+                socket.connect(self.outgoing(addr))?;
+                *peer.lock().unwrap() = Some((addr, bound));
+                Ok(())
+            }
+            #[allow(unreachable_patterns)]
+            _ => Ok(()),
+        }
+    }
+
+    /// Undoes `connect`, so datagrams from any address are received again.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    pub(crate) fn disconnect(&self) -> io::Result<()> {
+        match self {
+            MappedSocket::Udp { socket, peer, .. } => {
+                let mut peer = peer.lock().unwrap();
+                if let Some((_, bound)) = *peer {
+                    disconnect(socket)?;
+                    // Linux gives up a port the OS picked along with the
+                    // connection; take it back so peers can still reach us.
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if socket.local_addr()?.port() == 0 {
+                        SockRef::from(socket).bind(&bound.into())?;
+                    }
+                    *peer = None;
+                }
+                Ok(())
+            }
+            #[cfg(unix)]
+            MappedSocket::Unix(_) => Ok(()),
+            MappedSocket::Custom(_) => Ok(()),
+        }
+    }
+
+    /// Sends `buf` to `addr`, mapping IPv4 addresses for IPv6 sockets.
+    pub(crate) fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        if let MappedSocket::Udp { tcp, .. } = self {
+            if let Some(result) = tcp.get().and_then(|tcp| tcp.send_to(buf, addr)) {
+                return result;
+            }
+        }
+        match self {
+            MappedSocket::Udp {
+                socket,
+                proxy: Some(proxy),
+                ..
+            } => {
+                socket.send_to(&proxy.wrap(buf, addr), self.outgoing(proxy.relay()))?;
+                Ok(buf.len())
+            }
+            // Some platforms refuse a destination on a connected socket.
+            MappedSocket::Udp { socket, peer, .. } => {
+                let connected = peer.lock().unwrap().is_some_and(|(peer, _)| peer == addr);
+                if connected {
+                    socket.send(buf)
+                } else {
+                    socket.send_to(buf, self.outgoing(addr))
+                }
+            }
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => socket.send_to(buf, addr),
+            MappedSocket::Custom(socket) => socket.send_to(buf, addr),
+        }
+    }
+
+    /// Sends each datagram of `datagrams` to its address, returning one result
+    /// per datagram in the same order.
+    pub(crate) fn send_batch(&self, datagrams: &[(SocketAddr, Vec<u8>)]) -> Vec<io::Result<()>> {
+        match self {
+            MappedSocket::Udp {
+                socket,
+                peer,
+                proxy: None,
+                tcp,
+                ..
+            } if peer.lock().unwrap().is_none() && tcp.get().is_none() => {
+                let outgoing: Vec<(SocketAddr, &[u8])> = datagrams
+                    .iter()
+                    .map(|(addr, buf)| (self.outgoing(*addr), &buf[..]))
+                    .collect();
+                send_batch(socket, &outgoing)
+            }
+            MappedSocket::Udp { .. } => datagrams
+                .iter()
+                .map(|(addr, buf)| self.send_to(buf, *addr).map(|_| ()))
+                .collect(),
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => datagrams
+                .iter()
+                .map(|(addr, buf)| socket.send_to(buf, *addr).map(|_| ()))
+                .collect(),
+            MappedSocket::Custom(socket) => datagrams
+                .iter()
+                .map(|(addr, buf)| socket.send_to(buf, *addr).map(|_| ()))
+                .collect(),
+        }
+    }
+
+    /// Receives a datagram, reporting its sender in canonical form.
+    pub(crate) fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        match self {
+            MappedSocket::Udp {
+                socket,
+                proxy: Some(proxy),
+                ..
+            } => proxy.recv_from(buf, |scratch| socket.recv_from(scratch)),
+            MappedSocket::Udp { socket, peer, tcp, .. } => {
+                let recv = |buf: &mut [u8]| {
+                    // Not held while waiting, so the heartbeat thread can still send.
+                    let peer = *peer.lock().unwrap();
+                    match peer {
+                        Some((peer, _)) => Ok((socket.recv(buf)?, peer)),
+                        None => {
+                            let (len, addr) = socket.recv_from(buf)?;
+                            Ok((len, canonical(addr)))
+                        }
+                    }
+                };
+                match tcp.get() {
+                    Some(tcp) => tcp.recv_from(buf, recv),
+                    None => recv(buf),
+                }
+            }
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => socket.recv_from(buf),
+            MappedSocket::Custom(socket) => socket.recv_from(buf),
+        }
+    }
+
+    /// Returns the address to hand to the OS for sending to `addr`.
+    fn outgoing(&self, addr: SocketAddr) -> SocketAddr {
+        match (self, addr) {
+            (MappedSocket::Udp { ipv6: true, .. }, SocketAddr::V4(v4)) => {
+                SocketAddr::new(IpAddr::V6(v4.ip().to_ipv6_mapped()), v4.port())
+            }
+            _ => addr,
+        }
+    }
+
+    /// Returns where a peer known under `addr` is.
+    pub(crate) fn source(&self, addr: SocketAddr) -> RecvSource {
+        match self {
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => match socket.path_of(addr) {
+                Some(path) => RecvSource::Unix(path),
+                None => RecvSource::Network(addr),
+            },
+            _ => RecvSource::Network(addr),
+        }
+    }
+
+    /// Returns the UDP socket, or an `Unsupported` error for a Unix socket or
+    /// a custom transport.
+    pub(crate) fn udp(&self) -> io::Result<&UdpSocket> {
+        match self {
+            MappedSocket::Udp { socket, .. } => Ok(socket),
+            #[cfg(unix)]
+            MappedSocket::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "not available on a Unix socket",
+            )),
+            MappedSocket::Custom(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "not available on a custom transport",
+            )),
+        }
+    }
+
+    pub(crate) fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            MappedSocket::Udp { socket, .. } => socket.local_addr(),
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => Ok(socket.local_addr()),
+            MappedSocket::Custom(socket) => socket.local_addr(),
+        }
+    }
+
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            MappedSocket::Udp { socket, .. } => socket.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => socket.set_nonblocking(nonblocking),
+            MappedSocket::Custom(socket) => socket.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub(crate) fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        match self {
+            MappedSocket::Udp { socket, .. } => socket.read_timeout(),
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => socket.read_timeout(),
+            MappedSocket::Custom(socket) => socket.read_timeout(),
+        }
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            MappedSocket::Udp { socket, .. } => socket.set_read_timeout(timeout),
+            #[cfg(unix)]
+            MappedSocket::Unix(socket) => socket.set_read_timeout(timeout),
+            MappedSocket::Custom(socket) => socket.set_read_timeout(timeout),
+        }
+    }
+}
+
+/// Returns `addr` with an IPv4-mapped IPv6 address turned into plain IPv4, so a
+/// peer is tracked under the same address whichever family it was seen through.
+pub(crate) fn canonical(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip().to_canonical(), addr.port())
+}
+
+/// Socket options that can be set through the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SocketOption {
+    /// `SO_RCVBUF`
+    RecvBufferSize,
+    /// `SO_SNDBUF`
+    SendBufferSize,
+    /// `IP_TTL`, or `IPV6_UNICAST_HOPS` on IPv6 sockets
+    Ttl,
+    /// `IP_TOS`, or `IPV6_TCLASS` on IPv6 sockets
+    Tos,
+}
+
+/// Applies the socket options of the configuration to `socket`.
+///
+/// Options that can't be set are returned with their error, unless the
+/// configuration marks socket options as required, in which case the first
+/// failure is returned as an error.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn apply_options(
+    socket: &UdpSocket,
+    config: &ReUDPConfig,
+) -> io::Result<Vec<(SocketOption, io::Error)>> {
+    let socket = SockRef::from(socket);
+    let ipv6 = socket.local_addr()?.is_ipv6();
+    let mut failures = Vec::new();
+    let mut check = |option, result: io::Result<()>| match result {
+        Err(e) if config.socket_options_required => Err(e),
+        Err(e) => {
+            failures.push((option, e));
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    };
+
+    if let Some(size) = config.recv_buffer_bytes {
+        check(SocketOption::RecvBufferSize, socket.set_recv_buffer_size(size))?;
+    }
+    if let Some(size) = config.send_buffer_bytes {
+        check(SocketOption::SendBufferSize, socket.set_send_buffer_size(size))?;
+    }
+    if let Some(ttl) = config.ttl {
+        let result = if ipv6 {
+            socket.set_unicast_hops_v6(ttl)
+        } else {
+            socket.set_ttl(ttl)
+        };
+        check(SocketOption::Ttl, result)?;
+    }
+    if let Some(tos) = config.tos {
+        check(SocketOption::Tos, set_tos(&socket, tos, ipv6))?;
+    }
+    Ok(failures)
+}
+
+/// Returns the value the OS actually applied for `option`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn get_option(socket: &UdpSocket, option: SocketOption) -> io::Result<u32> {
+    let socket = SockRef::from(socket);
+    let ipv6 = socket.local_addr()?.is_ipv6();
+    match option {
+        SocketOption::RecvBufferSize => socket.recv_buffer_size().map(|size| size as u32),
+        SocketOption::SendBufferSize => socket.send_buffer_size().map(|size| size as u32),
+        SocketOption::Ttl if ipv6 => socket.unicast_hops_v6(),
+        SocketOption::Ttl => socket.ttl(),
+        SocketOption::Tos => get_tos(&socket, ipv6),
+    }
+}
+
+/// UDP sockets can't be opened in the browser, so there are none to configure.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn apply_options(
+    _socket: &UdpSocket,
+    _config: &ReUDPConfig,
+) -> io::Result<Vec<(SocketOption, io::Error)>> {
+    Err(no_udp())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn get_option(_socket: &UdpSocket, _option: SocketOption) -> io::Result<u32> {
+    Err(no_udp())
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn set_tos(socket: &SockRef<'_>, tos: u32, ipv6: bool) -> io::Result<()> {
+    if ipv6 {
+        socket.set_tclass_v6(tos)
+    } else {
+        socket.set_tos(tos)
+    }
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn get_tos(socket: &SockRef<'_>, ipv6: bool) -> io::Result<u32> {
+    if ipv6 {
+        socket.tclass_v6()
+    } else {
+        socket.tos()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+fn set_tos(_socket: &SockRef<'_>, _tos: u32, _ipv6: bool) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "TOS is not supported on this platform"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+fn get_tos(_socket: &SockRef<'_>, _ipv6: bool) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "TOS is not supported on this platform"))
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn set_device(socket: &Socket, interface: &str, _ipv6: bool) -> io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes()))
+}
+
+#[cfg(any(target_os = "ios", target_os = "macos"))]
+fn set_device(socket: &Socket, interface: &str, ipv6: bool) -> io::Result<()> {
+    let name = std::ffi::CString::new(interface).map_err(|_| invalid_input("interface name contains a NUL byte"))?;
+    // SAFETY: `name` is a valid NUL-terminated string that outlives the call.
+    let index = std::num::NonZeroU32::new(unsafe { libc::if_nametoindex(name.as_ptr()) })
+        .ok_or_else(io::Error::last_os_error)?;
+    if ipv6 {
+        socket.bind_device_by_index_v6(Some(index))
+    } else {
+        socket.bind_device_by_index_v4(Some(index))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "fuchsia",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "macos"
+)))]
+fn set_device(_socket: &Socket, _interface: &str, _ipv6: bool) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "binding to a device is not supported on this platform"))
+}
+
+/// Returns the name of the network interface `socket` is pinned to, if any.
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+pub(crate) fn device(socket: &UdpSocket) -> io::Result<Option<String>> {
+    Ok(SockRef::from(socket)
+        .device()?
+        .map(|name| String::from_utf8_lossy(&name).into_owned()))
+}
+
+/// Returns the name of the network interface `socket` is pinned to, if any.
+#[cfg(any(target_os = "ios", target_os = "macos"))]
+pub(crate) fn device(socket: &UdpSocket) -> io::Result<Option<String>> {
+    let socket = SockRef::from(socket);
+    let index = if socket.local_addr()?.is_ipv6() {
+        socket.device_index_v6()?
+    } else {
+        socket.device_index_v4()?
+    };
+    let Some(index) = index else {
+        return Ok(None);
+    };
+    let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+    // SAFETY: `name` has room for the longest interface name and its terminator.
+    if unsafe { libc::if_indextoname(index.get(), name.as_mut_ptr()) }.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `if_indextoname` succeeded, so `name` holds a NUL-terminated string.
+    let name = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) };
+    Ok(Some(name.to_string_lossy().into_owned()))
+}
+
+/// Returns the name of the network interface `socket` is pinned to, if any.
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "fuchsia",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "macos"
+)))]
+pub(crate) fn device(_socket: &UdpSocket) -> io::Result<Option<String>> {
+    Ok(None)
+}
+
+/// Returns whether `socket` is in non-blocking mode.
+#[cfg(unix)]
+pub(crate) fn is_nonblocking(socket: &UdpSocket) -> io::Result<bool> {
+    SockRef::from(socket).nonblocking()
+}
+
+/// Returns whether `socket` is in non-blocking mode.
+///
+/// Windows can't report the flag; sockets are blocking there unless changed.
+#[cfg(not(unix))]
+pub(crate) fn is_nonblocking(_socket: &UdpSocket) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Stops Windows from failing the next receive on `socket` with a connection
+/// reset after a datagram sent from it hit a port nobody listens on, which
+/// says nothing about the other peers of a server.
+#[cfg(windows)]
+pub(crate) fn disable_connection_reset(socket: &UdpSocket) -> io::Result<()> {
+    use std::os::windows::io::AsRawSocket;
+    use windows_sys::Win32::Networking::WinSock::{WSAIoctl, SIO_UDP_CONNRESET, SOCKET_ERROR};
+
+    let enabled: u32 = 0;
+    let mut returned = 0;
+    // SAFETY: the socket is open for the duration of the call, the input buffer
+    // is a valid `u32` and no output buffer or overlapped operation is used.
+    let result = unsafe {
+        WSAIoctl(
+            socket.as_raw_socket() as usize,
+            SIO_UDP_CONNRESET,
+            &enabled as *const u32 as *const _,
+            std::mem::size_of::<u32>() as u32,
+            std::ptr::null_mut(),
+            0,
+            &mut returned,
+            std::ptr::null_mut(),
+            None,
+        )
+    };
+    if result == SOCKET_ERROR {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Stops Windows from failing receives with a connection reset; other
+/// platforms don't report unreachable ports on unconnected sockets.
+#[cfg(not(windows))]
+pub(crate) fn disable_connection_reset(_socket: &UdpSocket) -> io::Result<()> {
+    Ok(())
+}
+
+/// Binds the UDP socket for a ReUDP instance according to its configuration.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn bind(local_addr: &str, config: &ReUDPConfig) -> io::Result<UdpSocket> {
+    if config.ip_family == IpFamily::Auto && config.bind_device.is_none() {
+        return UdpSocket::bind(local_addr);
+    }
+
+    let addr = resolve(local_addr)?;
+    let addr = match (config.ip_family, addr) {
+        (IpFamily::Auto, _) => addr,
+        (IpFamily::Ipv4Only, SocketAddr::V4(_)) => addr,
+        (IpFamily::Ipv4Only, SocketAddr::V6(_)) => {
+            return Err(invalid_input("IPv4-only socket requires an IPv4 local address"));
+        }
+        (IpFamily::Ipv6Only | IpFamily::DualStack, SocketAddr::V6(_)) => addr,
+        // "0.0.0.0" is the natural way to say "any address" for a dual-stack socket too.
+        (IpFamily::DualStack, SocketAddr::V4(v4)) if v4.ip().is_unspecified() => {
+            SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), v4.port())
+        }
+        (_, SocketAddr::V4(_)) => {
+            return Err(invalid_input("IPv6 socket requires an IPv6 local address"));
+        }
+    };
+
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if addr.is_ipv6() && config.ip_family != IpFamily::Auto {
+        socket.set_only_v6(config.ip_family == IpFamily::Ipv6Only)?;
+    }
+    if let Some(interface) = &config.bind_device {
+        set_device(&socket, interface, addr.is_ipv6())?;
+    }
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// UDP sockets can't be opened in the browser; a `WasmTransport` passed to
+/// `ReUDP::with_transport` carries the datagrams instead.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn bind(_local_addr: &str, _config: &ReUDPConfig) -> io::Result<UdpSocket> {
+    Err(no_udp())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn no_udp() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "UDP sockets are not available on wasm32")
+}
+
+/// Sends each datagram of `datagrams` to its address, returning one result per
+/// datagram in the same order.
+///
+/// On Linux the datagrams are handed to the kernel with `sendmmsg`, one syscall
+/// per batch; elsewhere they are sent one by one.
+#[cfg(target_os = "linux")]
+pub(crate) fn send_batch(socket: &UdpSocket, datagrams: &[(SocketAddr, &[u8])]) -> Vec<io::Result<()>> {
+    use std::os::fd::AsRawFd;
+
+    use socket2::SockAddr;
+
+    let sock_addrs: Vec<SockAddr> = datagrams.iter().map(|(addr, _)| SockAddr::from(*addr)).collect();
+    let mut iovs: Vec<libc::iovec> = datagrams
+        .iter()
+        .map(|(_, buf)| libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        })
+        .collect();
+    let mut headers: Vec<libc::mmsghdr> = sock_addrs
+        .iter()
+        .zip(iovs.iter_mut())
+        .map(|(addr, iov)| {
+            // SAFETY: `mmsghdr` is a plain C struct for which all zeroes is a valid value.
+            let mut header: libc::mmsghdr = unsafe { std::mem::zeroed() };
+            header.msg_hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            header.msg_hdr.msg_namelen = addr.len();
+            header.msg_hdr.msg_iov = iov;
+            header.msg_hdr.msg_iovlen = 1;
+            header
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(datagrams.len());
+    while results.len() < datagrams.len() {
+        let remaining = &mut headers[results.len()..];
+        // SAFETY: every header points to a live address and to its own entry of
+        // `iovs`, which points to its datagram; all of them outlive the call.
+        let sent = unsafe {
+            libc::sendmmsg(
+                socket.as_raw_fd(),
+                remaining.as_mut_ptr(),
+                remaining.len().min(libc::c_uint::MAX as usize) as libc::c_uint,
+                0,
+            )
+        };
+        if sent < 0 {
+            // The first remaining datagram failed; report it and carry on with the rest.
+            results.push(Err(io::Error::last_os_error()));
+        } else {
+            results.extend((0..sent).map(|_| Ok(())));
+            if (sent as usize) < remaining.len() {
+                // A short count means the next datagram failed: send it on its own
+                // to find out why.
+                let (addr, buf) = &datagrams[results.len()];
+                results.push(socket.send_to(buf, addr).map(|_| ()));
+            }
+        }
+    }
+    results
+}
+
+/// Sends each datagram of `datagrams` to its address, returning one result per
+/// datagram in the same order.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn send_batch(socket: &UdpSocket, datagrams: &[(SocketAddr, &[u8])]) -> Vec<io::Result<()>> {
+    datagrams
+        .iter()
+        .map(|(addr, buf)| socket.send_to(buf, addr).map(|_| ()))
+        .collect()
+}
+
+/// Dissolves the association of a connected UDP socket, by connecting it to an
+/// `AF_UNSPEC` address.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "ios"))]
+fn disconnect(socket: &UdpSocket) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: `sockaddr` is a plain C struct for which all zeroes is a valid value.
+    let mut addr: libc::sockaddr = unsafe { std::mem::zeroed() };
+    addr.sa_family = libc::AF_UNSPEC as libc::sa_family_t;
+    // SAFETY: `addr` is a valid address of the given length that outlives the call.
+    let result = unsafe {
+        libc::connect(
+            socket.as_raw_fd(),
+            &addr,
+            std::mem::size_of::<libc::sockaddr>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        let error = io::Error::last_os_error();
+        // BSDs dissolve the association but still report the unsupported family.
+        if error.raw_os_error() != Some(libc::EAFNOSUPPORT) {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Sockets are never connected where they can't be disconnected.
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "ios")))]
+fn disconnect(_socket: &UdpSocket) -> io::Result<()> {
+    Ok(())
+}
+
+/// Returns the first address `addr` resolves to.
+pub(crate) fn resolve<A: ToSocketAddrs>(addr: A) -> io::Result<SocketAddr> {
+    addr.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid_input("address didn't resolve to any address"))
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
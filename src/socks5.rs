@@ -0,0 +1,321 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::socket;
+
+const VERSION: u8 = 5;
+const NO_AUTH: u8 = 0x00;
+const USERNAME_PASSWORD: u8 = 0x02;
+const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const UDP_ASSOCIATE: u8 = 0x03;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+/// Largest header a relayed datagram can carry: a domain name of 255 bytes.
+const MAX_UDP_HEADER: usize = 4 + 1 + 255 + 2;
+
+/// A SOCKS5 proxy a client reaches its server through, using UDP ASSOCIATE
+/// (RFC 1928), for networks that only let traffic out through such a proxy.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Socks5Config {
+    pub(crate) proxy_addr: SocketAddr,
+    pub(crate) credentials: Option<(String, String)>,
+    pub(crate) timeout: Duration,
+}
+
+impl Socks5Config {
+    /// Creates a configuration for the proxy listening at `proxy_addr`, without
+    /// authentication.
+    pub fn new(proxy_addr: SocketAddr) -> Self {
+        Self {
+            proxy_addr,
+            credentials: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Authenticates with a username and password (RFC 1929). Each must be at
+    /// most 255 bytes long.
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Sets how long connecting to the proxy and each step of the handshake
+    /// may take.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// The password is left out, so configurations can be logged.
+impl fmt::Debug for Socks5Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Socks5Config")
+            .field("proxy_addr", &self.proxy_addr)
+            .field("username", &self.credentials.as_ref().map(|(username, _)| username))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// A UDP association set up with a SOCKS5 proxy: datagrams go through its
+/// relay, each with a header naming the peer it is for or from. The
+/// association lasts as long as the TCP control connection.
+#[derive(Debug)]
+pub(crate) struct Socks5Relay {
+    relay: SocketAddr,
+    control: TcpStream,
+    /// Set once the proxy closed the control connection
+    lost: Arc<AtomicBool>,
+    /// Buffer datagrams are read into before their header is stripped
+    scratch: Mutex<Vec<u8>>,
+}
+
+impl Socks5Relay {
+    /// Performs the handshake with the proxy for a UDP socket bound to
+    /// `udp_addr`, and starts watching the control connection.
+    pub(crate) fn associate(config: &Socks5Config, udp_addr: SocketAddr) -> io::Result<Self> {
+        let mut control = TcpStream::connect_timeout(&config.proxy_addr, config.timeout)?;
+        control.set_read_timeout(Some(config.timeout))?;
+        control.set_write_timeout(Some(config.timeout))?;
+        authenticate(&mut control, config)?;
+
+        let mut request = vec![VERSION, UDP_ASSOCIATE, 0];
+        encode_addr(&mut request, udp_addr);
+        control.write_all(&request)?;
+        let mut reply = [0; 4];
+        control.read_exact(&mut reply)?;
+        if reply[0] != VERSION {
+            return Err(protocol_error("SOCKS5 proxy answered with another protocol version"));
+        }
+        if reply[1] != 0 {
+            return Err(reply_error(reply[1]));
+        }
+        let relay = read_addr(&mut control, reply[3])?;
+        // An unspecified relay address stands for the proxy's own host.
+        let relay = if relay.ip().is_unspecified() {
+            SocketAddr::new(config.proxy_addr.ip(), relay.port())
+        } else {
+            relay
+        };
+
+        let lost = Arc::new(AtomicBool::new(false));
+        let mut watched = control.try_clone()?;
+        watched.set_read_timeout(None)?;
+        let watcher_lost = Arc::clone(&lost);
+        thread::spawn(move || {
+            let mut buf = [0; 64];
+            loop {
+                match watched.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(_) => break,
+                }
+            }
+            watcher_lost.store(true, Ordering::SeqCst);
+        });
+
+        Ok(Self {
+            relay: socket::canonical(relay),
+            control,
+            lost,
+            scratch: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Returns the address of the proxy's relay, which every datagram goes through.
+    pub(crate) fn relay(&self) -> SocketAddr {
+        self.relay
+    }
+
+    /// Returns whether the proxy closed the control connection, which ends the association.
+    pub(crate) fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Returns `buf` with the header asking the relay to pass it on to `addr`.
+    pub(crate) fn wrap(&self, buf: &[u8], addr: SocketAddr) -> Vec<u8> {
+        let mut datagram = Vec::with_capacity(MAX_UDP_HEADER + buf.len());
+        datagram.extend_from_slice(&[0, 0, 0]);
+        encode_addr(&mut datagram, socket::canonical(addr));
+        datagram.extend_from_slice(buf);
+        datagram
+    }
+
+    /// Receives a datagram through the relay with `recv`, which reads one
+    /// datagram and its source, and copies its payload into `buf`. Datagrams
+    /// that didn't come from the relay, are fragmented or name a peer by domain
+    /// are skipped.
+    pub(crate) fn recv_from<F>(&self, buf: &mut [u8], mut recv: F) -> io::Result<(usize, SocketAddr)>
+    where
+        F: FnMut(&mut [u8]) -> io::Result<(usize, SocketAddr)>,
+    {
+        let mut scratch = self.scratch.lock().unwrap();
+        scratch.resize(buf.len() + MAX_UDP_HEADER, 0);
+        loop {
+            let (len, from) = recv(&mut scratch)?;
+            if socket::canonical(from) != self.relay {
+                continue;
+            }
+            let Some((addr, offset)) = parse_udp_header(&scratch[..len]) else {
+                continue;
+            };
+            let payload_len = (len - offset).min(buf.len());
+            buf[..payload_len].copy_from_slice(&scratch[offset..offset + payload_len]);
+            return Ok((payload_len, socket::canonical(addr)));
+        }
+    }
+}
+
+/// Closing the control connection ends the association and the watcher thread.
+impl Drop for Socks5Relay {
+    fn drop(&mut self) {
+        let _ = self.control.shutdown(Shutdown::Both);
+    }
+}
+
+/// Negotiates an authentication method and, if the proxy asks for it,
+/// authenticates with the configured username and password.
+fn authenticate(control: &mut TcpStream, config: &Socks5Config) -> io::Result<()> {
+    let greeting: &[u8] = match config.credentials {
+        Some(_) => &[VERSION, 2, NO_AUTH, USERNAME_PASSWORD],
+        None => &[VERSION, 1, NO_AUTH],
+    };
+    control.write_all(greeting)?;
+    let mut choice = [0; 2];
+    control.read_exact(&mut choice)?;
+    if choice[0] != VERSION {
+        return Err(protocol_error("SOCKS5 proxy answered with another protocol version"));
+    }
+    match (choice[1], &config.credentials) {
+        (NO_AUTH, _) => Ok(()),
+        (USERNAME_PASSWORD, Some((username, password))) => {
+            if username.len() > 255 || password.len() > 255 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "SOCKS5 username and password must be at most 255 bytes long",
+                ));
+            }
+            let mut request = vec![1, username.len() as u8];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+            control.write_all(&request)?;
+            let mut status = [0; 2];
+            control.read_exact(&mut status)?;
+            if status[1] != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "SOCKS5 proxy rejected the credentials",
+                ));
+            }
+            Ok(())
+        }
+        (NO_ACCEPTABLE_METHOD, _) => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "SOCKS5 proxy accepts none of the offered authentication methods",
+        )),
+        (method, _) => Err(protocol_error(&format!(
+            "SOCKS5 proxy chose authentication method {} that wasn't offered",
+            method
+        ))),
+    }
+}
+
+/// Appends `addr` as an address type, address and port.
+fn encode_addr(out: &mut Vec<u8>, addr: SocketAddr) {
+    match addr.ip() {
+        IpAddr::V4(ip) => {
+            out.push(ATYP_IPV4);
+            out.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            out.push(ATYP_IPV6);
+            out.extend_from_slice(&ip.octets());
+        }
+    }
+    out.extend_from_slice(&addr.port().to_be_bytes());
+}
+
+/// Reads an address of type `atyp` and a port from the control connection,
+/// resolving domain names.
+fn read_addr(control: &mut TcpStream, atyp: u8) -> io::Result<SocketAddr> {
+    let ip = match atyp {
+        ATYP_IPV4 => {
+            let mut octets = [0; 4];
+            control.read_exact(&mut octets)?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        ATYP_IPV6 => {
+            let mut octets = [0; 16];
+            control.read_exact(&mut octets)?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        ATYP_DOMAIN => {
+            let mut len = [0; 1];
+            control.read_exact(&mut len)?;
+            let mut name = vec![0; len[0] as usize];
+            control.read_exact(&mut name)?;
+            let mut port = [0; 2];
+            control.read_exact(&mut port)?;
+            let name = String::from_utf8(name).map_err(|_| protocol_error("SOCKS5 relay name isn't UTF-8"))?;
+            return (name.as_str(), u16::from_be_bytes(port))
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| protocol_error("SOCKS5 relay name doesn't resolve"));
+        }
+        _ => return Err(protocol_error(&format!("SOCKS5 proxy sent unknown address type {}", atyp))),
+    };
+    let mut port = [0; 2];
+    control.read_exact(&mut port)?;
+    Ok(SocketAddr::new(ip, u16::from_be_bytes(port)))
+}
+
+/// Parses the header of a relayed datagram, returning the peer it came from
+/// and where the payload starts.
+fn parse_udp_header(datagram: &[u8]) -> Option<(SocketAddr, usize)> {
+    let (&fragment, &atyp) = (datagram.get(2)?, datagram.get(3)?);
+    if fragment != 0 {
+        return None;
+    }
+    let (ip, offset) = match atyp {
+        ATYP_IPV4 => {
+            let octets: [u8; 4] = datagram.get(4..8)?.try_into().ok()?;
+            (IpAddr::V4(Ipv4Addr::from(octets)), 8)
+        }
+        ATYP_IPV6 => {
+            let octets: [u8; 16] = datagram.get(4..20)?.try_into().ok()?;
+            (IpAddr::V6(Ipv6Addr::from(octets)), 20)
+        }
+        _ => return None,
+    };
+    let port = u16::from_be_bytes(datagram.get(offset..offset + 2)?.try_into().ok()?);
+    Some((SocketAddr::new(ip, port), offset + 2))
+}
+
+fn protocol_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Turns a failure reply to UDP ASSOCIATE into an error.
+fn reply_error(code: u8) -> io::Error {
+    let (kind, reason) = match code {
+        0x02 => (io::ErrorKind::PermissionDenied, "not allowed by ruleset"),
+        0x03 => (io::ErrorKind::Other, "network unreachable"),
+        0x04 => (io::ErrorKind::Other, "host unreachable"),
+        0x05 => (io::ErrorKind::ConnectionRefused, "connection refused"),
+        0x06 => (io::ErrorKind::TimedOut, "TTL expired"),
+        0x07 => (io::ErrorKind::Unsupported, "UDP ASSOCIATE not supported"),
+        0x08 => (io::ErrorKind::Unsupported, "address type not supported"),
+        _ => (io::ErrorKind::Other, "general failure"),
+    };
+    io::Error::new(kind, format!("SOCKS5 proxy refused the UDP association: {}", reason))
+}
@@ -0,0 +1,181 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::error::ReUDPError;
+use crate::event::Event;
+use crate::handle::ReUDPHandle;
+use crate::reudp::ReUDP;
+
+/// Sending half of a ReUDP instance, created by `ReUDP::split`.
+///
+/// Each call locks the instance only while it sends, so it can run on another
+/// thread than the `RecvHalf` without waiting for it to receive something.
+pub struct SendHalf {
+    handle: ReUDPHandle,
+}
+
+/// Receiving half of a ReUDP instance, created by `ReUDP::split`.
+///
+/// Receiving never waits while holding the lock on the instance, whatever its
+/// blocking mode, so the `SendHalf` is never stalled by it.
+pub struct RecvHalf {
+    handle: ReUDPHandle,
+}
+
+/// Shares one handle to `reudp` between the two halves.
+pub(crate) fn halves(reudp: ReUDP) -> (SendHalf, RecvHalf) {
+    let handle = reudp.into_handle();
+    (
+        SendHalf {
+            handle: handle.clone(),
+        },
+        RecvHalf { handle },
+    )
+}
+
+impl SendHalf {
+    /// Sends a message, like `ReUDP::send`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`, or an error.
+    pub fn send<D: AsRef<[u8]>>(&self, data: D, require_ack: bool) -> Result<(), ReUDPError> {
+        self.handle.send(data, require_ack)
+    }
+
+    /// Sends a message stamped with the current time, like `ReUDP::send_timestamped`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, `Closing` after `disconnect`, or an error.
+    pub fn send_timestamped<D: AsRef<[u8]>>(&self, data: D, require_ack: bool) -> Result<(), ReUDPError> {
+        self.handle.send_timestamped(data, require_ack)
+    }
+
+    /// Sends a message on an ordered channel, like `ReUDP::send_ordered_channel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - The channel to send on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn send_ordered_channel<D: AsRef<[u8]>>(
+        &self,
+        channel_id: u8,
+        data: D,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        self.handle.send_ordered_channel(channel_id, data, require_ack)
+    }
+
+    /// Sends a message on an unordered channel, like `ReUDP::send_unordered_channel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - The channel to send on.
+    /// * `data` - The data to be sent.
+    /// * `require_ack` - Whether the message requires an acknowledgment.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ReUDPError>` - Ok if successful, or an error.
+    pub fn send_unordered_channel<D: AsRef<[u8]>>(
+        &self,
+        channel_id: u8,
+        data: D,
+        require_ack: bool,
+    ) -> Result<(), ReUDPError> {
+        self.handle.send_unordered_channel(channel_id, data, require_ack)
+    }
+
+    /// Sends the messages of an open batch, like `ReUDP::flush`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, ReUDPError>` - The number of datagrams sent, or an error.
+    pub fn flush(&self) -> Result<usize, ReUDPError> {
+        self.handle.flush()
+    }
+
+    /// Puts the instance back together from its two halves.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The receiving half.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ReUDP, (SendHalf, RecvHalf)>` - The instance, or both halves
+    ///   back if they don't come from the same `split`.
+    pub fn reunite(self, other: RecvHalf) -> Result<ReUDP, (SendHalf, RecvHalf)> {
+        if !self.handle.same_instance(&other.handle) {
+            return Err((self, other));
+        }
+        drop(other);
+        Ok(self
+            .handle
+            .try_into_inner()
+            .unwrap_or_else(|_| unreachable!("both halves were given")))
+    }
+}
+
+impl RecvHalf {
+    /// Receives the next message without waiting, like `ReUDP::recv` in
+    /// non-blocking mode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError>` - The address and data received, or an error.
+    pub fn recv(&self) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        self.handle.recv()
+    }
+
+    /// Receives a message, waiting for up to `timeout` for one to arrive.
+    ///
+    /// The socket is looked at every millisecond; the lock is released in
+    /// between, so the sending half can go on meanwhile.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for a message.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError>` - The received message
+    ///   and its sender, `None` if none arrived in time, or an error.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        self.handle.recv_timeout(timeout)
+    }
+
+    /// Receives every message pending on the socket, like `ReUDP::recv_all`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<(SocketAddr, Vec<u8>)>, ReUDPError>` - The received messages, or an error.
+    pub fn recv_all(&self) -> Result<Vec<(SocketAddr, Vec<u8>)>, ReUDPError> {
+        self.handle.recv_all()
+    }
+
+    /// Returns the next pending event, like `ReUDP::poll_event`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Event>` - The oldest event not polled yet, if any.
+    pub fn poll_event(&self) -> Option<Event> {
+        self.handle.poll_event()
+    }
+}
@@ -0,0 +1,167 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use crate::error::ReUDPError;
+use crate::mode::Mode;
+use crate::session::SessionToken;
+
+/// Marks the start of persisted state, followed by the format version.
+const MAGIC: &[u8; 4] = b"RUDS";
+const VERSION: u8 = 1;
+
+/// What `ReUDP::export_state` persists of an instance: enough to pick its
+/// sessions up again after a restart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PersistedState {
+    pub(crate) local_addr: SocketAddr,
+    pub(crate) mode: Mode,
+    pub(crate) send_sequence: u64,
+    pub(crate) recv_sequence: u64,
+    pub(crate) recv_frontier: u64,
+    /// Token the client resumes its session with
+    pub(crate) session_token: Option<SessionToken>,
+    /// Connected clients, for a server
+    pub(crate) clients: Vec<SocketAddr>,
+    /// Resumption tokens issued by a server, with the address they were issued
+    /// to and their age, oldest first
+    pub(crate) issued_tokens: Vec<(SessionToken, SocketAddr, Duration)>,
+}
+
+impl PersistedState {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        write_addr(&mut bytes, self.local_addr);
+        match self.mode {
+            Mode::Server => bytes.push(0),
+            Mode::Client(server_addr) => {
+                bytes.push(1);
+                write_addr(&mut bytes, server_addr);
+            }
+        }
+        for sequence in [self.send_sequence, self.recv_sequence, self.recv_frontier] {
+            bytes.extend_from_slice(&sequence.to_be_bytes());
+        }
+        match &self.session_token {
+            Some(token) => {
+                bytes.push(1);
+                bytes.extend_from_slice(token);
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&(self.clients.len() as u32).to_be_bytes());
+        for addr in &self.clients {
+            write_addr(&mut bytes, *addr);
+        }
+        bytes.extend_from_slice(&(self.issued_tokens.len() as u32).to_be_bytes());
+        for (token, addr, age) in &self.issued_tokens {
+            bytes.extend_from_slice(token);
+            write_addr(&mut bytes, *addr);
+            bytes.extend_from_slice(&(age.as_millis() as u64).to_be_bytes());
+        }
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, ReUDPError> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid_state("not a ReUDP state"));
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(invalid_state(&format!("unsupported state version {}", version)));
+        }
+        let local_addr = reader.addr()?;
+        let mode = match reader.u8()? {
+            0 => Mode::Server,
+            1 => Mode::Client(reader.addr()?),
+            other => return Err(invalid_state(&format!("unknown mode {}", other))),
+        };
+        let (send_sequence, recv_sequence, recv_frontier) = (reader.u64()?, reader.u64()?, reader.u64()?);
+        let session_token = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.token()?),
+        };
+        let clients = (0..reader.u32()?).map(|_| reader.addr()).collect::<Result<_, _>>()?;
+        let issued_tokens = (0..reader.u32()?)
+            .map(|_| Ok((reader.token()?, reader.addr()?, Duration::from_millis(reader.u64()?))))
+            .collect::<Result<_, ReUDPError>>()?;
+        if !reader.bytes.is_empty() {
+            return Err(invalid_state("trailing bytes"));
+        }
+        Ok(Self {
+            local_addr,
+            mode,
+            send_sequence,
+            recv_sequence,
+            recv_frontier,
+            session_token,
+            clients,
+            issued_tokens,
+        })
+    }
+}
+
+fn write_addr(bytes: &mut Vec<u8>, addr: SocketAddr) {
+    match addr.ip() {
+        IpAddr::V4(ip) => {
+            bytes.push(4);
+            bytes.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            bytes.push(6);
+            bytes.extend_from_slice(&ip.octets());
+        }
+    }
+    bytes.extend_from_slice(&addr.port().to_be_bytes());
+}
+
+/// Reads persisted state front to back, failing on truncated input.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReUDPError> {
+        if self.bytes.len() < len {
+            return Err(invalid_state("truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, ReUDPError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ReUDPError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ReUDPError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn token(&mut self) -> Result<SessionToken, ReUDPError> {
+        Ok(self.take(32)?.try_into().unwrap())
+    }
+
+    fn addr(&mut self) -> Result<SocketAddr, ReUDPError> {
+        let ip = match self.u8()? {
+            4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(self.take(4)?).unwrap())),
+            6 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(self.take(16)?).unwrap())),
+            other => return Err(invalid_state(&format!("unknown address family {}", other))),
+        };
+        let port = u16::from_be_bytes(self.take(2)?.try_into().unwrap());
+        Ok(SocketAddr::new(ip, port))
+    }
+}
+
+fn invalid_state(reason: &str) -> ReUDPError {
+    ReUDPError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid ReUDP state: {}", reason),
+    ))
+}
@@ -0,0 +1,32 @@
+/// Counters describing the traffic handled by a ReUDP instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// Packets dropped because their sender isn't in the allowed senders
+    pub packets_dropped_unauthorized: u64,
+    /// Received messages dropped because their sender's delivery queue was full
+    pub messages_dropped_queue_full: u64,
+    /// Datagrams dropped because they were larger than the receive buffer
+    pub packets_dropped_truncated: u64,
+    /// Datagrams, or messages of a batch, dropped because they don't parse as
+    /// ReUDP messages
+    pub packets_dropped_malformed: u64,
+    /// Received messages dropped because they didn't decrypt, or because they
+    /// came unencrypted while an encryption key is set
+    pub messages_dropped_undecryptable: u64,
+    /// Receives that failed with a connection reset, which was ignored as it
+    /// doesn't say which peer it concerns
+    pub connection_resets_ignored: u64,
+    /// Largest number of clients connected at once (server mode)
+    pub peak_client_count: usize,
+    /// Clients that connected, counting a client again each time it reconnects
+    /// after being evicted or disconnecting (server mode)
+    pub total_client_connections: u64,
+}
+
+impl Statistics {
+    /// Records a client joining, leaving `client_count` clients connected.
+    pub(crate) fn on_client_added(&mut self, client_count: usize) {
+        self.total_client_connections += 1;
+        self.peak_client_count = self.peak_client_count.max(client_count);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::socket;
+
+/// Size of the length prefix in front of each packet on a stream.
+const PREFIX_SIZE: usize = 2;
+/// How long writing a packet may block before the stream is given up on, as a
+/// packet half written can't be taken back.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Packets read from the streams that may wait for a receive; beyond that they
+/// are dropped, as a full socket buffer would.
+const MAX_QUEUED: usize = 1024;
+
+/// Packets carried over TCP next to a UDP socket: each one goes on a stream
+/// with a 2-byte big-endian length in front of it.
+///
+/// A thread per stream reads the packets into a queue and wakes up a receive
+/// waiting on the UDP socket by sending it an empty datagram, so receiving
+/// keeps the blocking mode and timeouts of the UDP socket.
+#[derive(Debug)]
+pub(crate) struct TcpTransport {
+    shared: Arc<Shared>,
+    /// Address of the listener, for a server
+    listener: Option<SocketAddr>,
+}
+
+/// State shared with the threads reading the streams and accepting clients.
+#[derive(Debug)]
+struct Shared {
+    /// Stream to each peer reached over TCP
+    links: Mutex<HashMap<SocketAddr, Arc<Link>>>,
+    /// Packets read from the streams, with their sender, oldest first
+    incoming: Mutex<VecDeque<(SocketAddr, Vec<u8>)>>,
+    /// Socket sending the wake-up datagrams
+    waker: UdpSocket,
+    /// Address of `waker`, to tell its datagrams apart
+    waker_addr: SocketAddr,
+    /// Address of the UDP socket the wake-up datagrams go to
+    wake_addr: SocketAddr,
+    /// Set once the transport is dropped
+    closed: AtomicBool,
+}
+
+/// A stream to one peer.
+#[derive(Debug)]
+struct Link {
+    /// Held while a packet is written, so packets don't interleave
+    writer: Mutex<TcpStream>,
+    /// Handle on the same stream, for shutting it down while a write blocks
+    control: TcpStream,
+}
+
+impl TcpTransport {
+    /// Connects to the server at `server_addr`, giving up after `timeout`.
+    /// `udp_addr` is the address of the UDP socket receives go through.
+    pub(crate) fn connect(server_addr: SocketAddr, udp_addr: SocketAddr, timeout: Duration) -> io::Result<Self> {
+        let stream = TcpStream::connect_timeout(&server_addr, timeout)?;
+        let transport = Self {
+            shared: Shared::new(udp_addr)?,
+            listener: None,
+        };
+        transport.shared.add(socket::canonical(server_addr), stream)?;
+        Ok(transport)
+    }
+
+    /// Accepts clients on the TCP port matching the UDP socket at `udp_addr`.
+    pub(crate) fn listen(udp_addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(udp_addr)?;
+        let transport = Self {
+            shared: Shared::new(udp_addr)?,
+            listener: Some(listener.local_addr()?),
+        };
+        let shared = Arc::clone(&transport.shared);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if shared.closed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                if let Ok(addr) = stream.peer_addr() {
+                    let _ = shared.add(socket::canonical(addr), stream);
+                }
+            }
+        });
+        Ok(transport)
+    }
+
+    /// Sends `buf` to `addr` if it is reached over TCP, returning `None` otherwise.
+    pub(crate) fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Option<io::Result<usize>> {
+        let link = self.shared.links.lock().unwrap().get(&addr).cloned()?;
+        Some(link.send(buf))
+    }
+
+    /// Returns whether `addr` is reached over TCP.
+    pub(crate) fn reaches(&self, addr: SocketAddr) -> bool {
+        self.shared.links.lock().unwrap().contains_key(&addr)
+    }
+
+    /// Returns the next packet read from a stream, or else the next datagram
+    /// `recv` reads from the UDP socket that isn't a wake-up. Packets larger
+    /// than `buf` are truncated, as datagrams are.
+    pub(crate) fn recv_from<F>(&self, buf: &mut [u8], recv: F) -> io::Result<(usize, SocketAddr)>
+    where
+        F: Fn(&mut [u8]) -> io::Result<(usize, SocketAddr)>,
+    {
+        loop {
+            let packet = self.shared.incoming.lock().unwrap().pop_front();
+            if let Some((addr, packet)) = packet {
+                let len = packet.len().min(buf.len());
+                buf[..len].copy_from_slice(&packet[..len]);
+                return Ok((len, addr));
+            }
+            let (len, addr) = recv(buf)?;
+            if addr != self.shared.waker_addr {
+                return Ok((len, addr));
+            }
+        }
+    }
+}
+
+impl Drop for TcpTransport {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+        for link in self.shared.links.lock().unwrap().values() {
+            let _ = link.control.shutdown(Shutdown::Both);
+        }
+        // Wakes up the thread accepting clients, so it sees the flag.
+        if let Some(listener) = self.listener {
+            let _ = TcpStream::connect_timeout(&local_target(listener), WRITE_TIMEOUT);
+        }
+    }
+}
+
+impl Shared {
+    fn new(udp_addr: SocketAddr) -> io::Result<Arc<Self>> {
+        let wake_addr = local_target(udp_addr);
+        let waker = UdpSocket::bind(SocketAddr::new(wake_addr.ip(), 0))?;
+        Ok(Arc::new(Self {
+            links: Mutex::new(HashMap::new()),
+            incoming: Mutex::new(VecDeque::new()),
+            waker_addr: socket::canonical(waker.local_addr()?),
+            waker,
+            wake_addr,
+            closed: AtomicBool::new(false),
+        }))
+    }
+
+    /// Starts exchanging packets with `addr` over `stream`.
+    fn add(self: &Arc<Self>, addr: SocketAddr, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let reader = stream.try_clone()?;
+        let link = Arc::new(Link {
+            control: stream.try_clone()?,
+            writer: Mutex::new(stream),
+        });
+        self.links.lock().unwrap().insert(addr, Arc::clone(&link));
+        if self.closed.load(Ordering::SeqCst) {
+            let _ = link.control.shutdown(Shutdown::Both);
+        }
+        let shared = Arc::clone(self);
+        thread::spawn(move || shared.read_packets(addr, reader, &link));
+        Ok(())
+    }
+
+    /// Queues the packets arriving from `addr` until the stream ends.
+    fn read_packets(&self, addr: SocketAddr, mut stream: TcpStream, link: &Arc<Link>) {
+        let mut prefix = [0; PREFIX_SIZE];
+        while stream.read_exact(&mut prefix).is_ok() {
+            let mut packet = vec![0; u16::from_be_bytes(prefix) as usize];
+            if stream.read_exact(&mut packet).is_err() {
+                break;
+            }
+            let mut incoming = self.incoming.lock().unwrap();
+            if incoming.len() < MAX_QUEUED {
+                incoming.push_back((addr, packet));
+                drop(incoming);
+                let _ = self.waker.send_to(&[], self.wake_addr);
+            }
+        }
+        let mut links = self.links.lock().unwrap();
+        if links.get(&addr).is_some_and(|current| Arc::ptr_eq(current, link)) {
+            links.remove(&addr);
+        }
+    }
+}
+
+impl Link {
+    /// Writes `buf` with its length in front of it. A failed write leaves the
+    /// stream unusable, so it is shut down.
+    fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let len = u16::try_from(buf.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "packet too large to frame"))?;
+        let mut frame = Vec::with_capacity(PREFIX_SIZE + buf.len());
+        frame.extend_from_slice(&len.to_be_bytes());
+        frame.extend_from_slice(buf);
+        if let Err(error) = self.writer.lock().unwrap().write_all(&frame) {
+            let _ = self.control.shutdown(Shutdown::Both);
+            return Err(error);
+        }
+        Ok(buf.len())
+    }
+}
+
+/// Returns an address reaching the socket bound to `addr` from this host: the
+/// loopback address of its family in place of an unspecified one.
+fn local_target(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), addr.port()),
+        IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(Ipv6Addr::LOCALHOST.into(), addr.port()),
+        _ => addr,
+    }
+}
@@ -0,0 +1,8 @@
+//! The clock types used throughout the crate. The standard library has no
+//! clock on wasm32, so there they come from `web-time`, which reads the
+//! browser's.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::{Instant, SystemTime, UNIX_EPOCH};
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::{Instant, SystemTime, UNIX_EPOCH};
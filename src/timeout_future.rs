@@ -0,0 +1,114 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::ReUDPError;
+use crate::reudp::ReUDP;
+use crate::time::Instant;
+
+/// How often a pending future asks to be polled again to check the socket.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Future that sends a message and resolves to the first reply accepted by a
+/// matching function, created by `ReUDP::send_and_wait`.
+///
+/// It doesn't depend on any async runtime: the socket is read when the future
+/// is polled, and while it is pending a helper thread wakes it up at a short
+/// interval until it completes or is dropped. Messages the matching function
+/// rejects stay queued for `recv`.
+pub struct TimeoutFuture<'a, D, F> {
+    reudp: &'a mut ReUDP,
+    /// Data still to send, taken on the first poll
+    data: Option<D>,
+    match_fn: F,
+    timeout: Duration,
+    deadline: Option<Instant>,
+    ticker: Option<Ticker>,
+}
+
+impl<'a, D, F> TimeoutFuture<'a, D, F> {
+    pub(crate) fn new(reudp: &'a mut ReUDP, data: D, match_fn: F, timeout: Duration) -> Self {
+        Self {
+            reudp,
+            data: Some(data),
+            match_fn,
+            timeout,
+            deadline: None,
+            ticker: None,
+        }
+    }
+}
+
+// Nothing in the future points into itself, so it can be moved while pinned.
+impl<D, F> Unpin for TimeoutFuture<'_, D, F> {}
+
+impl<D, F> Future for TimeoutFuture<'_, D, F>
+where
+    D: AsRef<[u8]>,
+    F: Fn(&[u8]) -> bool + Send + 'static,
+{
+    type Output = Result<Vec<u8>, ReUDPError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(data) = this.data.take() {
+            // The timeout runs from the moment the message is actually sent.
+            this.deadline = Some(Instant::now() + this.timeout);
+            if let Err(e) = this.reudp.send(data, true) {
+                return Poll::Ready(Err(e));
+            }
+        }
+        match this.reudp.recv_matching(&this.match_fn) {
+            Ok(Some(payload)) => return Poll::Ready(Ok(payload)),
+            Ok(None) => {}
+            Err(e) => return Poll::Ready(Err(e)),
+        }
+        if this.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Poll::Ready(Err(ReUDPError::Timeout));
+        }
+        this.ticker.get_or_insert_with(Ticker::start).set_waker(cx.waker());
+        Poll::Pending
+    }
+}
+
+/// Thread waking the last registered waker every `POLL_INTERVAL` until dropped.
+struct Ticker {
+    waker: Arc<Mutex<Option<Waker>>>,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl Ticker {
+    fn start() -> Self {
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let waker = Arc::clone(&waker);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    thread::park_timeout(POLL_INTERVAL);
+                    if let Some(waker) = waker.lock().unwrap().take() {
+                        waker.wake();
+                    }
+                }
+            })
+        };
+        Self { waker, stop, thread }
+    }
+
+    fn set_waker(&self, waker: &Waker) {
+        *self.waker.lock().unwrap() = Some(waker.clone());
+    }
+}
+
+impl Drop for Ticker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.thread().unpark();
+    }
+}
@@ -0,0 +1,80 @@
+//! W3C Trace Context propagation in message extensions, with the
+//! `opentelemetry` feature.
+//!
+//! A message sent within a traced span carries the `traceparent` header value
+//! in a `TRACEPARENT_EXTENSION`, and a non-empty `tracestate` in a
+//! `TRACESTATE_EXTENSION`. The receiving span takes the sender's span as its
+//! remote parent, so both ends end up in the same trace.
+
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
+use opentelemetry::Context;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::message::{TRACEPARENT_EXTENSION, TRACESTATE_EXTENSION};
+
+/// The only `traceparent` version defined so far.
+const VERSION: u8 = 0;
+
+/// Returns the extensions carrying the trace context of `span`, none if it
+/// isn't part of a trace.
+pub(crate) fn extensions(span: &tracing::Span) -> Vec<(u8, Vec<u8>)> {
+    let context = span.context();
+    let span_ref = context.span();
+    let span_context = span_ref.span_context();
+    if !span_context.is_valid() {
+        return Vec::new();
+    }
+    let traceparent = format!(
+        "{:02x}-{}-{}-{:02x}",
+        VERSION,
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags() & TraceFlags::SAMPLED
+    );
+    let mut extensions = vec![(TRACEPARENT_EXTENSION, traceparent.into_bytes())];
+    let tracestate = span_context.trace_state().header();
+    // An extension holds at most 255 bytes; a longer state is left out.
+    if !tracestate.is_empty() && tracestate.len() <= u8::MAX as usize {
+        extensions.push((TRACESTATE_EXTENSION, tracestate.into_bytes()));
+    }
+    extensions
+}
+
+/// Makes the span whose context `traceparent` and `tracestate` describe the
+/// parent of `span`. Values that don't parse are ignored.
+pub(crate) fn set_remote_parent(span: &tracing::Span, traceparent: &[u8], tracestate: Option<&[u8]>) {
+    let Some(span_context) = parse(traceparent, tracestate) else {
+        return;
+    };
+    // Fails only if no OpenTelemetry layer traces the span, leaving nothing to connect.
+    let _ = span.set_parent(Context::new().with_remote_span_context(span_context));
+}
+
+/// Parses a version 0 `traceparent` value, as `00-<trace id>-<span id>-<flags>`.
+fn parse(traceparent: &[u8], tracestate: Option<&[u8]>) -> Option<SpanContext> {
+    let traceparent = std::str::from_utf8(traceparent).ok()?;
+    let parts: Vec<&str> = traceparent.split('-').collect();
+    let [version, trace_id, span_id, flags] = parts[..] else {
+        return None;
+    };
+    let lowercase_hex = |part: &str, len| part.len() == len && part.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    if !lowercase_hex(version, 2) || !lowercase_hex(trace_id, 32) || !lowercase_hex(span_id, 16) || !lowercase_hex(flags, 2) {
+        return None;
+    }
+    if u8::from_str_radix(version, 16).ok()? != VERSION {
+        return None;
+    }
+    let flags = TraceFlags::new(u8::from_str_radix(flags, 16).ok()?) & TraceFlags::SAMPLED;
+    let tracestate = tracestate
+        .and_then(|state| std::str::from_utf8(state).ok())
+        .and_then(|state| state.parse::<TraceState>().ok())
+        .unwrap_or_default();
+    let span_context = SpanContext::new(
+        TraceId::from_hex(trace_id).ok()?,
+        SpanId::from_hex(span_id).ok()?,
+        flags,
+        true,
+        tracestate,
+    );
+    span_context.is_valid().then_some(span_context)
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::socket;
+use crate::time::Instant;
+
+/// How long a receive waiting on a transport sleeps between two polls.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A datagram transport ReUDP can run over instead of its own UDP socket, e.g.
+/// to simulate a network in tests or to carry packets over another protocol.
+/// Used with `ReUDP::with_transport`.
+///
+/// The instance and its heartbeat thread share the transport, so it is called
+/// from both threads. Datagrams may be lost, duplicated or reordered, as over
+/// UDP; ReUDP takes care of that.
+pub trait Transport: Send + Sync {
+    /// Sends `buf` as one datagram to `addr`, returning the number of bytes sent.
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize>;
+
+    /// Receives one datagram into `buf`, returning its length and sender.
+    ///
+    /// Must not block: without a datagram waiting, it fails with an error of
+    /// kind `WouldBlock`, and ReUDP polls again when it is meant to wait. A
+    /// datagram larger than `buf` is truncated.
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)>;
+
+    /// Returns the address the transport is known under.
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+/// A `Transport` over a plain UDP socket, with peers reported as ReUDP's own
+/// socket reports them: IPv4 peers of a dual-stack socket as IPv4 addresses.
+///
+/// `ReUDP::with_config` doesn't go through it, so that its socket can also be
+/// connected and send in batches; it is meant as a base for transports that
+/// wrap UDP, e.g. to drop or delay some packets.
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+impl UdpTransport {
+    /// Binds a UDP socket to `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Self::new(UdpSocket::bind(addr)?)
+    }
+
+    /// Wraps `socket`, switching it to non-blocking mode. On Windows, the
+    /// socket also stops reporting connection resets, as with `ReUDP::with_config`.
+    pub fn new(socket: UdpSocket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        socket::disable_connection_reset(&socket)?;
+        Ok(Self { socket })
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        let addr = match (self.socket.local_addr()?, addr) {
+            (SocketAddr::V6(_), SocketAddr::V4(v4)) => SocketAddr::new(v4.ip().to_ipv6_mapped().into(), v4.port()),
+            _ => addr,
+        };
+        self.socket.send_to(buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let (len, addr) = self.socket.recv_from(buf)?;
+        Ok((len, socket::canonical(addr)))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+/// A `Transport` given to `ReUDP::with_transport`, with the blocking mode and
+/// read timeout ReUDP expects of a socket emulated by polling.
+pub(crate) struct CustomSocket {
+    transport: Box<dyn Transport>,
+    /// Whether receives fail right away, and how long they wait otherwise
+    wait: Mutex<(bool, Option<Duration>)>,
+}
+
+impl CustomSocket {
+    pub(crate) fn new(transport: Box<dyn Transport>) -> Self {
+        Self {
+            transport,
+            wait: Mutex::new((true, None)),
+        }
+    }
+
+    pub(crate) fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        self.transport.send_to(buf, addr)
+    }
+
+    /// Receives a datagram, polling the transport until one arrives or the
+    /// read timeout passes unless in non-blocking mode.
+    pub(crate) fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let (nonblocking, timeout) = *self.wait.lock().unwrap();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match self.transport.recv_from(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && !nonblocking => {
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(e);
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub(crate) fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.transport.local_addr()
+    }
+
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.wait.lock().unwrap().0 = nonblocking;
+        Ok(())
+    }
+
+    pub(crate) fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(self.wait.lock().unwrap().1)
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.wait.lock().unwrap().1 = timeout;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CustomSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomSocket").field("local_addr", &self.local_addr().ok()).finish()
+    }
+}
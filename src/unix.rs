@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv6Addr, SocketAddr};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A Unix datagram socket whose peers are presented as socket addresses, so the
+/// rest of ReUDP can track them like network peers.
+///
+/// Each path seen gets a placeholder address in `100::/64`, the discard-only
+/// prefix of RFC 6666, which no real peer can have. The socket's own path gets
+/// the first one.
+#[derive(Debug)]
+pub(crate) struct UnixSocket {
+    socket: UnixDatagram,
+    path: PathBuf,
+    addrs: Mutex<PathAddrs>,
+}
+
+/// Placeholder addresses given to paths, both ways.
+#[derive(Debug, Default)]
+struct PathAddrs {
+    by_path: HashMap<PathBuf, SocketAddr>,
+    by_addr: HashMap<SocketAddr, PathBuf>,
+}
+
+impl PathAddrs {
+    fn addr_of(&mut self, path: &Path) -> SocketAddr {
+        if let Some(addr) = self.by_path.get(path) {
+            return *addr;
+        }
+        let index = self.by_path.len() as u64;
+        let addr = SocketAddr::new(
+            Ipv6Addr::new(
+                0x100,
+                0,
+                0,
+                0,
+                (index >> 48) as u16,
+                (index >> 32) as u16,
+                (index >> 16) as u16,
+                index as u16,
+            )
+            .into(),
+            0,
+        );
+        self.by_path.insert(path.to_path_buf(), addr);
+        self.by_addr.insert(addr, path.to_path_buf());
+        addr
+    }
+}
+
+impl UnixSocket {
+    /// Binds a socket at `path`, which must not exist yet.
+    pub(crate) fn bind(path: &Path) -> io::Result<Self> {
+        let socket = UnixDatagram::bind(path)?;
+        let mut addrs = PathAddrs::default();
+        addrs.addr_of(path);
+        Ok(Self {
+            socket,
+            path: path.to_path_buf(),
+            addrs: Mutex::new(addrs),
+        })
+    }
+
+    /// Returns the placeholder address of `path`, giving it one if it has none yet.
+    pub(crate) fn addr_of(&self, path: &Path) -> SocketAddr {
+        self.addrs.lock().unwrap().addr_of(path)
+    }
+
+    /// Returns the path a placeholder address was given to.
+    pub(crate) fn path_of(&self, addr: SocketAddr) -> Option<PathBuf> {
+        self.addrs.lock().unwrap().by_addr.get(&addr).cloned()
+    }
+
+    pub(crate) fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        let path = self.path_of(addr).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::AddrNotAvailable, format!("{} is not a Unix socket peer", addr))
+        })?;
+        self.socket.send_to(buf, path)
+    }
+
+    /// Receives a datagram from a named socket. Datagrams from unnamed sockets
+    /// can't be answered and are skipped.
+    pub(crate) fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        loop {
+            let (len, addr) = self.socket.recv_from(buf)?;
+            if let Some(path) = addr.as_pathname() {
+                return Ok((len, self.addr_of(path)));
+            }
+        }
+    }
+
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.addr_of(&self.path)
+    }
+
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    pub(crate) fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.socket.read_timeout()
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_read_timeout(timeout)
+    }
+}
+
+/// The socket file is removed with the socket, so the path can be bound again.
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
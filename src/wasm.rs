@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use js_sys::{ArrayBuffer, Function, Promise, Uint8Array};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{MessageEvent, RtcDataChannel, RtcDataChannelType};
+
+use crate::transport::Transport;
+
+/// A `Transport` over a WebRTC data channel, to run ReUDP in a browser.
+///
+/// A data channel links two peers, so the transport has a single one: it only
+/// sends to `peer_addr`, and reports every datagram it receives as coming from
+/// there. Both addresses only name the ends of the channel for ReUDP; nothing
+/// is bound to them. The channel should be unordered and never retransmit
+/// (`ordered: false`, `maxRetransmits: 0`), so that it carries datagrams the
+/// way UDP does and leaves reliability to ReUDP.
+pub struct WasmTransport {
+    channel: RtcDataChannel,
+    /// Datagrams received and not read yet, filled by `on_message`
+    inbox: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+    /// Handler of the channel's `message` events, kept alive with the transport
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+// SAFETY: without the `atomics` target feature, a wasm32 module runs on a
+// single thread, so the JavaScript objects held are never used from two threads.
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl Send for WasmTransport {}
+// SAFETY: as for `Send`, there is no other thread to share the transport with.
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl Sync for WasmTransport {}
+
+impl WasmTransport {
+    /// Wraps `channel`, switching it to binary messages received as `ArrayBuffer`s.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The data channel to the peer.
+    /// * `local_addr` - The address this end is known under.
+    /// * `peer_addr` - The address the other end is known under.
+    pub fn new(channel: RtcDataChannel, local_addr: SocketAddr, peer_addr: SocketAddr) -> Self {
+        channel.set_binary_type(RtcDataChannelType::Arraybuffer);
+        let inbox = Arc::new(Mutex::new(VecDeque::new()));
+        let received = Arc::clone(&inbox);
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            // Text messages aren't datagrams of ours.
+            if let Ok(buffer) = event.data().dyn_into::<ArrayBuffer>() {
+                received.lock().unwrap().push_back(Uint8Array::new(&buffer).to_vec());
+            }
+        });
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        Self {
+            channel,
+            inbox,
+            local_addr,
+            peer_addr,
+            on_message,
+        }
+    }
+}
+
+impl Transport for WasmTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        if addr != self.peer_addr {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("the data channel only reaches {}", self.peer_addr),
+            ));
+        }
+        // Fails while the channel isn't open, as sends to an unreachable peer.
+        self.channel
+            .send_with_u8_array(buf)
+            .map_err(|error| io::Error::new(io::ErrorKind::NotConnected, format!("{:?}", error)))?;
+        Ok(buf.len())
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let Some(datagram) = self.inbox.lock().unwrap().pop_front() else {
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        };
+        let len = datagram.len().min(buf.len());
+        buf[..len].copy_from_slice(&datagram[..len]);
+        Ok((len, self.peer_addr))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+}
+
+impl Drop for WasmTransport {
+    fn drop(&mut self) {
+        // The handler is freed with the transport; the channel must not call it after.
+        let handler: &JsValue = self.on_message.as_ref();
+        if self.channel.onmessage().is_some_and(|installed| JsValue::from(installed) == *handler) {
+            self.channel.set_onmessage(None);
+        }
+    }
+}
+
+impl fmt::Debug for WasmTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmTransport")
+            .field("local_addr", &self.local_addr)
+            .field("peer_addr", &self.peer_addr)
+            .finish()
+    }
+}
+
+/// Runs the heartbeat work of an instance on the browser's event loop: `tick`
+/// does one round of it and returns how long to wait before the next, until
+/// it returns `None`.
+pub(crate) fn spawn_heartbeat(mut tick: impl FnMut() -> Option<Duration> + 'static) {
+    wasm_bindgen_futures::spawn_local(async move {
+        while let Some(wait) = tick() {
+            sleep(wait).await;
+        }
+    });
+}
+
+/// Resolves once `duration` has passed, without blocking the event loop.
+async fn sleep(duration: Duration) {
+    let millis = i32::try_from(duration.as_millis()).unwrap_or(i32::MAX);
+    let timer = Promise::new(&mut |resolve, _reject| {
+        set_timeout(&resolve, millis);
+    });
+    // The promise is never rejected.
+    let _ = JsFuture::from(timer).await;
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// `setTimeout` of the global scope, whether a window or a worker.
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &Function, timeout: i32) -> JsValue;
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::recv_within;
+
+/// Creates a client of `server_addr` whose socket isn't connected, so packets
+/// from other senders reach the allow-list rather than being dropped by the OS.
+fn unconnected_client(server_addr: SocketAddr) -> ReUDP {
+    let config = ReUDPConfig::default().connect_client_socket(false);
+    ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap()
+}
+
+fn data(sequence: u64, payload: &[u8]) -> Vec<u8> {
+    Message::new(sequence, MessageType::Data, payload.to_vec()).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_drops_packets_from_other_senders() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let intruder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        intruder.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let intruder_addr = intruder.local_addr().unwrap();
+
+        let mut client = unconnected_client(server.local_addr().unwrap());
+        let client_addr = client.local_addr().unwrap();
+
+        intruder.send_to(&data(0, b"injected"), client_addr).unwrap();
+        assert!(recv_within(&mut client, Duration::from_millis(200)).is_none());
+        assert_eq!(client.stats().packets_dropped_unauthorized, 1);
+        // Dropped packets are not acknowledged.
+        assert!(intruder.recv_from(&mut [0; 64]).is_err());
+
+        server.send_to(&data(0, b"genuine"), client_addr).unwrap();
+        let (addr, payload) = recv_within(&mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(addr, server.local_addr().unwrap());
+        assert_eq!(payload, b"genuine");
+
+        client.add_allowed_sender(intruder_addr);
+        intruder.send_to(&data(1, b"allowed"), client_addr).unwrap();
+        let (addr, _) = recv_within(&mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(addr, intruder_addr);
+
+        client.remove_allowed_sender(intruder_addr);
+        intruder.send_to(&data(2, b"revoked"), client_addr).unwrap();
+        assert!(recv_within(&mut client, Duration::from_millis(200)).is_none());
+        assert_eq!(client.stats().packets_dropped_unauthorized, 2);
+    }
+
+    #[test]
+    fn test_client_ignores_acks_from_other_senders() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let intruder = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let mut client = unconnected_client(server.local_addr().unwrap());
+        let client_addr = client.local_addr().unwrap();
+        client.send(b"important", true).unwrap();
+
+        let ack = Message::new(0, MessageType::Ack, vec![]).to_bytes();
+        intruder.send_to(&ack, client_addr).unwrap();
+        assert!(recv_within(&mut client, Duration::from_millis(200)).is_none());
+        assert_eq!(client.stats().packets_dropped_unauthorized, 1);
+        assert_eq!(client.pending_acks(), 1);
+
+        server.send_to(&ack, client_addr).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.pending_acks() > 0 && Instant::now() < deadline {
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(client.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_server_accepts_anyone_by_default() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        first.send_to(&data(0, b"first"), server_addr).unwrap();
+        assert!(recv_within(&mut server, Duration::from_secs(1)).is_some());
+
+        server.add_allowed_sender(first.local_addr().unwrap());
+        second.send_to(&data(1, b"second"), server_addr).unwrap();
+        assert!(recv_within(&mut server, Duration::from_millis(200)).is_none());
+        assert_eq!(server.stats().packets_dropped_unauthorized, 1);
+
+        server.allow_all_senders();
+        second.send_to(&data(1, b"second"), server_addr).unwrap();
+        assert!(recv_within(&mut server, Duration::from_secs(1)).is_some());
+    }
+}
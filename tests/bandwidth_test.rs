@@ -0,0 +1,64 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polls `client` until it has a bandwidth estimate, for up to `timeout`.
+fn wait_for_estimate(client: &mut ReUDP, timeout: Duration) -> Option<u64> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        client.recv().unwrap();
+        if let Some(estimate) = client.bandwidth_estimate() {
+            return Some(estimate);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_bandwidth_on_demand() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 2048).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 2048).unwrap();
+        assert!(client.bandwidth_estimate().is_none());
+
+        client.probe_bandwidth(1200).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut estimate = None;
+        while estimate.is_none() && Instant::now() < deadline {
+            server.recv().unwrap();
+            estimate = wait_for_estimate(&mut client, Duration::from_millis(5));
+        }
+        assert!(estimate.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_connect_probes_bandwidth() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 2048).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    server.recv().unwrap();
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        let config = ReUDPConfig::default().connect_probe_size(Some(1000));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap();
+        client.connect().unwrap();
+        assert!(wait_for_estimate(&mut client, Duration::from_secs(1)).is_some());
+
+        stop.store(true, Ordering::SeqCst);
+        server_thread.join().unwrap();
+    }
+}
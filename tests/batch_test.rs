@@ -0,0 +1,125 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+/// Binds a raw socket standing in for a server, and a client pointed at it.
+fn client_and_raw_server(config: ReUDPConfig) -> (ReUDP, UdpSocket) {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+    let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config).unwrap();
+    (client, server)
+}
+
+/// Reads the datagrams carrying data until none arrives for a while, returning
+/// the messages each one contains.
+fn recv_datagrams(socket: &UdpSocket) -> Vec<Vec<Message>> {
+    let mut datagrams = Vec::new();
+    let mut buf = [0; 2048];
+    while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        match message.message_type {
+            MessageType::Data => datagrams.push(vec![message]),
+            MessageType::Batch => {
+                let mut inner = Vec::new();
+                let mut rest = &message.payload[..];
+                while !rest.is_empty() {
+                    let message = Message::from_bytes(rest).unwrap();
+                    rest = &rest[message.encoded_len()..];
+                    inner.push(message);
+                }
+                datagrams.push(inner);
+            }
+            _ => {}
+        }
+    }
+    datagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batched_messages_are_sent_together_on_flush() {
+        let (mut client, server) = client_and_raw_server(ReUDPConfig::default().coalesce_window(Duration::from_secs(10)));
+        client.begin_batch();
+        for i in 0..5u8 {
+            client.send(vec![i], false).unwrap();
+        }
+        assert!(recv_datagrams(&server).is_empty());
+
+        assert_eq!(client.flush().unwrap(), 5);
+        let datagrams = recv_datagrams(&server);
+        assert_eq!(datagrams.len(), 1);
+        let sequences: Vec<u64> = datagrams[0].iter().map(|message| message.sequence).collect();
+        assert_eq!(sequences, vec![0, 1, 2, 3, 4]);
+
+        // The batch ended with the flush.
+        client.send(vec![5], false).unwrap();
+        assert_eq!(recv_datagrams(&server).len(), 1);
+        assert_eq!(client.flush().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_batches_respect_max_packet_size() {
+        let config = ReUDPConfig::default().max_packet_size(100);
+        let (mut client, server) = client_and_raw_server(config);
+        client.begin_batch();
+        for _ in 0..10 {
+            client.send(vec![0; 30], false).unwrap();
+        }
+        assert_eq!(client.flush().unwrap(), 10);
+
+        let datagrams = recv_datagrams(&server);
+        assert_eq!(datagrams.len(), 5);
+        assert!(datagrams.iter().all(|messages| messages.len() == 2));
+    }
+
+    #[test]
+    fn test_flush_if_full_and_coalesce_window() {
+        let config = ReUDPConfig::default().coalesce_window(Duration::from_millis(100));
+        let (mut client, server) = client_and_raw_server(config);
+        client.begin_batch();
+        client.send(vec![0; 50], false).unwrap();
+        assert_eq!(client.flush_if_full(100).unwrap(), 0);
+        client.send(vec![0; 50], false).unwrap();
+        assert_eq!(client.flush_if_full(100).unwrap(), 2);
+        assert_eq!(recv_datagrams(&server).len(), 1);
+
+        // Still batching: the next message waits for the coalesce window.
+        client.send(vec![1], false).unwrap();
+        client.recv().unwrap();
+        assert!(recv_datagrams(&server).is_empty());
+        thread::sleep(Duration::from_millis(100));
+        client.recv().unwrap();
+        assert_eq!(recv_datagrams(&server).len(), 1);
+    }
+
+    #[test]
+    fn test_batched_messages_are_delivered_in_order() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(server.local_addr().unwrap()),
+            ReUDPConfig::default(),
+        )
+        .unwrap();
+        client.begin_batch();
+        for i in 0..10u8 {
+            client.send(vec![i], true).unwrap();
+        }
+        client.send_ordered_channel(1, vec![10], true).unwrap();
+        assert_eq!(client.flush().unwrap(), 11);
+        thread::sleep(Duration::from_millis(50));
+
+        let received: Vec<Vec<u8>> = server.recv_all().unwrap().into_iter().map(|(_, data)| data).collect();
+        let expected: Vec<Vec<u8>> = (0..11u8).map(|i| vec![i]).collect();
+        assert_eq!(received, expected);
+
+        // Every message was acknowledged on its own.
+        thread::sleep(Duration::from_millis(50));
+        client.recv_all().unwrap();
+        assert_eq!(client.disconnect().unwrap(), 0);
+    }
+}
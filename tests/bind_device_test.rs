@@ -0,0 +1,46 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polls `reudp` for a delivered message for up to `timeout`.
+fn recv_within(reudp: &mut ReUDP, timeout: Duration) -> Option<Vec<u8>> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Some((_, data)) = reudp.recv().unwrap() {
+            return Some(data);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpinned_socket_reports_no_device() {
+        let reudp = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert_eq!(reudp.bound_device().unwrap(), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_bind_device_pins_socket_to_interface() {
+        let config = ReUDPConfig::default().bind_device("lo");
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config.clone()).unwrap();
+        assert_eq!(server.bound_device().unwrap().as_deref(), Some("lo"));
+
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap();
+        client.send(b"hello", true).unwrap();
+        assert_eq!(recv_within(&mut server, Duration::from_secs(1)).unwrap(), b"hello");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_bind_to_unknown_device_fails() {
+        let config = ReUDPConfig::default().bind_device("no-such-if0");
+        assert!(ReUDP::with_config("127.0.0.1:0", Mode::Server, config).is_err());
+    }
+}
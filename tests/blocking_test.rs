@@ -0,0 +1,86 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(20))
+        .resend_interval(Duration::from_millis(20))
+        .connect_probe_size(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_recv_waits_for_a_message() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        server.set_blocking(true).unwrap();
+        assert!(server.is_blocking());
+
+        // Heartbeats arrive first; only the message ends the wait.
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            client.send(b"hello", true).unwrap();
+            client
+        });
+        let started = Instant::now();
+        assert_eq!(server.recv().unwrap(), Some((client_addr, b"hello".to_vec())));
+        assert!(started.elapsed() >= Duration::from_millis(90));
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_switching_back_to_non_blocking() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        server.set_blocking(true).unwrap();
+        server.set_blocking(false).unwrap();
+        assert!(!server.is_blocking());
+
+        let started = Instant::now();
+        assert!(server.recv().unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_recv_timeout_in_blocking_mode() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        server.set_blocking(true).unwrap();
+
+        let started = Instant::now();
+        assert!(server.recv_timeout(Duration::from_millis(50)).unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(server.socket().unwrap().read_timeout().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_blocking_client_connects_and_disconnects() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    server.recv().unwrap();
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+        client.set_blocking(true).unwrap();
+        client.connect().unwrap();
+        client.send(b"hello", true).unwrap();
+        assert_eq!(client.disconnect().unwrap(), 0);
+
+        stop.store(true, Ordering::SeqCst);
+        server_thread.join().unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use reudp::{ConfigError, Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use common::deliver_to_server;
+
+/// Returns the `ConfigError` wrapped in `error`.
+fn config_error(error: ReUDPError) -> ConfigError {
+    let ReUDPError::IoError(error) = error else {
+        panic!("not an I/O error: {:?}", error);
+    };
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    error.into_inner().unwrap().downcast::<ConfigError>().map(|e| *e).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raised_buffer_receives_larger_datagrams() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert_eq!(server.buffer_size(), 1200);
+        let config = ReUDPConfig::default()
+            .buffer_size(4000)
+            .max_packet_size(4000)
+            .resend_interval(Duration::from_millis(50));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config).unwrap();
+
+        client.send(vec![7; 3000], true).unwrap();
+        assert!(deliver_to_server(&mut client, &mut server, Duration::from_millis(200)).is_none());
+
+        server.set_buffer_size(4000).unwrap();
+        assert_eq!(server.buffer_size(), 4000);
+        let (_, payload) = deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, vec![7; 3000]);
+    }
+
+    #[test]
+    fn test_invalid_sizes_are_rejected() {
+        let mut reudp = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+
+        let error = reudp.set_buffer_size(5).unwrap_err();
+        assert_eq!(config_error(error), ConfigError::BufferTooSmall { size: 5 });
+        let error = reudp.set_buffer_size(500).unwrap_err();
+        assert_eq!(
+            config_error(error),
+            ConfigError::PacketSizeAboveBuffer {
+                packet_size: 1024,
+                buffer_size: 500,
+            }
+        );
+        assert_eq!(reudp.buffer_size(), 1200);
+
+        reudp.update_config(|config| config.max_packet_size(400)).unwrap();
+        reudp.set_buffer_size(500).unwrap();
+        assert_eq!(reudp.buffer_size(), 500);
+        // The packet size can't outgrow the buffer afterwards either.
+        assert!(reudp.update_config(|config| config.max_packet_size(1024)).is_err());
+    }
+
+    #[test]
+    fn test_server_drops_datagrams_larger_than_the_buffer() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let oversized = Message::new(0, MessageType::Data, vec![7; 2000]);
+        peer.send_to(&oversized.to_bytes(), server.local_addr().unwrap()).unwrap();
+
+        let received = server.recv_timeout(Duration::from_millis(100)).unwrap();
+        assert_eq!(received, None);
+        assert_eq!(server.stats().packets_dropped_truncated, 1);
+    }
+
+    #[test]
+    fn test_client_reports_datagrams_larger_than_the_buffer() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = fake_server.local_addr().unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        let oversized = Message::new(0, MessageType::Data, vec![7; 1190]);
+        fake_server.send_to(&oversized.to_bytes(), client_addr).unwrap();
+        let error = client.recv_timeout(Duration::from_millis(100)).unwrap_err();
+        assert!(matches!(error, ReUDPError::Truncated { addr, buffer: 1200 } if addr == server_addr));
+        assert_eq!(client.stats().packets_dropped_truncated, 1);
+
+        // A datagram filling the buffer exactly is complete.
+        let filling = Message::new(0, MessageType::Data, vec![7; 1189]);
+        fake_server.send_to(&filling.to_bytes(), client_addr).unwrap();
+        let (_, payload) = client.recv_timeout(Duration::from_millis(100)).unwrap().unwrap();
+        assert_eq!(payload, vec![7; 1189]);
+    }
+}
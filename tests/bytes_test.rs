@@ -0,0 +1,42 @@
+#![cfg(feature = "bytes")]
+
+use bytes::Bytes;
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polls `reudp` with `recv_bytes` for up to `timeout`.
+fn recv_bytes_within(reudp: &mut ReUDP, timeout: Duration) -> Option<(SocketAddr, Bytes)> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Some(received) = reudp.recv_bytes().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default())
+                .unwrap();
+        let payload = Bytes::from(vec![7u8; 1000]);
+
+        client.send(payload.clone(), true).unwrap();
+        client.send(payload.slice(..10), true).unwrap();
+
+        let (from, data) = recv_bytes_within(&mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(from, client.local_addr().unwrap());
+        assert_eq!(data, payload);
+        let (_, data) = recv_bytes_within(&mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(data, payload.slice(..10));
+    }
+}
@@ -0,0 +1,176 @@
+use reudp::{LinkPolicy, Message, MessageType, Mode, NetworkEmulator, ReUDP, ReUDPConfig, ReUDPError, Transport};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A UDP transport whose next send fails once `fail` is set.
+struct FlakyTransport {
+    socket: UdpSocket,
+    fail: AtomicBool,
+}
+
+impl Transport for FlakyTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        if self.fail.swap(false, Ordering::SeqCst) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "blocked by firewall"));
+        }
+        self.socket.send_to(buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.socket.recv_from(buf)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+/// Reads datagrams until a channel message arrives, returning its sequence number.
+fn recv_channel_sequence(socket: &UdpSocket) -> u64 {
+    let mut buf = [0; 1024];
+    loop {
+        let len = socket.recv(&mut buf).unwrap();
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        if message.message_type == MessageType::ChannelData {
+            return message.sequence;
+        }
+    }
+}
+
+/// Sends a raw channel message from `socket` to `reudp`, carrying `sequence` as its data.
+fn send_raw(socket: &UdpSocket, reudp: &ReUDP, channel_id: u8, ordered: bool, sequence: u64) {
+    let mut payload = vec![channel_id, ordered as u8];
+    payload.extend_from_slice(&sequence.to_be_bytes());
+    let message = Message::new(sequence, MessageType::ChannelData, payload);
+    socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+}
+
+/// Receives for `wait`, returning each delivered message as its channel and sequence.
+fn recv_for(reudp: &mut ReUDP, wait: Duration) -> Vec<(Option<u8>, u64)> {
+    let deadline = Instant::now() + wait;
+    let mut received = Vec::new();
+    while Instant::now() < deadline {
+        while let Some((_, data)) = reudp.recv().unwrap() {
+            let sequence = u64::from_be_bytes(data[data.len() - 8..].try_into().unwrap());
+            received.push((reudp.last_message_channel(), sequence));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+/// Returns the sequences received on `channel_id`, in delivery order.
+fn on_channel(received: &[(Option<u8>, u64)], channel_id: u8) -> Vec<u64> {
+    received
+        .iter()
+        .filter(|(channel, _)| *channel == Some(channel_id))
+        .map(|(_, sequence)| *sequence)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_ordered_channel_does_not_block_other_channels() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for sequence in [0, 1, 2, 4, 5] {
+            send_raw(&socket, &server, 0, true, sequence);
+        }
+        for sequence in 0..6 {
+            send_raw(&socket, &server, 1, true, sequence);
+        }
+
+        let received = recv_for(&mut server, Duration::from_millis(100));
+        assert_eq!(on_channel(&received, 0), vec![0, 1, 2]);
+        assert_eq!(on_channel(&received, 1), vec![0, 1, 2, 3, 4, 5]);
+
+        send_raw(&socket, &server, 0, true, 3);
+        let received = recv_for(&mut server, Duration::from_millis(100));
+        assert_eq!(received, vec![(Some(0), 3), (Some(0), 4), (Some(0), 5)]);
+    }
+
+    #[test]
+    fn test_unordered_channel_delivers_immediately_and_once() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for sequence in [2, 0, 2, 3, 0, 1] {
+            send_raw(&socket, &server, 7, false, sequence);
+        }
+
+        let received = recv_for(&mut server, Duration::from_millis(100));
+        assert_eq!(on_channel(&received, 7), vec![2, 0, 3, 1]);
+    }
+
+    #[test]
+    fn test_channels_are_reliable_and_ordered_over_lossy_link() {
+        let config = ReUDPConfig::default().resend_interval(Duration::from_millis(50));
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config.clone()).unwrap();
+        let policy = LinkPolicy::default().drop_rate(0.2).reorder_rate(0.2);
+        let emulator = NetworkEmulator::new(server.local_addr().unwrap(), policy.clone(), policy).unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(emulator.addr()), config).unwrap();
+
+        for sequence in 0..20u64 {
+            client.send_ordered_channel(0, sequence.to_be_bytes(), true).unwrap();
+            client.send_unordered_channel(1, sequence.to_be_bytes(), true).unwrap();
+        }
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = Vec::new();
+        while received.len() < 40 && Instant::now() < deadline {
+            client.recv().unwrap();
+            received.extend(recv_for(&mut server, Duration::from_millis(10)));
+        }
+
+        assert_eq!(on_channel(&received, 0), (0..20).collect::<Vec<_>>());
+        let mut unordered = on_channel(&received, 1);
+        unordered.sort();
+        assert_eq!(unordered, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_keeps_its_ordering() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::client(server.local_addr().unwrap()).unwrap();
+        client.send_ordered_channel(0, b"ordered", false).unwrap();
+        assert!(client.send_unordered_channel(0, b"unordered", false).is_err());
+        client.send_unordered_channel(1, b"unordered", false).unwrap();
+        client.send(b"default", false).unwrap();
+    }
+
+    #[test]
+    fn test_refused_channel_sends_take_no_sequence_number() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let result = server.send_ordered_channel(0, b"nobody", true);
+        assert!(matches!(result, Err(ReUDPError::NotConnected)));
+
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let transport = FlakyTransport {
+            socket,
+            fail: AtomicBool::new(true),
+        };
+        let config = ReUDPConfig::default().max_unacked_packets(1);
+        let mode = Mode::Client(peer.local_addr().unwrap());
+        let mut client = ReUDP::with_transport(transport, mode, config).unwrap();
+
+        let result = client.send_ordered_channel(0, b"blocked", true);
+        assert!(matches!(result, Err(ReUDPError::PeerIo { .. })));
+        client.send_ordered_channel(0, b"first", true).unwrap();
+        assert_eq!(recv_channel_sequence(&peer), 0);
+        let result = client.send_ordered_channel(0, b"too many", true);
+        assert!(matches!(result, Err(ReUDPError::QueueFull { capacity: 1 })));
+        client.send_ordered_channel(0, b"second", false).unwrap();
+        assert_eq!(recv_channel_sequence(&peer), 1);
+
+        let _ = client.disconnect();
+        let result = client.send_ordered_channel(0, b"gone", false);
+        assert!(matches!(result, Err(ReUDPError::Closing)));
+    }
+}
@@ -0,0 +1,59 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+/// Sends a raw message of `message_type` from `socket` to `server` and lets the server process it.
+fn send_raw(socket: &UdpSocket, server: &mut ReUDP, message_type: MessageType) {
+    let message = Message::new(0, message_type, vec![]);
+    socket.send_to(&message.to_bytes(), server.local_addr().unwrap()).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    server.recv_all().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_counts() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert_eq!(server.client_count(), 0);
+        assert_eq!(server.max_client_count_ever(), 0);
+        assert_eq!(server.total_client_connections_ever(), 0);
+
+        let clients: Vec<UdpSocket> = (0..3).map(|_| UdpSocket::bind("127.0.0.1:0").unwrap()).collect();
+        for client in &clients {
+            send_raw(client, &mut server, MessageType::Data);
+        }
+        // Known clients aren't counted again.
+        send_raw(&clients[0], &mut server, MessageType::Data);
+        assert_eq!(server.client_count(), 3);
+        assert_eq!(server.max_client_count_ever(), 3);
+        assert_eq!(server.total_client_connections_ever(), 3);
+
+        send_raw(&clients[1], &mut server, MessageType::Disconnect);
+        send_raw(&clients[2], &mut server, MessageType::Disconnect);
+        assert_eq!(server.client_count(), 1);
+        assert_eq!(server.max_client_count_ever(), 3);
+        assert_eq!(server.total_client_connections_ever(), 3);
+
+        // A client coming back counts as a new connection.
+        send_raw(&clients[1], &mut server, MessageType::Data);
+        assert_eq!(server.client_count(), 2);
+        assert_eq!(server.max_client_count_ever(), 3);
+        assert_eq!(server.total_client_connections_ever(), 4);
+        assert_eq!(server.stats().peak_client_count, 3);
+        assert_eq!(server.stats().total_client_connections, 4);
+    }
+
+    #[test]
+    fn test_client_mode_has_no_clients() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default())
+                .unwrap();
+        assert_eq!(client.client_count(), 0);
+        assert_eq!(client.max_client_count_ever(), 0);
+    }
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use reudp::{ClientGroup, Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use common::{raw_client, recv_all, recv_data, reudp_client};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_group_membership() {
+        let addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let mut group = ClientGroup::default();
+        assert!(group.is_empty());
+
+        group.add(addr);
+        group.add(addr);
+        assert!(group.contains(addr));
+        assert!(group.contains("[::ffff:127.0.0.1]:4000".parse().unwrap()));
+        assert_eq!(group.len(), 1);
+
+        group.remove(addr);
+        assert!(!group.contains(addr));
+        assert!(group.is_empty());
+    }
+
+    #[test]
+    fn test_send_reaches_only_members() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let lobby = raw_client(&mut server);
+        let both = raw_client(&mut server);
+        let outsider = raw_client(&mut server);
+        server.group("lobby").add(lobby.local_addr().unwrap());
+        server.group("lobby").add(both.local_addr().unwrap());
+        server.group("chat").add(both.local_addr().unwrap());
+
+        assert_eq!(server.send_to_group_name("lobby", b"match found", false).unwrap(), 2);
+        assert_eq!(recv_data(&lobby).unwrap().payload, b"match found");
+        assert_eq!(recv_data(&both).unwrap().payload, b"match found");
+        assert!(recv_data(&outsider).is_none());
+
+        server.group("lobby").remove(both.local_addr().unwrap());
+        assert_eq!(server.send_to_group_name("lobby", b"starting", false).unwrap(), 1);
+        assert_eq!(server.send_to_group_name("chat", b"hi", false).unwrap(), 1);
+        assert_eq!(recv_data(&lobby).unwrap().payload, b"starting");
+        assert_eq!(recv_data(&both).unwrap().payload, b"hi");
+    }
+
+    #[test]
+    fn test_clients_outside_the_group_keep_receiving_broadcasts() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut member = reudp_client(&mut server);
+        let mut outsider = reudp_client(&mut server);
+        server.group("lobby").add(member.local_addr().unwrap());
+
+        assert_eq!(server.send_to_group_name("lobby", b"match found", true).unwrap(), 1);
+        server.send(b"news 1", true).unwrap();
+        server.send(b"news 2", true).unwrap();
+
+        let received = recv_all(&mut member, Duration::from_millis(200));
+        assert_eq!(received.len(), 3);
+        assert!(received.contains(&b"match found".to_vec()));
+        assert_eq!(recv_all(&mut outsider, Duration::from_millis(200)), [b"news 1".to_vec(), b"news 2".to_vec()]);
+    }
+
+    #[test]
+    fn test_missing_or_empty_group_takes_no_sequence_number() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert_eq!(server.send_to_group_name("nobody", b"hello?", true).unwrap(), 0);
+        assert!(server.group("empty").is_empty());
+        assert_eq!(server.send_to_group_name("empty", b"hello?", true).unwrap(), 0);
+        assert_eq!(server.send_sequence(), 0);
+    }
+
+    #[test]
+    fn test_members_leave_on_disconnect() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let leaving = raw_client(&mut server);
+        let leaving_addr = leaving.local_addr().unwrap();
+        server.group("lobby").add(leaving_addr);
+
+        let disconnect = Message::new(0, MessageType::Disconnect, vec![]);
+        leaving.send_to(&disconnect.to_bytes(), server_addr).unwrap();
+        while server.client_addrs().contains(&leaving_addr) {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!server.group("lobby").contains(leaving_addr));
+    }
+
+    #[test]
+    fn test_addresses_that_are_not_clients_are_dropped() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let stranger = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.group("lobby").add(stranger.local_addr().unwrap());
+
+        assert_eq!(server.send_to_group_name("lobby", b"hello", true).unwrap(), 0);
+        assert!(server.group("lobby").is_empty());
+        assert_eq!(server.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_groups_need_server_mode() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default())
+                .unwrap();
+        assert!(client.send_to_group_name("lobby", b"hello", false).is_err());
+    }
+}
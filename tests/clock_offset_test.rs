@@ -0,0 +1,38 @@
+use reudp::{Mode, ReUDP};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_offset_estimated_from_heartbeats() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_millis(100), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_millis(100), 1024).unwrap();
+
+        assert!(client.clock_offset(server_addr).is_none());
+        assert!(client.to_local_time(server_addr, SystemTime::now()).is_none());
+
+        // The heartbeat thread ticks once per second, so give it a few rounds.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while client.clock_offset(server_addr).is_none() && Instant::now() < deadline {
+            server.recv().unwrap();
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Both peers share the same clock, so the offset should be close to zero.
+        let offset = client.clock_offset(server_addr).expect("no clock offset estimate");
+        assert!(offset.offset() < Duration::from_millis(50));
+        assert!(offset.error_bound() < Duration::from_millis(50));
+
+        let remote_time = SystemTime::now();
+        let local_time = client.to_local_time(server_addr, remote_time).unwrap();
+        let difference = local_time
+            .duration_since(remote_time)
+            .unwrap_or_else(|e| e.duration());
+        assert_eq!(difference, offset.offset());
+    }
+}
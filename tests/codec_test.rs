@@ -0,0 +1,95 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use reudp::{Codec, CodecError, PostcardCodec, ReUDP, ReUDPError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Chat {
+    from: String,
+    text: String,
+}
+
+/// Codec prefixing postcard payloads with a version byte it checks on decode.
+struct VersionedCodec(u8);
+
+impl Codec for VersionedCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = vec![self.0];
+        bytes.extend(PostcardCodec.encode(value)?);
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        match bytes.split_first() {
+            Some((version, rest)) if *version == self.0 => PostcardCodec.decode(rest),
+            _ => Err("unsupported version".into()),
+        }
+    }
+}
+
+/// Polls `reudp` with `recv_typed_with` until something other than `Ok(None)` comes out.
+fn recv_with<C: Codec, T: DeserializeOwned>(reudp: &mut ReUDP, codec: &C) -> Result<T, ReUDPError> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        if let Some((_, value)) = reudp.recv_typed_with(codec)? {
+            return Ok(value);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("nothing received");
+}
+
+fn chat() -> Chat {
+    Chat { from: "alice".to_string(), text: "hi".to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_codec_round_trip() {
+        let (mut client, mut server) = pair();
+        client.send_typed_with(&VersionedCodec(2), &chat(), true).unwrap();
+        assert_eq!(recv_with::<_, Chat>(&mut server, &VersionedCodec(2)).unwrap(), chat());
+    }
+
+    #[test]
+    fn test_mismatched_codecs_fail_at_decode() {
+        let (mut client, mut server) = pair();
+        client.send_typed_with(&VersionedCodec(2), &chat(), true).unwrap();
+        match recv_with::<_, Chat>(&mut server, &VersionedCodec(3)) {
+            Err(ReUDPError::DecodeError { data, reason }) => {
+                assert_eq!(data[0], 2);
+                assert_eq!(reason, "unsupported version");
+            }
+            other => panic!("expected a decode error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_codec_round_trip() {
+        use reudp::JsonCodec;
+
+        let (mut client, mut server) = pair();
+        client.send_typed_with(&JsonCodec, &chat(), true).unwrap();
+        let (_, data) = loop {
+            if let Some(received) = server.recv().unwrap() {
+                break received;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(data, br#"{"from":"alice","text":"hi"}"#);
+
+        client.send_typed_with(&JsonCodec, &chat(), true).unwrap();
+        assert_eq!(recv_with::<_, Chat>(&mut server, &JsonCodec).unwrap(), chat());
+    }
+}
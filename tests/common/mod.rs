@@ -0,0 +1,109 @@
+// Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Creates a connected client and server.
+pub fn pair() -> (ReUDP, ReUDP) {
+    let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+    let client =
+        ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default()).unwrap();
+    (client, server)
+}
+
+/// Creates a ReUDP client of `server` and waits until the server knows it.
+pub fn reudp_client(server: &mut ReUDP) -> ReUDP {
+    let mode = Mode::Client(server.local_addr().unwrap());
+    let mut client = ReUDP::with_config("127.0.0.1:0", mode, ReUDPConfig::default()).unwrap();
+    let heartbeat = Message::new(0, MessageType::Heartbeat, vec![]);
+    client.send_raw(server.local_addr().unwrap(), &heartbeat.to_bytes()).unwrap();
+    while !server.client_addrs().contains(&client.local_addr().unwrap()) {
+        server.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    client
+}
+
+/// Binds a raw client socket and registers it with `server` through a heartbeat.
+pub fn raw_client(server: &mut ReUDP) -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_millis(300))).unwrap();
+    let heartbeat = Message::new(0, MessageType::Heartbeat, vec![]);
+    socket.send_to(&heartbeat.to_bytes(), server.local_addr().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+    while !server.client_addrs().contains(&addr) {
+        server.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    socket
+}
+
+/// Sends raw Data messages with the given sequence numbers from `socket` to `reudp`.
+pub fn send_raw(socket: &UdpSocket, reudp: &ReUDP, sequences: &[u64]) {
+    for &sequence in sequences {
+        let message = Message::new(sequence, MessageType::Data, sequence.to_be_bytes().to_vec());
+        socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+    }
+}
+
+/// Reads datagrams until a group message arrives, skipping heartbeat traffic.
+pub fn recv_data(socket: &UdpSocket) -> Option<Message> {
+    let mut buf = [0; 1024];
+    while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        if message.message_type == MessageType::GroupData {
+            return Some(message);
+        }
+    }
+    None
+}
+
+/// Calls `recv` on `reudp` for `duration`.
+pub fn pump(reudp: &mut ReUDP, duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        reudp.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Polls `reudp` until it delivers a message, for up to `timeout`.
+pub fn recv_within(reudp: &mut ReUDP, timeout: Duration) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Some(received) = reudp.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+/// Collects what `reudp` delivers within `timeout`.
+pub fn recv_all(reudp: &mut ReUDP, timeout: Duration) -> Vec<Vec<u8>> {
+    let deadline = Instant::now() + timeout;
+    let mut received = Vec::new();
+    while Instant::now() < deadline {
+        match reudp.recv().unwrap() {
+            Some((_, data)) => received.push(data),
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+    received
+}
+
+/// Polls both ends until `server` delivers a message, for up to `timeout`.
+pub fn deliver_to_server(client: &mut ReUDP, server: &mut ReUDP, timeout: Duration) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        let _ = client.recv();
+        if let Some(received) = server.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
@@ -0,0 +1,101 @@
+use reudp::{ConfigError, HeartbeatPolicy, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_rejects_invalid_combinations() {
+        assert!(ReUDPConfig::default().build().is_ok());
+        assert!(ReUDPConfig::low_power().build().is_ok());
+
+        assert_eq!(
+            ReUDPConfig::default().buffer_size(0).build().unwrap_err(),
+            ConfigError::BufferTooSmall { size: 0 }
+        );
+        assert_eq!(
+            ReUDPConfig::default().buffer_size(11).build().unwrap_err(),
+            ConfigError::BufferTooSmall { size: 11 }
+        );
+        assert!(ReUDPConfig::default().buffer_size(12).max_packet_size(12).build().is_ok());
+        assert_eq!(
+            ReUDPConfig::default().heartbeat_interval(Duration::ZERO).build().unwrap_err(),
+            ConfigError::ZeroHeartbeatInterval
+        );
+        let policy = HeartbeatPolicy::Adaptive {
+            min: Duration::from_secs(2),
+            max: Duration::from_secs(1),
+            rtt_multiplier: 4.0,
+        };
+        assert_eq!(
+            ReUDPConfig::default().heartbeat_policy(policy).build().unwrap_err(),
+            ConfigError::InvalidHeartbeatRange {
+                min: Duration::from_secs(2),
+                max: Duration::from_secs(1),
+            }
+        );
+        assert_eq!(
+            ReUDPConfig::default()
+                .resend_interval(Duration::from_secs(3))
+                .liveness_timeout(Duration::from_secs(3))
+                .build()
+                .unwrap_err(),
+            ConfigError::ResendNotBelowLivenessTimeout {
+                resend: Duration::from_secs(3),
+                liveness: Duration::from_secs(3),
+            }
+        );
+        assert_eq!(
+            ReUDPConfig::default().max_packet_size(9).build().unwrap_err(),
+            ConfigError::PacketSizeTooSmall { size: 9 }
+        );
+        assert_eq!(
+            ReUDPConfig::default().max_packet_size(1500).build().unwrap_err(),
+            ConfigError::PacketSizeAboveBuffer {
+                packet_size: 1500,
+                buffer_size: 1200,
+            }
+        );
+        assert!(ReUDPConfig::default().buffer_size(1500).max_packet_size(1500).build().is_ok());
+    }
+
+    #[test]
+    fn test_with_config_refuses_invalid_config() {
+        let config = ReUDPConfig::default().buffer_size(0);
+        let error = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), ConfigError::BufferTooSmall { size: 0 }.to_string());
+    }
+
+    #[test]
+    fn test_new_refuses_broken_arguments() {
+        let error = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::ZERO, 1024).err().unwrap();
+        assert_eq!(error.to_string(), ConfigError::ZeroHeartbeatInterval.to_string());
+
+        let error = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 0).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), ConfigError::BufferTooSmall { size: 0 }.to_string());
+    }
+
+    #[test]
+    fn test_new_with_short_heartbeat_interval_is_valid() {
+        let reudp = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_millis(100), 1024);
+        assert!(reudp.is_ok());
+    }
+
+    #[test]
+    fn test_send_refuses_messages_above_max_packet_size() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = ReUDPConfig::default().max_packet_size(111);
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config).unwrap();
+
+        client.send(vec![0; 100], true).unwrap();
+        assert!(matches!(
+            client.send(vec![0; 101], true),
+            Err(ReUDPError::MessageTooLarge { len: 112, max: 111 })
+        ));
+        assert_eq!(client.send_sequence(), 1);
+    }
+}
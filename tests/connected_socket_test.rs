@@ -0,0 +1,113 @@
+#![cfg(any(target_os = "linux", target_os = "macos", target_os = "ios"))]
+
+mod common;
+
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::recv_within;
+
+/// Creates a client of `server_addr` with `config`.
+fn client(server_addr: SocketAddr, config: ReUDPConfig) -> ReUDP {
+    ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap()
+}
+
+/// Calls `recv` on `reudp` until it fails, for up to `timeout`.
+fn recv_error_within(reudp: &mut ReUDP, timeout: Duration) -> Option<ReUDPError> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Err(e) = reudp.recv() {
+            return Some(e);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+fn data(sequence: u64, payload: &[u8]) -> Vec<u8> {
+    Message::new(sequence, MessageType::Data, payload.to_vec()).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_socket_is_connected() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        let connected = client(server_addr, ReUDPConfig::default());
+        assert_eq!(connected.socket().unwrap().peer_addr().unwrap(), server_addr);
+
+        let unconnected = client(server_addr, ReUDPConfig::default().connect_client_socket(false));
+        assert!(unconnected.socket().unwrap().peer_addr().is_err());
+
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert!(server.socket().unwrap().peer_addr().is_err());
+    }
+
+    #[test]
+    fn test_unreachable_server_is_reported() {
+        let closed_addr = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let mut connected = client(closed_addr, ReUDPConfig::default());
+        connected.send(b"anyone there?", false).unwrap();
+        let error = recv_error_within(&mut connected, Duration::from_secs(1)).unwrap();
+        assert!(matches!(error, ReUDPError::IoError(e) if e.kind() == std::io::ErrorKind::ConnectionRefused));
+
+        let mut unconnected = client(closed_addr, ReUDPConfig::default().connect_client_socket(false));
+        unconnected.send(b"anyone there?", false).unwrap();
+        assert!(recv_error_within(&mut unconnected, Duration::from_millis(200)).is_none());
+    }
+
+    #[test]
+    fn test_os_drops_packets_from_other_senders() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let intruder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = client(server.local_addr().unwrap(), ReUDPConfig::default());
+        let client_addr = client.local_addr().unwrap();
+
+        intruder.send_to(&data(0, b"injected"), client_addr).unwrap();
+        assert!(recv_within(&mut client, Duration::from_millis(200)).is_none());
+        // Never seen, so not even counted.
+        assert_eq!(client.stats().packets_dropped_unauthorized, 0);
+
+        server.send_to(&data(0, b"genuine"), client_addr).unwrap();
+        let (addr, payload) = recv_within(&mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(addr, server.local_addr().unwrap());
+        assert_eq!(payload, b"genuine");
+    }
+
+    #[test]
+    fn test_allowing_another_sender_disconnects_the_socket() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let other = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let other_addr = other.local_addr().unwrap();
+        let mut client = client(server.local_addr().unwrap(), ReUDPConfig::default());
+        let client_addr = client.local_addr().unwrap();
+
+        client.add_allowed_sender(other_addr);
+        assert!(client.socket().unwrap().peer_addr().is_err());
+        // Still reachable where it was.
+        assert_eq!(client.local_addr().unwrap(), client_addr);
+
+        other.send_to(&data(0, b"allowed"), client_addr).unwrap();
+        let (addr, payload) = recv_within(&mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(addr, other_addr);
+        assert_eq!(payload, b"allowed");
+
+        client.send(b"still to the server", false).unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mut buf = [0; 1024];
+        loop {
+            let (len, from) = server.recv_from(&mut buf).unwrap();
+            assert_eq!(from, client_addr);
+            if Message::from_bytes(&buf[..len]).unwrap().message_type == MessageType::Data {
+                break;
+            }
+        }
+    }
+}
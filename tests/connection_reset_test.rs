@@ -0,0 +1,66 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig, Transport, UdpTransport};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A transport over UDP whose receives fail with a connection reset, as on
+/// Windows after a datagram hit a closed port, as many times as asked.
+struct ResettingTransport {
+    inner: UdpTransport,
+    resets: Arc<AtomicUsize>,
+}
+
+impl Transport for ResettingTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        self.inner.send_to(buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let pending = self.resets.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if pending.is_ok() {
+            return Err(io::Error::from(io::ErrorKind::ConnectionReset));
+        }
+        self.inner.recv_from(buf)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_resets_do_not_fail_recv() {
+        let resets = Arc::new(AtomicUsize::new(3));
+        let transport = ResettingTransport {
+            inner: UdpTransport::bind("127.0.0.1:0").unwrap(),
+            resets: Arc::clone(&resets),
+        };
+        let mut server = ReUDP::with_transport(transport, Mode::Server, ReUDPConfig::default()).unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let data = Message::new(0, MessageType::Data, b"still here".to_vec());
+        client.send_to(&data.to_bytes(), server.local_addr().unwrap()).unwrap();
+
+        assert_eq!(server.recv().unwrap(), None);
+        let received = server.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(received, Some((client.local_addr().unwrap(), b"still here".to_vec())));
+        assert_eq!(server.stats().connection_resets_ignored, 3);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_sending_to_a_closed_port_is_not_reported() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let closed = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        server.send_raw(closed, b"anyone?").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(server.recv().unwrap(), None);
+        assert_eq!(server.stats().connection_resets_ignored, 0);
+    }
+}
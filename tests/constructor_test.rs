@@ -0,0 +1,50 @@
+mod common;
+
+use reudp::{Mode, ReUDP};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use common::deliver_to_server;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_and_server() {
+        let mut server = ReUDP::server("127.0.0.1:0").unwrap();
+        assert_eq!(*server.mode(), Mode::Server);
+        let server_addr = server.local_addr().unwrap();
+
+        let mut client = ReUDP::client(server_addr).unwrap();
+        assert_eq!(*client.mode(), Mode::Client(server_addr));
+        assert!(client.local_addr().unwrap().is_ipv4());
+
+        client.send(b"hello", true).unwrap();
+        let (_, payload) = deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, b"hello");
+    }
+
+    #[test]
+    fn test_client_resolves_its_server_address() {
+        let server = ReUDP::server("localhost:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        let client = ReUDP::client(format!("127.0.0.1:{}", port)).unwrap();
+        assert_eq!(*client.mode(), Mode::Client(SocketAddr::from(([127, 0, 0, 1], port))));
+    }
+
+    #[test]
+    fn test_client_of_an_ipv6_server() {
+        let Ok(server) = ReUDP::server("[::1]:0") else {
+            return; // No IPv6 on this host.
+        };
+        let client = ReUDP::client(server.local_addr().unwrap()).unwrap();
+        assert!(client.local_addr().unwrap().is_ipv6());
+    }
+
+    #[test]
+    fn test_unresolvable_address() {
+        assert!(ReUDP::client("not an address").is_err());
+        assert!(ReUDP::server("not an address").is_err());
+    }
+}
@@ -0,0 +1,286 @@
+#![cfg(feature = "crypto")]
+
+mod common;
+
+use reudp::{MalformedPolicy, Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError, FIRST_CUSTOM_TYPE};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{pair, raw_client};
+
+const KEY: [u8; 32] = [7; 32];
+
+/// Polls both ends until `server` delivers a message or fails, for up to `timeout`.
+fn recv_on_server(
+    client: &mut ReUDP,
+    server: &mut ReUDP,
+    timeout: Duration,
+) -> Result<Option<(SocketAddr, Message)>, ReUDPError> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        let _ = client.recv();
+        if let Some(received) = server.recv_message()? {
+            return Ok(Some(received));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    Ok(None)
+}
+
+/// Reads datagrams until a message of `message_type` arrives, and checks its
+/// payload carries `data` encrypted, along with a nonce and a tag.
+fn assert_sealed(socket: &UdpSocket, message_type: MessageType, data: &[u8]) {
+    let mut buf = [0; 1024];
+    let message = loop {
+        let len = socket.recv(&mut buf).unwrap();
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        if message.message_type == message_type {
+            break message;
+        }
+    };
+    assert!(message.payload.len() >= 12 + data.len() + 16, "{}", message_type);
+    assert!(!message.payload.windows(data.len()).any(|window| window == data), "{}", message_type);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let (mut client, mut server) = pair();
+        client.set_encryption_key(KEY);
+        server.set_encryption_key(KEY);
+
+        client.send(b"secret", true).unwrap();
+        let (_, message) = recv_on_server(&mut client, &mut server, Duration::from_secs(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.message_type, MessageType::EncryptedData);
+        assert_eq!(message.payload, b"secret");
+
+        server.send(b"reply", true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = client.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received.unwrap().1, b"reply");
+    }
+
+    #[test]
+    fn test_payload_is_encrypted_on_the_wire() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mut client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(server.local_addr().unwrap()),
+            ReUDPConfig::default(),
+        )
+        .unwrap();
+        client.set_encryption_key(KEY);
+
+        client.send(b"attack at dawn", false).unwrap();
+        let mut buf = [0; 1024];
+        let message = loop {
+            let (len, _) = server.recv_from(&mut buf).unwrap();
+            let message = Message::from_bytes(&buf[..len]).unwrap();
+            if message.message_type == MessageType::EncryptedData {
+                break message;
+            }
+        };
+        // 12-byte nonce, then the ciphertext and its 16-byte tag.
+        assert_eq!(message.payload.len(), 12 + b"attack at dawn".len() + 16);
+        assert!(!message.payload.windows(6).any(|window| window == b"attack"));
+    }
+
+    #[test]
+    fn test_wrong_key_fails_and_is_not_acknowledged() {
+        let (mut client, mut server) = pair();
+        client.set_encryption_key(KEY);
+        server.set_encryption_key([8; 32]);
+
+        client.send(b"secret", true).unwrap();
+        let result = recv_on_server(&mut client, &mut server, Duration::from_millis(200));
+        assert!(matches!(result, Ok(None)));
+        assert!(server.stats().messages_dropped_undecryptable >= 1);
+
+        thread::sleep(Duration::from_millis(50));
+        client.recv().unwrap();
+        assert_eq!(client.pending_acks(), 1);
+    }
+
+    #[test]
+    fn test_encrypted_send_at_the_limit() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = ReUDPConfig::default().max_packet_size(200);
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config).unwrap();
+        assert_eq!(client.max_payload_len(), 200 - 11);
+
+        client.set_encryption_key(KEY);
+        let max = client.max_payload_len();
+        assert_eq!(max, 200 - Message::overhead_for(&MessageType::EncryptedData));
+        client.send(vec![0; max], true).unwrap();
+        assert!(client.send(vec![0; max + 1], true).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_frames_interleave_with_sends() {
+        let (mut client, mut server) = pair();
+        client.set_encryption_key(KEY);
+        server.set_encryption_key(KEY);
+        client.set_frame_fn(|| Some(b"frame".to_vec()));
+        client.set_frame_rate(200.0);
+
+        for _ in 0..20 {
+            client.send(b"send", true).unwrap();
+            thread::sleep(Duration::from_millis(2));
+        }
+        client.set_frame_rate(0.0);
+
+        let mut sends = 0;
+        let deadline = Instant::now() + Duration::from_millis(300);
+        while Instant::now() < deadline {
+            if let Some((_, message)) = recv_on_server(&mut client, &mut server, Duration::from_millis(10)).unwrap() {
+                assert_eq!(message.message_type, MessageType::EncryptedData);
+                sends += usize::from(message.payload == b"send");
+            }
+        }
+        assert_eq!(sends, 20);
+    }
+
+    #[test]
+    fn test_every_way_of_sending_is_encrypted() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mode = Mode::Client(peer.local_addr().unwrap());
+        let mut client = ReUDP::with_config("127.0.0.1:0", mode, ReUDPConfig::default()).unwrap();
+        client.set_encryption_key(KEY);
+
+        client.send_timestamped(b"timestamped secret", false).unwrap();
+        assert_sealed(&peer, MessageType::TimestampedData, b"timestamped secret");
+        client.send_with_type(FIRST_CUSTOM_TYPE, b"custom secret", false).unwrap();
+        assert_sealed(&peer, MessageType::Custom(FIRST_CUSTOM_TYPE), b"custom secret");
+        client.send_ordered_channel(3, b"channel secret", false).unwrap();
+        assert_sealed(&peer, MessageType::ChannelData, b"channel secret");
+        #[cfg(feature = "serde")]
+        {
+            client.send_typed(&"typed secret", false).unwrap();
+            assert_sealed(&peer, MessageType::TypedData, b"typed secret");
+        }
+
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        server.set_encryption_key(KEY);
+        let client = raw_client(&mut server);
+        let addr = client.local_addr().unwrap();
+        server.send_to_group([addr], b"group secret", false).unwrap();
+        assert_sealed(&client, MessageType::GroupData, b"group secret");
+        server.subscribe_client(addr, "news").unwrap();
+        server.publish("news", b"topic secret", false).unwrap();
+        assert_sealed(&client, MessageType::GroupData, b"topic secret");
+        server.group("lobby").add(addr);
+        server.send_to_group_name("lobby", b"lobby secret", false).unwrap();
+        assert_sealed(&client, MessageType::GroupData, b"lobby secret");
+    }
+
+    #[test]
+    fn test_every_way_of_sending_round_trips() {
+        let (mut client, mut server) = pair();
+        client.set_encryption_key(KEY);
+        server.set_encryption_key(KEY);
+
+        client.send_timestamped(b"timestamped", true).unwrap();
+        client.send_with_type(FIRST_CUSTOM_TYPE, b"custom", true).unwrap();
+        client.send_ordered_channel(3, b"channel", true).unwrap();
+        let mut expected = vec![b"timestamped".to_vec(), b"custom".to_vec(), b"channel".to_vec()];
+        #[cfg(feature = "serde")]
+        {
+            client.send_typed(&7u8, true).unwrap();
+            expected.push(vec![7]);
+        }
+        let mut received = Vec::new();
+        while let Some((_, message)) = recv_on_server(&mut client, &mut server, Duration::from_millis(200)).unwrap() {
+            received.push(message.payload);
+        }
+        received.sort();
+        expected.sort();
+        assert_eq!(received, expected);
+
+        let addr = client.local_addr().unwrap();
+        server.send_to_group([addr], b"group", true).unwrap();
+        server.subscribe_client(addr, "news").unwrap();
+        server.publish("news", b"topic", true).unwrap();
+        server.group("lobby").add(addr);
+        server.send_to_group_name("lobby", b"lobby", true).unwrap();
+        let mut received = Vec::new();
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            let _ = server.recv();
+            received.extend(client.recv().unwrap().map(|(_, data)| data));
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, [b"group".to_vec(), b"topic".to_vec(), b"lobby".to_vec()]);
+    }
+
+    #[test]
+    fn test_unencrypted_data_is_refused_once_a_key_is_set() {
+        let config = ReUDPConfig::default().on_malformed(MalformedPolicy::Error);
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        server.set_encryption_key(KEY);
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+
+        let forged = [
+            Message::new(0, MessageType::Data, b"forged".to_vec()),
+            Message::new(0, MessageType::ChannelData, b"\x00\x01forged".to_vec()),
+            Message::new(0, MessageType::GroupData, b"forged".to_vec()),
+        ];
+        for message in forged {
+            peer.send_to(&message.to_bytes(), server.local_addr().unwrap()).unwrap();
+            let result = server.recv_timeout(Duration::from_millis(100));
+            assert!(matches!(result, Err(ReUDPError::DecryptionFailed)), "{}", message.message_type);
+        }
+        // None of them was acknowledged, nor made the peer a client.
+        assert!(peer.recv(&mut [0; 1024]).is_err());
+        assert_eq!(server.client_count(), 0);
+    }
+
+    #[test]
+    fn test_missing_key_fails() {
+        let (mut client, mut server) = pair();
+        client.set_encryption_key(KEY);
+
+        client.send(b"secret", true).unwrap();
+        let result = recv_on_server(&mut client, &mut server, Duration::from_millis(200));
+        assert!(matches!(result, Ok(None)));
+        assert!(server.stats().messages_dropped_undecryptable >= 1);
+    }
+
+    #[test]
+    fn test_forged_ciphertext_is_dropped_under_the_default_policy() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        server.set_encryption_key(KEY);
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_millis(300))).unwrap();
+
+        let forged = Message::new(0, MessageType::EncryptedData, vec![0; 12 + 6 + 16]);
+        peer.send_to(&forged.to_bytes(), server.local_addr().unwrap()).unwrap();
+        assert!(matches!(server.recv_timeout(Duration::from_millis(100)), Ok(None)));
+        assert_eq!(server.stats().messages_dropped_undecryptable, 1);
+        assert_eq!(server.malformed_packets(), 1);
+        assert_eq!(server.client_count(), 0);
+
+        // The messages batched after a forged one are still handled.
+        let heartbeat = Message::new(0, MessageType::Heartbeat, vec![]);
+        let payload = [forged.to_bytes(), heartbeat.to_bytes()].concat();
+        let batch = Message::new(0, MessageType::Batch, payload);
+        peer.send_to(&batch.to_bytes(), server.local_addr().unwrap()).unwrap();
+        assert!(matches!(server.recv_timeout(Duration::from_millis(100)), Ok(None)));
+        assert_eq!(server.stats().messages_dropped_undecryptable, 2);
+        let mut buf = [0; 1024];
+        let len = peer.recv(&mut buf).unwrap();
+        assert_eq!(Message::from_bytes(&buf[..len]).unwrap().message_type, MessageType::HeartbeatAck);
+    }
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use reudp::{Message, MessageType, ReUDP, ReUDPError, FIRST_CUSTOM_TYPE};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+/// Polls both ends until `server` delivers a message, for up to `timeout`.
+fn recv_message_within(client: &mut ReUDP, server: &mut ReUDP, timeout: Duration) -> Option<(SocketAddr, Message)> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        let _ = client.recv();
+        if let Some(received) = server.recv_message().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_type_round_trip() {
+        let (mut client, mut server) = pair();
+        client.send_with_type(100, b"codec offer", true).unwrap();
+        client.send(b"plain", true).unwrap();
+
+        let (_, message) = recv_message_within(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(message.message_type, MessageType::Custom(100));
+        assert_eq!(message.payload, b"codec offer");
+        let (_, message) = recv_message_within(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(message.message_type, MessageType::Data);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.pending_acks() > 0 && Instant::now() < deadline {
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(client.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_reserved_types_are_rejected() {
+        let (mut client, _server) = pair();
+        for msg_type in [0, 1, FIRST_CUSTOM_TYPE - 1, 128, 255] {
+            assert!(matches!(client.send_with_type(msg_type, b"x", true), Err(ReUDPError::InvalidMessageType(_))));
+        }
+        assert_eq!(client.send_sequence(), 0);
+        client.send_with_type(FIRST_CUSTOM_TYPE, b"x", true).unwrap();
+        client.send_with_type(127, b"x", true).unwrap();
+    }
+
+    #[test]
+    fn test_custom_type_wire_code() {
+        let bytes = Message::new(3, MessageType::Custom(77), b"x".to_vec()).to_bytes();
+        assert_eq!(bytes[8], 77);
+        assert_eq!(Message::from_bytes(&bytes).unwrap().message_type, MessageType::Custom(77));
+    }
+}
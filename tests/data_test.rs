@@ -1,17 +1,17 @@
 use reudp::{ReUDP, Mode, ReUDPError};
+use std::net::SocketAddr;
 use std::thread;
 use std::time::Duration;
 use std::sync::{Arc, Mutex};
 
-fn run_server(server_addr: &str, received_data: Arc<Mutex<Option<Vec<u8>>>>) -> Result<(), ReUDPError> {
-    let mut reudp = ReUDP::new(server_addr, Mode::Server, Duration::from_secs(1), 1024)?;
+fn run_server(mut reudp: ReUDP, received_data: Arc<Mutex<Option<Vec<u8>>>>) -> Result<(), ReUDPError> {
 
     for _ in 0..10 { // Run for a limited number of iterations
         match reudp.recv() {
             Ok(Some((addr, data))) => {
                 println!("Server received from {}: {:?}", addr, String::from_utf8(data.clone()));
                 *received_data.lock().unwrap() = Some(data.clone());
-                reudp.send(b"Hello from server!".to_vec(), true)?;
+                reudp.send(b"Hello from server!", true)?;
             },
             Ok(None) => (),
             Err(ReUDPError::ConnectionLost) => {
@@ -22,7 +22,7 @@ fn run_server(server_addr: &str, received_data: Arc<Mutex<Option<Vec<u8>>>>) ->
                 println!("Server: No response from server.");
                 return Err(ReUDPError::NoResponseFromServer);
             },
-            Err(e) => return Err(e.into()),
+            Err(e) => return Err(e),
         }
 
         if let Some(ping) = reudp.get_current_ping() {
@@ -34,9 +34,8 @@ fn run_server(server_addr: &str, received_data: Arc<Mutex<Option<Vec<u8>>>>) ->
     Ok(())
 }
 
-fn run_client(client_addr: &str, server_addr: &str, data_to_send: Vec<u8>, received_data: Arc<Mutex<Option<Vec<u8>>>>) -> Result<(), ReUDPError> {
-    let server_addr = server_addr.parse().unwrap();
-    let mut reudp = ReUDP::new(client_addr, Mode::Client(server_addr), Duration::from_secs(1), 1024)?;
+fn run_client(server_addr: SocketAddr, data_to_send: Vec<u8>, received_data: Arc<Mutex<Option<Vec<u8>>>>) -> Result<(), ReUDPError> {
+    let mut reudp = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024)?;
 
     for _ in 0..10 { // Run for a limited number of iterations
         reudp.send(data_to_send.clone(), true)?;
@@ -55,7 +54,7 @@ fn run_client(client_addr: &str, server_addr: &str, data_to_send: Vec<u8>, recei
                 println!("Client: No response from server.");
                 return Err(ReUDPError::NoResponseFromServer);
             },
-            Err(e) => return Err(e.into()),
+            Err(e) => return Err(e),
         }
 
         if let Some(ping) = reudp.get_current_ping() {
@@ -74,8 +73,9 @@ mod tests {
 
     #[test]
     fn test_reudp_communication() {
-        let server_addr = "127.0.0.1:8080";
-        let client_addr = "127.0.0.1:8081";
+        // Bind to a port picked by the OS so parallel test runs don't collide.
+        let server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
         let data_to_send = b"Test message from client".to_vec();
         let server_received_data = Arc::new(Mutex::new(None));
         let client_received_data = Arc::new(Mutex::new(None));
@@ -90,7 +90,7 @@ mod tests {
 
         // Start the server in a separate thread
         let server_thread = thread::spawn(move || {
-            let result = run_server(server_addr, server_received_data_clone);
+            let result = run_server(server, server_received_data_clone);
             server_running_clone.store(false, Ordering::SeqCst);
             result.unwrap();
         });
@@ -98,7 +98,7 @@ mod tests {
         // Start the client in a separate thread
         let data_to_send_clone = data_to_send.clone();
         let client_thread = thread::spawn(move || {
-            let result = run_client(client_addr, server_addr, data_to_send_clone, client_received_data_clone);
+            let result = run_client(server_addr, data_to_send_clone, client_received_data_clone);
             client_running_clone.store(false, Ordering::SeqCst);
             result.unwrap();
         });
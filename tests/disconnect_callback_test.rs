@@ -0,0 +1,121 @@
+use reudp::{DisconnectReason, Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(20))
+        .resend_interval(Duration::from_millis(20))
+        .liveness_timeout(Duration::from_millis(200))
+}
+
+/// Sets an `on_disconnect` callback on `reudp` recording the reasons it is told.
+fn record_disconnects(reudp: &mut ReUDP) -> Arc<Mutex<Vec<DisconnectReason>>> {
+    let reasons = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&reasons);
+    reudp.on_disconnect(move |reason| recorded.lock().unwrap().push(reason));
+    reasons
+}
+
+/// Sets an `on_client_connect` callback on `server` recording the clients it is told about.
+fn record_clients(server: &mut ReUDP) -> Arc<Mutex<Vec<SocketAddr>>> {
+    let clients = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&clients);
+    server.on_client_connect(move |addr| recorded.lock().unwrap().push(addr));
+    clients
+}
+
+/// Waits up to a second, without receiving, for `reudp` to stop.
+fn wait_stopped(reudp: &ReUDP) {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while reudp.is_running() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silent_server_is_reported_without_receiving() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), config()).unwrap();
+        let reasons = record_disconnects(&mut client);
+
+        wait_stopped(&client);
+        assert!(!client.is_running());
+        assert_eq!(*reasons.lock().unwrap(), vec![DisconnectReason::NoResponseFromServer]);
+    }
+
+    #[test]
+    fn test_lost_server_is_reported_once() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config()).unwrap();
+        let reasons = record_disconnects(&mut client);
+
+        client.send(b"hello", true).unwrap();
+        let deadline = Instant::now() + Duration::from_millis(100);
+        while Instant::now() < deadline {
+            server.recv().unwrap();
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(client.is_running());
+        drop(server);
+
+        wait_stopped(&client);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(*reasons.lock().unwrap(), vec![DisconnectReason::ConnectionLost]);
+    }
+
+    #[test]
+    fn test_disconnect_is_not_reported() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), config()).unwrap();
+        let reasons = record_disconnects(&mut client);
+
+        client.disconnect().unwrap();
+        thread::sleep(Duration::from_millis(300));
+        assert!(reasons.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_new_clients_are_reported_once() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let clients = record_clients(&mut server);
+
+        // One client goes through the handshake, the other just sends.
+        let mut connecting = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+        let connecting_addr = connecting.local_addr().unwrap();
+        let handshake = thread::spawn(move || {
+            connecting.connect().unwrap();
+            connecting
+        });
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.client_count() == 0 && Instant::now() < deadline {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        let mut connecting = handshake.join().unwrap();
+
+        let mut sending = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+        let sending_addr = sending.local_addr().unwrap();
+        for _ in 0..3 {
+            sending.send(b"hello", true).unwrap();
+            connecting.send(b"hi", true).unwrap();
+        }
+        let deadline = Instant::now() + Duration::from_millis(100);
+        while Instant::now() < deadline {
+            server.recv().unwrap();
+            connecting.recv().unwrap();
+            sending.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(*clients.lock().unwrap(), vec![connecting_addr, sending_addr]);
+    }
+}
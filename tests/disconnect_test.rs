@@ -0,0 +1,110 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disconnect_delivers_pending_messages() {
+        let server = Arc::new(Mutex::new(ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap()));
+        let server_addr = server.lock().unwrap().local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let server_thread = {
+            let (server, received, stop) = (Arc::clone(&server), Arc::clone(&received), Arc::clone(&stop));
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    if let Some((_, data)) = server.lock().unwrap().recv().unwrap() {
+                        received.lock().unwrap().push(data);
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        client.connect().unwrap();
+        client.send(b"player left", true).unwrap();
+        client.send(b"final stats", true).unwrap();
+
+        assert_eq!(client.disconnect().unwrap(), 0);
+        assert!(!client.is_running());
+        assert!(matches!(client.send(b"too late", true), Err(ReUDPError::Closing)));
+
+        // Give the server a moment to process the disconnect.
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.lock().unwrap().client_addrs().contains(&client_addr) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        stop.store(true, Ordering::SeqCst);
+        server_thread.join().unwrap();
+
+        assert!(!server.lock().unwrap().client_addrs().contains(&client_addr));
+        assert_eq!(*received.lock().unwrap(), vec![b"player left".to_vec(), b"final stats".to_vec()]);
+    }
+
+    #[test]
+    fn test_disconnect_reports_unacked_messages_after_timeout() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        silent.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let config = ReUDPConfig::default().drain_timeout(Duration::from_millis(300));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), config).unwrap();
+
+        client.send(b"one", true).unwrap();
+        client.send(b"two", true).unwrap();
+        client.send(b"unreliable", false).unwrap();
+
+        let started = Instant::now();
+        assert_eq!(client.disconnect().unwrap(), 2);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        // The disconnect is still sent once draining gives up.
+        let mut buf = [0; 1024];
+        loop {
+            let (len, _) = silent.recv_from(&mut buf).unwrap();
+            if Message::from_bytes(&buf[..len]).unwrap().message_type == MessageType::Disconnect {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_messages_received_while_draining_stay_readable() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mut client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(server.local_addr().unwrap()),
+            ReUDPConfig::default(),
+        )
+        .unwrap();
+        let client_addr = client.local_addr().unwrap();
+        client.send(b"bye", true).unwrap();
+
+        // Queued for the drain: a datagram the client fails on, a message and the ack.
+        server.send_to(b"garbage", client_addr).unwrap();
+        let data = Message::new(0, MessageType::Data, b"late reply".to_vec());
+        server.send_to(&data.to_bytes(), client_addr).unwrap();
+        server.send_to(&Message::new(0, MessageType::Ack, vec![]).to_bytes(), client_addr).unwrap();
+
+        assert_eq!(client.disconnect().unwrap(), 0);
+        assert!(!client.is_running());
+        let mut buf = [0; 1024];
+        loop {
+            let (len, _) = server.recv_from(&mut buf).unwrap();
+            if Message::from_bytes(&buf[..len]).unwrap().message_type == MessageType::Disconnect {
+                break;
+            }
+        }
+
+        assert!(matches!(client.recv(), Err(ReUDPError::MalformedPacket { .. })));
+        assert_eq!(client.recv().unwrap().map(|(_, data)| data), Some(b"late reply".to_vec()));
+    }
+}
@@ -0,0 +1,64 @@
+use reudp::{LinkPolicy, Mode, NetworkEmulator, ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .resend_interval(Duration::from_millis(50))
+        .liveness_timeout(Duration::from_secs(10))
+}
+
+/// Polls both ends until `server` has delivered `count` messages, for up to `timeout`.
+fn deliver_all(client: &mut ReUDP, server: &mut ReUDP, count: usize, timeout: Duration) -> Vec<Vec<u8>> {
+    let mut received = Vec::new();
+    let deadline = Instant::now() + timeout;
+    while received.len() < count && Instant::now() < deadline {
+        client.recv().unwrap();
+        while let Some((_, payload)) = server.recv().unwrap() {
+            received.push(payload);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reliable_delivery_over_lossy_link() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let lossy = LinkPolicy::default().drop_rate(0.1);
+        let emulator = NetworkEmulator::new(server_addr, lossy.clone(), lossy).unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(emulator.addr()), config()).unwrap();
+
+        let sent: Vec<Vec<u8>> = (0..50u32).map(|i| i.to_be_bytes().to_vec()).collect();
+        for payload in &sent {
+            client.send(payload.clone(), true).unwrap();
+        }
+        let received = deliver_all(&mut client, &mut server, sent.len(), Duration::from_secs(10));
+        assert_eq!(received, sent);
+    }
+
+    #[test]
+    fn test_reliable_delivery_over_reordering_duplicating_link() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let messy = LinkPolicy::default()
+            .delay(Duration::from_millis(5))
+            .delay_jitter(Duration::from_millis(5))
+            .reorder_rate(0.2)
+            .duplicate_rate(0.2);
+        let emulator = NetworkEmulator::new(server_addr, messy, LinkPolicy::default()).unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(emulator.addr()), config()).unwrap();
+
+        let sent: Vec<Vec<u8>> = (0..50u32).map(|i| i.to_be_bytes().to_vec()).collect();
+        for payload in &sent {
+            client.send(payload.clone(), true).unwrap();
+        }
+        let received = deliver_all(&mut client, &mut server, sent.len(), Duration::from_secs(10));
+        assert_eq!(received, sent);
+    }
+}
@@ -0,0 +1,101 @@
+use reudp::{Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::error::Error;
+use std::io;
+
+/// Sends after disconnecting, returning the error through `io::Result`.
+fn send_after_disconnect() -> io::Result<()> {
+    let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default())?;
+    server.disconnect()?;
+    server.send(b"too late", true)?;
+    Ok(())
+}
+
+/// Fails with a `ReUDPError` through a boxed error.
+fn boxed() -> Result<(), Box<dyn Error + Send + Sync>> {
+    Err(ReUDPError::NoResponseFromServer)?;
+    Ok(())
+}
+
+/// Sends after disconnecting, returning the error through `anyhow`.
+fn send_with_anyhow() -> anyhow::Result<()> {
+    let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default())?;
+    server.disconnect()?;
+    server.send(b"too late", true)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_map_to_io_error_kinds() {
+        let _: io::Result<()> = Err(ReUDPError::ConnectionLost.into());
+        let cases = [
+            (ReUDPError::ConnectionLost, io::ErrorKind::ConnectionReset),
+            (ReUDPError::NoResponseFromServer, io::ErrorKind::TimedOut),
+            (ReUDPError::HandshakeTimeout, io::ErrorKind::TimedOut),
+            (ReUDPError::ConnectionRefused { reason: b"full".to_vec() }, io::ErrorKind::ConnectionRefused),
+            (ReUDPError::Closing, io::ErrorKind::BrokenPipe),
+            (ReUDPError::DecryptionFailed, io::ErrorKind::InvalidData),
+            (ReUDPError::BufferTooSmall { needed: 100 }, io::ErrorKind::InvalidInput),
+            (ReUDPError::WouldBlock, io::ErrorKind::WouldBlock),
+        ];
+        for (error, kind) in cases {
+            assert_eq!(io::Error::from(error).kind(), kind);
+        }
+
+        let refused = io::Error::from(ReUDPError::ConnectionRefused { reason: b"server full".to_vec() });
+        assert_eq!(refused.to_string(), "connection refused: server full");
+    }
+
+    #[test]
+    fn test_io_errors_pass_through() {
+        let original = io::Error::new(io::ErrorKind::AddrInUse, "in use");
+        let error = io::Error::from(ReUDPError::IoError(original));
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(error.to_string(), "in use");
+    }
+
+    #[test]
+    fn test_display_and_source() {
+        let cases = [
+            (ReUDPError::ConnectionLost, "connection lost"),
+            (ReUDPError::ConnectionRefused { reason: vec![] }, "connection refused"),
+            (ReUDPError::ConnectionRefused { reason: b"full".to_vec() }, "connection refused: full"),
+            (ReUDPError::Timeout, "timed out"),
+            (
+                ReUDPError::DecodeError { data: vec![1], reason: "bad tag".into() },
+                "decode error: bad tag",
+            ),
+            (ReUDPError::BufferTooSmall { needed: 100 }, "buffer too small: 100 bytes needed"),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+            assert!(error.source().is_none());
+        }
+
+        let error = ReUDPError::IoError(io::Error::new(io::ErrorKind::AddrInUse, "in use"));
+        assert_eq!(error.to_string(), "I/O error");
+        assert_eq!(error.source().unwrap().to_string(), "in use");
+    }
+
+    #[test]
+    fn test_io_error_keeps_the_original() {
+        let error = io::Error::from(ReUDPError::BufferTooSmall { needed: 100 });
+        let inner = error.into_inner().unwrap().downcast::<ReUDPError>().unwrap();
+        assert!(matches!(*inner, ReUDPError::BufferTooSmall { needed: 100 }));
+    }
+
+    #[test]
+    fn test_question_mark_converts() {
+        let error = send_after_disconnect().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+
+        let error = boxed().unwrap_err();
+        assert_eq!(error.to_string(), "no response from server");
+
+        let error = send_with_anyhow().unwrap_err();
+        assert!(matches!(error.downcast_ref::<ReUDPError>(), Some(ReUDPError::Closing)));
+    }
+}
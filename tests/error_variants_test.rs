@@ -0,0 +1,97 @@
+use reudp::{Message, Mode, ModeKind, ReUDP, ReUDPConfig, ReUDPError, FIRST_CUSTOM_TYPE};
+use std::io;
+use std::net::UdpSocket;
+
+/// Creates a client of a socket that never answers.
+fn client(config: ReUDPConfig) -> (ReUDP, UdpSocket) {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), config).unwrap();
+    (client, silent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_without_clients_is_not_connected() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert!(matches!(server.send(b"anyone?", true), Err(ReUDPError::NotConnected)));
+        assert_eq!(server.send_sequence(), 0);
+        assert_eq!(server.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_message_too_large() {
+        let (mut client, _silent) = client(ReUDPConfig::default().max_packet_size(100));
+        let error = client.send(vec![0; 90], true).unwrap_err();
+        assert!(matches!(error, ReUDPError::MessageTooLarge { len: 101, max: 100 }));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_malformed_packet() {
+        let error = Message::from_bytes(&[0; 5]).unwrap_err();
+        assert!(matches!(error, ReUDPError::MalformedPacket { len: 5, .. }));
+        assert_eq!(error.to_string(), "malformed packet of 5 bytes: 5 bytes is shorter than the 11-byte header");
+    }
+
+    #[test]
+    fn test_queue_full() {
+        let (mut client, _silent) = client(ReUDPConfig::default().max_unacked_packets(2));
+        client.send(b"one", true).unwrap();
+        client.send(b"two", true).unwrap();
+        assert!(matches!(client.send(b"three", true), Err(ReUDPError::QueueFull { capacity: 2 })));
+        assert_eq!(client.send_sequence(), 2);
+
+        // Unreliable messages don't wait for an acknowledgment.
+        client.send(b"unreliable", false).unwrap();
+    }
+
+    #[test]
+    fn test_client_not_found() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let stranger = "127.0.0.1:9".parse().unwrap();
+        let error = server.subscribe_client(stranger, "lobby").unwrap_err();
+        assert!(matches!(error, ReUDPError::ClientNotFound { addr } if addr == stranger));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_wrong_mode() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let error = server.connect().unwrap_err();
+        assert!(matches!(
+            error,
+            ReUDPError::WrongMode { operation: "connect", expected: ModeKind::Client }
+        ));
+        assert_eq!(error.to_string(), "connect is only available in client mode");
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidInput);
+
+        let (mut client, _silent) = client(ReUDPConfig::default());
+        let error = client.publish("lobby", b"data", false).unwrap_err();
+        assert!(matches!(
+            error,
+            ReUDPError::WrongMode { operation: "publish", expected: ModeKind::Server }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_message_type() {
+        let (mut client, _silent) = client(ReUDPConfig::default());
+        let reserved = FIRST_CUSTOM_TYPE - 1;
+        let error = client.send_with_type(reserved, b"data", false).unwrap_err();
+        assert!(matches!(error, ReUDPError::InvalidMessageType(code) if code == reserved));
+        assert!(matches!(client.send_with_type(128, b"data", false), Err(ReUDPError::InvalidMessageType(128))));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_channel_mismatch() {
+        let (mut client, _silent) = client(ReUDPConfig::default());
+        client.send_ordered_channel(3, b"ordered", false).unwrap();
+        let error = client.send_unordered_channel(3, b"unordered", false).unwrap_err();
+        assert!(matches!(error, ReUDPError::ChannelMismatch { channel: 3, ordered: false }));
+        assert_eq!(error.to_string(), "channel 3 is ordered");
+    }
+}
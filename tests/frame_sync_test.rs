@@ -0,0 +1,129 @@
+mod common;
+
+use reudp::ReUDP;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+/// Receives on both ends for `duration`, returning what `server` delivered.
+fn collect_on_server(client: &mut ReUDP, server: &mut ReUDP, duration: Duration) -> Vec<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + duration;
+    let mut received = Vec::new();
+    while Instant::now() < deadline {
+        let _ = client.recv();
+        while let Some(message) = server.recv().unwrap() {
+            received.push(message);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+/// Installs a frame function numbering its frames, returning the counter it uses.
+fn numbered_frames(client: &mut ReUDP) -> Arc<AtomicU64> {
+    let counter = Arc::new(AtomicU64::new(0));
+    let frame_counter = Arc::clone(&counter);
+    client.set_frame_fn(move || Some(frame_counter.fetch_add(1, Ordering::SeqCst).to_be_bytes().to_vec()));
+    counter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_are_sent_at_the_frame_rate() {
+        let (mut client, mut server) = pair();
+        numbered_frames(&mut client);
+        client.set_frame_rate(50.0);
+
+        let received = collect_on_server(&mut client, &mut server, Duration::from_millis(500));
+        // 25 frames are due; leave room for a slow scheduler.
+        assert!((10..=26).contains(&received.len()), "{} frames", received.len());
+        for (i, (_, payload)) in received.iter().enumerate() {
+            assert_eq!(payload, &(i as u64).to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_skipped_frames_take_no_sequence_number() {
+        let (mut client, mut server) = pair();
+        let ticks = Arc::new(AtomicU64::new(0));
+        let frame_ticks = Arc::clone(&ticks);
+        client.set_frame_fn(move || {
+            let tick = frame_ticks.fetch_add(1, Ordering::SeqCst);
+            tick.is_multiple_of(2).then(|| tick.to_be_bytes().to_vec())
+        });
+        client.set_frame_rate(100.0);
+
+        let mut received = collect_on_server(&mut client, &mut server, Duration::from_millis(200));
+        client.set_frame_rate(0.0);
+        // A frame may have been underway when the frames were stopped.
+        received.extend(collect_on_server(&mut client, &mut server, Duration::from_millis(50)));
+        assert!(!received.is_empty());
+        assert!(ticks.load(Ordering::SeqCst) >= 2 * received.len() as u64 - 1);
+        assert_eq!(client.send_sequence(), received.len() as u64);
+    }
+
+    #[test]
+    fn test_frames_interleave_with_sends() {
+        let (mut client, mut server) = pair();
+        numbered_frames(&mut client);
+        client.set_frame_require_ack(true);
+        client.set_frame_rate(200.0);
+
+        for i in 0..20u8 {
+            client.send([i], true).unwrap();
+            thread::sleep(Duration::from_millis(2));
+        }
+        client.set_frame_rate(0.0);
+        let received = collect_on_server(&mut client, &mut server, Duration::from_millis(300));
+
+        let sends: Vec<u8> = received
+            .iter()
+            .filter(|(_, payload)| payload.len() == 1)
+            .map(|(_, payload)| payload[0])
+            .collect();
+        assert_eq!(sends, (0..20).collect::<Vec<u8>>());
+        assert_eq!(received.len() as u64, client.send_sequence());
+        assert_eq!(client.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_unreliable_frames_are_not_kept() {
+        let (mut client, mut server) = pair();
+        numbered_frames(&mut client);
+        client.set_frame_rate(100.0);
+
+        let received = collect_on_server(&mut client, &mut server, Duration::from_millis(100));
+        assert!(!received.is_empty());
+        assert_eq!(client.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_zero_rate_stops_frames() {
+        let (mut client, mut server) = pair();
+        let counter = numbered_frames(&mut client);
+        client.set_frame_rate(100.0);
+        collect_on_server(&mut client, &mut server, Duration::from_millis(100));
+
+        client.set_frame_rate(0.0);
+        collect_on_server(&mut client, &mut server, Duration::from_millis(50));
+        let produced = counter.load(Ordering::SeqCst);
+        assert!(produced > 0);
+        assert!(collect_on_server(&mut client, &mut server, Duration::from_millis(100)).is_empty());
+        assert_eq!(counter.load(Ordering::SeqCst), produced);
+    }
+
+    #[test]
+    fn test_no_frames_without_a_frame_fn() {
+        let (mut client, mut server) = pair();
+        client.set_frame_rate(100.0);
+        assert!(collect_on_server(&mut client, &mut server, Duration::from_millis(100)).is_empty());
+        assert_eq!(client.send_sequence(), 0);
+    }
+}
@@ -0,0 +1,57 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_socket_keeps_socket_options() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_ttl(7).unwrap();
+        let client_addr = socket.local_addr().unwrap();
+        let mut client = ReUDP::from_socket(socket, Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+        assert_eq!(client.socket().unwrap().ttl().unwrap(), 7);
+        assert_eq!(client.local_addr().unwrap(), client_addr);
+
+        // Switched to non-blocking: nothing pending means an immediate `None`.
+        let started = Instant::now();
+        assert!(client.recv().unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        client.send(b"hello", true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, Some((client_addr, b"hello".to_vec())));
+    }
+
+    #[test]
+    fn test_from_socket_can_respect_blocking_mode() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        let config = ReUDPConfig::default().respect_socket_blocking(true);
+        let mut client = ReUDP::from_socket(socket, Mode::Client(server.local_addr().unwrap()), config).unwrap();
+
+        let started = Instant::now();
+        assert!(client.recv().unwrap().is_none());
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_from_socket_validates_config() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = ReUDPConfig::default().buffer_size(0);
+        let error = ReUDP::from_socket(socket, Mode::Server, config).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
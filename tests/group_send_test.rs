@@ -0,0 +1,68 @@
+mod common;
+
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::Duration;
+
+use common::{raw_client, recv_all, recv_data, reudp_client};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_to_group_reaches_only_members_and_retransmits_per_member() {
+        let config = ReUDPConfig::default().resend_interval(Duration::from_millis(50));
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let acking = raw_client(&mut server);
+        let silent = raw_client(&mut server);
+        let outsider = raw_client(&mut server);
+        let members = [acking.local_addr().unwrap(), silent.local_addr().unwrap()];
+
+        let results = server.send_to_group(members, b"room update", true).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(members.iter().all(|addr| matches!(results.get(addr), Some(Ok(())))));
+
+        let message = recv_data(&acking).unwrap();
+        assert_eq!(message.payload, b"room update");
+        let ack = Message::new(message.sequence, MessageType::GroupAck, vec![]);
+        acking.send_to(&ack.to_bytes(), server_addr).unwrap();
+        let message = recv_data(&silent).unwrap();
+        assert_eq!(message.payload, b"room update");
+        // Each member numbers group messages on its own.
+        assert_eq!(message.sequence, 0);
+        assert_eq!(server.send_sequence(), 0);
+        assert!(recv_data(&outsider).is_none());
+
+        // Once the ack is processed, only the member that didn't acknowledge gets
+        // retransmissions.
+        for _ in 0..20 {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        while recv_data(&acking).is_some() {}
+        assert_eq!(recv_data(&silent).unwrap().payload, b"room update");
+        assert!(recv_data(&acking).is_none());
+        assert!(recv_data(&outsider).is_none());
+    }
+
+    #[test]
+    fn test_group_send_does_not_hold_back_other_clients() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut member = reudp_client(&mut server);
+        let mut other = reudp_client(&mut server);
+
+        server.send_to_group([member.local_addr().unwrap()], b"members only", true).unwrap();
+        for update in [b"tick 1", b"tick 2", b"tick 3"] {
+            server.send(update, true).unwrap();
+        }
+        server.send_to_group([member.local_addr().unwrap()], b"members again", true).unwrap();
+
+        let received = recv_all(&mut member, Duration::from_millis(200));
+        assert_eq!(received.len(), 5);
+        let group_messages: Vec<Vec<u8>> = received.into_iter().filter(|data| data.starts_with(b"members")).collect();
+        assert_eq!(group_messages, [b"members only".to_vec(), b"members again".to_vec()]);
+        assert_eq!(recv_all(&mut other, Duration::from_millis(200)), [b"tick 1", b"tick 2", b"tick 3"]);
+    }
+}
@@ -0,0 +1,95 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const THREADS: u32 = 8;
+const MESSAGES_PER_THREAD: u32 = 50;
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .resend_interval(Duration::from_millis(50))
+        .drain_timeout(Duration::from_secs(2))
+}
+
+/// Runs a server until it has received `expected` distinct messages or `timeout` passes.
+fn spawn_server(mut server: ReUDP, expected: usize, timeout: Duration) -> thread::JoinHandle<HashSet<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut received = HashSet::new();
+        let deadline = Instant::now() + timeout;
+        while received.len() < expected && Instant::now() < deadline {
+            if let Some((_, data)) = server.recv_timeout(Duration::from_millis(10)).unwrap() {
+                received.insert(data);
+            }
+        }
+        // Keep acknowledging retransmissions for a while.
+        let deadline = Instant::now() + Duration::from_millis(300);
+        while Instant::now() < deadline {
+            server.recv_timeout(Duration::from_millis(10)).unwrap();
+        }
+        received
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_senders_lose_nothing() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let expected = (THREADS * MESSAGES_PER_THREAD) as usize;
+        let server = spawn_server(server, expected, Duration::from_secs(5));
+
+        let handle = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config())
+            .unwrap()
+            .into_handle();
+        // A dedicated thread drains the socket, processing the acknowledgments.
+        let done = Arc::new(AtomicBool::new(false));
+        let receiver = {
+            let handle = handle.clone();
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    handle.recv_timeout(Duration::from_millis(10)).unwrap();
+                }
+            })
+        };
+        let senders: Vec<_> = (0..THREADS)
+            .map(|thread_id| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    for i in 0..MESSAGES_PER_THREAD {
+                        let data = [thread_id.to_be_bytes(), i.to_be_bytes()].concat();
+                        handle.send(data, true).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        let received = server.join().unwrap();
+        assert_eq!(received.len(), expected);
+        done.store(true, Ordering::Relaxed);
+        receiver.join().unwrap();
+        assert_eq!(handle.disconnect().unwrap(), 0);
+        assert!(handle.try_into_inner().is_ok());
+    }
+
+    #[test]
+    fn test_try_into_inner_needs_the_last_handle() {
+        let handle = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default())
+            .unwrap()
+            .into_handle();
+        let other = handle.clone();
+        let handle = handle.try_into_inner().err().unwrap();
+        drop(other);
+        let reudp = handle.try_into_inner().ok().unwrap();
+        assert!(reudp.local_addr().is_ok());
+    }
+}
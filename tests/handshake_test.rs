@@ -0,0 +1,153 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs a server on its own thread until `stop` is set, returning its address.
+fn spawn_server(config: ReUDPConfig, stop: Arc<AtomicBool>) -> SocketAddr {
+    let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+    addr
+}
+
+fn fast_handshake() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .handshake_retries(2)
+        .handshake_retry_interval(Duration::from_millis(100))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connect_accepted() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_addr = spawn_server(ReUDPConfig::default(), Arc::clone(&stop));
+
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), fast_handshake()).unwrap();
+        assert!(!client.is_connected());
+        client.connect().unwrap();
+        assert!(client.is_connected());
+
+        stop.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_connect_to_non_reudp_port_times_out() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let silent_addr = silent.local_addr().unwrap();
+
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent_addr), fast_handshake()).unwrap();
+        let started = Instant::now();
+        match client.connect() {
+            Err(ReUDPError::HandshakeTimeout) => {}
+            other => panic!("expected HandshakeTimeout, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(!client.is_connected());
+    }
+
+    #[test]
+    fn test_connect_refused_when_server_full() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_addr = spawn_server(ReUDPConfig::default().max_clients(1), Arc::clone(&stop));
+
+        let mut first = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), fast_handshake()).unwrap();
+        first.connect().unwrap();
+
+        let mut second = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), fast_handshake()).unwrap();
+        match second.connect() {
+            Err(ReUDPError::ConnectionRefused { reason }) => assert_eq!(reason, b"server full"),
+            other => panic!("expected ConnectionRefused, got {:?}", other),
+        }
+        assert!(!second.is_connected());
+
+        stop.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_late_accept_is_ignored() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        fake_server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let fake_addr = fake_server.local_addr().unwrap();
+
+        let config = ReUDPConfig::default()
+            .handshake_retries(0)
+            .handshake_retry_interval(Duration::from_millis(100));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(fake_addr), config).unwrap();
+        assert!(matches!(client.connect(), Err(ReUDPError::HandshakeTimeout)));
+
+        // Answer the request only after the client gave up.
+        let mut buf = [0; 1024];
+        let (request, client_addr) = loop {
+            let (len, addr) = fake_server.recv_from(&mut buf).unwrap();
+            let message = Message::from_bytes(&buf[..len]).unwrap();
+            if message.message_type == MessageType::Connect {
+                break (message, addr);
+            }
+        };
+        let accept = Message::new(0, MessageType::Accept, request.payload);
+        fake_server.send_to(&accept.to_bytes(), client_addr).unwrap();
+
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            client.recv().unwrap();
+        }
+        assert!(!client.is_connected());
+
+        // The client tears down the session the late Accept would have created.
+        loop {
+            let (len, _) = fake_server.recv_from(&mut buf).unwrap();
+            if Message::from_bytes(&buf[..len]).unwrap().message_type == MessageType::Disconnect {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_messages_sent_with_the_accept_are_kept() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        fake_server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let fake_addr = fake_server.local_addr().unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(fake_addr), fast_handshake()).unwrap();
+
+        let answer = thread::spawn(move || {
+            let mut buf = [0; 1024];
+            let (request, client_addr) = loop {
+                let (len, addr) = fake_server.recv_from(&mut buf).unwrap();
+                let message = Message::from_bytes(&buf[..len]).unwrap();
+                if message.message_type == MessageType::Connect {
+                    break (message, addr);
+                }
+            };
+            let accept = Message::new(0, MessageType::Accept, request.payload);
+            fake_server.send_to(&accept.to_bytes(), client_addr).unwrap();
+            for (sequence, payload) in [&b"welcome"[..], b"motd"].into_iter().enumerate() {
+                let data = Message::new(sequence as u64, MessageType::Data, payload.to_vec());
+                fake_server.send_to(&data.to_bytes(), client_addr).unwrap();
+            }
+            fake_server
+        });
+        client.connect().unwrap();
+        let _fake_server = answer.join().unwrap();
+
+        let mut received = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while received.len() < 2 && Instant::now() < deadline {
+            if let Some((_, payload)) = client.recv().unwrap() {
+                received.push(payload);
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, vec![b"welcome".to_vec(), b"motd".to_vec()]);
+    }
+}
@@ -0,0 +1,99 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Creates a client of a plain socket standing in for the server.
+fn client_of_fake_server() -> (ReUDP, UdpSocket) {
+    let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    fake_server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    let config = ReUDPConfig::default().liveness_timeout(Duration::from_secs(10));
+    let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(fake_server.local_addr().unwrap()), config).unwrap();
+    (client, fake_server)
+}
+
+/// Calls `recv` on `reudp` for `duration`.
+fn pump_for(reudp: &mut ReUDP, duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        reudp.recv().unwrap();
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_answer_echoes_the_sequence() {
+        let (mut client, fake_server) = client_of_fake_server();
+        let client_addr = client.local_addr().unwrap();
+        let heartbeat = Message::new(42, MessageType::Heartbeat, 0u64.to_be_bytes().to_vec());
+        fake_server.send_to(&heartbeat.to_bytes(), client_addr).unwrap();
+        pump_for(&mut client, Duration::from_millis(50));
+
+        let mut buf = [0; 1024];
+        let answer = loop {
+            let (len, _) = fake_server.recv_from(&mut buf).unwrap();
+            let message = Message::from_bytes(&buf[..len]).unwrap();
+            if message.message_type == MessageType::HeartbeatAck {
+                break message;
+            }
+        };
+        assert_eq!(answer.sequence, 42);
+    }
+
+    #[test]
+    fn test_duplicated_answer_is_ignored() {
+        let (mut client, fake_server) = client_of_fake_server();
+        let mut buf = [0; 1024];
+        let (heartbeat, addr) = loop {
+            let (len, addr) = fake_server.recv_from(&mut buf).unwrap();
+            let message = Message::from_bytes(&buf[..len]).unwrap();
+            if message.message_type == MessageType::Heartbeat {
+                break (message, addr);
+            }
+        };
+        let sent_at = &heartbeat.payload[..8];
+        let answer = Message::new(heartbeat.sequence, MessageType::HeartbeatAck, [sent_at, sent_at, sent_at].concat());
+
+        fake_server.send_to(&answer.to_bytes(), addr).unwrap();
+        pump_for(&mut client, Duration::from_millis(50));
+        let srtt = client.srtt().expect("no RTT sample");
+
+        // The same answer again, much later, as a duplicated datagram would be.
+        thread::sleep(Duration::from_millis(200));
+        fake_server.send_to(&answer.to_bytes(), addr).unwrap();
+        pump_for(&mut client, Duration::from_millis(50));
+        assert_eq!(client.srtt(), Some(srtt));
+    }
+
+    #[test]
+    fn test_keepalives_and_acks_match_their_message_encoding() {
+        let (mut client, fake_server) = client_of_fake_server();
+        let client_addr = client.local_addr().unwrap();
+        let data = Message::new(7, MessageType::Data, b"hi".to_vec());
+        fake_server.send_to(&data.to_bytes(), client_addr).unwrap();
+        let heartbeat = Message::new(9, MessageType::Heartbeat, 0u64.to_be_bytes().to_vec());
+        fake_server.send_to(&heartbeat.to_bytes(), client_addr).unwrap();
+        pump_for(&mut client, Duration::from_millis(50));
+
+        let mut buf = [0; 1024];
+        let (mut ack, mut answer, mut own_heartbeat) = (None, None, None);
+        while ack.is_none() || answer.is_none() || own_heartbeat.is_none() {
+            let (len, _) = fake_server.recv_from(&mut buf).unwrap();
+            let message = Message::from_bytes(&buf[..len]).unwrap();
+            assert_eq!(message.to_bytes(), &buf[..len]);
+            match message.message_type {
+                MessageType::Ack => ack = Some(message),
+                MessageType::HeartbeatAck => answer = Some(message),
+                MessageType::Heartbeat => own_heartbeat = Some(message),
+                _ => {}
+            }
+        }
+        assert_eq!(ack.unwrap(), Message::new(7, MessageType::Ack, vec![]));
+        assert_eq!(answer.unwrap().payload.len(), 24);
+        assert_eq!(own_heartbeat.unwrap().payload.len(), 8);
+    }
+}
@@ -0,0 +1,90 @@
+use reudp::{HeartbeatPolicy, Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Answers the first heartbeat received on `socket` after `delay`, reporting no
+/// processing time so the whole delay counts as round-trip time.
+fn answer_heartbeat_after(socket: &UdpSocket, delay: Duration) {
+    let mut buf = [0; 1024];
+    loop {
+        let (len, addr) = socket.recv_from(&mut buf).unwrap();
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        if message.message_type == MessageType::Heartbeat {
+            thread::sleep(delay);
+            let sent_at = &message.payload[..8];
+            let payload = [sent_at, sent_at, sent_at].concat();
+            let response = Message::new(message.sequence, MessageType::HeartbeatAck, payload);
+            socket.send_to(&response.to_bytes(), addr).unwrap();
+            return;
+        }
+    }
+}
+
+/// Calls `recv` on `reudp` until `condition` holds or `timeout` elapses.
+fn pump_until(reudp: &mut ReUDP, timeout: Duration, condition: impl Fn(&ReUDP) -> bool) {
+    let deadline = Instant::now() + timeout;
+    while !condition(reudp) && Instant::now() < deadline {
+        reudp.recv().unwrap();
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_heartbeat_follows_rtt() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let policy = HeartbeatPolicy::Adaptive {
+            min: Duration::from_millis(100),
+            max: Duration::from_secs(2),
+            rtt_multiplier: 4.0,
+        };
+        let config = ReUDPConfig::default()
+            .heartbeat_policy(policy)
+            .liveness_timeout(Duration::from_secs(10));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(fake_server.local_addr().unwrap()), config).unwrap();
+        assert_eq!(client.heartbeat_interval(), Duration::from_millis(100));
+
+        answer_heartbeat_after(&fake_server, Duration::from_millis(150));
+        pump_until(&mut client, Duration::from_secs(2), |c| c.srtt().is_some());
+
+        let srtt = client.srtt().expect("no RTT sample");
+        assert!(srtt >= Duration::from_millis(150));
+        assert_eq!(client.heartbeat_interval(), srtt.mul_f64(4.0));
+    }
+
+    #[test]
+    fn test_adaptive_heartbeat_is_clamped() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let policy = HeartbeatPolicy::Adaptive {
+            min: Duration::from_millis(50),
+            max: Duration::from_millis(300),
+            rtt_multiplier: 4.0,
+        };
+        let config = ReUDPConfig::default()
+            .heartbeat_policy(policy)
+            .liveness_timeout(Duration::from_secs(10));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(fake_server.local_addr().unwrap()), config).unwrap();
+
+        answer_heartbeat_after(&fake_server, Duration::from_millis(150));
+        pump_until(&mut client, Duration::from_secs(2), |c| c.srtt().is_some());
+        assert_eq!(client.heartbeat_interval(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_fixed_heartbeat_ignores_rtt() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = ReUDPConfig::default()
+            .heartbeat_policy(HeartbeatPolicy::Fixed(Duration::from_millis(100)))
+            .liveness_timeout(Duration::from_secs(10));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(fake_server.local_addr().unwrap()), config).unwrap();
+
+        answer_heartbeat_after(&fake_server, Duration::from_millis(50));
+        pump_until(&mut client, Duration::from_secs(2), |c| c.srtt().is_some());
+        assert!(client.srtt().is_some());
+        assert_eq!(client.heartbeat_interval(), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,108 @@
+use reudp::{Hook, Mode, ReUDP, ReUDPConfig};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Flips every bit of the payload in both directions.
+struct Invert;
+
+impl Hook for Invert {
+    fn on_recv(&self, _addr: SocketAddr, payload: Vec<u8>) -> Option<Vec<u8>> {
+        Some(payload.iter().map(|b| !b).collect())
+    }
+
+    fn on_send(&self, payload: Vec<u8>) -> Option<Vec<u8>> {
+        Some(payload.iter().map(|b| !b).collect())
+    }
+}
+
+/// Appends a tag to outgoing payloads and drops incoming ones without it.
+struct Tag;
+
+impl Hook for Tag {
+    fn on_recv(&self, _addr: SocketAddr, payload: Vec<u8>) -> Option<Vec<u8>> {
+        payload.strip_suffix(b"#tag").map(<[u8]>::to_vec)
+    }
+
+    fn on_send(&self, mut payload: Vec<u8>) -> Option<Vec<u8>> {
+        payload.extend_from_slice(b"#tag");
+        Some(payload)
+    }
+}
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default().resend_interval(Duration::from_millis(20))
+}
+
+fn pair() -> (ReUDP, ReUDP) {
+    let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+    (client, server)
+}
+
+/// Polls both ends for `duration`, returning what `server` delivered.
+fn deliver(client: &mut ReUDP, server: &mut ReUDP, duration: Duration) -> Vec<Vec<u8>> {
+    let mut received = Vec::new();
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        client.recv().unwrap();
+        while let Some((_, payload)) = server.recv().unwrap() {
+            received.push(payload);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    received
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_is_undone_in_reverse_order() {
+        let (mut client, mut server) = pair();
+        for reudp in [&mut client, &mut server] {
+            reudp.add_hook(Tag);
+            reudp.add_hook(Invert);
+        }
+
+        client.send(b"plain", true).unwrap();
+        client.send_ordered_channel(1, b"on a channel", true).unwrap();
+        client.send_timestamped(b"timestamped", true).unwrap();
+        let received = deliver(&mut client, &mut server, Duration::from_millis(100));
+        assert_eq!(received, vec![b"plain".to_vec(), b"on a channel".to_vec(), b"timestamped".to_vec()]);
+    }
+
+    #[test]
+    fn test_recv_hook_drop_is_not_acknowledged() {
+        let (mut client, mut server) = pair();
+        let accept = Arc::new(AtomicBool::new(false));
+        let hook_accept = Arc::clone(&accept);
+        server.set_recv_hook(move |_, payload| hook_accept.load(Ordering::SeqCst).then_some(payload));
+
+        client.send(b"held back", true).unwrap();
+        assert!(deliver(&mut client, &mut server, Duration::from_millis(100)).is_empty());
+        assert_eq!(client.pending_acks(), 1);
+
+        accept.store(true, Ordering::SeqCst);
+        assert_eq!(deliver(&mut client, &mut server, Duration::from_millis(100)), vec![b"held back".to_vec()]);
+        assert_eq!(client.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_send_hook_drop_sends_nothing() {
+        let (mut client, mut server) = pair();
+        client.set_send_hook(|payload| (payload != b"secret").then_some(payload));
+        client.set_send_hook(|payload| Some([b"> ".as_slice(), &payload].concat()));
+
+        client.send(b"secret", true).unwrap();
+        assert_eq!(client.send_sequence(), 0);
+        assert_eq!(client.pending_acks(), 0);
+
+        client.send(b"public", true).unwrap();
+        assert_eq!(deliver(&mut client, &mut server, Duration::from_millis(100)), vec![b"> public".to_vec()]);
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use reudp::{Message, MessageType, Mode, ReUDP};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pump;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sends_update_last_send_time() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::new(
+            "127.0.0.1:0",
+            Mode::Client(peer.local_addr().unwrap()),
+            Duration::from_secs(1),
+            1024,
+        )
+        .unwrap();
+        let created = client.last_send_time();
+        assert_eq!(client.last_recv_time(), created);
+
+        thread::sleep(Duration::from_millis(20));
+        let before = Instant::now();
+        client.send(b"unreliable", false).unwrap();
+        assert!(client.last_send_time() >= before);
+
+        let before = Instant::now();
+        client.send_ordered_channel(1, b"on a channel", true).unwrap();
+        assert!(client.last_send_time() >= before);
+        assert!(client.idle_since() < Duration::from_millis(20));
+        assert_eq!(client.last_recv_time(), created);
+    }
+
+    #[test]
+    fn test_only_data_updates_last_recv_time() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let created = server.last_recv_time();
+
+        let heartbeat = Message::new(0, MessageType::Heartbeat, vec![]);
+        peer.send_to(&heartbeat.to_bytes(), server_addr).unwrap();
+        pump(&mut server, Duration::from_millis(50));
+        assert!(server.last_seen(peer.local_addr().unwrap()).is_some());
+        assert_eq!(server.last_recv_time(), created);
+        assert!(server.idle_since() >= Duration::from_millis(50));
+
+        let before = Instant::now();
+        let data = Message::new(0, MessageType::Data, b"hi".to_vec());
+        peer.send_to(&data.to_bytes(), server_addr).unwrap();
+        pump(&mut server, Duration::from_millis(50));
+        assert!(server.last_recv_time() >= before);
+        // The acknowledgment sent back isn't application data.
+        assert_eq!(server.last_send_time(), created);
+        assert!(server.idle_since() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use reudp::{MalformedPolicy, Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+use common::send_raw;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incoming_yields_pending_messages_then_ends() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert!(server.incoming().next().is_none());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[1, 2, 0, 3]);
+        thread::sleep(Duration::from_millis(50));
+
+        let mut received = Vec::new();
+        for message in server.incoming() {
+            let (addr, data) = message.unwrap();
+            assert_eq!(addr, socket.local_addr().unwrap());
+            received.push(data);
+        }
+        let expected: Vec<Vec<u8>> = (0..4u64).map(|i| i.to_be_bytes().to_vec()).collect();
+        assert_eq!(received, expected);
+        assert!(server.incoming().next().is_none());
+    }
+
+    #[test]
+    fn test_incoming_keeps_messages_when_stopped_early() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[2, 1, 0]);
+        thread::sleep(Duration::from_millis(50));
+
+        let (_, first) = server.incoming().next().unwrap().unwrap();
+        assert_eq!(first, 0u64.to_be_bytes());
+        let rest: Vec<Vec<u8>> = server.incoming().map(|message| message.unwrap().1).collect();
+        assert_eq!(rest, vec![1u64.to_be_bytes().to_vec(), 2u64.to_be_bytes().to_vec()]);
+    }
+
+    #[test]
+    fn test_incoming_yields_messages_received_before_an_error() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(server.local_addr().unwrap()),
+            ReUDPConfig::default(),
+        )
+        .unwrap();
+        client.send(b"hello", true).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        for sequence in 0..2u64 {
+            let data = Message::new(sequence, MessageType::Data, sequence.to_be_bytes().to_vec());
+            server.send_to(&data.to_bytes(), client_addr).unwrap();
+        }
+        // The server lost our session, then sends more data in a new one.
+        let unknown = Message::new(0, MessageType::SessionUnknown, vec![]);
+        server.send_to(&unknown.to_bytes(), client_addr).unwrap();
+        let data = Message::new(0, MessageType::Data, b"after".to_vec());
+        server.send_to(&data.to_bytes(), client_addr).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let mut incoming = client.incoming();
+        assert_eq!(incoming.next().unwrap().unwrap().1, 0u64.to_be_bytes());
+        assert_eq!(incoming.next().unwrap().unwrap().1, 1u64.to_be_bytes());
+        assert!(matches!(incoming.next(), Some(Err(ReUDPError::ConnectionLost))));
+        assert!(incoming.next().is_none());
+
+        let rest: Vec<Vec<u8>> = client.incoming().map(|message| message.unwrap().1).collect();
+        assert_eq!(rest, vec![b"after".to_vec()]);
+    }
+
+    #[test]
+    fn test_messages_reads_on_past_errors_about_one_datagram() {
+        let config = ReUDPConfig::default().on_malformed(MalformedPolicy::Error);
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        assert!(server.messages().next().is_none());
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        let data = Message::new(0, MessageType::Data, b"before".to_vec());
+        socket.send_to(&data.to_bytes(), server_addr).unwrap();
+        socket.send_to(b"garbage", server_addr).unwrap();
+        let data = Message::new(1, MessageType::Data, b"after".to_vec());
+        socket.send_to(&data.to_bytes(), server_addr).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let mut messages = server.messages();
+        assert_eq!(messages.next().unwrap().unwrap().1, b"before");
+        assert!(matches!(messages.next(), Some(Err(ReUDPError::MalformedPacket { len: 7, .. }))));
+        assert_eq!(messages.next().unwrap().unwrap().1, b"after");
+        assert!(messages.next().is_none());
+        assert!(messages.next().is_none());
+    }
+}
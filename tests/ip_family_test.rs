@@ -0,0 +1,77 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sends a raw Data message with `sequence` from a fresh socket bound to `from` to `to`.
+fn send_from(from: &str, to: SocketAddr, sequence: u64) {
+    let socket = UdpSocket::bind(from).unwrap();
+    let message = Message::new(sequence, MessageType::Data, b"ping".to_vec());
+    // Sending to a family the socket can't reach may fail outright, which is fine.
+    let _ = socket.send_to(&message.to_bytes(), to);
+}
+
+/// Polls `reudp` for a delivered message for up to `timeout`.
+fn received_within(reudp: &mut ReUDP, timeout: Duration) -> Option<SocketAddr> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Some((addr, _)) = reudp.recv().unwrap() {
+            return Some(addr);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+fn server(local_addr: &str, config: ReUDPConfig) -> (ReUDP, u16) {
+    let reudp = ReUDP::with_config(local_addr, Mode::Server, config).unwrap();
+    let port = reudp.local_addr().unwrap().port();
+    (reudp, port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipv4_only_rejects_ipv6_address_and_traffic() {
+        assert!(ReUDP::with_config("[::1]:0", Mode::Server, ReUDPConfig::default().ipv4_only()).is_err());
+
+        let (mut reudp, port) = server("0.0.0.0:0", ReUDPConfig::default().ipv4_only());
+        send_from("[::1]:0", SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], port)), 0);
+        assert!(received_within(&mut reudp, Duration::from_millis(200)).is_none());
+
+        send_from("127.0.0.1:0", SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        assert!(received_within(&mut reudp, Duration::from_secs(1)).unwrap().is_ipv4());
+    }
+
+    #[test]
+    fn test_ipv6_only_rejects_ipv4_traffic() {
+        assert!(ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default().ipv6_only()).is_err());
+
+        let (mut reudp, port) = server("[::]:0", ReUDPConfig::default().ipv6_only());
+        send_from("127.0.0.1:0", SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        assert!(received_within(&mut reudp, Duration::from_millis(200)).is_none());
+
+        send_from("[::1]:0", SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], port)), 0);
+        assert!(received_within(&mut reudp, Duration::from_secs(1)).unwrap().is_ipv6());
+    }
+
+    #[test]
+    fn test_dual_stack_accepts_both_families() {
+        let (mut reudp, port) = server("[::]:0", ReUDPConfig::default().dual_stack());
+        assert!(reudp.local_addr().unwrap().is_ipv6());
+
+        send_from("127.0.0.1:0", SocketAddr::from(([127, 0, 0, 1], port)), 0);
+        assert!(received_within(&mut reudp, Duration::from_secs(1)).is_some());
+
+        send_from("[::1]:0", SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], port)), 1);
+        assert!(received_within(&mut reudp, Duration::from_secs(1)).is_some());
+    }
+
+    #[test]
+    fn test_dual_stack_accepts_unspecified_ipv4_address() {
+        let reudp = ReUDP::with_config("0.0.0.0:0", Mode::Server, ReUDPConfig::default().dual_stack()).unwrap();
+        assert!(reudp.local_addr().unwrap().is_ipv6());
+    }
+}
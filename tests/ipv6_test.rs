@@ -0,0 +1,71 @@
+mod common;
+
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use common::recv_within;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exchange_over_ipv6_loopback() {
+        let mut server = ReUDP::with_config("[::1]:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        assert!(server_addr.is_ipv6());
+        let mut client = ReUDP::with_config("[::1]:0", Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        client.send(b"hello", true).unwrap();
+        let (from, data) = recv_within(&mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(from, client_addr);
+        assert_eq!(data, b"hello");
+
+        server.send(b"world", true).unwrap();
+        let (from, data) = recv_within(&mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(from, server_addr);
+        assert_eq!(data, b"world");
+        assert!(server.last_seen(client_addr).is_some());
+    }
+
+    #[test]
+    fn test_dual_stack_server_reports_plain_ipv4_peers() {
+        let mut server = ReUDP::with_config("[::]:0", Mode::Server, ReUDPConfig::default().dual_stack()).unwrap();
+        let port = server.local_addr().unwrap().port();
+        let server_addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        client.send(b"hello", true).unwrap();
+        let (from, data) = recv_within(&mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(from, client_addr);
+        assert!(from.is_ipv4());
+        assert_eq!(data, b"hello");
+        assert!(server.last_seen(client_addr).is_some());
+
+        // Replies go out to the plain IPv4 address through the IPv6 socket.
+        server.send(b"world", true).unwrap();
+        let (from, data) = recv_within(&mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(from, server_addr);
+        assert_eq!(data, b"world");
+    }
+
+    #[test]
+    fn test_dual_stack_client_accepts_ipv4_mapped_server_address() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let port = server.local_addr().unwrap().port();
+        let mapped: SocketAddr = format!("[::ffff:127.0.0.1]:{port}").parse().unwrap();
+        let mut client = ReUDP::with_config("[::]:0", Mode::Client(mapped), ReUDPConfig::default().dual_stack()).unwrap();
+
+        client.send(b"hello", true).unwrap();
+        let (_, data) = recv_within(&mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(data, b"hello");
+
+        server.send(b"world", true).unwrap();
+        let (from, data) = recv_within(&mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(from, SocketAddr::from(([127, 0, 0, 1], port)));
+        assert_eq!(data, b"world");
+    }
+}
@@ -0,0 +1,52 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+/// Sends a raw message of `message_type` from `socket` to `reudp`.
+fn send_raw(socket: &UdpSocket, reudp: &ReUDP, message_type: MessageType) {
+    let message = Message::new(0, message_type, b"data".to_vec());
+    socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+}
+
+/// Processes whatever the socket holds after a short wait.
+fn drain(reudp: &mut ReUDP) {
+    thread::sleep(Duration::from_millis(20));
+    while reudp.recv().unwrap().is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_the_last_sender() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert_eq!(server.last_recv_addr(), None);
+
+        send_raw(&first, &server, MessageType::Data);
+        drain(&mut server);
+        assert_eq!(server.last_recv_addr(), Some(first.local_addr().unwrap()));
+
+        // Control messages count as well.
+        send_raw(&second, &server, MessageType::Heartbeat);
+        drain(&mut server);
+        assert_eq!(server.last_recv_addr(), Some(second.local_addr().unwrap()));
+    }
+
+    #[test]
+    fn test_ignores_unauthorized_senders() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let intruder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default()).unwrap();
+
+        send_raw(&server, &client, MessageType::Data);
+        drain(&mut client);
+        send_raw(&intruder, &client, MessageType::Data);
+        drain(&mut client);
+        assert_eq!(client.last_recv_addr(), Some(server.local_addr().unwrap()));
+    }
+}
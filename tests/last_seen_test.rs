@@ -0,0 +1,66 @@
+use reudp::{Message, MessageType, Mode, ReUDP};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Calls `recv` on `reudp` until it has heard from `addr`, for up to `timeout`.
+fn wait_until_seen(reudp: &mut ReUDP, addr: SocketAddr, timeout: Duration) -> Option<Instant> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        reudp.recv().unwrap();
+        if let Some(last_seen) = reudp.last_seen(addr) {
+            return Some(last_seen);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_tracks_last_packet_from_each_client() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client_addr = client.local_addr().unwrap();
+        assert!(server.last_seen(client_addr).is_none());
+        assert!(server.idle_time(client_addr).is_none());
+
+        // Any packet counts, not only heartbeats.
+        let before = Instant::now();
+        let data = Message::new(0, MessageType::Data, b"hi".to_vec());
+        client.send_to(&data.to_bytes(), server_addr).unwrap();
+        let first_seen = wait_until_seen(&mut server, client_addr, Duration::from_secs(1)).unwrap();
+        assert!(first_seen >= before);
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(server.idle_time(client_addr).unwrap() >= Duration::from_millis(50));
+
+        let ack = Message::new(0, MessageType::Ack, vec![]);
+        client.send_to(&ack.to_bytes(), server_addr).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.last_seen(client_addr) == Some(first_seen) && Instant::now() < deadline {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(server.last_seen(client_addr).unwrap() > first_seen);
+        assert!(server.idle_time(client_addr).unwrap() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_client_tracks_last_packet_from_server() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        assert!(client.last_seen(server_addr).is_none());
+
+        let data = Message::new(0, MessageType::Data, b"welcome".to_vec());
+        server.send_to(&data.to_bytes(), client_addr).unwrap();
+        assert!(wait_until_seen(&mut client, server_addr, Duration::from_secs(1)).is_some());
+        assert!(client.idle_time(server_addr).unwrap() < Duration::from_secs(1));
+    }
+}
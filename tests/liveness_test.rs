@@ -0,0 +1,63 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(200))
+        .liveness_timeout(Duration::from_millis(1500))
+}
+
+/// Keeps calling `recv` on every instance for `duration`.
+fn pump(instances: &mut [&mut ReUDP], duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        for reudp in instances.iter_mut() {
+            reudp.recv().unwrap();
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_evicts_only_the_silent_client() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        // A peer that sends a single message and then goes silent.
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let silent_addr = silent.local_addr().unwrap();
+        silent
+            .send_to(&Message::new(0, MessageType::Data, b"hi".to_vec()).to_bytes(), server_addr)
+            .unwrap();
+
+        let mut chatty = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+        let chatty_addr = chatty.local_addr().unwrap();
+        chatty.send(b"hello", true).unwrap();
+
+        pump(&mut [&mut server, &mut chatty], Duration::from_millis(300));
+        assert!(server.client_addrs().contains(&silent_addr));
+        assert!(server.client_addrs().contains(&chatty_addr));
+
+        pump(&mut [&mut server, &mut chatty], Duration::from_millis(3000));
+        let clients = server.client_addrs();
+        assert!(!clients.contains(&silent_addr), "silent client wasn't evicted");
+        assert!(clients.contains(&chatty_addr), "chatty client was evicted");
+        assert!(server.is_running());
+        assert!(chatty.is_running());
+    }
+
+    #[test]
+    fn test_client_stops_when_server_is_silent() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), config()).unwrap();
+
+        pump(&mut [&mut client], Duration::from_millis(2500));
+        assert!(!client.is_running());
+    }
+}
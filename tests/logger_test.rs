@@ -0,0 +1,63 @@
+use reudp::{LogLevel, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Messages passed to a logger.
+type Logged = Arc<Mutex<Vec<(LogLevel, String)>>>;
+
+/// Sets a logger on `reudp` recording what it is told.
+fn record_logs(reudp: &mut ReUDP) -> Logged {
+    let logged = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&logged);
+    reudp.set_logger(move |level, message| recorded.lock().unwrap().push((level, message.to_string())));
+    logged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_message_type_is_logged() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let logged = record_logs(&mut server);
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        // Type 50 is neither a known type nor a custom one.
+        let mut datagram = vec![0; 11];
+        datagram[8] = 50;
+        socket.send_to(&datagram, server.local_addr().unwrap()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        server.recv_all().unwrap();
+
+        let logged = logged.lock().unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].0, LogLevel::Debug);
+        assert!(logged[0].1.contains("unknown message type 50"));
+    }
+
+    #[test]
+    fn test_heartbeat_thread_logs_to_its_instance() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = ReUDPConfig::default()
+            .heartbeat_interval(Duration::from_millis(20))
+            .resend_interval(Duration::from_millis(20))
+            .liveness_timeout(Duration::from_millis(100));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), config.clone()).unwrap();
+        let logged = record_logs(&mut client);
+        // A second instance with no logger stays silent.
+        let _quiet = ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), config).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.is_running() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!client.is_running());
+        assert_eq!(
+            *logged.lock().unwrap(),
+            vec![(LogLevel::Warn, "No response from server".to_string())]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::time::Duration;
+
+use common::{pump, recv_within};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announce_sleep_suppresses_server_traffic() {
+        let config = ReUDPConfig::low_power().resend_interval(Duration::from_millis(500));
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config.clone()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        client.send(b"hello", false).unwrap();
+        let (addr, _) = recv_within(&mut server, Duration::from_secs(1)).expect("server received nothing");
+        assert_eq!(addr, client_addr);
+
+        client.announce_sleep(Duration::from_secs(30)).unwrap();
+        pump(&mut server, Duration::from_millis(100));
+        assert!(server.is_sleeping(client_addr));
+
+        // Neither the message nor its retransmissions reach the sleeping client.
+        server.send(b"while asleep", true).unwrap();
+        assert!(recv_within(&mut client, Duration::from_millis(1500)).is_none());
+
+        // Sending again wakes the client up without a reconnect.
+        client.send(b"awake", false).unwrap();
+        let (_, data) = recv_within(&mut server, Duration::from_secs(1)).expect("server received nothing");
+        assert_eq!(data, b"awake");
+        assert!(!server.is_sleeping(client_addr));
+
+        // The held-back reliable message is retransmitted once the client is awake.
+        let (_, data) = recv_within(&mut client, Duration::from_secs(3)).expect("client received nothing");
+        assert_eq!(data, b"while asleep");
+    }
+
+    #[test]
+    fn test_lazy_ack_flushing_batches_acks() {
+        let config = ReUDPConfig::default().ack_flush_interval(Some(Duration::from_millis(200)));
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+
+        client.send(b"one", true).unwrap();
+        client.send(b"two", true).unwrap();
+        assert!(recv_within(&mut server, Duration::from_secs(1)).is_some());
+        assert!(recv_within(&mut server, Duration::from_secs(1)).is_some());
+
+        // Acks are held back until the flush interval elapses...
+        pump(&mut client, Duration::from_millis(50));
+        assert_eq!(client.pending_acks(), 2);
+
+        // ...and then both are acknowledged together.
+        pump(&mut server, Duration::from_millis(250));
+        pump(&mut client, Duration::from_millis(50));
+        assert_eq!(client.pending_acks(), 0);
+    }
+}
@@ -0,0 +1,92 @@
+use reudp::{LogLevel, MalformedPolicy, Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Creates a server, or a client of a raw socket, along with that socket to
+/// send it garbage from.
+fn instance(config: ReUDPConfig, server: bool) -> (ReUDP, UdpSocket) {
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mode = match server {
+        true => Mode::Server,
+        false => Mode::Client(peer.local_addr().unwrap()),
+    };
+    (ReUDP::with_config("127.0.0.1:0", mode, config).unwrap(), peer)
+}
+
+fn send(peer: &UdpSocket, reudp: &ReUDP, bytes: &[u8]) -> SocketAddr {
+    peer.send_to(bytes, reudp.local_addr().unwrap()).unwrap();
+    peer.local_addr().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_servers_drop_and_clients_fail_by_default() {
+        let (mut server, peer) = instance(ReUDPConfig::default(), true);
+        send(&peer, &server, b"garbage");
+        assert_eq!(server.recv_timeout(Duration::from_millis(50)).unwrap(), None);
+        assert_eq!(server.malformed_packets(), 1);
+
+        let (mut client, peer) = instance(ReUDPConfig::default(), false);
+        send(&peer, &client, b"garbage");
+        let error = client.recv_timeout(Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(error, ReUDPError::MalformedPacket { len: 7, .. }));
+        assert_eq!(client.malformed_packets(), 1);
+        assert_eq!(client.stats().packets_dropped_malformed, 1);
+    }
+
+    #[test]
+    fn test_drop_and_log() {
+        let config = ReUDPConfig::default().on_malformed(MalformedPolicy::DropAndLog);
+        let (mut client, peer) = instance(config, false);
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&logged);
+        client.set_logger(move |level, message| sink.lock().unwrap().push((level, message.to_string())));
+
+        let from = send(&peer, &client, b"garbage");
+        assert_eq!(client.recv_timeout(Duration::from_millis(50)).unwrap(), None);
+        let logged = logged.lock().unwrap();
+        let expected = format!(
+            "Dropped a datagram from {}: malformed packet of 7 bytes: 7 bytes is shorter than the 11-byte header",
+            from
+        );
+        assert!(logged.contains(&(LogLevel::Warn, expected)));
+    }
+
+    #[test]
+    fn test_error_policy_covers_batches_and_truncated_datagrams() {
+        let config = ReUDPConfig::default().on_malformed(MalformedPolicy::Error);
+        let (mut server, peer) = instance(config, true);
+
+        let mut payload = Message::new(0, MessageType::Data, b"kept".to_vec()).to_bytes();
+        payload.extend_from_slice(&[0; 3]);
+        send(&peer, &server, &Message::new(0, MessageType::Batch, payload).to_bytes());
+        let error = server.recv_timeout(Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(error, ReUDPError::MalformedPacket { len: 3, .. }));
+        // The messages before the malformed one still count.
+        let received = server.recv_timeout(Duration::from_millis(50)).unwrap();
+        assert_eq!(received.map(|(_, payload)| payload), Some(b"kept".to_vec()));
+
+        let from = send(&peer, &server, &[0; 2000]);
+        let error = server.recv_timeout(Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(error, ReUDPError::Truncated { addr, buffer: 1200 } if addr == from));
+        assert_eq!(server.malformed_packets(), 2);
+    }
+
+    #[test]
+    fn test_raw_handler_takes_non_reudp_datagrams() {
+        let config = ReUDPConfig::default().on_malformed(MalformedPolicy::Error);
+        let (mut server, peer) = instance(config, true);
+        let raw = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&raw);
+        server.set_raw_handler(move |_, bytes| sink.lock().unwrap().push(bytes.to_vec()));
+
+        send(&peer, &server, b"garbage");
+        assert_eq!(server.recv_timeout(Duration::from_millis(50)).unwrap(), None);
+        assert_eq!(*raw.lock().unwrap(), vec![b"garbage".to_vec()]);
+        assert_eq!(server.malformed_packets(), 0);
+    }
+}
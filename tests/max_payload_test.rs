@@ -0,0 +1,95 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::UdpSocket;
+
+/// Size of the message header.
+const HEADER_SIZE: usize = 11;
+
+/// Creates a client sending to a plain socket, with a maximum packet size of `max_packet_size`.
+fn client(max_packet_size: usize) -> (ReUDP, UdpSocket) {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let config = ReUDPConfig::default().max_packet_size(max_packet_size);
+    let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config).unwrap();
+    (client, server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overhead_for() {
+        assert_eq!(Message::overhead_for(&MessageType::Data), HEADER_SIZE);
+        assert_eq!(Message::overhead_for(&MessageType::TimestampedData), HEADER_SIZE + 8);
+        assert_eq!(Message::overhead_for(&MessageType::ChannelData), HEADER_SIZE + 2);
+        assert_eq!(Message::overhead_for(&MessageType::EncryptedData), HEADER_SIZE + 28);
+    }
+
+    #[test]
+    fn test_send_at_the_limit() {
+        let (mut client, _server) = client(200);
+        let max = client.max_payload_len();
+        assert_eq!(max, 200 - HEADER_SIZE);
+
+        client.send(vec![0; max], true).unwrap();
+        assert!(matches!(
+            client.send(vec![0; max + 1], true),
+            Err(ReUDPError::MessageTooLarge { max: 200, .. })
+        ));
+        assert_eq!(client.send_sequence(), 1);
+    }
+
+    #[test]
+    fn test_other_sends_at_their_limit() {
+        let (mut client, _server) = client(200);
+
+        let max = 200 - Message::overhead_for(&MessageType::TimestampedData);
+        client.send_timestamped(vec![0; max], true).unwrap();
+        assert!(client.send_timestamped(vec![0; max + 1], true).is_err());
+
+        let max = 200 - Message::overhead_for(&MessageType::ChannelData);
+        client.send_ordered_channel(1, vec![0; max], true).unwrap();
+        assert!(client.send_ordered_channel(1, vec![0; max + 1], true).is_err());
+    }
+
+    #[test]
+    fn test_max_payload_len_follows_config_updates() {
+        let (mut client, _server) = client(200);
+        client.update_config(|config| config.max_packet_size(100)).unwrap();
+        let max = client.max_payload_len();
+        assert_eq!(max, 100 - HEADER_SIZE);
+
+        client.send(vec![0; max], true).unwrap();
+        assert!(client.send(vec![0; max + 1], true).is_err());
+    }
+
+    #[test]
+    fn test_max_payload_len_is_capped_by_the_header() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = ReUDPConfig::default().buffer_size(100_000).max_packet_size(100_000);
+        let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config).unwrap();
+        assert_eq!(client.max_payload_len(), u16::MAX as usize);
+    }
+
+    #[test]
+    fn test_split_payload_pieces_can_be_sent() {
+        let (mut client, _server) = client(100);
+        let max = client.max_payload_len();
+        let data: Vec<u8> = (0..=255).collect();
+
+        let pieces: Vec<&[u8]> = client.split_payload(&data).collect();
+        assert_eq!(pieces.len(), data.len().div_ceil(max));
+        assert!(pieces[..pieces.len() - 1].iter().all(|piece| piece.len() == max));
+        assert_eq!(pieces.concat(), data);
+
+        for piece in pieces {
+            client.send(piece, true).unwrap();
+        }
+        assert_eq!(client.send_sequence(), data.len().div_ceil(max) as u64);
+    }
+
+    #[test]
+    fn test_split_empty_payload() {
+        let (client, _server) = client(100);
+        assert_eq!(client.split_payload(&[]).count(), 0);
+    }
+}
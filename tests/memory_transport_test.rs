@@ -0,0 +1,148 @@
+#![cfg(feature = "test-util")]
+
+use reudp::{LinkPolicy, MemoryNetwork, MemoryTransport, Mode, ReUDP, ReUDPConfig, ReUDPError, Transport};
+use std::io;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(20))
+        .resend_interval(Duration::from_millis(20))
+        .handshake_retries(2)
+        .handshake_retry_interval(Duration::from_millis(50))
+}
+
+/// Creates a server and a client over the two ends of `transports`.
+fn instances((server, client): (MemoryTransport, MemoryTransport)) -> (ReUDP, ReUDP) {
+    let server_addr = server.local_addr().unwrap();
+    let server = ReUDP::with_transport(server, Mode::Server, config()).unwrap();
+    let client = ReUDP::with_transport(client, Mode::Client(server_addr), config()).unwrap();
+    (server, client)
+}
+
+/// Connects `client` while `server` keeps receiving.
+fn connect(mut client: ReUDP, server: &mut ReUDP) -> (ReUDP, Result<(), ReUDPError>) {
+    let handshake = thread::spawn(move || {
+        let result = client.connect();
+        (client, result)
+    });
+    while !handshake.is_finished() {
+        server.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    handshake.join().unwrap()
+}
+
+/// Polls both ends until `target` has delivered `count` messages, for up to `timeout`.
+fn deliver_all(target: &mut ReUDP, other: &mut ReUDP, count: usize, timeout: Duration) -> Vec<Vec<u8>> {
+    let mut received = Vec::new();
+    let deadline = Instant::now() + timeout;
+    while received.len() < count && Instant::now() < deadline {
+        other.recv().unwrap();
+        while let Some((_, payload)) = target.recv().unwrap() {
+            received.push(payload);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+fn recv(transport: &MemoryTransport) -> io::Result<(Vec<u8>, SocketAddr)> {
+    let mut buf = [0; 64];
+    let (len, from) = transport.recv_from(&mut buf)?;
+    Ok((buf[..len].to_vec(), from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_communication() {
+        let (mut server, client) = instances(MemoryTransport::pair());
+        let (mut client, result) = connect(client, &mut server);
+        result.unwrap();
+        let client_addr = client.local_addr().unwrap();
+        assert_eq!(server.client_addrs(), vec![client_addr]);
+
+        client.send(b"Test message from client", true).unwrap();
+        assert_eq!(
+            deliver_all(&mut server, &mut client, 1, Duration::from_secs(1)),
+            vec![b"Test message from client".to_vec()]
+        );
+        server.send(b"Hello from server!", true).unwrap();
+        assert_eq!(
+            deliver_all(&mut client, &mut server, 1, Duration::from_secs(1)),
+            vec![b"Hello from server!".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_reliable_delivery_over_lossy_links() {
+        let (server, client) = MemoryTransport::pair();
+        server.network().set_default_link(LinkPolicy::default().drop_rate(0.1).reorder_rate(0.1));
+        let (mut server, client) = instances((server, client));
+        let (mut client, result) = connect(client, &mut server);
+        result.unwrap();
+
+        let sent: Vec<Vec<u8>> = (0..50u32).map(|i| i.to_be_bytes().to_vec()).collect();
+        for payload in &sent {
+            client.send(payload.clone(), true).unwrap();
+        }
+        assert_eq!(deliver_all(&mut server, &mut client, sent.len(), Duration::from_secs(10)), sent);
+    }
+
+    #[test]
+    fn test_handshake_times_out_over_a_dead_link() {
+        let (server, client) = MemoryTransport::pair();
+        let network = server.network();
+        let dead = LinkPolicy::default().drop_rate(1.0);
+        network.set_link(client.local_addr().unwrap(), server.local_addr().unwrap(), dead);
+        let (mut server, client) = instances((server, client));
+
+        let (client, result) = connect(client, &mut server);
+        assert!(matches!(result, Err(ReUDPError::HandshakeTimeout)));
+        assert!(!client.is_connected());
+        assert!(server.client_addrs().is_empty());
+    }
+
+    #[test]
+    fn test_manual_clock_delays_delivery() {
+        let network = MemoryNetwork::with_manual_clock();
+        let a = network.bind("10.0.0.1:1000".parse().unwrap()).unwrap();
+        let b = network.bind("10.0.0.2:2000".parse().unwrap()).unwrap();
+        let (a_addr, b_addr) = (a.local_addr().unwrap(), b.local_addr().unwrap());
+        network.set_link(a_addr, b_addr, LinkPolicy::default().delay(Duration::from_millis(100)));
+
+        a.send_to(b"delayed", b_addr).unwrap();
+        b.send_to(b"instant", a_addr).unwrap();
+        assert_eq!(recv(&a).unwrap(), (b"instant".to_vec(), b_addr));
+        assert_eq!(recv(&b).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(recv(&b).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        network.advance(Duration::from_millis(99));
+        assert_eq!(recv(&b).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        network.advance(Duration::from_millis(1));
+        assert_eq!(recv(&b).unwrap(), (b"delayed".to_vec(), a_addr));
+    }
+
+    #[test]
+    fn test_addresses() {
+        let network = MemoryNetwork::new();
+        let addr: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        let endpoint = network.bind(addr).unwrap();
+        assert_eq!(network.bind(addr).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+
+        let other = network.bind("10.0.0.1:0".parse().unwrap()).unwrap();
+        assert_ne!(other.local_addr().unwrap().port(), 0);
+        other.send_to(b"lost", "10.0.0.9:9".parse().unwrap()).unwrap();
+
+        drop(endpoint);
+        other.send_to(b"lost too", addr).unwrap();
+        let endpoint = network.bind(addr).unwrap();
+        assert_eq!(recv(&endpoint).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+}
@@ -0,0 +1,105 @@
+use reudp::{Message, MessageType, FIRST_CUSTOM_TYPE};
+
+/// Every named type with its code on the wire. Changing a code breaks
+/// compatibility with peers running an older version.
+const NAMED_TYPES: [(MessageType, u8); 26] = [
+    (MessageType::Data, 0),
+    (MessageType::Ack, 1),
+    (MessageType::Heartbeat, 2),
+    (MessageType::HeartbeatAck, 3),
+    (MessageType::Sleep, 4),
+    (MessageType::Connect, 5),
+    (MessageType::Accept, 6),
+    (MessageType::ConnectDeny, 7),
+    (MessageType::Disconnect, 8),
+    (MessageType::SessionUnknown, 9),
+    (MessageType::PathChallenge, 10),
+    (MessageType::PathResponse, 11),
+    (MessageType::ResetAck, 16),
+    (MessageType::Reset, 17),
+    (MessageType::TimestampedData, 18),
+    (MessageType::Probe, 19),
+    (MessageType::ProbeReply, 20),
+    (MessageType::ChannelData, 21),
+    (MessageType::ChannelAck, 22),
+    (MessageType::Batch, 23),
+    (MessageType::TypedData, 24),
+    (MessageType::EncryptedData, 25),
+    (MessageType::Rollback, 26),
+    (MessageType::Nack, 27),
+    (MessageType::GroupData, 28),
+    (MessageType::GroupAck, 29),
+];
+
+/// Serializes `message`, parses it back and checks nothing changed.
+fn assert_roundtrips(message: &Message) {
+    let bytes = message.to_bytes();
+    assert_eq!(bytes.len(), message.encoded_len());
+    assert_eq!(&Message::from_bytes(&bytes).unwrap(), message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_types_roundtrip() {
+        for (message_type, code) in NAMED_TYPES {
+            let message = Message::new(42, message_type, b"payload".to_vec());
+            assert_eq!(message.to_bytes()[8], code, "{}", message.message_type);
+            assert_roundtrips(&message);
+        }
+    }
+
+    #[test]
+    fn test_custom_and_unknown_types_roundtrip() {
+        for code in FIRST_CUSTOM_TYPE..=127 {
+            assert_roundtrips(&Message::new(1, MessageType::Custom(code), vec![code]));
+        }
+        for code in (12..=15).chain(30..FIRST_CUSTOM_TYPE) {
+            assert_roundtrips(&Message::new(1, MessageType::Unknown(code), vec![code]));
+        }
+    }
+
+    #[test]
+    fn test_every_type_code_roundtrips() {
+        for code in 0..=127u8 {
+            let mut bytes = 7u64.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&[code, 0, 1, 0xAB]);
+            let message = Message::from_bytes(&bytes).unwrap();
+            assert_eq!(message.to_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_type_codes_above_127_lose_the_extensions_flag() {
+        // The high bit of the type byte says extensions follow, so no type
+        // code above 127 can go on the wire as is.
+        let bytes = Message::new(0, MessageType::Unknown(255), vec![]).to_bytes();
+        assert_eq!(bytes[8], 127);
+        assert_eq!(Message::from_bytes(&bytes).unwrap().message_type, MessageType::Custom(127));
+    }
+
+    #[test]
+    fn test_empty_payload_roundtrips() {
+        let message = Message::new(u64::MAX, MessageType::Data, vec![]);
+        assert_eq!(message.to_bytes().len(), 11);
+        assert_roundtrips(&message);
+    }
+
+    #[test]
+    fn test_largest_payload_roundtrips() {
+        let payload: Vec<u8> = (0..u16::MAX as usize).map(|i| i as u8).collect();
+        let message = Message::new(3, MessageType::Data, payload);
+        assert_eq!(message.to_bytes().len(), 11 + 65535);
+        assert_roundtrips(&message);
+    }
+
+    #[test]
+    fn test_extensions_roundtrip() {
+        let mut message = Message::new(5, MessageType::Heartbeat, b"beat".to_vec());
+        message.extensions.push((1, b"first".to_vec()));
+        message.extensions.push((200, vec![]));
+        assert_roundtrips(&message);
+    }
+}
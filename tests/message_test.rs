@@ -0,0 +1,110 @@
+use reudp::{Message, MessageType};
+use std::collections::BTreeSet;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_encodes_payload_length() {
+        let message = Message::new(7, MessageType::Data, b"hello".to_vec());
+        let bytes = message.to_bytes();
+        assert_eq!(bytes.len(), message.encoded_len());
+        assert_eq!(&bytes[9..11], &5u16.to_be_bytes());
+
+        let parsed = Message::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.sequence, 7);
+        assert_eq!(parsed.message_type, MessageType::Data);
+        assert_eq!(parsed.payload, b"hello");
+    }
+
+    #[test]
+    fn test_concatenated_messages_can_be_split() {
+        let first = Message::new(1, MessageType::Data, b"first".to_vec());
+        let second = Message::new(2, MessageType::Ack, vec![]);
+        let mut bytes = first.to_bytes();
+        bytes.extend_from_slice(&second.to_bytes());
+
+        let parsed = Message::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.payload, b"first");
+        let rest = &bytes[parsed.encoded_len()..];
+        let parsed = Message::from_bytes(rest).unwrap();
+        assert_eq!(parsed.sequence, 2);
+        assert_eq!(parsed.message_type, MessageType::Ack);
+        assert!(parsed.payload.is_empty());
+    }
+
+    #[test]
+    fn test_truncated_messages_are_rejected() {
+        let bytes = Message::new(1, MessageType::Data, b"payload".to_vec()).to_bytes();
+        assert!(Message::from_bytes(&bytes[..5]).is_err());
+        assert!(Message::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_extensions_round_trip() {
+        let message = Message::new(3, MessageType::Data, b"payload".to_vec())
+            .with_extension(1, vec![0xaa; 4])
+            .with_extension(9, vec![]);
+        let bytes = message.to_bytes();
+        assert_eq!(bytes.len(), message.encoded_len());
+        assert_eq!(bytes[8], 0x80);
+
+        let parsed = Message::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.message_type, MessageType::Data);
+        assert_eq!(parsed.payload, b"payload");
+        assert_eq!(parsed.extensions, message.extensions);
+        assert_eq!(parsed.extension(1), Some(&[0xaa; 4][..]));
+        assert_eq!(parsed.extension(9), Some(&[][..]));
+        assert_eq!(parsed.extension(2), None);
+    }
+
+    #[test]
+    fn test_messages_without_extensions_keep_the_plain_header() {
+        let bytes = Message::new(1, MessageType::Ack, b"ack".to_vec()).to_bytes();
+        assert_eq!(bytes[8], 1);
+        assert_eq!(bytes.len(), 11 + 3);
+        assert!(Message::from_bytes(&bytes).unwrap().extensions.is_empty());
+    }
+
+    #[test]
+    fn test_round_trip_preserves_equality() {
+        let message = Message::new(5, MessageType::TimestampedData, b"payload".to_vec()).with_extension(2, vec![1, 2]);
+        assert_eq!(Message::from_bytes(&message.to_bytes()).unwrap(), message);
+        assert_ne!(Message::new(5, MessageType::Data, b"payload".to_vec()), message);
+    }
+
+    #[test]
+    fn test_messages_order_by_sequence() {
+        let mut messages = [
+            Message::new(3, MessageType::Data, b"c".to_vec()),
+            Message::new(1, MessageType::Data, b"z".to_vec()),
+            Message::new(2, MessageType::Ack, vec![]),
+        ];
+        messages.sort();
+        let sequences: Vec<u64> = messages.iter().map(|message| message.sequence).collect();
+        assert_eq!(sequences, [1, 2, 3]);
+
+        // Same sequence but different content stays distinct.
+        let set: BTreeSet<Message> = [
+            Message::new(1, MessageType::Data, b"a".to_vec()),
+            Message::new(1, MessageType::Data, b"b".to_vec()),
+            Message::new(0, MessageType::Data, b"c".to_vec()),
+            Message::new(1, MessageType::Data, b"a".to_vec()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.first().unwrap().sequence, 0);
+    }
+
+    #[test]
+    fn test_truncated_extensions_are_rejected() {
+        let bytes = Message::new(1, MessageType::Data, b"payload".to_vec())
+            .with_extension(1, vec![0; 8])
+            .to_bytes();
+        for len in 11..bytes.len() {
+            assert!(Message::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use reudp::{Event, Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polls every instance until `target` delivers a message, for up to `timeout`.
+fn deliver(target: &mut ReUDP, others: &mut [&mut ReUDP], timeout: Duration) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        for other in others.iter_mut() {
+            let _ = other.recv();
+        }
+        if let Some(received) = target.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+/// Polls every instance until `client` emits an event, for up to `timeout`.
+fn next_event(client: &mut ReUDP, others: &mut [&mut ReUDP], timeout: Duration) -> Option<Event> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        for other in others.iter_mut() {
+            let _ = other.recv();
+        }
+        client.recv().unwrap();
+        if let Some(event) = client.poll_event() {
+            return Some(event);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_migrates_to_new_server_address() {
+        let mut old_server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let old_addr = old_server.local_addr().unwrap();
+        let mut new_server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let new_addr = new_server.local_addr().unwrap();
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(old_addr), Duration::from_secs(1), 1024).unwrap();
+
+        // Register the client with the old server.
+        client.send(b"hello", true).unwrap();
+        deliver(&mut old_server, &mut [&mut client], Duration::from_secs(1)).unwrap();
+        // The new address fronts the same session, so it expects the next sequence number.
+        let mut other = ReUDP::new("127.0.0.1:0", Mode::Client(new_addr), Duration::from_secs(1), 1024).unwrap();
+        other.send(b"hello", true).unwrap();
+        deliver(&mut new_server, &mut [&mut other], Duration::from_secs(1)).unwrap();
+        assert_eq!(new_server.recv_sequence(), 1);
+
+        client.migrate_to(new_addr).unwrap();
+        assert!(client.is_migration_pending());
+        let event = next_event(&mut client, &mut [&mut new_server], Duration::from_secs(1));
+        assert_eq!(event, Some(Event::Migrated { old: old_addr, new: new_addr }));
+        assert!(!client.is_migration_pending());
+        assert!(matches!(client.mode(), Mode::Client(addr) if *addr == new_addr));
+
+        client.send(b"moved", true).unwrap();
+        let (_, payload) = deliver(&mut new_server, &mut [&mut client], Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, b"moved");
+
+        // Stragglers from the old address are still accepted during the grace period.
+        old_server.send(b"straggler", true).unwrap();
+        let (from, payload) = deliver(&mut client, &mut [&mut old_server], Duration::from_secs(1)).unwrap();
+        assert_eq!(from, old_addr);
+        assert_eq!(payload, b"straggler");
+    }
+
+    #[test]
+    fn test_migration_to_silent_address_fails() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let silent_addr = silent.local_addr().unwrap();
+        let config = ReUDPConfig::default()
+            .handshake_retries(2)
+            .handshake_retry_interval(Duration::from_millis(50));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap();
+
+        client.migrate_to(silent_addr).unwrap();
+        let event = next_event(&mut client, &mut [], Duration::from_secs(1));
+        assert_eq!(event, Some(Event::MigrationFailed { addr: silent_addr }));
+        assert!(!client.is_migration_pending());
+        assert!(matches!(client.mode(), Mode::Client(addr) if *addr == server_addr));
+    }
+}
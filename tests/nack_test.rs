@@ -0,0 +1,82 @@
+mod common;
+
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use common::pump;
+
+/// Binds a raw socket standing in for the other end.
+fn raw_peer() -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+    socket
+}
+
+/// Reads datagrams until a message of `message_type` arrives.
+fn recv_of_type(socket: &UdpSocket, message_type: MessageType) -> Option<Message> {
+    let mut buf = [0; 1024];
+    while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        if message.message_type == message_type {
+            return Some(message);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receiver_falling_behind_reports_missing_sequences() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = raw_peer();
+        // 0, 3 and 5 are lost; the rest waits for them.
+        for sequence in (1..=11).filter(|sequence| ![3, 5].contains(sequence)) {
+            let data = Message::new(sequence, MessageType::Data, vec![sequence as u8]);
+            client.send_to(&data.to_bytes(), server_addr).unwrap();
+        }
+        pump(&mut server, Duration::from_millis(50));
+
+        let nack = recv_of_type(&client, MessageType::Nack).unwrap();
+        assert_eq!(nack.payload.len(), 24);
+        assert_eq!(&nack.payload[..8], &0u64.to_be_bytes());
+        let mut bitmap = [0; 16];
+        bitmap[0] = 0b0010_1001;
+        assert_eq!(&nack.payload[8..], &bitmap);
+        // Only one per round trip, however many more arrive out of order.
+        let data = Message::new(12, MessageType::Data, vec![12]);
+        client.send_to(&data.to_bytes(), server_addr).unwrap();
+        pump(&mut server, Duration::from_millis(20));
+        assert!(recv_of_type(&client, MessageType::Nack).is_none());
+    }
+
+    #[test]
+    fn test_sender_resends_the_marked_messages_at_once() {
+        let server = raw_peer();
+        let config = ReUDPConfig::default()
+            .liveness_timeout(Duration::from_secs(10))
+            .resend_interval(Duration::from_secs(5));
+        let mode = Mode::Client(server.local_addr().unwrap());
+        let mut client = ReUDP::with_config("127.0.0.1:0", mode, config).unwrap();
+        for i in 0..4u8 {
+            client.send([i], true).unwrap();
+        }
+        while recv_of_type(&server, MessageType::Data).is_some() {}
+
+        let mut payload = 0u64.to_be_bytes().to_vec();
+        payload.push(0b0000_0101);
+        payload.extend_from_slice(&[0; 15]);
+        let nack = Message::new(0, MessageType::Nack, payload);
+        server.send_to(&nack.to_bytes(), client.local_addr().unwrap()).unwrap();
+        pump(&mut client, Duration::from_millis(20));
+
+        let resent: Vec<u64> = std::iter::from_fn(|| recv_of_type(&server, MessageType::Data))
+            .map(|message| message.sequence)
+            .collect();
+        assert_eq!(resent, vec![0, 2]);
+    }
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "opentelemetry")]
+
+mod common;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{SdkTracerProvider, SpanData, SpanExporter};
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig, TRACEPARENT_EXTENSION};
+use std::future::{self, Future};
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+
+use common::{deliver_to_server, pair, recv_within};
+
+/// Exporter keeping the finished spans for the test to look at.
+#[derive(Debug, Clone, Default)]
+struct SpanRecorder(Arc<Mutex<Vec<SpanData>>>);
+
+impl SpanRecorder {
+    fn get_finished_spans(&self) -> Vec<SpanData> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl SpanExporter for SpanRecorder {
+    fn export(&self, batch: Vec<SpanData>) -> impl Future<Output = OTelSdkResult> + Send {
+        self.0.lock().unwrap().extend(batch);
+        future::ready(Ok(()))
+    }
+}
+
+/// Traces the spans of this thread into a `SpanRecorder` until the guard is dropped.
+fn trace_spans() -> (SdkTracerProvider, SpanRecorder, tracing::subscriber::DefaultGuard) {
+    let exporter = SpanRecorder::default();
+    let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("reudp-test"));
+    let guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+    (provider, exporter, guard)
+}
+
+fn spans_named<'a>(spans: &'a [SpanData], name: &str) -> Vec<&'a SpanData> {
+    spans.iter().filter(|span| span.name == name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receive_spans_continue_the_trace_of_the_send() {
+        let (provider, exporter, _guard) = trace_spans();
+        let (mut client, mut server) = pair();
+        client.send(b"traced", true).unwrap();
+        assert!(deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).is_some());
+        provider.force_flush().unwrap();
+
+        let spans = exporter.get_finished_spans();
+        let sends = spans_named(&spans, "reudp.send");
+        assert_eq!(sends.len(), 1);
+        let send = sends[0].span_context.clone();
+        let continued: Vec<_> = spans_named(&spans, "reudp.recv")
+            .into_iter()
+            .filter(|recv| recv.parent_span_id == send.span_id())
+            .collect();
+        assert_eq!(continued.len(), 1);
+        assert_eq!(continued[0].span_context.trace_id(), send.trace_id());
+    }
+
+    #[test]
+    fn test_traceparent_travels_in_an_extension() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let client = || {
+            let mode = Mode::Client(server.local_addr().unwrap());
+            ReUDP::with_config("127.0.0.1:0", mode, ReUDPConfig::default()).unwrap()
+        };
+        let read_data = || {
+            let mut buf = [0; 1024];
+            loop {
+                let len = server.recv(&mut buf).unwrap();
+                let message = Message::from_bytes(&buf[..len]).unwrap();
+                if message.message_type == MessageType::Data {
+                    return message;
+                }
+            }
+        };
+
+        // Outside a trace, nothing is added.
+        client().send(b"untraced", false).unwrap();
+        assert!(read_data().extensions.is_empty());
+
+        let (provider, exporter, _guard) = trace_spans();
+        client().send(b"traced", false).unwrap();
+        provider.force_flush().unwrap();
+        let message = read_data();
+        assert_eq!(message.payload, b"traced");
+        let send = spans_named(&exporter.get_finished_spans(), "reudp.send")[0].span_context.clone();
+        let expected = format!("00-{}-{}-01", send.trace_id(), send.span_id());
+        assert_eq!(message.extension(TRACEPARENT_EXTENSION), Some(expected.as_bytes()));
+    }
+
+    #[test]
+    fn test_unparsable_traceparent_is_ignored() {
+        let (_provider, _exporter, _guard) = trace_spans();
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let data = Message::new(0, MessageType::Data, b"kept".to_vec())
+            .with_extension(TRACEPARENT_EXTENSION, b"00-not-a-trace-01".to_vec());
+        socket.send_to(&data.to_bytes(), server.local_addr().unwrap()).unwrap();
+
+        let received = recv_within(&mut server, Duration::from_secs(1));
+        assert_eq!(received.map(|(_, payload)| payload), Some(b"kept".to_vec()));
+    }
+}
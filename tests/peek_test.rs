@@ -0,0 +1,73 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+/// Sends raw Data messages carrying `(sequence, data)` from `socket` to `reudp`.
+fn send_raw(socket: &UdpSocket, reudp: &ReUDP, messages: &[(u64, &[u8])]) {
+    for &(sequence, data) in messages {
+        let message = Message::new(sequence, MessageType::Data, data.to_vec());
+        socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peek_leaves_the_message_for_recv() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert!(server.peek().is_none());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[(0, b"first"), (1, b"second")]);
+        thread::sleep(Duration::from_millis(50));
+
+        let client_addr = socket.local_addr().unwrap();
+        assert_eq!(server.peek(), Some((&client_addr, &b"first"[..])));
+        assert_eq!(server.peek(), Some((&client_addr, &b"first"[..])));
+        assert_eq!(server.recv().unwrap(), Some((client_addr, b"first".to_vec())));
+        assert_eq!(server.peek(), Some((&client_addr, &b"second"[..])));
+        assert_eq!(server.recv().unwrap(), Some((client_addr, b"second".to_vec())));
+        assert!(server.peek().is_none());
+    }
+
+    #[test]
+    fn test_peek_waits_for_gaps_in_order() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[(1, b"second")]);
+        thread::sleep(Duration::from_millis(50));
+        assert!(server.peek().is_none());
+
+        send_raw(&socket, &server, &[(0, b"first")]);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(server.peek().map(|(_, data)| data.to_vec()), Some(b"first".to_vec()));
+        let received: Vec<Vec<u8>> = server.recv_all().unwrap().into_iter().map(|(_, data)| data).collect();
+        assert_eq!(received, vec![b"first".to_vec(), b"second".to_vec()]);
+    }
+
+    #[test]
+    fn test_peek_does_not_change_message_metadata() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(server.local_addr().unwrap()),
+            ReUDPConfig::default(),
+        )
+        .unwrap();
+        client.send_ordered_channel(3, b"on channel", false).unwrap();
+        client.send(b"default", false).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(server.peek().map(|(_, data)| data.to_vec()), Some(b"on channel".to_vec()));
+        assert_eq!(server.last_message_channel(), None);
+        server.recv().unwrap().unwrap();
+        assert_eq!(server.last_message_channel(), Some(3));
+        assert_eq!(server.peek().map(|(_, data)| data.to_vec()), Some(b"default".to_vec()));
+        assert_eq!(server.last_message_channel(), Some(3));
+        server.recv().unwrap().unwrap();
+        assert_eq!(server.last_message_channel(), None);
+    }
+}
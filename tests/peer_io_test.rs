@@ -0,0 +1,79 @@
+use reudp::{IoContext, Mode, ReUDP, ReUDPConfig, ReUDPError, Transport};
+use std::error::Error;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A transport whose sends all fail, as with a peer the OS refuses to reach.
+struct FailingTransport {
+    socket: UdpSocket,
+}
+
+impl FailingTransport {
+    fn bind() -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_nonblocking(true).unwrap();
+        Self { socket }
+    }
+}
+
+impl Transport for FailingTransport {
+    fn send_to(&self, _buf: &[u8], _addr: SocketAddr) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "blocked by firewall"))
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.socket.recv_from(buf)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+fn server_addr() -> SocketAddr {
+    "127.0.0.1:9".parse().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_errors_name_the_peer() {
+        let config = ReUDPConfig::default();
+        let mut client = ReUDP::with_transport(FailingTransport::bind(), Mode::Client(server_addr()), config).unwrap();
+
+        let error = client.send(b"hello", false).unwrap_err();
+        let ReUDPError::PeerIo { addr, ref source, during } = error else {
+            panic!("expected PeerIo, got {:?}", error);
+        };
+        assert_eq!(addr, server_addr());
+        assert_eq!(source.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(during, IoContext::Send);
+        assert_eq!(error.to_string(), "I/O error during send to 127.0.0.1:9");
+        assert_eq!(error.source().unwrap().to_string(), "blocked by firewall");
+        assert_eq!(error.io_error().unwrap().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::PermissionDenied);
+
+        let error = client.disconnect().unwrap_err();
+        assert!(matches!(error, ReUDPError::PeerIo { during: IoContext::Control, .. }));
+    }
+
+    #[test]
+    fn test_background_send_errors_are_logged_with_the_peer() {
+        let config = ReUDPConfig::default().heartbeat_interval(Duration::from_millis(10));
+        let mut client = ReUDP::with_transport(FailingTransport::bind(), Mode::Client(server_addr()), config).unwrap();
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&logged);
+        client.set_logger(move |_, message| sink.lock().unwrap().push(message.to_string()));
+
+        thread::sleep(Duration::from_millis(100));
+        let logged = logged.lock().unwrap();
+        assert!(logged
+            .iter()
+            .any(|message| message == "I/O error during heartbeat to 127.0.0.1:9: blocked by firewall"));
+    }
+}
@@ -0,0 +1,67 @@
+use reudp::{LinkPolicy, Mode, NetworkEmulator, ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(20))
+        .resend_interval(Duration::from_millis(20))
+        .liveness_timeout(Duration::from_secs(5))
+}
+
+/// Keeps calling `recv` on every instance for `duration`.
+fn pump(instances: &mut [&mut ReUDP], duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        for reudp in instances.iter_mut() {
+            reudp.recv().unwrap();
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_statistics_follow_the_link() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let link = LinkPolicy::default()
+            .delay(Duration::from_millis(20))
+            .delay_jitter(Duration::from_millis(10));
+        let emulator = NetworkEmulator::new(server.local_addr().unwrap(), link.clone(), link).unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(emulator.addr()), config()).unwrap();
+        assert_eq!(client.jitter(), None);
+        assert_eq!(client.min_ping(), None);
+        assert_eq!(client.ping_percentile(95.0), None);
+
+        pump(&mut [&mut client, &mut server], Duration::from_millis(500));
+        let min = client.min_ping().unwrap();
+        let max = client.max_ping().unwrap();
+        let median = client.ping_percentile(50.0).unwrap();
+        let p99 = client.ping_percentile(99.0).unwrap();
+        assert!(min >= Duration::from_millis(40), "min ping {:?}", min);
+        assert!(min <= median && median <= p99 && p99 <= max);
+        assert_eq!(client.ping_percentile(0.0), Some(min));
+        assert_eq!(client.ping_percentile(100.0), Some(max));
+        assert_eq!(client.ping_percentile(101.0), None);
+        assert!(client.jitter().unwrap() <= max - min);
+    }
+
+    #[test]
+    fn test_empty_history() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let mut client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(server.local_addr().unwrap()),
+            config().ping_history_size(0),
+        )
+        .unwrap();
+
+        pump(&mut [&mut client, &mut server], Duration::from_millis(200));
+        assert!(client.get_current_ping().is_some());
+        assert_eq!(client.jitter(), None);
+        assert_eq!(client.max_ping(), None);
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use reudp::ReUDP;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+/// Pumps `client` and `server` until the client has `count` probe results or `timeout` elapses.
+fn pump_until_results(client: &mut ReUDP, server: &mut ReUDP, count: usize, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while client.probe_results().len() < count && Instant::now() < deadline {
+        server.recv().unwrap();
+        client.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_run_collects_results() {
+        let (mut client, mut server) = pair();
+        let server_addr = server.local_addr().unwrap();
+        assert!(client.probe_results().is_empty());
+
+        client.start_probing(Duration::from_millis(10), 5);
+        pump_until_results(&mut client, &mut server, 5, Duration::from_secs(2));
+
+        let results = client.probe_results();
+        assert_eq!(results.len(), 5);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.addr(), server_addr);
+            assert_eq!(result.sequence(), i as u64);
+            assert!(result.rtt() < Duration::from_secs(1));
+            // Both ends share the same clock here, so the timestamps are ordered.
+            assert!(result.sent_at_micros() <= result.remote_received_at_micros());
+            assert!(result.remote_received_at_micros() <= result.received_at_micros());
+            assert_eq!(
+                result.forward_delay_micros() + result.return_delay_micros(),
+                result.rtt().as_micros() as i64
+            );
+        }
+        assert_eq!(results[0].jitter(), Duration::ZERO);
+
+        // The run is over: no more probes go out.
+        thread::sleep(Duration::from_millis(50));
+        pump_until_results(&mut client, &mut server, 6, Duration::from_millis(100));
+        assert_eq!(client.probe_results().len(), 5);
+    }
+
+    #[test]
+    fn test_new_run_clears_results() {
+        let (mut client, mut server) = pair();
+        client.start_probing(Duration::from_millis(5), 2);
+        pump_until_results(&mut client, &mut server, 2, Duration::from_secs(2));
+        assert_eq!(client.probe_results().len(), 2);
+
+        client.start_probing(Duration::from_millis(5), 1);
+        assert!(client.probe_results().is_empty());
+        pump_until_results(&mut client, &mut server, 1, Duration::from_secs(2));
+        assert_eq!(client.probe_results().len(), 1);
+        assert_eq!(client.probe_results()[0].sequence(), 0);
+    }
+}
@@ -0,0 +1,64 @@
+use reudp::{ConnectionQuality, Event, LinkPolicy, Mode, NetworkEmulator, ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(20))
+        .resend_interval(Duration::from_millis(20))
+        .liveness_timeout(Duration::from_secs(5))
+}
+
+/// Keeps calling `recv` on every instance for `duration`.
+fn pump(instances: &mut [&mut ReUDP], duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        for reudp in instances.iter_mut() {
+            reudp.recv().unwrap();
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_levels() {
+        assert!(ConnectionQuality::Excellent > ConnectionQuality::Good);
+        assert!(ConnectionQuality::Poor > ConnectionQuality::Critical);
+        assert_eq!(ConnectionQuality::Excellent.to_string(), "Excellent");
+        assert_eq!(ConnectionQuality::Excellent.score(), 100);
+        assert_eq!(ConnectionQuality::Critical.score(), 0);
+    }
+
+    #[test]
+    fn test_quality_follows_rtt() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let slow = LinkPolicy::default().delay(Duration::from_millis(50));
+        let emulator = NetworkEmulator::new(server_addr, slow.clone(), slow).unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(emulator.addr()), config()).unwrap();
+        assert_eq!(client.connection_quality(), ConnectionQuality::Excellent);
+
+        pump(&mut [&mut client, &mut server], Duration::from_millis(500));
+        // About 100 ms of RTT and no loss.
+        assert_eq!(client.connection_quality(), ConnectionQuality::Fair);
+        assert_eq!(client.poll_event(), Some(Event::QualityChanged(ConnectionQuality::Fair)));
+    }
+
+    #[test]
+    fn test_loss_rate_from_heartbeat_gaps() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let lossy = LinkPolicy::default().drop_rate(0.5);
+        let emulator = NetworkEmulator::new(server_addr, LinkPolicy::default(), lossy).unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(emulator.addr()), config()).unwrap();
+
+        pump(&mut [&mut client, &mut server], Duration::from_millis(800));
+        assert!(client.packet_loss_rate() > 0.2, "{}", client.packet_loss_rate());
+        assert_eq!(server.packet_loss_rate(), 0.0);
+        assert!(client.connection_quality() <= ConnectionQuality::Poor);
+    }
+}
@@ -0,0 +1,108 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Datagrams given to a raw handler, with their sender.
+type RawLog = Arc<Mutex<Vec<(SocketAddr, Vec<u8>)>>>;
+
+/// Sets a raw handler on `reudp` recording what it is given.
+fn record_raw(reudp: &mut ReUDP) -> RawLog {
+    let log = RawLog::default();
+    let handler_log = Arc::clone(&log);
+    reudp.set_raw_handler(move |addr, data| handler_log.lock().unwrap().push((addr, data.to_vec())));
+    log
+}
+
+/// A datagram shaped like a STUN binding request, which isn't a ReUDP message.
+fn binding_request() -> Vec<u8> {
+    let mut request = vec![0x00, 0x01, 0x00, 0x00, 0x21, 0x12, 0xa4, 0x42];
+    request.extend_from_slice(&[0xff; 12]);
+    request
+}
+
+/// Receives on `reudp` until a raw datagram was handled, for up to a second.
+fn recv_raw(reudp: &mut ReUDP, log: &RawLog) {
+    for _ in 0..1000 {
+        assert!(reudp.recv().unwrap().is_none());
+        if !log.lock().unwrap().is_empty() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_raw_is_verbatim() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+
+        let sent = server.send_raw(peer.local_addr().unwrap(), &binding_request()).unwrap();
+        assert_eq!(sent, binding_request().len());
+        let mut buf = [0; 64];
+        let (len, _) = peer.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &binding_request()[..]);
+        assert_eq!(server.send_sequence(), 0);
+        assert_eq!(server.pending_acks(), 0);
+        assert_eq!(server.client_count(), 0);
+    }
+
+    #[test]
+    fn test_raw_datagrams_go_to_the_handler() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let log = record_raw(&mut server);
+
+        peer.send_to(&binding_request(), server.local_addr().unwrap()).unwrap();
+        recv_raw(&mut server, &log);
+        assert_eq!(*log.lock().unwrap(), vec![(peer.local_addr().unwrap(), binding_request())]);
+        assert_eq!(server.client_count(), 0);
+        assert_eq!(server.recv_sequence(), 0);
+    }
+
+    #[test]
+    fn test_raw_datagrams_need_an_allowed_sender() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let config = ReUDPConfig::default().connect_client_socket(false);
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), config).unwrap();
+        let log = record_raw(&mut client);
+        let stun = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        stun.send_to(&binding_request(), client.local_addr().unwrap()).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        client.recv().unwrap();
+        assert!(log.lock().unwrap().is_empty());
+
+        client.add_allowed_sender(stun.local_addr().unwrap());
+        stun.send_to(&binding_request(), client.local_addr().unwrap()).unwrap();
+        recv_raw(&mut client, &log);
+        assert_eq!(*log.lock().unwrap(), vec![(stun.local_addr().unwrap(), binding_request())]);
+    }
+
+    #[test]
+    fn test_messages_still_reach_recv() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default())
+                .unwrap();
+        let log = record_raw(&mut server);
+
+        client.send(b"hello", true).unwrap();
+        let mut received = None;
+        for _ in 0..1000 {
+            let _ = client.recv();
+            received = server.recv().unwrap();
+            if received.is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received.unwrap().1, b"hello");
+        assert!(log.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs a server on its own thread until `stop` is set, returning its address.
+fn spawn_server(stop: Arc<AtomicBool>) -> SocketAddr {
+    let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+    addr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_starts_fresh_stats() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_addr = spawn_server(Arc::clone(&stop));
+        // Unconnected, so the stranger's packet isn't dropped by the OS.
+        let config = ReUDPConfig::default()
+            .heartbeat_interval(Duration::from_millis(20))
+            .connect_client_socket(false);
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap();
+        client.connect().unwrap();
+        assert_eq!(client.session_number(), 0);
+        assert!(client.previous_session_stats().is_none());
+
+        // A packet from a stranger is counted, and heartbeats give RTT samples.
+        let intruder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ack = Message::new(0, MessageType::Ack, vec![]).to_bytes();
+        intruder.send_to(&ack, client.local_addr().unwrap()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while (client.stats().packets_dropped_unauthorized == 0 || client.srtt().is_none()) && Instant::now() < deadline {
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(client.stats().packets_dropped_unauthorized, 1);
+        assert!(client.srtt().is_some());
+
+        // Quiet the heartbeats so no new sample lands during the reconnect.
+        client
+            .update_config(|config| config.heartbeat_interval(Duration::from_secs(10)))
+            .unwrap();
+        thread::sleep(Duration::from_millis(50));
+        while client.recv().unwrap().is_some() {}
+
+        client.reconnect().unwrap();
+        assert!(client.is_connected());
+        assert_eq!(client.session_number(), 1);
+        assert_eq!(client.previous_session_stats().unwrap().packets_dropped_unauthorized, 1);
+        assert_eq!(client.stats().packets_dropped_unauthorized, 0);
+        assert!(client.srtt().is_none());
+        assert!(client.max_ping().is_none());
+
+        stop.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_reconnect_is_client_only() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert!(matches!(server.reconnect(), Err(ReUDPError::WrongMode { .. })));
+        assert_eq!(server.session_number(), 0);
+    }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::send_raw;
+
+/// Calls `recv_all` until `count` messages have been collected or a second passes.
+fn collect(reudp: &mut ReUDP, count: usize) -> Vec<Vec<u8>> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    let mut received = Vec::new();
+    while received.len() < count && Instant::now() < deadline {
+        received.extend(reudp.recv_all().unwrap().into_iter().map(|(_, data)| data));
+        thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_all_drains_pending_messages_in_one_call() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        assert!(server.recv_all().unwrap().is_empty());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sequences: Vec<u64> = (0..100).collect();
+        send_raw(&socket, &server, &sequences);
+        thread::sleep(Duration::from_millis(50));
+
+        let received = server.recv_all().unwrap();
+        assert_eq!(received.len(), 100);
+        for (i, (addr, data)) in received.iter().enumerate() {
+            assert_eq!(*addr, socket.local_addr().unwrap());
+            assert_eq!(data, &(i as u64).to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_recv_all_releases_reordered_messages() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[1, 2, 0]);
+
+        let received = collect(&mut server, 3);
+        let expected: Vec<Vec<u8>> = (0..3u64).map(|i| i.to_be_bytes().to_vec()).collect();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_recv_all_is_capped() {
+        let config = ReUDPConfig::default().max_recv_batch(10);
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sequences: Vec<u64> = (0..50).collect();
+        send_raw(&socket, &server, &sequences);
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(server.recv_all().unwrap().len(), 10);
+        assert_eq!(collect(&mut server, 40).len(), 40);
+    }
+
+    #[test]
+    fn test_recv_all_into_appends_to_existing_messages() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[0, 1]);
+        thread::sleep(Duration::from_millis(50));
+
+        let mut messages = vec![(socket.local_addr().unwrap(), b"earlier".to_vec())];
+        assert_eq!(server.recv_all_into(&mut messages).unwrap(), 2);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].1, b"earlier");
+    }
+
+    #[test]
+    fn test_recv_all_in_blocking_mode_returns_when_empty() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        server.set_blocking(true).unwrap();
+        let started = Instant::now();
+        assert!(server.recv_all().unwrap().is_empty());
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,57 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+/// Sends a raw Data message from `socket` to `reudp`.
+fn send_raw(socket: &UdpSocket, reudp: &ReUDP, sequence: u64, data: &[u8]) {
+    let message = Message::new(sequence, MessageType::Data, data.to_vec());
+    socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_from_leaves_other_clients_messages_queued() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&b, &server, 0, b"b0");
+        send_raw(&a, &server, 1, b"a0");
+        send_raw(&b, &server, 2, b"b1");
+        send_raw(&a, &server, 3, b"a1");
+        thread::sleep(Duration::from_millis(50));
+
+        let a_addr = a.local_addr().unwrap();
+        let b_addr = b.local_addr().unwrap();
+        assert_eq!(server.recv_from(a_addr).unwrap(), Some(b"a0".to_vec()));
+        assert_eq!(server.recv_from(a_addr).unwrap(), Some(b"a1".to_vec()));
+        assert_eq!(server.recv_from(a_addr).unwrap(), None);
+
+        assert_eq!(server.recv().unwrap(), Some((b_addr, b"b0".to_vec())));
+        assert_eq!(server.recv_from(b_addr).unwrap(), Some(b"b1".to_vec()));
+        assert_eq!(server.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn test_unread_client_queue_is_capped() {
+        let config = ReUDPConfig::default().max_queued_per_peer(3);
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for sequence in 0..5u64 {
+            send_raw(&b, &server, sequence, &sequence.to_be_bytes());
+        }
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(server.recv_from(a.local_addr().unwrap()).unwrap(), None);
+        assert_eq!(server.stats().messages_dropped_queue_full, 2);
+        let b_addr = b.local_addr().unwrap();
+        for sequence in 2..5u64 {
+            assert_eq!(server.recv_from(b_addr).unwrap(), Some(sequence.to_be_bytes().to_vec()));
+        }
+        assert_eq!(server.recv_from(b_addr).unwrap(), None);
+    }
+}
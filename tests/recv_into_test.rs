@@ -0,0 +1,57 @@
+mod common;
+
+use reudp::{ReUDP, ReUDPError};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+/// Polls `server` with `recv_into` until it delivers a message or fails, for up to `timeout`.
+fn recv_into_within(
+    server: &mut ReUDP,
+    buf: &mut [u8],
+    timeout: Duration,
+) -> Result<Option<(SocketAddr, usize)>, ReUDPError> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Some(received) = server.recv_into(buf)? {
+            return Ok(Some(received));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_into_copies_into_the_buffer() {
+        let (mut client, mut server) = pair();
+        client.send(b"hello", true).unwrap();
+
+        let mut buf = [0; 64];
+        let (addr, len) = recv_into_within(&mut server, &mut buf, Duration::from_secs(1)).unwrap().unwrap();
+        assert_eq!(addr, client.local_addr().unwrap());
+        assert_eq!(&buf[..len], b"hello");
+    }
+
+    #[test]
+    fn test_too_small_buffer_keeps_the_message() {
+        let (mut client, mut server) = pair();
+        client.send(vec![7; 100], true).unwrap();
+        client.send(b"next", true).unwrap();
+
+        let mut small = [0; 10];
+        let result = recv_into_within(&mut server, &mut small, Duration::from_secs(1));
+        assert!(matches!(result, Err(ReUDPError::BufferTooSmall { needed: 100 })));
+
+        let mut buf = [0; 128];
+        let (_, len) = server.recv_into(&mut buf).unwrap().unwrap();
+        assert_eq!(&buf[..len], &[7; 100][..]);
+        let (_, len) = recv_into_within(&mut server, &mut buf, Duration::from_secs(1)).unwrap().unwrap();
+        assert_eq!(&buf[..len], b"next");
+    }
+}
@@ -0,0 +1,85 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sends `message` raw from `socket` to `reudp`.
+fn send_raw(socket: &UdpSocket, reudp: &ReUDP, message: Message) {
+    socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+}
+
+/// Receives messages for `wait`, returning each one whole.
+fn recv_messages_for(reudp: &mut ReUDP, wait: Duration) -> Vec<Message> {
+    let deadline = Instant::now() + wait;
+    let mut received = Vec::new();
+    while Instant::now() < deadline {
+        while let Some((_, message)) = reudp.recv_message().unwrap() {
+            received.push(message);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_message_keeps_sequence_and_type() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, Message::new(0, MessageType::Data, b"plain".to_vec()));
+        let mut timestamped = 0u64.to_be_bytes().to_vec();
+        timestamped.extend_from_slice(b"timed");
+        send_raw(&socket, &server, Message::new(1, MessageType::TimestampedData, timestamped));
+        send_raw(&socket, &server, Message::new(0, MessageType::ChannelData, b"\x03\x01chan".to_vec()));
+
+        let received = recv_messages_for(&mut server, Duration::from_millis(100));
+        let summary: Vec<_> = received
+            .iter()
+            .map(|message| (message.sequence, message.message_type.clone(), message.payload.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, MessageType::Data, b"plain".to_vec()),
+                (1, MessageType::TimestampedData, b"timed".to_vec()),
+                (0, MessageType::ChannelData, b"chan".to_vec()),
+            ]
+        );
+        assert_eq!(server.last_message_channel(), Some(3));
+    }
+
+    #[test]
+    fn test_recv_message_keeps_extensions() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let message = Message::new(0, MessageType::Data, b"data".to_vec()).with_extension(42, vec![1, 2, 3]);
+        send_raw(&socket, &server, message);
+
+        let received = recv_messages_for(&mut server, Duration::from_millis(100));
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].extension(42), Some(&[1, 2, 3][..]));
+        assert_eq!(received[0].payload, b"data");
+    }
+
+    #[test]
+    fn test_recv_still_returns_only_the_payload() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut timestamped = 0u64.to_be_bytes().to_vec();
+        timestamped.extend_from_slice(b"timed");
+        send_raw(&socket, &server, Message::new(0, MessageType::TimestampedData, timestamped));
+
+        let deadline = Instant::now() + Duration::from_millis(100);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        let (addr, data) = received.expect("message not received");
+        assert_eq!(addr, socket.local_addr().unwrap());
+        assert_eq!(data, b"timed");
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_timeout_returns_none_after_deadline() {
+        let (_client, mut server) = pair();
+        let started = Instant::now();
+        assert!(server.recv_timeout(Duration::from_millis(100)).unwrap().is_none());
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_recv_timeout_returns_message_when_it_arrives() {
+        let (mut client, mut server) = pair();
+        let client_addr = client.local_addr().unwrap();
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            client.send(b"hello", true).unwrap();
+            client
+        });
+
+        let started = Instant::now();
+        let received = server.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(received, Some((client_addr, b"hello".to_vec())));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_recv_timeout_processes_control_traffic_while_waiting() {
+        let config = ReUDPConfig::default().heartbeat_interval(Duration::from_millis(20));
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config.clone()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap();
+        let server_thread = thread::spawn(move || {
+            server.recv_timeout(Duration::from_millis(300)).unwrap();
+        });
+
+        // Heartbeat acknowledgments are handled even though no data arrives.
+        assert!(client.recv_timeout(Duration::from_millis(200)).unwrap().is_none());
+        assert!(client.get_current_ping().is_some());
+        server_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_recv_timeout_restores_non_blocking_mode() {
+        let (_client, mut server) = pair();
+        server.recv_timeout(Duration::from_millis(20)).unwrap();
+
+        let started = Instant::now();
+        assert!(server.recv().unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_millis(10));
+        assert_eq!(server.socket().unwrap().read_timeout().unwrap(), None);
+    }
+}
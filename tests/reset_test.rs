@@ -0,0 +1,82 @@
+use reudp::{Message, MessageType, Mode, ReUDP};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polls both ends until `server` delivers a message, for up to `timeout`.
+fn deliver_to_server(client: &mut ReUDP, server: &mut ReUDP, timeout: Duration) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        client.recv().unwrap();
+        if let Some(received) = server.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+fn pair() -> (ReUDP, ReUDP) {
+    let server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+    (client, server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reset_resynchronizes_both_ends() {
+        let (mut client, mut server) = pair();
+        for i in 0..3u8 {
+            client.send(vec![i], true).unwrap();
+            assert!(deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).is_some());
+        }
+        assert_eq!(server.recv_sequence(), 3);
+
+        client.reset_sequence().unwrap();
+        assert!(client.is_reset_pending());
+        assert_eq!(client.send_sequence(), 0);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.is_reset_pending() && Instant::now() < deadline {
+            server.recv().unwrap();
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!client.is_reset_pending());
+        assert_eq!(server.recv_sequence(), 0);
+        assert_eq!(server.send_sequence(), 0);
+
+        client.send(b"after reset", true).unwrap();
+        let (_, payload) = deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, b"after reset");
+    }
+
+    #[test]
+    fn test_resent_reset_is_applied_once() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let reset = Message::new(0, MessageType::Reset, 42u64.to_be_bytes().to_vec()).to_bytes();
+
+        peer.send_to(&reset, server_addr).unwrap();
+        peer.send_to(&Message::new(0, MessageType::Data, b"first".to_vec()).to_bytes(), server_addr).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.recv_sequence() == 0 && Instant::now() < deadline {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(server.recv_sequence(), 1);
+
+        // A late copy of the same reset must not rewind the sequence again.
+        peer.send_to(&reset, server_addr).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        for _ in 0..10 {
+            server.recv().unwrap();
+        }
+        assert_eq!(server.recv_sequence(), 1);
+    }
+}
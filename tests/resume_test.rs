@@ -0,0 +1,145 @@
+use reudp::{Event, Mode, ReUDP, ReUDPConfig, ReUDPError, SessionToken};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Runs `server` on its own thread until `stop` is set, then hands it back.
+fn spawn_server(mut server: ReUDP, stop: Arc<AtomicBool>) -> JoinHandle<ReUDP> {
+    thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        server
+    })
+}
+
+/// Connects a fresh client to `server`, returning the server, the client, the
+/// token it was issued and its address.
+fn first_session(server: ReUDP) -> (ReUDP, ReUDP, SessionToken, SocketAddr) {
+    let server_addr = server.local_addr().unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    let server_thread = spawn_server(server, Arc::clone(&stop));
+    let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), client_config()).unwrap();
+    client.connect().unwrap();
+    stop.store(true, Ordering::SeqCst);
+    let token = client.session_token().unwrap();
+    let client_addr = client.local_addr().unwrap();
+    (server_thread.join().unwrap(), client, token, client_addr)
+}
+
+fn client_config() -> ReUDPConfig {
+    ReUDPConfig::default().connect_probe_size(None)
+}
+
+/// Keeps calling `recv` on both instances until `condition` holds or a second passes.
+fn pump_until(client: &mut ReUDP, server: &mut ReUDP, condition: impl Fn(&ReUDP, &ReUDP) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while !condition(client, server) && Instant::now() < deadline {
+        server.recv().unwrap();
+        client.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_sends_data_without_waiting() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let (mut server, _first, token, old_addr) = first_session(server);
+
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), client_config()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        client.set_session_token(token);
+        client.resume().unwrap();
+        assert!(client.is_connected());
+        client.send(b"hello", true).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = server.recv().unwrap();
+        }
+        assert_eq!(received, Some((client_addr, b"hello".to_vec())));
+        assert_eq!(
+            server.poll_event(),
+            Some(Event::SessionResumed {
+                addr: client_addr,
+                previous: old_addr
+            })
+        );
+
+        // The server hands out a fresh token with its answer.
+        pump_until(&mut client, &mut server, |client, _| client.session_token() != Some(token));
+        assert!(client.session_token().is_some());
+        assert_ne!(client.session_token(), Some(token));
+        assert!(client.is_connected());
+    }
+
+    #[test]
+    fn test_token_is_single_use() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let (mut server, _first, token, _) = first_session(server);
+
+        for resumed in [true, false] {
+            let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), client_config()).unwrap();
+            client.set_session_token(token);
+            client.resume().unwrap();
+            pump_until(&mut client, &mut server, |client, _| client.session_token() != Some(token));
+            assert_ne!(client.session_token(), Some(token));
+            let event = server.poll_event();
+            assert_eq!(matches!(event, Some(Event::SessionResumed { .. })), resumed, "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn test_expired_token_falls_back_to_handshake() {
+        let config = ReUDPConfig::default().session_token_ttl(Duration::from_millis(50));
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let (mut server, _first, token, _) = first_session(server);
+        thread::sleep(Duration::from_millis(100));
+
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), client_config()).unwrap();
+        client.set_session_token(token);
+        client.resume().unwrap();
+        pump_until(&mut client, &mut server, |client, _| client.session_token() != Some(token));
+
+        // Accepted all the same, but not as a resumed session.
+        assert_ne!(client.session_token(), Some(token));
+        assert!(client.is_connected());
+        assert_eq!(server.poll_event(), None);
+    }
+
+    #[test]
+    fn test_refused_resume_is_reported_by_recv() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default().max_clients(1)).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        // The first session keeps the only slot.
+        let (mut server, _first, token, _) = first_session(server);
+
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), client_config()).unwrap();
+        client.set_session_token(token);
+        client.resume().unwrap();
+        assert!(client.is_connected());
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut result = Ok(None);
+        while matches!(result, Ok(None)) && Instant::now() < deadline {
+            server.recv().unwrap();
+            result = client.recv();
+        }
+        match result {
+            Err(ReUDPError::ConnectionRefused { reason }) => assert_eq!(reason, b"server full"),
+            other => panic!("expected ConnectionRefused, got {:?}", other),
+        }
+        assert!(!client.is_connected());
+    }
+}
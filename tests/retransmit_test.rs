@@ -0,0 +1,90 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default().resend_interval(Duration::from_millis(20))
+}
+
+/// Binds a raw peer socket that never acknowledges anything on its own.
+fn raw_peer() -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_millis(300))).unwrap();
+    socket
+}
+
+/// Counts the copies of the Data message with `sequence` reaching `socket`
+/// until it stays quiet for the read timeout.
+fn copies_received(socket: &UdpSocket, sequence: u64) -> usize {
+    let mut buf = [0; 1024];
+    let mut copies = 0;
+    while let Ok(len) = socket.recv(&mut buf) {
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        if message.message_type == MessageType::Data && message.sequence == sequence {
+            copies += 1;
+            if copies == 3 {
+                break;
+            }
+        }
+    }
+    copies
+}
+
+/// Calls `recv` on `reudp` until the queue of unacknowledged messages is empty.
+fn wait_for_acks(reudp: &mut ReUDP) {
+    for _ in 0..200 {
+        if reudp.pending_acks() == 0 {
+            return;
+        }
+        reudp.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("{} messages still unacknowledged", reudp.pending_acks());
+}
+
+fn ack(socket: &UdpSocket, sequence: u64, to: SocketAddr) {
+    let ack = Message::new(sequence, MessageType::Ack, vec![]);
+    socket.send_to(&ack.to_bytes(), to).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_sent_after_start_are_retransmitted() {
+        let server = raw_peer();
+        let mode = Mode::Client(server.local_addr().unwrap());
+        let mut client = ReUDP::with_config("127.0.0.1:0", mode, config()).unwrap();
+        // Let the heartbeat thread run before the message is queued.
+        thread::sleep(Duration::from_millis(50));
+
+        client.send(b"again", true).unwrap();
+        assert_eq!(copies_received(&server, 0), 3);
+
+        ack(&server, 0, client.local_addr().unwrap());
+        wait_for_acks(&mut client);
+    }
+
+    #[test]
+    fn test_clients_joining_after_start_get_retransmissions() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let client = raw_peer();
+        let heartbeat = Message::new(0, MessageType::Heartbeat, vec![]);
+        client.send_to(&heartbeat.to_bytes(), server_addr).unwrap();
+        while server.client_addrs().is_empty() {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        server.send(b"welcome", true).unwrap();
+        assert_eq!(copies_received(&client, 0), 3);
+
+        ack(&client, 0, server_addr);
+        wait_for_acks(&mut server);
+    }
+}
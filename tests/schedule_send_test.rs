@@ -0,0 +1,56 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{deliver_to_server, pair};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduled_send_waits_for_its_time() {
+        let (mut client, mut server) = pair();
+        let send_at = Instant::now() + Duration::from_millis(100);
+        client.schedule_send(b"later".to_vec(), true, send_at);
+        assert_eq!(client.pending_scheduled(), 1);
+
+        assert!(deliver_to_server(&mut client, &mut server, Duration::from_millis(50)).is_none());
+        assert_eq!(client.pending_scheduled(), 1);
+
+        let (_, payload) = deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, b"later");
+        assert!(Instant::now() >= send_at);
+        assert_eq!(client.pending_scheduled(), 0);
+    }
+
+    #[test]
+    fn test_scheduled_sends_go_out_in_time_order() {
+        let (mut client, mut server) = pair();
+        let now = Instant::now();
+        client.schedule_send(b"third".to_vec(), true, now + Duration::from_millis(40));
+        client.schedule_send(b"first".to_vec(), true, now + Duration::from_millis(20));
+        client.schedule_send(b"second".to_vec(), true, now + Duration::from_millis(20));
+
+        for expected in [&b"first"[..], b"second", b"third"] {
+            let (_, payload) = deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+            assert_eq!(payload, expected);
+        }
+    }
+
+    #[test]
+    fn test_cancel_scheduled() {
+        let (mut client, mut server) = pair();
+        let now = Instant::now();
+        let cancelled = client.schedule_send(b"cancelled".to_vec(), true, now + Duration::from_millis(20));
+        client.schedule_send(b"kept".to_vec(), true, now + Duration::from_millis(40));
+
+        assert!(client.cancel_scheduled(cancelled));
+        assert!(!client.cancel_scheduled(cancelled));
+        assert_eq!(client.pending_scheduled(), 1);
+
+        let (_, payload) = deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, b"kept");
+        assert!(deliver_to_server(&mut client, &mut server, Duration::from_millis(100)).is_none());
+    }
+}
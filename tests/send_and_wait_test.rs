@@ -0,0 +1,93 @@
+use reudp::{Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// Wakes the thread blocked in `block_on`.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal executor running `future` to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+/// Runs a server for `duration` that answers every message with `noise` and
+/// then the message prefixed with "re:".
+fn spawn_server(mut server: ReUDP, duration: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            if let Some((_, data)) = server.recv_timeout(Duration::from_millis(10)).unwrap() {
+                server.send(b"noise", true).unwrap();
+                server.send([b"re:".as_slice(), &data].concat(), true).unwrap();
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_and_wait_resolves_to_matching_reply() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let handle = spawn_server(server, Duration::from_millis(500));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+
+        let reply = block_on(client.send_and_wait(
+            b"ping",
+            |payload: &[u8]| payload.starts_with(b"re:"),
+            Duration::from_secs(1),
+        ));
+        assert_eq!(reply.unwrap(), b"re:ping");
+
+        // The reply that didn't match is still there for recv.
+        let (_, data) = client.recv_timeout(Duration::from_millis(100)).unwrap().unwrap();
+        assert_eq!(data, b"noise");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_send_and_wait_times_out() {
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), ReUDPConfig::default()).unwrap();
+
+        let start = Instant::now();
+        let reply = block_on(client.send_and_wait(b"ping", |_: &[u8]| true, Duration::from_millis(100)));
+        assert!(matches!(reply, Err(ReUDPError::Timeout)));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_send_and_wait_is_lazy() {
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        silent.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(silent.local_addr().unwrap()), ReUDPConfig::default()).unwrap();
+
+        drop(client.send_and_wait(b"never sent", |_: &[u8]| true, Duration::from_secs(1)));
+        let mut buf = [0; 64];
+        assert!(silent.recv_from(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polls `reudp` for delivered messages for `wait`, returning their data.
+fn recv_for(reudp: &mut ReUDP, wait: Duration) -> Vec<Vec<u8>> {
+    let deadline = Instant::now() + wait;
+    let mut received = Vec::new();
+    while Instant::now() < deadline {
+        while let Some((_, data)) = reudp.recv().unwrap() {
+            received.push(data);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+/// Returns a server and a client connected to it.
+fn pair() -> (ReUDP, ReUDP, SocketAddr) {
+    let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+    (server, client, server_addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_accepts_any_byte_container() {
+        let (mut server, mut client, _) = pair();
+        let borrowed: &[u8] = b"slice";
+        client.send(borrowed, true).unwrap();
+        client.send(String::from("string"), true).unwrap();
+        client.send("str", false).unwrap();
+        client.send([1u8, 2, 3], false).unwrap();
+        client.send(b"vec", true).unwrap();
+
+        let received = recv_for(&mut server, Duration::from_millis(200));
+        assert_eq!(
+            received,
+            vec![b"slice".to_vec(), b"string".to_vec(), b"str".to_vec(), vec![1, 2, 3], b"vec".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_timestamped_and_channel_sends_accept_slices() {
+        let (mut server, mut client, _) = pair();
+        let data = b"payload".to_vec();
+        client.send_timestamped(&data, true).unwrap();
+        client.send_ordered_channel(1, &data[..3], true).unwrap();
+        client.send_unordered_channel(2, &data[3..], true).unwrap();
+
+        let received = recv_for(&mut server, Duration::from_millis(200));
+        assert_eq!(received, vec![b"payload".to_vec(), b"pay".to_vec(), b"load".to_vec()]);
+    }
+
+    #[test]
+    fn test_oversized_slice_is_refused() {
+        let (_server, mut client, _) = pair();
+        let data = vec![0u8; 2048];
+        assert!(client.send(&data[..], false).is_err());
+        assert!(client.send_ordered_channel(0, &data[..], false).is_err());
+        client.send(&data[..100], false).unwrap();
+    }
+}
@@ -0,0 +1,15 @@
+use reudp::{Event, Incoming, Message, ProbeResult, ReUDP, ReUDPConfig, ReUDPError, ReUDPHandle, RecvHalf, SendHalf, Statistics, TimeoutFuture};
+use static_assertions::assert_impl_all;
+
+assert_impl_all!(ReUDP: Send, Sync);
+assert_impl_all!(ReUDPConfig: Send, Sync);
+assert_impl_all!(Message: Send, Sync);
+assert_impl_all!(Statistics: Send, Sync);
+assert_impl_all!(ReUDPError: Send, Sync);
+assert_impl_all!(Event: Send, Sync);
+assert_impl_all!(ProbeResult: Send, Sync);
+assert_impl_all!(Incoming<'static>: Send, Sync);
+assert_impl_all!(ReUDPHandle: Send, Sync, Clone);
+assert_impl_all!(SendHalf: Send, Sync);
+assert_impl_all!(RecvHalf: Send, Sync);
+assert_impl_all!(TimeoutFuture<'static, Vec<u8>, fn(&[u8]) -> bool>: Send, Sync);
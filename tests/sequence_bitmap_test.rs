@@ -0,0 +1,66 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+/// Sends raw messages of `message_type` with the given sequence numbers from `socket` to `reudp`.
+fn send_raw(socket: &UdpSocket, reudp: &ReUDP, message_type: MessageType, sequences: &[u64]) {
+    for &sequence in sequences {
+        let message = Message::new(sequence, message_type.clone(), vec![]);
+        socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+    }
+}
+
+/// Creates a client of a plain socket standing in for the server.
+fn client_of_fake_server() -> (ReUDP, UdpSocket) {
+    let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let client = ReUDP::with_config(
+        "127.0.0.1:0",
+        Mode::Client(fake_server.local_addr().unwrap()),
+        ReUDPConfig::default(),
+    )
+    .unwrap();
+    (client, fake_server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_sequence_bitmap_shows_missing_sequences() {
+        let (mut client, fake_server) = client_of_fake_server();
+        send_raw(&fake_server, &client, MessageType::Data, &[0, 2, 4, 5]);
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(client.recv_all().unwrap().len(), 1);
+        assert_eq!(client.recv_sequence(), 1);
+        assert_eq!(client.recv_sequence_bitmap(6), vec![false, true, false, true, true, false]);
+
+        send_raw(&fake_server, &client, MessageType::Data, &[1, 3]);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(client.recv_all().unwrap().len(), 5);
+        assert_eq!(client.recv_sequence_bitmap(3), vec![false; 3]);
+    }
+
+    #[test]
+    fn test_unacked_sequence_bitmap_shows_pending_acks() {
+        let (mut client, fake_server) = client_of_fake_server();
+        for _ in 0..5 {
+            client.send(b"reliable", true).unwrap();
+        }
+        assert_eq!(client.unacked_sequence_bitmap(5), vec![true; 5]);
+
+        send_raw(&fake_server, &client, MessageType::Ack, &[1, 3]);
+        thread::sleep(Duration::from_millis(50));
+        client.recv_all().unwrap();
+
+        assert_eq!(client.unacked_sequence_bitmap(5), vec![true, false, true, false, true]);
+        // Sequence numbers before the first one sent are never pending.
+        assert_eq!(
+            client.unacked_sequence_bitmap(8),
+            vec![false, false, false, true, false, true, false, true]
+        );
+        assert!(client.unacked_sequence_bitmap(0).is_empty());
+    }
+}
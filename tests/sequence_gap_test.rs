@@ -0,0 +1,52 @@
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Gaps reported to the callback, as `(expected, received)`.
+type Gaps = Arc<Mutex<Vec<(u64, u64)>>>;
+
+/// Sends raw Data messages with the given sequence numbers from `socket` to `reudp`.
+fn send_raw(socket: &UdpSocket, reudp: &ReUDP, sequences: &[u64]) {
+    for &sequence in sequences {
+        let message = Message::new(sequence, MessageType::Data, vec![]);
+        socket.send_to(&message.to_bytes(), reudp.local_addr().unwrap()).unwrap();
+    }
+}
+
+/// Creates a server recording the gaps its callback is told about.
+fn server_with_gaps() -> (ReUDP, Gaps) {
+    let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+    let gaps = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&gaps);
+    server.on_sequence_gap(move |expected, received| recorded.lock().unwrap().push((expected, received)));
+    (server, gaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_callback_fires_once_per_gap() {
+        let (mut server, gaps) = server_with_gaps();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[0, 3, 4, 7, 1, 2, 5, 6, 8]);
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(server.recv_all().unwrap().len(), 9);
+        assert_eq!(*gaps.lock().unwrap(), vec![(1, 3), (5, 7)]);
+    }
+
+    #[test]
+    fn test_gap_callback_ignores_in_order_and_duplicate_messages() {
+        let (mut server, gaps) = server_with_gaps();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_raw(&socket, &server, &[0, 1, 1, 0, 2]);
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(server.recv_all().unwrap().len(), 3);
+        assert!(gaps.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use reudp::{Mode, ReUDP};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polls both ends until `target` delivers a message, for up to a second.
+fn deliver(target: &mut ReUDP, other: &mut ReUDP) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        other.recv().unwrap();
+        if let Some(received) = target.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+fn pair() -> (ReUDP, ReUDP) {
+    let server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+    (client, server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_rewinds_both_ends() {
+        let (mut client, mut server) = pair();
+        for payload in [b"one", b"two"] {
+            client.send(payload, true).unwrap();
+            assert!(deliver(&mut server, &mut client).is_some());
+        }
+        server.send(b"reply", true).unwrap();
+        assert!(deliver(&mut client, &mut server).is_some());
+
+        let snapshot = client.take_sequence_snapshot();
+        assert_eq!((snapshot.send_sequence(), snapshot.recv_sequence()), (2, 1));
+
+        client.send(b"aborted", true).unwrap();
+        assert!(deliver(&mut server, &mut client).is_some());
+        server.send(b"aborted reply", true).unwrap();
+        assert!(deliver(&mut client, &mut server).is_some());
+
+        client.restore_sequence_snapshot(snapshot).unwrap();
+        assert_eq!(client.take_sequence_snapshot(), snapshot);
+        assert!(client.is_reset_pending());
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.is_reset_pending() && Instant::now() < deadline {
+            server.recv().unwrap();
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!client.is_reset_pending());
+        assert_eq!((server.send_sequence(), server.recv_sequence()), (1, 2));
+
+        client.send(b"retried", true).unwrap();
+        assert_eq!(deliver(&mut server, &mut client).unwrap().1, b"retried");
+        server.send(b"retried reply", true).unwrap();
+        assert_eq!(deliver(&mut client, &mut server).unwrap().1, b"retried reply");
+    }
+
+    #[test]
+    fn test_rollback_drops_messages_sent_since_the_snapshot() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::new(
+            "127.0.0.1:0",
+            Mode::Client(silent.local_addr().unwrap()),
+            Duration::from_secs(1),
+            1024,
+        )
+        .unwrap();
+        client.send(b"kept", true).unwrap();
+        let snapshot = client.take_sequence_snapshot();
+        client.send(b"dropped", true).unwrap();
+        client.send(b"dropped too", true).unwrap();
+        assert_eq!(client.pending_acks(), 3);
+
+        client.restore_sequence_snapshot(snapshot).unwrap();
+        assert_eq!(client.pending_acks(), 1);
+        assert_eq!(client.send_sequence(), 1);
+    }
+}
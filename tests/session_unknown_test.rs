@@ -0,0 +1,66 @@
+mod common;
+
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPError};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::deliver_to_server;
+
+/// Plays the part of a server for a while: acknowledges every Data message it
+/// receives until `count` have been seen.
+fn ack_data(server: &UdpSocket, count: usize) {
+    let mut buf = [0; 1024];
+    let mut seen = 0;
+    while seen < count {
+        let (len, addr) = server.recv_from(&mut buf).unwrap();
+        let message = Message::from_bytes(&buf[..len]).unwrap();
+        if message.message_type == MessageType::Data {
+            let ack = Message::new(message.sequence, MessageType::Ack, vec![]);
+            server.send_to(&ack.to_bytes(), addr).unwrap();
+            seen += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_recovers_after_server_restart() {
+        let old_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        old_server.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let server_addr = old_server.local_addr().unwrap();
+
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+        // Let a long session go by first.
+        for _ in 0..1100 {
+            client.send(b"before restart", true).unwrap();
+            ack_data(&old_server, 1);
+            while client.pending_acks() > 0 {
+                client.recv().unwrap();
+            }
+        }
+
+        // The server restarts on the same address and knows nothing of the session.
+        drop(old_server);
+        let mut server = ReUDP::new(&server_addr.to_string(), Mode::Server, Duration::from_secs(1), 1024).unwrap();
+
+        client.send(b"lost", true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut error = None;
+        while error.is_none() && Instant::now() < deadline {
+            assert!(server.recv().unwrap().is_none());
+            error = client.recv().err();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(matches!(error, Some(ReUDPError::ConnectionLost)));
+        assert_eq!(client.send_sequence(), 0);
+        assert_eq!(client.pending_acks(), 0);
+
+        client.send(b"after restart", true).unwrap();
+        let (_, payload) = deliver_to_server(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, b"after restart");
+    }
+}
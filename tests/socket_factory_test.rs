@@ -0,0 +1,50 @@
+use reudp::{
+    DefaultSocketFactory, FailingSocketFactory, Mode, PreBoundSocketFactory, ReUDP, ReUDPConfig, SocketFactory,
+};
+use std::io;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Binds like the default factory and counts how often it was asked to.
+#[derive(Debug, Default)]
+struct CountingSocketFactory(Arc<AtomicUsize>);
+
+impl SocketFactory for CountingSocketFactory {
+    fn create(&self, addr: &str) -> io::Result<UdpSocket> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        DefaultSocketFactory.create(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failing_factory_error_is_returned() {
+        let config = ReUDPConfig::default().socket_factory(Box::new(FailingSocketFactory));
+        let error = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+    }
+
+    #[test]
+    fn test_pre_bound_socket_is_used() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let config = ReUDPConfig::default().socket_factory(Box::new(PreBoundSocketFactory(socket)));
+        // The local address is ignored in favor of the socket's.
+        let reudp = ReUDP::with_config("0.0.0.0:1", Mode::Server, config).unwrap();
+        assert_eq!(reudp.local_addr().unwrap(), addr);
+    }
+
+    #[test]
+    fn test_custom_factory_is_called_with_local_address() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let factory = CountingSocketFactory(Arc::clone(&calls));
+        let config = ReUDPConfig::default().socket_factory(Box::new(factory));
+        let reudp = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(reudp.local_addr().unwrap().ip().is_loopback());
+    }
+}
@@ -0,0 +1,39 @@
+use reudp::{Event, Mode, ReUDP, ReUDPConfig, SocketOption};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_options_are_applied() {
+        let config = ReUDPConfig::default()
+            .recv_buffer_bytes(65536)
+            .send_buffer_bytes(65536)
+            .ttl(7)
+            .tos(0xb8);
+        let mut reudp = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+
+        // The OS may round buffer sizes up (Linux doubles them).
+        assert!(reudp.recv_buffer_bytes().unwrap() >= 65536);
+        assert!(reudp.send_buffer_bytes().unwrap() >= 65536);
+        assert_eq!(reudp.ttl().unwrap(), 7);
+        assert_eq!(reudp.tos().unwrap(), 0xb8);
+        assert!(reudp.poll_event().is_none());
+    }
+
+    #[test]
+    fn test_failed_socket_option_is_reported_as_event() {
+        let config = ReUDPConfig::default().ttl(1000);
+        let mut reudp = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).unwrap();
+        match reudp.poll_event() {
+            Some(Event::SocketOptionFailed { option, .. }) => assert_eq!(option, SocketOption::Ttl),
+            other => panic!("expected SocketOptionFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_required_socket_option_fails_construction() {
+        let config = ReUDPConfig::default().ttl(1000).socket_options_required(true);
+        assert!(ReUDP::with_config("127.0.0.1:0", Mode::Server, config).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use reudp::{Mode, ReUDP, ReUDPConfig, ReUDPError, Socks5Config};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A SOCKS5 proxy serving a single UDP association for IPv4 peers.
+struct FakeProxy {
+    addr: SocketAddr,
+    /// The control connection, once the client opened it
+    control: Arc<Mutex<Option<TcpStream>>>,
+    /// Number of datagrams relayed either way
+    relayed: Arc<AtomicUsize>,
+}
+
+impl FakeProxy {
+    /// Starts a proxy, requiring `credentials` if given.
+    fn start(credentials: Option<(&'static str, &'static str)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let control = Arc::new(Mutex::new(None));
+        let relayed = Arc::new(AtomicUsize::new(0));
+        let (proxy_control, proxy_relayed) = (Arc::clone(&control), Arc::clone(&relayed));
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut greeting = [0; 2];
+            stream.read_exact(&mut greeting).unwrap();
+            let mut methods = vec![0; greeting[1] as usize];
+            stream.read_exact(&mut methods).unwrap();
+            match credentials {
+                Some(expected) if methods.contains(&2) => {
+                    stream.write_all(&[5, 2]).unwrap();
+                    let (username, password) = read_credentials(&mut stream);
+                    let accepted = (username.as_str(), password.as_str()) == expected;
+                    stream.write_all(&[1, if accepted { 0 } else { 1 }]).unwrap();
+                    if !accepted {
+                        return;
+                    }
+                }
+                Some(_) => {
+                    stream.write_all(&[5, 0xff]).unwrap();
+                    return;
+                }
+                None => stream.write_all(&[5, 0]).unwrap(),
+            }
+
+            let mut request = [0; 10];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!(&request[..4], &[5, 3, 0, 1]);
+            let client = SocketAddr::from((
+                Ipv4Addr::new(request[4], request[5], request[6], request[7]),
+                u16::from_be_bytes([request[8], request[9]]),
+            ));
+            let relay = UdpSocket::bind("127.0.0.1:0").unwrap();
+            // An unspecified address stands for the proxy's host.
+            let port = relay.local_addr().unwrap().port().to_be_bytes();
+            stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, port[0], port[1]]).unwrap();
+            *proxy_control.lock().unwrap() = Some(stream);
+            run_relay(relay, client, proxy_relayed);
+        });
+        Self { addr, control, relayed }
+    }
+
+    /// Closes the control connection, as a proxy ending the association would.
+    fn close_control(&self) {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Some(stream) = self.control.lock().unwrap().take() {
+                stream.shutdown(Shutdown::Both).unwrap();
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("no control connection to close");
+    }
+}
+
+/// Reads a username/password authentication request (RFC 1929).
+fn read_credentials(stream: &mut TcpStream) -> (String, String) {
+    let mut header = [0; 2];
+    stream.read_exact(&mut header).unwrap();
+    let mut username = vec![0; header[1] as usize];
+    stream.read_exact(&mut username).unwrap();
+    let mut len = [0; 1];
+    stream.read_exact(&mut len).unwrap();
+    let mut password = vec![0; len[0] as usize];
+    stream.read_exact(&mut password).unwrap();
+    (String::from_utf8(username).unwrap(), String::from_utf8(password).unwrap())
+}
+
+/// Passes datagrams from `client` on to the peer their header names, and
+/// datagrams from anyone else back to `client` with such a header.
+fn run_relay(relay: UdpSocket, client: SocketAddr, relayed: Arc<AtomicUsize>) {
+    let mut buf = [0; 2048];
+    while let Ok((len, from)) = relay.recv_from(&mut buf) {
+        if from == client {
+            assert_eq!(&buf[..4], &[0, 0, 0, 1]);
+            let to = SocketAddr::from((
+                Ipv4Addr::new(buf[4], buf[5], buf[6], buf[7]),
+                u16::from_be_bytes([buf[8], buf[9]]),
+            ));
+            let _ = relay.send_to(&buf[10..len], to);
+        } else {
+            let SocketAddr::V4(from) = from else { continue };
+            let mut datagram = vec![0, 0, 0, 1];
+            datagram.extend_from_slice(&from.ip().octets());
+            datagram.extend_from_slice(&from.port().to_be_bytes());
+            datagram.extend_from_slice(&buf[..len]);
+            let _ = relay.send_to(&datagram, client);
+        }
+        relayed.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Creates a server and a client reaching it through `proxy`.
+fn pair_through(proxy: Socks5Config) -> Result<(ReUDP, ReUDP), std::io::Error> {
+    let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default())?;
+    let config = ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(50))
+        .proxy(proxy);
+    let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr()?), config)?;
+    Ok((client, server))
+}
+
+/// Polls both ends until `target` delivers a message, for up to a second.
+fn recv_on(target: &mut ReUDP, other: &mut ReUDP) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        let _ = other.recv();
+        if let Some(received) = target.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_through_the_relay() {
+        let proxy = FakeProxy::start(None);
+        let (mut client, mut server) = pair_through(Socks5Config::new(proxy.addr)).unwrap();
+        let handshake = thread::spawn(move || {
+            client.connect().unwrap();
+            client
+        });
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.client_count() == 0 && Instant::now() < deadline {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        let mut client = handshake.join().unwrap();
+        assert!(client.is_connected());
+
+        client.send(b"hello", true).unwrap();
+        let (from, payload) = recv_on(&mut server, &mut client).unwrap();
+        assert_eq!(payload, b"hello");
+        // The server only ever sees the relay.
+        assert_ne!(from, client.local_addr().unwrap());
+
+        server.send(b"hi", true).unwrap();
+        let (from, payload) = recv_on(&mut client, &mut server).unwrap();
+        assert_eq!(payload, b"hi");
+        assert_eq!(from, server.local_addr().unwrap());
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while (client.pending_acks() > 0 || client.srtt().is_none()) && Instant::now() < deadline {
+            let _ = server.recv();
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(client.pending_acks(), 0);
+        assert!(client.srtt().is_some());
+        assert!(proxy.relayed.load(Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn test_closed_control_connection_is_connection_lost() {
+        let proxy = FakeProxy::start(None);
+        let (mut client, _server) = pair_through(Socks5Config::new(proxy.addr)).unwrap();
+        proxy.close_control();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let result = loop {
+            match client.recv() {
+                Ok(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(1)),
+                result => break result,
+            }
+        };
+        assert!(matches!(result, Err(ReUDPError::ConnectionLost)));
+        assert!(!client.is_running());
+    }
+
+    #[test]
+    fn test_messages_ends_once_the_proxy_is_lost() {
+        let proxy = FakeProxy::start(None);
+        let (mut client, _server) = pair_through(Socks5Config::new(proxy.addr)).unwrap();
+        proxy.close_control();
+        thread::sleep(Duration::from_millis(100));
+
+        // Every receive fails from now on, so the iterator has to stop by itself.
+        let received: Vec<_> = client.messages().take(10).collect();
+        assert_eq!(received.len(), 1);
+        assert!(matches!(received[0], Err(ReUDPError::ConnectionLost)));
+    }
+
+    #[test]
+    fn test_credentials() {
+        let proxy = FakeProxy::start(Some(("alice", "secret")));
+        pair_through(Socks5Config::new(proxy.addr).credentials("alice", "secret")).unwrap();
+
+        let proxy = FakeProxy::start(Some(("alice", "secret")));
+        let error = pair_through(Socks5Config::new(proxy.addr).credentials("alice", "guess")).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+
+        let proxy = FakeProxy::start(Some(("alice", "secret")));
+        let error = pair_through(Socks5Config::new(proxy.addr)).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_servers_cannot_use_a_proxy() {
+        let proxy = FakeProxy::start(None);
+        let config = ReUDPConfig::default().proxy(Socks5Config::new(proxy.addr));
+        let error = ReUDP::with_config("127.0.0.1:0", Mode::Server, config).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_debug_leaves_out_the_password() {
+        let config = Socks5Config::new("127.0.0.1:1080".parse().unwrap()).credentials("alice", "secret");
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("alice"));
+        assert!(!debug.contains("secret"));
+    }
+}
@@ -0,0 +1,65 @@
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs a server for `duration` that echoes every message back.
+fn spawn_echo_server(mut server: ReUDP, duration: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            if let Some((_, data)) = server.recv_timeout(Duration::from_millis(10)).unwrap() {
+                server.send(data, true).unwrap();
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halves_send_and_receive_concurrently() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let echo = spawn_echo_server(server, Duration::from_secs(1));
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+        // Even in blocking mode, receiving must not hold up sending.
+        client.set_blocking(true).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        let (send_half, recv_half) = client.split();
+        let receiver = thread::spawn(move || {
+            let mut received = Vec::new();
+            let deadline = Instant::now() + Duration::from_secs(1);
+            while received.len() < 20 && Instant::now() < deadline {
+                if let Some((_, data)) = recv_half.recv_timeout(Duration::from_millis(50)).unwrap() {
+                    received.push(data[0]);
+                }
+            }
+            (recv_half, received)
+        });
+        for i in 0..20u8 {
+            send_half.send([i], true).unwrap();
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        let (recv_half, received) = receiver.join().unwrap();
+        assert_eq!(received, (0..20).collect::<Vec<u8>>());
+        let client = send_half.reunite(recv_half).ok().unwrap();
+        assert_eq!(client.local_addr().unwrap(), client_addr);
+        echo.join().unwrap();
+    }
+
+    #[test]
+    fn test_reunite_rejects_halves_of_different_instances() {
+        let first = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let second = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let (first_send, first_recv) = first.split();
+        let (second_send, second_recv) = second.split();
+
+        let (first_send, second_recv) = first_send.reunite(second_recv).err().unwrap();
+        assert!(first_send.reunite(first_recv).is_ok());
+        assert!(second_send.reunite(second_recv).is_ok());
+    }
+}
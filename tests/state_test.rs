@@ -0,0 +1,90 @@
+use reudp::{Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::io;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default().heartbeat_interval(Duration::from_millis(20))
+}
+
+/// Connects `client` while `server` keeps receiving.
+fn connect(mut client: ReUDP, server: &mut ReUDP) -> ReUDP {
+    let handshake = thread::spawn(move || {
+        client.connect().unwrap();
+        client
+    });
+    while !handshake.is_finished() {
+        server.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    handshake.join().unwrap()
+}
+
+/// Polls both ends until `target` delivers a message, for up to a second.
+fn recv_on(target: &mut ReUDP, other: &mut ReUDP) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        let _ = other.recv();
+        if let Some(received) = target.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_picks_up_its_clients_after_a_restart() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+        let mut client = connect(client, &mut server);
+        let client_addr = client.local_addr().unwrap();
+
+        client.send(b"before", true).unwrap();
+        assert_eq!(recv_on(&mut server, &mut client), Some((client_addr, b"before".to_vec())));
+        let state = server.export_state().unwrap();
+        drop(server);
+
+        let mut server = ReUDP::import_state_with_config(&state, config()).unwrap();
+        assert_eq!(server.local_addr().unwrap(), server_addr);
+        assert_eq!(server.client_addrs(), vec![client_addr]);
+
+        client.send(b"after", true).unwrap();
+        assert_eq!(recv_on(&mut server, &mut client), Some((client_addr, b"after".to_vec())));
+        server.send(b"back", true).unwrap();
+        assert_eq!(recv_on(&mut client, &mut server), Some((server_addr, b"back".to_vec())));
+    }
+
+    #[test]
+    fn test_client_keeps_its_session_token() {
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config()).unwrap();
+        client.set_session_token([7; 32]);
+        let client_addr = client.local_addr().unwrap();
+
+        let state = client.export_state().unwrap();
+        drop(client);
+        let client = ReUDP::import_state(&state).unwrap();
+        assert_eq!(client.local_addr().unwrap(), client_addr);
+        assert_eq!(client.session_token(), Some([7; 32]));
+        assert!(!client.is_connected());
+    }
+
+    #[test]
+    fn test_rejects_invalid_state() {
+        let error = ReUDP::import_state(b"not a state").err().unwrap();
+        assert!(matches!(error, ReUDPError::IoError(e) if e.kind() == io::ErrorKind::InvalidData));
+
+        let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let state = server.export_state().unwrap();
+        let error = ReUDP::import_state(&state[..state.len() - 1]).err().unwrap();
+        assert!(matches!(error, ReUDPError::IoError(e) if e.kind() == io::ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,75 @@
+use reudp::{Message, MessageType, Mode, ReUDP};
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Reads datagrams until a Disconnect arrives, skipping other traffic.
+fn recv_disconnect(socket: &UdpSocket) -> bool {
+    let mut buf = [0; 1024];
+    while let Ok(len) = socket.recv(&mut buf) {
+        if Message::from_bytes(&buf[..len]).unwrap().message_type == MessageType::Disconnect {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_disconnects_and_joins_heartbeat_thread() {
+        let server = Arc::new(Mutex::new(ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap()));
+        let server_addr = server.lock().unwrap().local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_thread = {
+            let (server, stop) = (Arc::clone(&server), Arc::clone(&stop));
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    server.lock().unwrap().recv().unwrap();
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        client.connect().unwrap();
+        assert!(server.lock().unwrap().client_addrs().contains(&client_addr));
+
+        // The heartbeat thread is woken up rather than waited out.
+        let started = Instant::now();
+        client.stop().unwrap();
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(!client.is_running());
+        assert!(!client.is_connected());
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.lock().unwrap().client_addrs().contains(&client_addr) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        stop.store(true, Ordering::SeqCst);
+        server_thread.join().unwrap();
+        assert!(!server.lock().unwrap().client_addrs().contains(&client_addr));
+    }
+
+    #[test]
+    fn test_drop_stops_running_server() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let heartbeat = Message::new(0, MessageType::Heartbeat, vec![]);
+        client.send_to(&heartbeat.to_bytes(), server_addr).unwrap();
+        while !server.client_addrs().contains(&client.local_addr().unwrap()) {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        drop(server);
+        assert!(recv_disconnect(&client));
+    }
+}
@@ -0,0 +1,152 @@
+use reudp::{Event, Mode, ReUDP, ReUDPConfig, ReUDPError, Socks5Config, TcpFallback};
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn config(fallback: TcpFallback) -> ReUDPConfig {
+    ReUDPConfig::default()
+        .heartbeat_interval(Duration::from_millis(50))
+        .handshake_retries(1)
+        .handshake_retry_interval(Duration::from_millis(100))
+        .tcp_fallback(fallback)
+}
+
+/// A server address where UDP goes nowhere but TCP is passed on to `server`,
+/// as on a network dropping UDP. Returns the address and the black-hole socket.
+fn udp_blocked(server: SocketAddr) -> (SocketAddr, UdpSocket) {
+    let black_hole = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = black_hole.local_addr().unwrap();
+    let listener = TcpListener::bind(addr).unwrap();
+    thread::spawn(move || {
+        let (client, _) = listener.accept().unwrap();
+        let upstream = TcpStream::connect(server).unwrap();
+        let (mut client_read, mut upstream_write) = (client.try_clone().unwrap(), upstream.try_clone().unwrap());
+        thread::spawn(move || io::copy(&mut client_read, &mut upstream_write));
+        let (mut upstream_read, mut client_write) = (upstream, client);
+        let _ = io::copy(&mut upstream_read, &mut client_write);
+    });
+    (addr, black_hole)
+}
+
+/// Connects `client` while `server` keeps receiving.
+fn connect(client: ReUDP, server: &mut ReUDP) -> (ReUDP, Result<(), ReUDPError>) {
+    let mut client = client;
+    let handshake = thread::spawn(move || {
+        let result = client.connect();
+        (client, result)
+    });
+    while !handshake.is_finished() {
+        server.recv().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    handshake.join().unwrap()
+}
+
+/// Polls both ends until `target` delivers a message, for up to a second.
+fn recv_on(target: &mut ReUDP, other: &mut ReUDP) -> Option<(SocketAddr, Vec<u8>)> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        let _ = other.recv();
+        if let Some(received) = target.recv().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_always_uses_tcp() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config(TcpFallback::Always)).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config(TcpFallback::Always)).unwrap();
+        assert!(client.uses_tcp(server_addr));
+
+        let (mut client, result) = connect(client, &mut server);
+        result.unwrap();
+        client.send(b"hello", true).unwrap();
+        let (from, payload) = recv_on(&mut server, &mut client).unwrap();
+        assert_eq!(payload, b"hello");
+        assert!(server.uses_tcp(from));
+        // The stream is the client's, not its UDP socket.
+        assert_ne!(from, client.local_addr().unwrap());
+
+        server.send(b"hi", true).unwrap();
+        let (from, payload) = recv_on(&mut client, &mut server).unwrap();
+        assert_eq!((from, payload.as_slice()), (server_addr, &b"hi"[..]));
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while (client.pending_acks() > 0 || server.pending_acks() > 0) && Instant::now() < deadline {
+            server.recv().unwrap();
+            client.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!((client.pending_acks(), server.pending_acks()), (0, 0));
+    }
+
+    #[test]
+    fn test_falls_back_when_udp_is_blocked() {
+        let mut server =
+            ReUDP::with_config("127.0.0.1:0", Mode::Server, config(TcpFallback::OnHandshakeTimeout)).unwrap();
+        let (blocked_addr, _black_hole) = udp_blocked(server.local_addr().unwrap());
+        let client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(blocked_addr),
+            config(TcpFallback::OnHandshakeTimeout),
+        )
+        .unwrap();
+        assert!(!client.uses_tcp(blocked_addr));
+
+        let (mut client, result) = connect(client, &mut server);
+        result.unwrap();
+        assert!(client.is_connected());
+        assert!(client.uses_tcp(blocked_addr));
+        assert_eq!(client.poll_event(), Some(Event::FellBackToTcp));
+
+        client.send(b"over tcp", true).unwrap();
+        let (_, payload) = recv_on(&mut server, &mut client).unwrap();
+        assert_eq!(payload, b"over tcp");
+        server.send(b"back", true).unwrap();
+        let (from, payload) = recv_on(&mut client, &mut server).unwrap();
+        assert_eq!((from, payload.as_slice()), (blocked_addr, &b"back"[..]));
+    }
+
+    #[test]
+    fn test_no_fallback_by_default() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config(TcpFallback::Always)).unwrap();
+        let (blocked_addr, _black_hole) = udp_blocked(server.local_addr().unwrap());
+        let client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(blocked_addr), config(TcpFallback::Never)).unwrap();
+
+        let (client, result) = connect(client, &mut server);
+        assert!(matches!(result, Err(ReUDPError::HandshakeTimeout)));
+        assert!(!client.uses_tcp(blocked_addr));
+    }
+
+    #[test]
+    fn test_timeout_when_tcp_is_unreachable_too() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = ReUDP::with_config(
+            "127.0.0.1:0",
+            Mode::Client(silent.local_addr().unwrap()),
+            config(TcpFallback::OnHandshakeTimeout),
+        )
+        .unwrap();
+
+        assert!(matches!(client.connect(), Err(ReUDPError::HandshakeTimeout)));
+        assert_eq!(client.poll_event(), None);
+    }
+
+    #[test]
+    fn test_not_combined_with_a_proxy() {
+        let config = config(TcpFallback::Always).proxy(Socks5Config::new("127.0.0.1:1080".parse().unwrap()));
+        let server_addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let error = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
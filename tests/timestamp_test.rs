@@ -0,0 +1,32 @@
+mod common;
+
+use reudp::{Mode, ReUDP};
+use std::time::{Duration, Instant};
+
+use common::recv_within;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamped_message_reports_latency() {
+        let mut server = ReUDP::new("127.0.0.1:0", Mode::Server, Duration::from_secs(1), 1024).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let mut client = ReUDP::new("127.0.0.1:0", Mode::Client(server_addr), Duration::from_secs(1), 1024).unwrap();
+        assert!(server.last_message_latency().is_none());
+
+        let sent_at = Instant::now();
+        client.send_timestamped(b"timed", true).unwrap();
+        let (_, payload) = recv_within(&mut server, Duration::from_secs(1)).unwrap();
+        let elapsed = sent_at.elapsed();
+        assert_eq!(payload, b"timed");
+        let latency = server.last_message_latency().unwrap();
+        assert!(latency <= elapsed + Duration::from_millis(1), "{:?} > {:?}", latency, elapsed);
+
+        client.send(b"untimed", true).unwrap();
+        let (_, payload) = recv_within(&mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(payload, b"untimed");
+        assert!(server.last_message_latency().is_none());
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use reudp::{Message, MessageType, Mode, ReUDP, ReUDPConfig};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+
+use common::{raw_client, recv_all, recv_data, reudp_client};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_reaches_only_subscribers() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let zone_1 = raw_client(&mut server);
+        let both = raw_client(&mut server);
+        let outsider = raw_client(&mut server);
+        server.subscribe_client(zone_1.local_addr().unwrap(), "zone_1_events").unwrap();
+        server.subscribe_client(both.local_addr().unwrap(), "zone_1_events").unwrap();
+        server.subscribe_client(both.local_addr().unwrap(), "zone_2_events").unwrap();
+
+        assert_eq!(server.publish("zone_1_events", b"boss spawned", false).unwrap(), 2);
+        assert_eq!(recv_data(&zone_1).unwrap().payload, b"boss spawned");
+        assert_eq!(recv_data(&both).unwrap().payload, b"boss spawned");
+        assert!(recv_data(&outsider).is_none());
+
+        server.unsubscribe_client(both.local_addr().unwrap(), "zone_1_events").unwrap();
+        assert_eq!(server.publish("zone_1_events", b"boss defeated", false).unwrap(), 1);
+        assert_eq!(server.publish("zone_2_events", b"rain", false).unwrap(), 1);
+        assert_eq!(server.publish("nobody", b"hello?", false).unwrap(), 0);
+        assert_eq!(recv_data(&zone_1).unwrap().payload, b"boss defeated");
+        assert_eq!(recv_data(&both).unwrap().payload, b"rain");
+    }
+
+    #[test]
+    fn test_publish_does_not_hold_back_other_clients() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut subscriber = reudp_client(&mut server);
+        let mut other = reudp_client(&mut server);
+        server.subscribe_client(subscriber.local_addr().unwrap(), "scores").unwrap();
+
+        assert_eq!(server.publish("scores", b"1-0", true).unwrap(), 1);
+        server.send(b"half time", true).unwrap();
+        assert_eq!(server.publish("scores", b"2-0", true).unwrap(), 1);
+        server.send(b"full time", true).unwrap();
+
+        let received = recv_all(&mut subscriber, Duration::from_millis(200));
+        assert_eq!(received.len(), 4);
+        let scores: Vec<Vec<u8>> = received.into_iter().filter(|data| data.contains(&b'-')).collect();
+        assert_eq!(scores, [b"1-0".to_vec(), b"2-0".to_vec()]);
+        assert_eq!(recv_all(&mut other, Duration::from_millis(200)), [b"half time".to_vec(), b"full time".to_vec()]);
+    }
+
+    #[test]
+    fn test_disconnected_clients_are_unsubscribed() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = raw_client(&mut server);
+        server.subscribe_client(client.local_addr().unwrap(), "lobby").unwrap();
+
+        let disconnect = Message::new(0, MessageType::Disconnect, vec![]);
+        client.send_to(&disconnect.to_bytes(), server_addr).unwrap();
+        while server.client_addrs().contains(&client.local_addr().unwrap()) {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(server.publish("lobby", b"anyone?", false).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_topics_need_a_server_and_a_known_client() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let stranger: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        assert!(server.subscribe_client(stranger, "lobby").is_err());
+        assert!(server.unsubscribe_client(stranger, "lobby").is_ok());
+
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default())
+                .unwrap();
+        assert!(client.subscribe_client(stranger, "lobby").is_err());
+        assert!(client.publish("lobby", b"data", false).is_err());
+    }
+}
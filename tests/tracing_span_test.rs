@@ -0,0 +1,80 @@
+#![cfg(feature = "tracing")]
+
+use reudp::{Mode, ReUDP, ReUDPConfig};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Spans created so far, as `(id, name, parent id)`.
+type Spans = Arc<Mutex<Vec<(u64, &'static str, Option<u64>)>>>;
+
+/// Subscriber recording the spans created and their explicit parents.
+struct SpanRecorder {
+    next_id: AtomicU64,
+    spans: Spans,
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let parent = attributes.parent().map(|parent| parent.into_u64());
+        self.spans.lock().unwrap().push((id, attributes.metadata().name(), parent));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, _: &Event<'_>) {}
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_are_children_of_the_connection_span() {
+        let spans = Spans::default();
+        let recorder = SpanRecorder { next_id: AtomicU64::new(1), spans: Arc::clone(&spans) };
+        tracing::subscriber::set_global_default(recorder).unwrap();
+
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default())
+                .unwrap();
+        client.send(b"hello", true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.recv().unwrap().is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        // The heartbeat threads may not have been scheduled yet.
+        while spans.lock().unwrap().iter().filter(|(_, name, _)| *name == "reudp.heartbeat").count() < 2
+            && Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let spans = spans.lock().unwrap().clone();
+        let connections: Vec<u64> = spans
+            .iter()
+            .filter(|(_, name, _)| *name == "reudp.connection")
+            .map(|(id, _, _)| *id)
+            .collect();
+        assert_eq!(connections.len(), 2);
+        for name in ["reudp.send", "reudp.recv", "reudp.heartbeat"] {
+            let children: Vec<_> = spans.iter().filter(|(_, n, _)| *n == name).collect();
+            assert!(!children.is_empty(), "no {} span", name);
+            for (_, _, parent) in children {
+                assert!(parent.is_some_and(|parent| connections.contains(&parent)), "{} span without connection parent", name);
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use reudp::{ConnectionQuality, Event, Message, MessageType, Mode, ProbeResult, Statistics};
+use static_assertions::assert_impl_all;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+assert_impl_all!(Mode: Debug, Clone, PartialEq, Eq, Hash, Display);
+assert_impl_all!(MessageType: Debug, Clone, PartialEq, Eq, Hash, Display);
+assert_impl_all!(Message: Debug, Clone, PartialEq, Eq, Hash);
+assert_impl_all!(Event: Debug, Clone, PartialEq, Eq, Hash);
+assert_impl_all!(Statistics: Debug, Clone, Default, PartialEq, Eq);
+assert_impl_all!(ProbeResult: Debug, Clone, Copy, PartialEq, Eq);
+assert_impl_all!(ConnectionQuality: Debug, Clone, Copy, PartialEq, Eq, Hash, Display);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Mode::Server.to_string(), "Server");
+        assert_eq!(Mode::Client("127.0.0.1:4000".parse().unwrap()).to_string(), "Client of 127.0.0.1:4000");
+        assert_eq!(MessageType::HeartbeatAck.to_string(), "HeartbeatAck");
+        assert_eq!(MessageType::Unknown(42).to_string(), "Unknown(42)");
+    }
+}
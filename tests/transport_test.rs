@@ -0,0 +1,104 @@
+use reudp::{Mode, ReUDP, ReUDPConfig, Transport, UdpTransport};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A transport over UDP counting the datagrams sent through it.
+struct CountingTransport {
+    inner: UdpTransport,
+    sent: Arc<AtomicUsize>,
+}
+
+impl CountingTransport {
+    fn bind() -> (Self, Arc<AtomicUsize>) {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let transport = Self {
+            inner: UdpTransport::bind("127.0.0.1:0").unwrap(),
+            sent: Arc::clone(&sent),
+        };
+        (transport, sent)
+    }
+}
+
+impl Transport for CountingTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        self.sent.fetch_add(1, Ordering::SeqCst);
+        self.inner.send_to(buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.inner.recv_from(buf)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+fn config() -> ReUDPConfig {
+    ReUDPConfig::default().heartbeat_interval(Duration::from_millis(20))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_over_a_custom_transport() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, config()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let (transport, sent) = CountingTransport::bind();
+        let mut client = ReUDP::with_transport(transport, Mode::Client(server_addr), config()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        assert!(client.socket().is_none());
+
+        let handshake = thread::spawn(move || {
+            client.connect().unwrap();
+            client
+        });
+        while !handshake.is_finished() {
+            server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        let mut client = handshake.join().unwrap();
+        assert!(server.client_addrs().contains(&client_addr));
+
+        client.send(b"hello", true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, Some((client_addr, b"hello".to_vec())));
+
+        server.send(b"hi", true).unwrap();
+        let received = client.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(received, Some((server_addr, b"hi".to_vec())));
+        assert!(sent.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[test]
+    fn test_heartbeats_go_through_the_transport() {
+        let server = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let (transport, sent) = CountingTransport::bind();
+        let _client = ReUDP::with_transport(transport, Mode::Client(server.local_addr().unwrap()), config()).unwrap();
+
+        thread::sleep(Duration::from_millis(150));
+        assert!(sent.load(Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn test_waiting_polls_the_transport() {
+        let (transport, _) = CountingTransport::bind();
+        let mut server = ReUDP::with_transport(transport, Mode::Server, config()).unwrap();
+        assert_eq!(server.recv().unwrap(), None);
+
+        let started = Instant::now();
+        assert_eq!(server.recv_timeout(Duration::from_millis(50)).unwrap(), None);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+}
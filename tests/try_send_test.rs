@@ -0,0 +1,72 @@
+mod common;
+
+use reudp::{ReUDP, ReUDPConfig};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_send_delivers() {
+        let (mut client, mut server) = pair();
+        assert!(client.try_send(b"hello", true).unwrap());
+        assert_eq!(client.send_sequence(), 1);
+        assert_eq!(client.pending_acks(), 1);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = server.recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received.unwrap().1, b"hello");
+    }
+
+    #[test]
+    fn test_try_send_skips_the_batch() {
+        let (mut client, _server) = pair();
+        client.begin_batch();
+        assert!(client.try_send(b"now", false).unwrap());
+        assert_eq!(client.flush().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_try_send_does_not_block_in_blocking_mode() {
+        let (mut client, _server) = pair();
+        client.set_blocking(true).unwrap();
+        assert!(client.try_send(b"hello", false).unwrap());
+        assert!(client.is_blocking());
+    }
+
+    /// Unix datagram sockets refuse to send while the receiver's queue is full,
+    /// where UDP would drop the datagram instead.
+    #[cfg(unix)]
+    #[test]
+    fn test_full_send_buffer() {
+        use reudp::ReUDPError;
+
+        let dir = std::env::temp_dir();
+        let server_path = dir.join(format!("reudp-try-send-server-{}.sock", std::process::id()));
+        let client_path = dir.join(format!("reudp-try-send-client-{}.sock", std::process::id()));
+        let mut server = ReUDP::new_unix(&server_path, None, ReUDPConfig::default()).unwrap();
+        let mut client = ReUDP::new_unix(&client_path, Some(&server_path), ReUDPConfig::default()).unwrap();
+
+        let mut sent = 0;
+        while client.try_send(vec![0; 512], true).unwrap() {
+            sent += 1;
+            assert!(sent < 100_000, "the send buffer never filled up");
+        }
+        assert_eq!(client.send_sequence(), sent);
+        assert_eq!(client.pending_acks() as u64, sent);
+        assert!(matches!(client.send(vec![0; 512], true), Err(ReUDPError::WouldBlock)));
+        assert_eq!(client.send_sequence(), sent);
+
+        while server.recv().unwrap().is_some() {}
+        assert!(client.try_send(vec![0; 512], true).unwrap());
+        assert_eq!(client.send_sequence(), sent + 1);
+    }
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use reudp::{ReUDP, ReUDPError};
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::pair;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PlayerMoved {
+    id: u32,
+    x: f32,
+    y: f32,
+    name: String,
+}
+
+/// Polls `reudp` with `recv_typed` until something other than `Ok(None)` comes out, or `timeout` passes.
+fn recv_typed_within<T: serde::de::DeserializeOwned>(
+    reudp: &mut ReUDP,
+    timeout: Duration,
+) -> Option<Result<T, ReUDPError>> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        match reudp.recv_typed::<T>() {
+            Ok(Some((_, value))) => return Some(Ok(value)),
+            Ok(None) => thread::sleep(Duration::from_millis(1)),
+            Err(e) => return Some(Err(e)),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_round_trip() {
+        let (mut client, mut server) = pair();
+        let sent = PlayerMoved { id: 7, x: 1.5, y: -2.0, name: "alice".to_string() };
+        client.send_typed(&sent, true).unwrap();
+
+        let received = recv_typed_within::<PlayerMoved>(&mut server, Duration::from_secs(1));
+        assert_eq!(received.unwrap().unwrap(), sent);
+    }
+
+    #[test]
+    fn test_untyped_message_is_returned_raw() {
+        let (mut client, mut server) = pair();
+        client.send(b"raw bytes", true).unwrap();
+
+        match recv_typed_within::<PlayerMoved>(&mut server, Duration::from_secs(1)) {
+            Some(Err(ReUDPError::DecodeError { data, .. })) => assert_eq!(data, b"raw bytes"),
+            other => panic!("expected a decode error, got {:?}", other.map(|r| r.map(|_| ()))),
+        }
+    }
+
+    #[test]
+    fn test_undecodable_message_is_returned_raw() {
+        let (mut client, mut server) = pair();
+        client.send_typed(&1u8, true).unwrap();
+
+        match recv_typed_within::<PlayerMoved>(&mut server, Duration::from_secs(1)) {
+            Some(Err(ReUDPError::DecodeError { data, .. })) => assert_eq!(data, vec![1]),
+            other => panic!("expected a decode error, got {:?}", other.map(|r| r.map(|_| ()))),
+        }
+    }
+}
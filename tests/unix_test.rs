@@ -0,0 +1,85 @@
+#![cfg(unix)]
+
+use reudp::{Mode, RecvSource, ReUDP, ReUDPConfig};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Returns a socket path no other test uses.
+fn socket_path(name: &str) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("reudp-{}-{}-{}.sock", name, std::process::id(), n))
+}
+
+/// Polls both ends until `target` delivers a message, for up to `timeout`.
+fn recv_source_within(target: &mut ReUDP, other: &mut ReUDP, timeout: Duration) -> Option<(RecvSource, Vec<u8>)> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        let _ = other.recv();
+        if let Some(received) = target.recv_source().unwrap() {
+            return Some(received);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unix_round_trip() {
+        let server_path = socket_path("server");
+        let client_path = socket_path("client");
+        let mut server = ReUDP::new_unix(&server_path, None, ReUDPConfig::default()).unwrap();
+        let mut client = ReUDP::new_unix(&client_path, Some(&server_path), ReUDPConfig::default()).unwrap();
+        assert!(matches!(client.mode(), Mode::Client(_)));
+        assert!(client.socket().is_none());
+
+        client.send(b"over ipc", true).unwrap();
+        let (source, payload) = recv_source_within(&mut server, &mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(source, RecvSource::Unix(client_path.clone()));
+        assert_eq!(payload, b"over ipc");
+
+        let client_addr = server.client_addrs()[0];
+        assert_eq!(server.source_of(client_addr), RecvSource::Unix(client_path.clone()));
+
+        server.send(b"reply", true).unwrap();
+        let (source, payload) = recv_source_within(&mut client, &mut server, Duration::from_secs(1)).unwrap();
+        assert_eq!(source, RecvSource::Unix(server_path.clone()));
+        assert_eq!(payload, b"reply");
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.pending_acks() > 0 && Instant::now() < deadline {
+            let _ = server.recv();
+            let _ = client.recv();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(client.pending_acks(), 0);
+    }
+
+    #[test]
+    fn test_socket_file_is_removed_on_drop() {
+        let path = socket_path("drop");
+        let reudp = ReUDP::new_unix(&path, None, ReUDPConfig::default()).unwrap();
+        assert!(path.exists());
+        assert!(ReUDP::new_unix(&path, None, ReUDPConfig::default()).is_err());
+        drop(reudp);
+        assert!(!path.exists());
+        ReUDP::new_unix(&path, None, ReUDPConfig::default()).unwrap();
+    }
+
+    #[test]
+    fn test_network_source() {
+        let mut server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let mut client =
+            ReUDP::with_config("127.0.0.1:0", Mode::Client(server.local_addr().unwrap()), ReUDPConfig::default())
+                .unwrap();
+        client.send(b"hello", true).unwrap();
+        let (source, _) = recv_source_within(&mut server, &mut client, Duration::from_secs(1)).unwrap();
+        assert_eq!(source, RecvSource::Network(client.local_addr().unwrap()));
+    }
+}
@@ -0,0 +1,91 @@
+use reudp::{ConfigError, Message, MessageType, Mode, ReUDP, ReUDPConfig, ReUDPError};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs a server on its own thread until `stop` is set, returning it and its address.
+fn spawn_server(stop: Arc<AtomicBool>) -> (Arc<Mutex<ReUDP>>, SocketAddr) {
+    let server = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = Arc::new(Mutex::new(server));
+    let shared = Arc::clone(&server);
+    thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            shared.lock().unwrap().recv().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+    (server, addr)
+}
+
+/// Counts the heartbeats arriving on `socket` during `window`.
+fn heartbeats_within(socket: &UdpSocket, window: Duration) -> usize {
+    let deadline = Instant::now() + window;
+    let mut buf = [0; 1024];
+    let mut count = 0;
+    while Instant::now() < deadline {
+        if let Ok(len) = socket.recv(&mut buf) {
+            let message = Message::from_bytes(&buf[..len]).unwrap();
+            if message.message_type == MessageType::Heartbeat {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_thread_picks_up_new_interval() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        fake_server.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let config = ReUDPConfig::default()
+            .heartbeat_interval(Duration::from_secs(5))
+            .liveness_timeout(Duration::from_secs(10));
+        let client = ReUDP::with_config("127.0.0.1:0", Mode::Client(fake_server.local_addr().unwrap()), config).unwrap();
+        assert_eq!(heartbeats_within(&fake_server, Duration::from_millis(200)), 0);
+
+        client
+            .update_config(|config| config.heartbeat_interval(Duration::from_millis(20)))
+            .unwrap();
+        assert_eq!(client.heartbeat_interval(), Duration::from_millis(20));
+        assert!(heartbeats_within(&fake_server, Duration::from_millis(300)) >= 3);
+    }
+
+    #[test]
+    fn test_invalid_update_is_rejected() {
+        let reudp = ReUDP::with_config("127.0.0.1:0", Mode::Server, ReUDPConfig::default()).unwrap();
+        let interval = reudp.heartbeat_interval();
+
+        let result = reudp.update_config(|config| {
+            config
+                .heartbeat_interval(Duration::from_millis(10))
+                .resend_interval(Duration::from_secs(5))
+        });
+        assert!(matches!(result, Err(ConfigError::ResendNotBelowLivenessTimeout { .. })));
+        assert_eq!(reudp.heartbeat_interval(), interval);
+    }
+
+    #[test]
+    fn test_max_clients_applies_to_running_server() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (server, server_addr) = spawn_server(Arc::clone(&stop));
+        server.lock().unwrap().update_config(|config| config.max_clients(1)).unwrap();
+
+        let mut first = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), ReUDPConfig::default()).unwrap();
+        first.connect().unwrap();
+
+        let config = ReUDPConfig::default()
+            .handshake_retries(2)
+            .handshake_retry_interval(Duration::from_millis(100));
+        let mut second = ReUDP::with_config("127.0.0.1:0", Mode::Client(server_addr), config).unwrap();
+        assert!(matches!(second.connect(), Err(ReUDPError::ConnectionRefused { .. })));
+
+        stop.store(true, Ordering::SeqCst);
+    }
+}